@@ -1,4 +1,4 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use ferragate::health::{AppState, HealthStatus};
 use std::hint::black_box;
 
@@ -109,10 +109,43 @@ fn benchmark_health_check_batch_operations(c: &mut Criterion) {
     });
 }
 
+fn benchmark_health_check_lookup_scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("health_check_lookup");
+    for check_count in [10, 100, 1000] {
+        let app_state = AppState::with_max_health_checks(check_count);
+        for i in 0..check_count {
+            app_state.register_health_check(format!("service_{i}"), HealthStatus::Healthy, None);
+        }
+        let last = format!("service_{}", check_count - 1);
+
+        group.bench_with_input(
+            BenchmarkId::new("update_last", check_count),
+            &last,
+            |b, name| {
+                b.iter(|| {
+                    app_state.update_health_check(name, HealthStatus::Healthy, None);
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("unregister_and_register_last", check_count),
+            &last,
+            |b, name| {
+                b.iter(|| {
+                    app_state.unregister_health_check(name);
+                    app_state.register_health_check(name.clone(), HealthStatus::Healthy, None);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
 criterion_group!(
     health_benches,
     benchmark_health_operations,
     benchmark_health_check_operations,
-    benchmark_health_check_batch_operations
+    benchmark_health_check_batch_operations,
+    benchmark_health_check_lookup_scaling
 );
 criterion_main!(health_benches);
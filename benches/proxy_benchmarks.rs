@@ -1,3 +1,4 @@
+use axum::http::HeaderMap;
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use ferragate::config::{GatewayConfig, RouteConfig, ServerConfig};
 use ferragate::proxy::ProxyState;
@@ -12,6 +13,7 @@ fn create_proxy_state() -> ProxyState {
             workers: Some(4),
             timeout_ms: Some(30000),
             tls: None,
+            ..Default::default()
         },
         routes: vec![
             RouteConfig {
@@ -22,6 +24,7 @@ fn create_proxy_state() -> ProxyState {
                 strip_path: true,
                 preserve_host: false,
                 timeout_ms: Some(5000),
+                ..Default::default()
             },
             RouteConfig {
                 path: "/api/v2/*".to_string(),
@@ -31,6 +34,7 @@ fn create_proxy_state() -> ProxyState {
                 strip_path: false,
                 preserve_host: true,
                 timeout_ms: Some(10000),
+                ..Default::default()
             },
             RouteConfig {
                 path: "/health".to_string(),
@@ -40,6 +44,7 @@ fn create_proxy_state() -> ProxyState {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             },
             RouteConfig {
                 path: "/static/*".to_string(),
@@ -49,6 +54,7 @@ fn create_proxy_state() -> ProxyState {
                 strip_path: true,
                 preserve_host: false,
                 timeout_ms: Some(15000),
+                ..Default::default()
             },
         ],
         logging: ferragate::config::LoggingConfig::default(),
@@ -74,7 +80,13 @@ fn benchmark_route_finding(c: &mut Criterion) {
 
     c.bench_function("find_matching_route_single", |b| {
         b.iter(|| {
-            black_box(proxy_state.find_matching_route("/api/v1/users/123", "GET"));
+            black_box(proxy_state.find_matching_route(
+                "/api/v1/users/123",
+                "GET",
+                &HeaderMap::new(),
+                "",
+                None,
+            ));
         })
     });
 
@@ -88,7 +100,13 @@ fn benchmark_route_finding(c: &mut Criterion) {
                 b.iter(|| {
                     for i in 0..path_count {
                         let path = &test_paths[i % test_paths.len()];
-                        black_box(proxy_state.find_matching_route(path, "GET"));
+                        black_box(proxy_state.find_matching_route(
+                            path,
+                            "GET",
+                            &HeaderMap::new(),
+                            "",
+                            None,
+                        ));
                     }
                 })
             },
@@ -116,7 +134,13 @@ fn benchmark_route_matching_patterns(c: &mut Criterion) {
     for (name, path, method) in test_scenarios {
         group.bench_function(name, |b| {
             b.iter(|| {
-                black_box(proxy_state.find_matching_route(path, method));
+                black_box(proxy_state.find_matching_route(
+                    path,
+                    method,
+                    &HeaderMap::new(),
+                    "",
+                    None,
+                ));
             })
         });
     }
@@ -131,7 +155,13 @@ fn benchmark_method_validation(c: &mut Criterion) {
     c.bench_function("method_validation_allowed", |b| {
         b.iter(|| {
             for method in &methods {
-                black_box(proxy_state.find_matching_route("/api/v1/users", method));
+                black_box(proxy_state.find_matching_route(
+                    "/api/v1/users",
+                    method,
+                    &HeaderMap::new(),
+                    "",
+                    None,
+                ));
             }
         })
     });
@@ -139,7 +169,13 @@ fn benchmark_method_validation(c: &mut Criterion) {
     c.bench_function("method_validation_restricted", |b| {
         b.iter(|| {
             for method in &methods {
-                black_box(proxy_state.find_matching_route("/health", method));
+                black_box(proxy_state.find_matching_route(
+                    "/health",
+                    method,
+                    &HeaderMap::new(),
+                    "",
+                    None,
+                ));
             }
         })
     });
@@ -1,5 +1,7 @@
+use axum::http::HeaderMap;
 use criterion::{criterion_group, criterion_main, Criterion};
 use ferragate::config::{GatewayConfig, RouteConfig, ServerConfig};
+use ferragate::proxy::ProxyState;
 use std::collections::HashMap;
 use std::hint::black_box;
 
@@ -11,6 +13,7 @@ fn create_test_config() -> GatewayConfig {
             workers: Some(4),
             timeout_ms: Some(30000),
             tls: None,
+            ..Default::default()
         },
         routes: vec![
             RouteConfig {
@@ -21,6 +24,7 @@ fn create_test_config() -> GatewayConfig {
                 strip_path: true,
                 preserve_host: false,
                 timeout_ms: Some(5000),
+                ..Default::default()
             },
             RouteConfig {
                 path: "/health".to_string(),
@@ -30,6 +34,7 @@ fn create_test_config() -> GatewayConfig {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             },
         ],
         logging: ferragate::config::LoggingConfig::default(),
@@ -64,6 +69,47 @@ fn benchmark_route_matching(c: &mut Criterion) {
     });
 }
 
+fn benchmark_large_route_table(c: &mut Criterion) {
+    let routes: Vec<RouteConfig> = (0..1000)
+        .map(|i| RouteConfig {
+            // Zero-padded so no route path is a prefix of another
+            path: format!("/service{i:04}/*"),
+            upstream: format!("http://service{i}:8080"),
+            methods: vec!["GET".to_string()],
+            ..Default::default()
+        })
+        .collect();
+    let config = GatewayConfig {
+        routes,
+        ..create_test_config()
+    };
+    let headers = HeaderMap::new();
+    // The last route, the worst case for a linear scan
+    let path = "/service0999/items/42";
+
+    let mut group = c.benchmark_group("route_table_1000");
+    group.bench_function("linear_scan", |b| {
+        b.iter(|| {
+            black_box(config.routes.iter().find(|route| {
+                route.enabled
+                    && route.matches_path(black_box(path))
+                    && route.matches_method("GET")
+                    && route.matches_headers(&headers)
+                    && route.matches_query("")
+                    && route.matches_tls(None)
+            }))
+        })
+    });
+
+    let proxy_state = ProxyState::new(config.clone());
+    group.bench_function("trie", |b| {
+        b.iter(|| {
+            black_box(proxy_state.find_matching_route(black_box(path), "GET", &headers, "", None))
+        })
+    });
+    group.finish();
+}
+
 fn benchmark_path_transformation(c: &mut Criterion) {
     let route = RouteConfig {
         path: "/api/v1/*".to_string(),
@@ -73,6 +119,7 @@ fn benchmark_path_transformation(c: &mut Criterion) {
         strip_path: true,
         preserve_host: false,
         timeout_ms: None,
+        ..Default::default()
     };
 
     c.bench_function("path_transformation", |b| {
@@ -95,6 +142,7 @@ fn benchmark_config_validation(c: &mut Criterion) {
 criterion_group!(
     route_benches,
     benchmark_route_matching,
+    benchmark_large_route_table,
     benchmark_path_transformation,
     benchmark_config_validation
 );
@@ -0,0 +1,197 @@
+/// Read-only admin endpoints for operating the gateway
+///
+/// `POST /admin/validate` runs a candidate configuration through the same
+/// parsing and validation as a reload, so a change can be checked before it is
+/// deployed. The submitted configuration is never applied. `GET /admin/route-order`
+/// lists the running routes in the order requests are matched against them.
+use axum::extract::State;
+use axum::http::{header::CONTENT_TYPE, HeaderMap, StatusCode};
+use axum::Json;
+use serde::Serialize;
+
+use crate::config::{GatewayConfig, ValidationReport};
+use crate::error::{FerragateError, FerragateResult};
+use crate::reload::SharedProxyState;
+
+/// Format of a submitted configuration, taken from its `Content-Type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// TOML unless the body is declared as JSON or YAML
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        if essence == "application/json" || essence.ends_with("+json") {
+            Self::Json
+        } else if essence.contains("yaml") {
+            Self::Yaml
+        } else {
+            Self::Toml
+        }
+    }
+}
+
+/// Parse a submitted configuration without validating it
+fn parse_config(body: &str, format: ConfigFormat) -> FerragateResult<GatewayConfig> {
+    match format {
+        ConfigFormat::Toml => toml::from_str(body)
+            .map_err(|e| FerragateError::config(format!("Failed to parse config: {e}"))),
+        ConfigFormat::Json => serde_json::from_str(body)
+            .map_err(|e| FerragateError::config(format!("Failed to parse config: {e}"))),
+        ConfigFormat::Yaml => Err(FerragateError::config(
+            "YAML configurations are not supported; submit TOML or JSON",
+        )),
+    }
+}
+
+/// Admin endpoint handler validating a submitted configuration
+///
+/// Responds `200 OK` when the configuration is valid, `422 Unprocessable Entity`
+/// when it is not and `415 Unsupported Media Type` for YAML, always with the
+/// validation report as the body.
+pub async fn validate_config_handler(
+    headers: HeaderMap,
+    body: String,
+) -> (StatusCode, Json<ValidationReport>) {
+    let format = ConfigFormat::from_headers(&headers);
+    let report = ValidationReport::new(parse_config(&body, format));
+    let status = match (report.valid, format) {
+        (true, _) => StatusCode::OK,
+        (false, ConfigFormat::Yaml) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        (false, _) => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+    (status, Json(report))
+}
+
+/// A route's place in the matching order
+#[derive(Debug, Serialize)]
+pub struct RouteOrderEntry {
+    /// Position of the route in the configuration
+    pub index: usize,
+    /// Rank of the route's path; higher values are evaluated first
+    pub specificity: usize,
+    pub path: String,
+    pub methods: Vec<String>,
+    pub upstream: String,
+}
+
+/// Admin endpoint handler listing enabled routes in evaluation order
+///
+/// The first route in the list that matches a request handles it. The list is
+/// the route trie's precomputed order: by descending path specificity, then by
+/// configuration index. Disabled routes are left out because they are never
+/// evaluated.
+pub async fn route_order_handler(
+    State(proxy): State<SharedProxyState>,
+) -> Json<Vec<RouteOrderEntry>> {
+    let state = proxy.current();
+    let order = state
+        .evaluation_order()
+        .map(|(index, route)| RouteOrderEntry {
+            index,
+            specificity: route.specificity(),
+            path: route.path.clone(),
+            methods: route.methods.clone(),
+            upstream: route.upstream.clone(),
+        })
+        .collect();
+    Json(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteConfig;
+    use crate::proxy::ProxyState;
+    use axum::http::HeaderValue;
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[test]
+    fn test_format_from_content_type() {
+        assert_eq!(
+            ConfigFormat::from_headers(&HeaderMap::new()),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_headers(&headers("application/toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_headers(&headers("Application/JSON; charset=utf-8")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_headers(&headers("application/yaml")),
+            ConfigFormat::Yaml
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_config_is_validated() {
+        let body = r#"{
+            "server": {"port": 8080},
+            "routes": [{"path": "/api/*", "upstream": "http://backend:3000"}]
+        }"#;
+        let (status, Json(report)) =
+            validate_config_handler(headers("application/json"), body.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(report.valid);
+        assert_eq!(report.routes[0].upstream, "http://backend:3000");
+    }
+
+    #[tokio::test]
+    async fn test_route_order_follows_evaluation_order() {
+        let route = |path: &str, upstream: &str, enabled: bool| RouteConfig {
+            path: path.to_string(),
+            upstream: upstream.to_string(),
+            enabled,
+            ..Default::default()
+        };
+        let config = GatewayConfig::builder()
+            .route(route("/*", "http://fallback:8000", true))
+            .route(route("/api/*", "http://api:8000", true))
+            .route(route("/api/legacy/*", "http://legacy:8000", false))
+            .route(route("/api/users/*", "http://users:8000", true))
+            .route(route("/api/health", "http://api:8000", true))
+            .build()
+            .unwrap();
+        let proxy = SharedProxyState::new(ProxyState::new(config));
+
+        let Json(order) = route_order_handler(State(proxy)).await;
+        let order: Vec<_> = order
+            .iter()
+            .map(|entry| (entry.index, entry.specificity, entry.path.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (4, 23, "/api/health"),
+                (3, 20, "/api/users/*"),
+                (1, 8, "/api/*"),
+                (0, 0, "/*"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_yaml_config_is_unsupported() {
+        let (status, Json(report)) =
+            validate_config_handler(headers("application/yaml"), "server: {}".to_string()).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(!report.valid);
+    }
+}
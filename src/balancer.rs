@@ -0,0 +1,253 @@
+/// Load balancing across a route's upstreams
+///
+/// A route with `upstreams` spreads its traffic over them and its primary
+/// `upstream` at random. With `health_weighting`, each upstream's share follows
+/// its recent error rate and latency from the rolling upstream stats, so a
+/// degraded backend gradually receives less traffic and wins it back as it
+/// recovers. Routes that also set `eject_error_rate` take a failing backend out
+/// of the pool for a while, and have no upstream to offer once all are ejected.
+/// Routes with an active `health_check` likewise skip upstreams failing it.
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::{HealthWeightingConfig, RouteConfig};
+use crate::upstream_stats::{UpstreamStats, UpstreamStatsSnapshot};
+
+/// Weight of an upstream relative to a fully healthy one (1.0)
+///
+/// Upstreams without enough recent requests keep the full weight.
+pub fn health_weight(stats: Option<&UpstreamStatsSnapshot>, config: &HealthWeightingConfig) -> f64 {
+    let Some(stats) = stats.filter(|stats| stats.requests >= config.min_requests) else {
+        return 1.0;
+    };
+
+    let mut weight = (1.0 - stats.error_rate).powf(config.error_sensitivity);
+    if let Some(threshold) = config.latency_threshold_ms {
+        let threshold = threshold as f64;
+        if stats.latency_p90_ms > threshold {
+            weight *= (threshold / stats.latency_p90_ms).powf(config.latency_sensitivity);
+        }
+    }
+    weight.max(config.min_weight)
+}
+
+/// Whether an upstream may receive traffic, ejecting it once it fails too often
+///
+/// Upstreams are only ejected when the route sets `eject_error_rate`, and only
+/// after serving `min_requests` requests in the stats window.
+pub fn is_admissible(
+    upstream: &str,
+    config: &HealthWeightingConfig,
+    stats: &UpstreamStats,
+) -> bool {
+    let Some(eject_error_rate) = config.eject_error_rate else {
+        return true;
+    };
+    if stats.is_ejected(upstream) {
+        return false;
+    }
+
+    let failing = stats.snapshot_for(upstream).is_some_and(|stats| {
+        stats.requests >= config.min_requests && stats.error_rate >= eject_error_rate
+    });
+    if failing {
+        warn!(
+            "Ejecting upstream {} for {}s after repeated failures",
+            upstream, config.eject_secs
+        );
+        stats.eject(upstream, Duration::from_secs(config.eject_secs));
+    }
+    !failing
+}
+
+/// Choose the upstream for a request from the route's pool
+///
+/// Returns None when every upstream in the pool is ejected or failing its health check.
+pub fn choose_upstream<'a>(route: &'a RouteConfig, stats: &UpstreamStats) -> Option<&'a str> {
+    let checked = route.health_check.is_some();
+    let pool = std::iter::once(&route.upstream)
+        .chain(&route.upstreams)
+        .filter(|upstream| !(checked && stats.failed_health_check(upstream)));
+    if route.upstreams.is_empty() {
+        return pool.map(String::as_str).next();
+    }
+
+    let weighted: Vec<(&str, f64)> = match &route.health_weighting {
+        Some(config) => pool
+            .filter(|upstream| is_admissible(upstream, config, stats))
+            .map(|upstream| {
+                let weight = health_weight(stats.snapshot_for(upstream).as_ref(), config);
+                (upstream.as_str(), weight)
+            })
+            .collect(),
+        None => pool.map(|upstream| (upstream.as_str(), 1.0)).collect(),
+    };
+    (!weighted.is_empty()).then(|| pick_weighted(&weighted))
+}
+
+/// Pick an entry at random in proportion to its weight
+fn pick_weighted<'a>(weighted: &[(&'a str, f64)]) -> &'a str {
+    let total: f64 = weighted.iter().map(|(_, weight)| weight).sum();
+    let mut target = fastrand::f64() * total;
+    for (upstream, weight) in weighted {
+        if target < *weight {
+            return upstream;
+        }
+        target -= weight;
+    }
+    weighted[weighted.len() - 1].0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::UpstreamHealthCheckConfig;
+    use std::time::Duration;
+
+    fn pooled_route() -> RouteConfig {
+        RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://a".to_string(),
+            upstreams: vec!["http://b".to_string()],
+            health_weighting: Some(HealthWeightingConfig::default()),
+            ..Default::default()
+        }
+    }
+
+    /// Fraction of `picks` requests sent to upstream b
+    fn share_of_b(route: &RouteConfig, stats: &UpstreamStats, picks: usize) -> f64 {
+        let hits = (0..picks)
+            .filter(|_| choose_upstream(route, stats) == Some("http://b"))
+            .count();
+        hits as f64 / picks as f64
+    }
+
+    fn record(stats: &UpstreamStats, upstream: &str, requests: usize, errors: usize) {
+        for i in 0..requests {
+            stats.record(upstream, Duration::from_millis(10), i < errors);
+        }
+    }
+
+    #[test]
+    fn test_rising_error_rate_reduces_traffic_share() {
+        let route = pooled_route();
+        let stats = UpstreamStats::with_window(100);
+        record(&stats, "http://a", 100, 0);
+        record(&stats, "http://b", 100, 0);
+        let healthy = share_of_b(&route, &stats, 4000);
+        assert!((0.45..0.55).contains(&healthy), "healthy share {healthy}");
+
+        // 30% errors: weight 0.49, about a third of the traffic
+        record(&stats, "http://b", 100, 30);
+        let degraded = share_of_b(&route, &stats, 4000);
+        assert!(
+            (0.28..0.38).contains(&degraded),
+            "degraded share {degraded}"
+        );
+
+        // 90% errors: weight floored at min_weight, but still probed
+        record(&stats, "http://b", 100, 90);
+        let failing = share_of_b(&route, &stats, 4000);
+        assert!((0.02..0.08).contains(&failing), "failing share {failing}");
+
+        // Recovery restores the full share
+        record(&stats, "http://b", 100, 0);
+        let recovered = share_of_b(&route, &stats, 4000);
+        assert!(
+            (0.45..0.55).contains(&recovered),
+            "recovered share {recovered}"
+        );
+    }
+
+    #[test]
+    fn test_health_weight() {
+        let config = HealthWeightingConfig {
+            latency_threshold_ms: Some(100),
+            ..Default::default()
+        };
+        let stats = |requests, error_rate, latency_p90_ms| UpstreamStatsSnapshot {
+            upstream: "http://a".to_string(),
+            requests,
+            error_rate,
+            latency_p50_ms: 0.0,
+            latency_p90_ms,
+            latency_p99_ms: 0.0,
+        };
+
+        assert_eq!(health_weight(None, &config), 1.0);
+        assert_eq!(health_weight(Some(&stats(100, 0.0, 50.0)), &config), 1.0);
+        assert_eq!(health_weight(Some(&stats(100, 0.5, 50.0)), &config), 0.25);
+        assert_eq!(health_weight(Some(&stats(100, 0.0, 200.0)), &config), 0.5);
+        assert_eq!(health_weight(Some(&stats(100, 1.0, 50.0)), &config), 0.05);
+        // Too few requests to judge the upstream
+        assert_eq!(health_weight(Some(&stats(5, 1.0, 50.0)), &config), 1.0);
+    }
+
+    #[test]
+    fn test_failing_upstreams_are_ejected() {
+        let route = RouteConfig {
+            health_weighting: Some(HealthWeightingConfig {
+                eject_error_rate: Some(0.5),
+                ..Default::default()
+            }),
+            ..pooled_route()
+        };
+        let stats = UpstreamStats::with_window(100);
+        record(&stats, "http://a", 20, 0);
+        record(&stats, "http://b", 20, 10);
+
+        assert_eq!(share_of_b(&route, &stats, 200), 0.0);
+        assert!(stats.is_ejected("http://b"));
+        assert!(!stats.is_ejected("http://a"));
+
+        record(&stats, "http://a", 20, 20);
+        assert_eq!(choose_upstream(&route, &stats), None);
+
+        // Without eject_error_rate, failing upstreams keep a minimal share
+        assert!(choose_upstream(&pooled_route(), &stats).is_some());
+    }
+
+    #[test]
+    fn test_unweighted_pool_uses_every_upstream() {
+        let route = RouteConfig {
+            health_weighting: None,
+            ..pooled_route()
+        };
+        let share = share_of_b(&route, &UpstreamStats::new(), 4000);
+        assert!((0.45..0.55).contains(&share), "share {share}");
+
+        let single = RouteConfig {
+            upstreams: Vec::new(),
+            ..route
+        };
+        assert_eq!(
+            choose_upstream(&single, &UpstreamStats::new()),
+            Some("http://a")
+        );
+    }
+
+    #[test]
+    fn test_upstreams_failing_health_checks_are_skipped() {
+        let route = RouteConfig {
+            health_check: Some(UpstreamHealthCheckConfig::default()),
+            ..pooled_route()
+        };
+        let stats = UpstreamStats::new();
+        stats.record_health_check("http://b", false);
+        assert_eq!(share_of_b(&route, &stats, 200), 0.0);
+
+        // Routes without a health check of their own ignore the results
+        assert!(share_of_b(&pooled_route(), &stats, 200) > 0.0);
+
+        stats.record_health_check("http://a", false);
+        assert_eq!(choose_upstream(&route, &stats), None);
+        let single = RouteConfig {
+            upstreams: Vec::new(),
+            ..route
+        };
+        assert_eq!(choose_upstream(&single, &stats), None);
+
+        stats.record_health_check("http://a", true);
+        assert_eq!(choose_upstream(&single, &stats), Some("http://a"));
+    }
+}
@@ -0,0 +1,234 @@
+/// Rough load generator for the `bench` subcommand
+///
+/// The configuration is served by a gateway embedded on an ephemeral local port,
+/// and requests are fired at it from a fixed number of concurrent workers. The
+/// client, gateway and upstreams share one machine, so the numbers are only good
+/// for sanity-checking a configuration; they are no substitute for a dedicated
+/// benchmarking tool.
+use axum::http::{HeaderMap, Method};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::GatewayConfig;
+use crate::error::{FerragateError, FerragateResult};
+use crate::proxy::{proxy_router, ProxyState};
+use crate::upstream_stats::percentile;
+
+/// What to send during a bench run
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub method: Method,
+    /// Request path, optionally with a query string
+    pub path: String,
+    pub headers: HeaderMap,
+    /// Total number of requests to send
+    pub requests: usize,
+    /// Number of requests in flight at once
+    pub concurrency: usize,
+}
+
+/// Results of a bench run
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub requests: usize,
+    pub concurrency: usize,
+    pub elapsed: Duration,
+    /// Latencies of all requests in milliseconds, sorted ascending
+    pub latencies_ms: Vec<f64>,
+    /// Number of responses per status code
+    pub statuses: BTreeMap<u16, usize>,
+    /// Requests that failed without a response
+    pub errors: usize,
+}
+
+impl BenchReport {
+    /// Render the report for the terminal
+    pub fn render(&self) -> String {
+        let throughput = self.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut output = format!(
+            "Rough load test; use a dedicated benchmarking tool for real measurements\n\
+             Requests: {} (concurrency {}) in {:.2}s, {:.1} req/s\n\
+             Latency: p50 {:.2}ms, p90 {:.2}ms, p99 {:.2}ms, max {:.2}ms\n\
+             Status codes:",
+            self.requests,
+            self.concurrency,
+            self.elapsed.as_secs_f64(),
+            throughput,
+            percentile(&self.latencies_ms, 0.50),
+            percentile(&self.latencies_ms, 0.90),
+            percentile(&self.latencies_ms, 0.99),
+            self.latencies_ms.last().copied().unwrap_or(0.0),
+        );
+        for (status, count) in &self.statuses {
+            output.push_str(&format!("\n  {status}: {count}"));
+        }
+        if self.errors > 0 {
+            output.push_str(&format!("\n  errors: {}", self.errors));
+        }
+        output
+    }
+}
+
+/// Serve the configuration on a local port and load it with requests
+pub async fn run_bench(
+    config: GatewayConfig,
+    options: BenchOptions,
+) -> FerragateResult<BenchReport> {
+    if options.requests == 0 || options.concurrency == 0 {
+        return Err(FerragateError::validation(
+            "requests and concurrency must be greater than 0",
+        ));
+    }
+    if !options.path.starts_with('/') {
+        return Err(FerragateError::validation(format!(
+            "Invalid path '{}': must start with '/'",
+            options.path
+        )));
+    }
+
+    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let addr = listener.local_addr()?;
+    let app = proxy_router(ProxyState::new(config));
+    let server = tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+    });
+
+    let client = reqwest::Client::builder()
+        .pool_max_idle_per_host(options.concurrency)
+        .build()?;
+    let url = format!("http://{addr}{}", options.path);
+    let next = Arc::new(AtomicUsize::new(0));
+
+    // reqwest uses its own copies of the http types
+    let method = reqwest::Method::from_bytes(options.method.as_str().as_bytes())
+        .map_err(|e| FerragateError::validation(format!("Invalid HTTP method: {e}")))?;
+    let headers: reqwest::header::HeaderMap = options
+        .headers
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((
+                reqwest::header::HeaderName::from_bytes(name.as_str().as_bytes()).ok()?,
+                reqwest::header::HeaderValue::from_bytes(value.as_bytes()).ok()?,
+            ))
+        })
+        .collect();
+
+    let started = Instant::now();
+    let workers: Vec<_> = (0..options.concurrency.min(options.requests))
+        .map(|_| {
+            let client = client.clone();
+            let url = url.clone();
+            let next = next.clone();
+            let method = method.clone();
+            let headers = headers.clone();
+            let requests = options.requests;
+            tokio::spawn(async move {
+                let mut results = Vec::new();
+                while next.fetch_add(1, Ordering::Relaxed) < requests {
+                    let sent = Instant::now();
+                    let status = client
+                        .request(method.clone(), &url)
+                        .headers(headers.clone())
+                        .send()
+                        .await
+                        .map(|response| response.status().as_u16())
+                        .ok();
+                    results.push((sent.elapsed().as_secs_f64() * 1000.0, status));
+                }
+                results
+            })
+        })
+        .collect();
+
+    let mut report = BenchReport {
+        requests: options.requests,
+        concurrency: options.concurrency,
+        elapsed: Duration::ZERO,
+        latencies_ms: Vec::with_capacity(options.requests),
+        statuses: BTreeMap::new(),
+        errors: 0,
+    };
+    for worker in workers {
+        let results = worker
+            .await
+            .map_err(|e| FerragateError::server(format!("Bench worker failed: {e}")))?;
+        for (latency_ms, status) in results {
+            report.latencies_ms.push(latency_ms);
+            match status {
+                Some(status) => *report.statuses.entry(status).or_default() += 1,
+                None => report.errors += 1,
+            }
+        }
+    }
+    report.elapsed = started.elapsed();
+    report.latencies_ms.sort_by(f64::total_cmp);
+
+    server.abort();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RouteConfig, ServerConfig};
+
+    #[tokio::test]
+    async fn test_bench_against_mock_upstream() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/items"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+        let config = GatewayConfig {
+            server: ServerConfig::default(),
+            routes: vec![RouteConfig {
+                path: "/api/*".to_string(),
+                upstream: upstream.uri(),
+                ..Default::default()
+            }],
+            logging: Default::default(),
+        };
+        let options = BenchOptions {
+            method: Method::GET,
+            path: "/api/items".to_string(),
+            headers: HeaderMap::new(),
+            requests: 20,
+            concurrency: 4,
+        };
+
+        let report = run_bench(config, options).await.unwrap();
+
+        assert_eq!(report.latencies_ms.len(), 20);
+        assert_eq!(report.statuses, BTreeMap::from([(200, 20)]));
+        assert_eq!(report.errors, 0);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 20);
+
+        let output = report.render();
+        assert!(output.starts_with("Rough load test"));
+        assert!(output.contains("Requests: 20 (concurrency 4)"));
+        assert!(output.contains("\n  200: 20"));
+    }
+
+    #[tokio::test]
+    async fn test_bench_rejects_invalid_options() {
+        let options = BenchOptions {
+            method: Method::GET,
+            path: "/".to_string(),
+            headers: HeaderMap::new(),
+            requests: 0,
+            concurrency: 1,
+        };
+        let result = run_bench(GatewayConfig::default_config(), options).await;
+        assert!(result.is_err());
+    }
+}
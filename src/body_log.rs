@@ -0,0 +1,200 @@
+/// Debug logging of request and response bodies
+///
+/// Routes with `log_bodies` write the bodies they forward to the log, so
+/// traffic can be inspected while debugging an integration. Sensitive values
+/// are redacted before anything is written: the values at the configured JSON
+/// field paths, then every match of the configured patterns, become `***`.
+/// The redacted text is cut to the configured size.
+use serde_json::Value;
+use std::fmt;
+use tracing::info;
+
+use crate::config::{BodyLoggingConfig, RouteConfig};
+use crate::constants::BODY_LOG_REDACTED;
+
+/// Which body of an exchange is logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyDirection {
+    Request,
+    Response,
+}
+
+impl fmt::Display for BodyDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request => write!(f, "Request"),
+            Self::Response => write!(f, "Response"),
+        }
+    }
+}
+
+/// Log a body forwarded through the route, if it has `log_bodies`
+pub fn log_body(route: &RouteConfig, direction: BodyDirection, body: &[u8]) {
+    if let Some(config) = &route.log_bodies {
+        info!(
+            "{} body for route {}: {}",
+            direction,
+            route.path,
+            render(config, body)
+        );
+    }
+}
+
+/// Note that a body of a route with `log_bodies` was streamed, so it is not logged
+pub fn log_unbuffered_body(route: &RouteConfig, direction: BodyDirection) {
+    if route.log_bodies.is_some() {
+        info!(
+            "{} body for route {} not logged: too large to buffer",
+            direction, route.path
+        );
+    }
+}
+
+/// Render a body for the log, redacted and cut to `max_bytes`
+///
+/// Bodies that are not UTF-8 text are described by their size only. Field
+/// redaction applies to JSON bodies; patterns apply to any text.
+pub fn render(config: &BodyLoggingConfig, body: &[u8]) -> String {
+    if body.is_empty() {
+        return "<empty>".to_string();
+    }
+    let Ok(text) = std::str::from_utf8(body) else {
+        return format!("<{} bytes of binary data>", body.len());
+    };
+
+    let mut text = redact_json_fields(&config.redact_fields, text);
+    for pattern in &config.redact_patterns {
+        text = pattern.redact(&text).into_owned();
+    }
+    truncate(text, config.max_bytes)
+}
+
+/// Replace the values at the given field paths of a JSON body
+///
+/// Text that is not JSON is returned as-is.
+fn redact_json_fields(fields: &[String], text: &str) -> String {
+    if fields.is_empty() {
+        return text.to_string();
+    }
+    let Ok(mut value) = serde_json::from_str::<Value>(text) else {
+        return text.to_string();
+    };
+    for field in fields {
+        let path: Vec<&str> = field.split('.').collect();
+        redact_path(&mut value, &path);
+    }
+    value.to_string()
+}
+
+/// Redact the value at a field path, applying the path to each element of arrays on the way
+fn redact_path(value: &mut Value, path: &[&str]) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                redact_path(item, path);
+            }
+        }
+        Value::Object(fields) => {
+            let Some((name, rest)) = path.split_first() else {
+                return;
+            };
+            match fields.get_mut(*name) {
+                Some(field) if rest.is_empty() => {
+                    *field = Value::String(BODY_LOG_REDACTED.to_string());
+                }
+                Some(field) => redact_path(field, rest),
+                None => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Cut text to at most `max_bytes`, noting the full size when anything was cut
+fn truncate(text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... ({} bytes total)", &text[..end], text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RedactPattern;
+
+    fn config(fields: &[&str], patterns: &[&str]) -> BodyLoggingConfig {
+        BodyLoggingConfig {
+            redact_fields: fields.iter().map(|field| field.to_string()).collect(),
+            redact_patterns: patterns
+                .iter()
+                .map(|pattern| RedactPattern::try_from(pattern.to_string()).unwrap())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_json_fields_are_redacted() {
+        let config = config(&["password", "user.ssn", "cards.number"], &[]);
+        let body = br#"{"password":"hunter2","user":{"name":"ada","ssn":"078-05-1120"},"cards":[{"number":"4111111111111111"},{"number":"5500000000000004"}]}"#;
+
+        let logged = render(&config, body);
+        assert_eq!(
+            logged,
+            r#"{"cards":[{"number":"***"},{"number":"***"}],"password":"***","user":{"name":"ada","ssn":"***"}}"#
+        );
+        for secret in [
+            "hunter2",
+            "078-05-1120",
+            "4111111111111111",
+            "5500000000000004",
+        ] {
+            assert!(!logged.contains(secret), "{logged}");
+        }
+    }
+
+    #[test]
+    fn test_patterns_are_redacted_in_any_text() {
+        let config = config(&["token"], &[r"Bearer [\w.]+", r"\d{16}"]);
+
+        let form = b"card=4111111111111111&auth=Bearer abc.def&name=ada";
+        assert_eq!(render(&config, form), "card=***&auth=***&name=ada");
+
+        // Fields and patterns both apply to JSON
+        let json = br#"{"token":"t0k3n","note":"card 4111111111111111"}"#;
+        assert_eq!(
+            render(&config, json),
+            r#"{"note":"card ***","token":"***"}"#
+        );
+    }
+
+    #[test]
+    fn test_bodies_are_cut_to_max_bytes() {
+        let config = BodyLoggingConfig {
+            max_bytes: 5,
+            ..Default::default()
+        };
+        assert_eq!(render(&config, b"hello"), "hello");
+        assert_eq!(render(&config, b"hello world"), "hello... (11 bytes total)");
+        // Cuts never split a character
+        assert_eq!(
+            render(&config, "abcdé".as_bytes()),
+            "abcd... (6 bytes total)"
+        );
+    }
+
+    #[test]
+    fn test_empty_and_binary_bodies() {
+        let config = config(&[], &[]);
+        assert_eq!(render(&config, b""), "<empty>");
+        assert_eq!(
+            render(&config, &[0xff, 0xfe, 0x00]),
+            "<3 bytes of binary data>"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+/// Built-in responses for `/favicon.ico` and `/robots.txt`
+///
+/// Browsers and crawlers request these paths from every host they visit.
+/// Answering them at the gateway keeps them out of the 404 logs and away from
+/// upstreams behind broad routes. Configured files are read once, when the
+/// router is built.
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use bytes::Bytes;
+use tracing::error;
+
+use crate::config::ServerConfig;
+use crate::constants::{
+    BUILTIN_FILE_CACHE_CONTROL, DEFAULT_ROBOTS_TXT, FAVICON_ENDPOINT, ROBOTS_TXT_ENDPOINT,
+};
+
+/// A small file served from memory
+#[derive(Debug, Clone)]
+struct BuiltinFile {
+    content_type: &'static str,
+    /// File contents; without any the response is `204 No Content`
+    body: Option<Bytes>,
+}
+
+impl BuiltinFile {
+    /// Load the configured file, falling back to the default contents
+    fn load(path: Option<&str>, content_type: &'static str, default: Option<Bytes>) -> Self {
+        let body = match path.map(|path| (path, std::fs::read(path))) {
+            Some((_, Ok(contents))) => Some(Bytes::from(contents)),
+            Some((path, Err(e))) => {
+                error!(
+                    "Failed to read {}, serving the default instead: {}",
+                    path, e
+                );
+                default
+            }
+            None => default,
+        };
+        Self { content_type, body }
+    }
+}
+
+impl IntoResponse for BuiltinFile {
+    fn into_response(self) -> Response {
+        let cache_control = (
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(BUILTIN_FILE_CACHE_CONTROL),
+        );
+        match self.body {
+            Some(body) => (
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(self.content_type),
+                    ),
+                    cache_control,
+                ],
+                body,
+            )
+                .into_response(),
+            None => (StatusCode::NO_CONTENT, [cache_control]).into_response(),
+        }
+    }
+}
+
+/// Media type of a favicon file, from its extension
+fn favicon_content_type(path: Option<&str>) -> &'static str {
+    let extension = path
+        .and_then(|path| path.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("gif") => "image/gif",
+        _ => "image/x-icon",
+    }
+}
+
+/// Router serving the built-in files enabled in the server configuration
+///
+/// Its routes are more specific than the proxy's catch-all, so they answer
+/// before any route is matched.
+pub fn builtin_files_router(server: &ServerConfig) -> Router {
+    let mut router = Router::new();
+    if server.serve_favicon {
+        let path = server.favicon_file.as_deref();
+        let favicon = BuiltinFile::load(path, favicon_content_type(path), None);
+        router = router.route(FAVICON_ENDPOINT, get(move || async move { favicon }));
+    }
+    if server.serve_robots_txt {
+        let robots = BuiltinFile::load(
+            server.robots_txt_file.as_deref(),
+            "text/plain; charset=utf-8",
+            Some(Bytes::from_static(DEFAULT_ROBOTS_TXT.as_bytes())),
+        );
+        router = router.route(ROBOTS_TXT_ENDPOINT, get(move || async move { robots }));
+    }
+    router
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn get_path(router: Router, path: &str) -> Response {
+        let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        router.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_defaults_are_served_when_enabled() {
+        let server = ServerConfig {
+            serve_favicon: true,
+            serve_robots_txt: true,
+            ..Default::default()
+        };
+
+        let response = get_path(builtin_files_router(&server), FAVICON_ENDPOINT).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            BUILTIN_FILE_CACHE_CONTROL
+        );
+
+        let response = get_path(builtin_files_router(&server), ROBOTS_TXT_ENDPOINT).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, DEFAULT_ROBOTS_TXT);
+    }
+
+    #[tokio::test]
+    async fn test_configured_files_are_served() {
+        let dir = tempfile::tempdir().unwrap();
+        let favicon = dir.path().join("favicon.png");
+        let robots = dir.path().join("robots.txt");
+        std::fs::write(&favicon, b"\x89PNG").unwrap();
+        std::fs::write(&robots, "User-agent: *\nAllow: /\n").unwrap();
+        let server = ServerConfig {
+            serve_favicon: true,
+            favicon_file: Some(favicon.to_str().unwrap().to_string()),
+            serve_robots_txt: true,
+            robots_txt_file: Some(robots.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        let response = get_path(builtin_files_router(&server), FAVICON_ENDPOINT).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"\x89PNG");
+
+        let response = get_path(builtin_files_router(&server), ROBOTS_TXT_ENDPOINT).await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "User-agent: *\nAllow: /\n");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_files_are_not_routed() {
+        let router = builtin_files_router(&ServerConfig::default());
+        let response = get_path(router.clone(), FAVICON_ENDPOINT).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = get_path(router, ROBOTS_TXT_ENDPOINT).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
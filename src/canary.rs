@@ -0,0 +1,119 @@
+/// Deterministic percentage-based canary routing
+///
+/// Requests are bucketed by hashing a stable key (the client IP or a request
+/// header), so the same client consistently lands in or out of the canary for
+/// a given percentage. This is independent of any random load balancing.
+use axum::http::HeaderMap;
+use std::net::IpAddr;
+
+/// Number of buckets the key space is divided into (0.01% granularity)
+const CANARY_BUCKETS: u64 = 10_000;
+
+/// Request attribute used to assign a client to a canary bucket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanaryKey {
+    /// The client's IP address
+    ClientIp,
+    /// The value of the named request header
+    Header(String),
+}
+
+impl CanaryKey {
+    /// Parse a key specification: "client_ip" or "header:<name>"
+    pub fn parse(spec: &str) -> Option<Self> {
+        match spec {
+            "client_ip" => Some(Self::ClientIp),
+            _ => spec
+                .strip_prefix("header:")
+                .filter(|name| axum::http::HeaderName::from_bytes(name.as_bytes()).is_ok())
+                .map(|name| Self::Header(name.to_ascii_lowercase())),
+        }
+    }
+
+    /// Extract this key's value from the request
+    pub fn extract(&self, headers: &HeaderMap, client_ip: Option<IpAddr>) -> Option<String> {
+        match self {
+            Self::ClientIp => client_ip.map(|ip| ip.to_string()),
+            Self::Header(name) => headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+}
+
+/// Get the bucket (0 - 9999) a key falls into
+pub fn bucket(key: &str) -> u64 {
+    fnv1a(key.as_bytes()) % CANARY_BUCKETS
+}
+
+/// Check whether a key falls within the canary for the given percentage
+pub fn in_canary(key: &str, percent: f64) -> bool {
+    (bucket(key) as f64) < percent * (CANARY_BUCKETS as f64 / 100.0)
+}
+
+/// 64-bit FNV-1a hash, stable across processes and releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_key_always_resolves_to_same_bucket() {
+        for key in ["10.0.0.1", "user-42", ""] {
+            let first = bucket(key);
+            assert!((0..10).all(|_| bucket(key) == first));
+            assert_eq!(in_canary(key, 5.0), in_canary(key, 5.0));
+        }
+    }
+
+    #[test]
+    fn test_aggregate_percentage_is_correct() {
+        let keys = 100_000;
+        let selected = (0..keys)
+            .filter(|i| in_canary(&format!("client-{i}"), 5.0))
+            .count();
+        let percent = selected as f64 * 100.0 / keys as f64;
+
+        assert!((4.5..=5.5).contains(&percent), "got {percent}%");
+    }
+
+    #[test]
+    fn test_boundary_percentages() {
+        assert!(!in_canary("anyone", 0.0));
+        assert!(in_canary("anyone", 100.0));
+    }
+
+    #[test]
+    fn test_canary_key_parse_and_extract() {
+        assert_eq!(CanaryKey::parse("client_ip"), Some(CanaryKey::ClientIp));
+        assert_eq!(
+            CanaryKey::parse("header:X-User-Id"),
+            Some(CanaryKey::Header("x-user-id".to_string()))
+        );
+        assert_eq!(CanaryKey::parse("header:"), None);
+        assert_eq!(CanaryKey::parse("cookie"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-user-id", "42".parse().unwrap());
+        let ip: IpAddr = "192.168.1.5".parse().unwrap();
+
+        assert_eq!(
+            CanaryKey::ClientIp.extract(&headers, Some(ip)),
+            Some("192.168.1.5".to_string())
+        );
+        assert_eq!(CanaryKey::ClientIp.extract(&headers, None), None);
+        assert_eq!(
+            CanaryKey::Header("x-user-id".to_string()).extract(&headers, None),
+            Some("42".to_string())
+        );
+    }
+}
@@ -1,13 +1,23 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
 
-use crate::config::GatewayConfig;
+use crate::bench::{run_bench, BenchOptions};
+use crate::config::{GatewayConfig, ValidationReport};
 use crate::constants::{
-    CERT_FILE_EXTENSION, DEFAULT_CERT_DIR, DEFAULT_CONFIG_FILE, DEFAULT_HOSTNAME,
-    KEY_FILE_EXTENSION,
+    CERT_FILE_EXTENSION, CONFIG_POLL_DEBOUNCE_MS, CONTROL_SOCKET_DIR_ENV,
+    DEFAULT_BENCH_CONCURRENCY, DEFAULT_BENCH_REQUESTS, DEFAULT_CERT_DIR, DEFAULT_CONFIG_FILE,
+    DEFAULT_HOSTNAME, DEFAULT_TIMEOUT_MS, KEY_FILE_EXTENSION, VALIDATE_WATCH_INTERVAL_MS,
 };
 use crate::error::{FerragateError, FerragateResult};
+use crate::proxy::{build_target_url, ProxyState};
+use crate::reload::FileWatcher;
+use crate::server::Instance;
 
 /// Ferragate API Gateway CLI
 ///
@@ -39,6 +49,10 @@ pub enum Commands {
         /// Override server port
         #[arg(short, long, help = "Override the port from config")]
         port: Option<u16>,
+
+        /// Name telling this instance apart from others using the same config file
+        #[arg(long)]
+        instance_name: Option<String>,
     },
 
     /// Validate configuration file
@@ -46,6 +60,18 @@ pub enum Commands {
         /// Configuration file path
         #[arg(short, long, default_value = DEFAULT_CONFIG_FILE)]
         config: PathBuf,
+
+        /// Print the fully-resolved configuration with all defaults filled in
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "toml")]
+        print_effective: Option<OutputFormat>,
+
+        /// Format of the validation result
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Validate again whenever the file changes, until interrupted
+        #[arg(long, conflicts_with = "print_effective")]
+        watch: bool,
     },
 
     /// Generate example configuration file
@@ -74,6 +100,50 @@ pub enum Commands {
         force: bool,
     },
 
+    /// Show which route a request would match, without sending any traffic
+    RouteTest {
+        /// Configuration file path
+        #[arg(short, long, default_value = DEFAULT_CONFIG_FILE)]
+        config: PathBuf,
+
+        /// Request method, e.g. GET
+        method: String,
+
+        /// Request path, optionally with a query string
+        path: String,
+
+        /// Request header as NAME:VALUE, for routes with header_match rules
+        #[arg(short = 'H', long = "header")]
+        headers: Vec<String>,
+    },
+
+    /// Fire requests through a local gateway and report latencies (rough tool, not a real benchmark)
+    Bench {
+        /// Configuration file path
+        #[arg(short, long, default_value = DEFAULT_CONFIG_FILE)]
+        config: PathBuf,
+
+        /// Request path, optionally with a query string
+        #[arg(long, default_value = "/")]
+        path: String,
+
+        /// Request method
+        #[arg(short = 'X', long, default_value = "GET")]
+        method: String,
+
+        /// Total number of requests to send
+        #[arg(short = 'n', long, default_value_t = DEFAULT_BENCH_REQUESTS)]
+        requests: usize,
+
+        /// Number of requests in flight at once
+        #[arg(long, default_value_t = DEFAULT_BENCH_CONCURRENCY)]
+        concurrency: usize,
+
+        /// Request header as NAME:VALUE
+        #[arg(short = 'H', long = "header")]
+        headers: Vec<String>,
+    },
+
     /// Stop the running gateway server
     Stop {
         /// Configuration file path (to identify the correct server instance)
@@ -83,40 +153,137 @@ pub enum Commands {
         /// Force stop (kill process immediately)
         #[arg(long, help = "Force immediate shutdown without graceful stop")]
         force: bool,
+
+        /// Seconds to wait for a graceful stop before killing the process (0 kills at once)
+        #[arg(long)]
+        timeout: Option<u64>,
+
+        /// Host override the instance was started with
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port override the instance was started with
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// Name the instance was started with
+        #[arg(long)]
+        instance_name: Option<String>,
+    },
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
     },
 }
 
+/// Output format for printed configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Toml,
+    Json,
+}
+
+/// Output format for the validation result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Human-readable log lines
+    Text,
+    /// A single JSON object for CI pipelines
+    Json,
+}
+
 impl Cli {
     pub fn parse_args() -> Self {
         Self::parse()
     }
 
+    /// Whether the command prints a script to stdout that log lines would corrupt
+    pub fn writes_script_to_stdout(&self) -> bool {
+        matches!(self.command, Commands::Completions { .. })
+    }
+
     /// Execute the CLI command
     ///
     /// Dispatches to the appropriate handler function based on the command type.
     pub async fn execute(self) -> FerragateResult<()> {
         match self.command {
-            Commands::Start { config, host, port } => start_server(config, host, port).await,
-            Commands::Validate { config } => validate_config(config),
+            Commands::Start {
+                config,
+                host,
+                port,
+                instance_name,
+            } => start_server(config, host, port, instance_name).await,
+            Commands::Validate {
+                config,
+                print_effective,
+                format,
+                watch,
+            } => {
+                if watch {
+                    watch_config(config, format).await
+                } else {
+                    validate_config(config, print_effective, format)
+                }
+            }
             Commands::Init { output, force } => init_config(output, force),
             Commands::GenCerts {
                 output_dir,
                 hostname,
                 force,
             } => generate_certs(output_dir, hostname, force),
-            Commands::Stop { config, force } => stop_server(config, force).await,
+            Commands::RouteTest {
+                config,
+                method,
+                path,
+                headers,
+            } => route_test(config, method, path, headers),
+            Commands::Bench {
+                config,
+                path,
+                method,
+                requests,
+                concurrency,
+                headers,
+            } => bench(config, path, method, requests, concurrency, headers).await,
+            Commands::Stop {
+                config,
+                force,
+                timeout,
+                host,
+                port,
+                instance_name,
+            } => {
+                let instance = stopped_instance(&config, host, port, instance_name);
+                stop_server(instance, force, timeout).await
+            }
+            Commands::Completions { shell } => {
+                write_completions(shell, &mut std::io::stdout());
+                Ok(())
+            }
         }
     }
 }
 
+/// Write the completion script for `shell`, covering every subcommand and flag
+pub fn write_completions(shell: Shell, out: &mut dyn Write) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
+}
+
 async fn start_server(
     config_path: PathBuf,
     host_override: Option<String>,
     port_override: Option<u16>,
+    instance_name: Option<String>,
 ) -> FerragateResult<()> {
     info!("Starting FerraGate server...");
 
     let mut config = GatewayConfig::from_file(config_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE))?;
+    let overrides_bind = host_override.is_some() || port_override.is_some();
 
     // Apply CLI overrides
     if let Some(host) = host_override {
@@ -129,14 +296,90 @@ async fn start_server(
         config.server.port = port;
     }
 
+    let bind = overrides_bind.then_some((config.server.host.as_str(), config.server.port));
+    let instance = select_instance(config_path.to_str(), instance_name.as_deref(), bind)
+        .with_socket_dir(control_socket_dir(config.server.control_socket_dir.clone()));
+
     // Start the server
-    crate::server::start_server(config, config_path.to_str()).await
+    crate::server::start_server(config, &instance).await
 }
 
-fn validate_config(config_path: PathBuf) -> FerragateResult<()> {
+/// Instance a start or stop command addresses
+///
+/// An explicit instance name wins. Otherwise an instance whose host or port was
+/// overridden on the command line is named after its effective bind address, so
+/// gateways sharing a configuration file do not share a PID file or control socket.
+fn select_instance(
+    config_path: Option<&str>,
+    instance_name: Option<&str>,
+    bind: Option<(&str, u16)>,
+) -> Instance {
+    match (instance_name, bind) {
+        (Some(name), _) => Instance::new(config_path, Some(name)),
+        (None, Some((host, port))) => Instance::for_bind_address(config_path, host, port),
+        (None, None) => Instance::new(config_path, None),
+    }
+}
+
+/// Directory for the control socket, with the environment variable winning over the configuration
+fn control_socket_dir(configured: Option<String>) -> Option<String> {
+    std::env::var(CONTROL_SOCKET_DIR_ENV)
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .or(configured)
+}
+
+/// Instance a stop command addresses, given the overrides it was started with
+///
+/// Overrides left out are filled in from the configuration file, as they were
+/// when the instance started.
+fn stopped_instance(
+    config_path: &Path,
+    host: Option<String>,
+    port: Option<u16>,
+    instance_name: Option<String>,
+) -> Instance {
+    let config_path = config_path.to_str();
+    let server = GatewayConfig::read_file(config_path.unwrap_or(DEFAULT_CONFIG_FILE))
+        .map(|config| config.server)
+        .unwrap_or_default();
+    let bind = (host.is_some() || port.is_some()).then(|| {
+        (
+            host.unwrap_or_else(|| server.host.clone()),
+            port.unwrap_or(server.port),
+        )
+    });
+    select_instance(
+        config_path,
+        instance_name.as_deref(),
+        bind.as_ref().map(|(host, port)| (host.as_str(), *port)),
+    )
+    .with_socket_dir(control_socket_dir(server.control_socket_dir))
+}
+
+fn validate_config(
+    config_path: PathBuf,
+    print_effective: Option<OutputFormat>,
+    format: ReportFormat,
+) -> FerragateResult<()> {
     info!("Validating configuration...");
 
-    let config = GatewayConfig::from_file(config_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE))?;
+    let path = config_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE);
+    if format == ReportFormat::Json && print_effective.is_none() {
+        let report = validation_report(path);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return match report.error {
+            Some(error) => Err(FerragateError::validation(error)),
+            None => Ok(()),
+        };
+    }
+
+    let config = GatewayConfig::from_file(path)?;
+
+    if let Some(format) = print_effective {
+        println!("{}", render_effective_config(&config, format)?);
+        return Ok(());
+    }
 
     info!("✅ Configuration is valid!");
     info!("Server: {}:{}", config.server.host, config.server.port);
@@ -149,6 +392,169 @@ fn validate_config(config_path: PathBuf) -> FerragateResult<()> {
     Ok(())
 }
 
+/// Validate a configuration file every time it changes, until Ctrl+C
+async fn watch_config(config_path: PathBuf, format: ReportFormat) -> FerragateResult<()> {
+    let path = config_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE);
+    let shutdown = CancellationToken::new();
+    let interrupt = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            interrupt.cancel();
+        }
+    });
+
+    info!("Watching {} for changes, press Ctrl+C to stop", path);
+    watch_validation(
+        path,
+        format,
+        Duration::from_millis(VALIDATE_WATCH_INTERVAL_MS),
+        Duration::from_millis(CONFIG_POLL_DEBOUNCE_MS),
+        &shutdown,
+        |output| println!("{output}"),
+    )
+    .await;
+    Ok(())
+}
+
+/// Validate `path` now and after every change, handing each result to `emit`
+async fn watch_validation(
+    path: &str,
+    format: ReportFormat,
+    interval: Duration,
+    debounce: Duration,
+    shutdown: &CancellationToken,
+    mut emit: impl FnMut(String),
+) {
+    let mut watcher = FileWatcher::new(path, interval, debounce);
+    loop {
+        emit(render_watch_report(&validation_report(path), format));
+        if !watcher.changed(shutdown).await {
+            return;
+        }
+    }
+}
+
+/// One validation result of watch mode: a line of JSON, or a short summary
+fn render_watch_report(report: &ValidationReport, format: ReportFormat) -> String {
+    if format == ReportFormat::Json {
+        return serde_json::to_string(report).unwrap_or_default();
+    }
+    let mut output = match &report.error {
+        None => format!("✅ Configuration is valid ({} routes)", report.routes.len()),
+        Some(error) => format!("❌ Configuration is invalid: {error}"),
+    };
+    for warning in &report.warnings {
+        output.push_str(&format!("\n⚠️  {warning}"));
+    }
+    output
+}
+
+/// Validate a configuration file, collecting the outcome instead of only logging it
+fn validation_report(path: &str) -> ValidationReport {
+    ValidationReport::new(GatewayConfig::read_file(path))
+}
+
+/// Render the effective configuration in the requested format
+fn render_effective_config(
+    config: &GatewayConfig,
+    format: OutputFormat,
+) -> FerragateResult<String> {
+    let effective = config.effective();
+    match format {
+        OutputFormat::Toml => Ok(toml::to_string_pretty(&effective)?),
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(&effective)?),
+    }
+}
+
+fn route_test(
+    config_path: PathBuf,
+    method: String,
+    path: String,
+    headers: Vec<String>,
+) -> FerragateResult<()> {
+    let config = GatewayConfig::from_file(config_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE))?;
+    println!("{}", render_route_test(config, &method, &path, &headers)?);
+    Ok(())
+}
+
+/// Describe the route a request would match, its target URL and timeout
+fn render_route_test(
+    config: GatewayConfig,
+    method: &str,
+    path_and_query: &str,
+    headers: &[String],
+) -> FerragateResult<String> {
+    let method = method.to_uppercase();
+    let (path, query) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+    let headers = parse_headers(headers)?;
+
+    let state = ProxyState::new(config);
+    let Some((index, route)) =
+        state.find_matching_route_index(path, &method, &headers, query, None)
+    else {
+        return Ok(format!("No route matches {method} {path_and_query}"));
+    };
+
+    let default_timeout = state.config.server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+    Ok(format!(
+        "Matched route {}: {} -> {}\nTarget URL: {}\nEffective timeout: {}ms",
+        index + 1,
+        route.path,
+        route.upstream,
+        build_target_url(&route.upstream, route, path, query),
+        route.effective_timeout(default_timeout)
+    ))
+}
+
+async fn bench(
+    config_path: PathBuf,
+    path: String,
+    method: String,
+    requests: usize,
+    concurrency: usize,
+    headers: Vec<String>,
+) -> FerragateResult<()> {
+    let config = GatewayConfig::from_file(config_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE))?;
+    let method = Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| FerragateError::validation(format!("Invalid HTTP method: {method}")))?;
+    let options = BenchOptions {
+        method,
+        path,
+        headers: parse_headers(&headers)?,
+        requests,
+        concurrency,
+    };
+
+    info!(
+        "Sending {} requests to {} {} with concurrency {}...",
+        options.requests, options.method, options.path, options.concurrency
+    );
+    println!("{}", run_bench(config, options).await?.render());
+    Ok(())
+}
+
+/// Parse NAME:VALUE header arguments
+fn parse_headers(headers: &[String]) -> FerragateResult<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for header in headers {
+        let parsed = header.split_once(':').and_then(|(name, value)| {
+            Some((
+                HeaderName::from_bytes(name.trim().as_bytes()).ok()?,
+                HeaderValue::from_str(value.trim()).ok()?,
+            ))
+        });
+        let Some((name, value)) = parsed else {
+            return Err(FerragateError::validation(format!(
+                "Invalid header '{header}', expected NAME:VALUE"
+            )));
+        };
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
 fn init_config(output_path: PathBuf, force: bool) -> FerragateResult<()> {
     let path_str = output_path.to_str().unwrap_or(DEFAULT_CONFIG_FILE);
 
@@ -205,9 +611,14 @@ fn generate_certs(output_dir: PathBuf, hostname: String, force: bool) -> Ferraga
     Ok(())
 }
 
-async fn stop_server(config_path: PathBuf, force: bool) -> FerragateResult<()> {
+async fn stop_server(
+    instance: Instance,
+    force: bool,
+    timeout_secs: Option<u64>,
+) -> FerragateResult<()> {
     // Delegate to server module - CLI should not contain business logic
-    crate::server::stop_server(config_path.to_str(), force).await
+    let timeout = timeout_secs.map(std::time::Duration::from_secs);
+    crate::server::stop_server(&instance, force, timeout).await
 }
 
 #[cfg(test)]
@@ -224,12 +635,15 @@ mod tests {
                 config: PathBuf::from("test.toml"),
                 host: Some("localhost".to_string()),
                 port: Some(8080),
+                instance_name: None,
             },
         };
 
         // Verify the command structure
         match cli.command {
-            Commands::Start { config, host, port } => {
+            Commands::Start {
+                config, host, port, ..
+            } => {
                 assert_eq!(config, PathBuf::from("test.toml"));
                 assert_eq!(host, Some("localhost".to_string()));
                 assert_eq!(port, Some(8080));
@@ -246,6 +660,7 @@ mod tests {
                 config: PathBuf::from("custom.toml"),
                 host: None,
                 port: None,
+                instance_name: None,
             },
         };
         assert!(matches!(start_cli.command, Commands::Start { .. }));
@@ -254,6 +669,9 @@ mod tests {
         let validate_cli = Cli {
             command: Commands::Validate {
                 config: PathBuf::from("test.toml"),
+                print_effective: None,
+                format: ReportFormat::Text,
+                watch: false,
             },
         };
         assert!(matches!(validate_cli.command, Commands::Validate { .. }));
@@ -277,11 +695,69 @@ mod tests {
         };
         assert!(matches!(gencerts_cli.command, Commands::GenCerts { .. }));
 
+        // Test RouteTest command
+        let route_test_cli = Cli::try_parse_from([
+            "ferragate",
+            "route-test",
+            "--config",
+            "gateway.toml",
+            "GET",
+            "/api/v1/users",
+            "-H",
+            "x-tenant:acme",
+        ])
+        .unwrap();
+        match route_test_cli.command {
+            Commands::RouteTest {
+                method,
+                path,
+                headers,
+                ..
+            } => {
+                assert_eq!(method, "GET");
+                assert_eq!(path, "/api/v1/users");
+                assert_eq!(headers, ["x-tenant:acme"]);
+            }
+            _ => panic!("Expected RouteTest command"),
+        }
+
+        // Test Bench command
+        let bench_cli = Cli::try_parse_from([
+            "ferragate",
+            "bench",
+            "--path",
+            "/api",
+            "--requests",
+            "100",
+            "--concurrency",
+            "5",
+        ])
+        .unwrap();
+        match bench_cli.command {
+            Commands::Bench {
+                path,
+                method,
+                requests,
+                concurrency,
+                ..
+            } => {
+                assert_eq!(path, "/api");
+                assert_eq!(method, "GET");
+                assert_eq!(requests, 100);
+                assert_eq!(concurrency, 5);
+            }
+            _ => panic!("Expected Bench command"),
+        }
+
         // Test Stop command
         let stop_cli = Cli {
             command: Commands::Stop {
                 config: PathBuf::from("test.toml"),
                 force: false,
+                timeout: None,
+                host: None,
+                port: None,
+                instance_name: None,
             },
         };
         assert!(matches!(stop_cli.command, Commands::Stop { .. }));
@@ -307,14 +783,14 @@ methods = ["GET", "POST"]
         fs::write(&config_path, config_content).unwrap();
 
         // Test validation
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_validate_config_invalid_file() {
         let config_path = PathBuf::from("nonexistent.toml");
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_err());
     }
 
@@ -331,7 +807,7 @@ port = invalid
 "#;
         fs::write(&config_path, malformed_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_err());
     }
 
@@ -353,7 +829,7 @@ methods = ["GET"]
 "#;
         fs::write(&config_path, config_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_err());
     }
 
@@ -377,7 +853,7 @@ json = false
 "#;
         fs::write(&config_path, config_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         // Since routes field is required in the struct but not marked as #[serde(default)],
         // missing routes will cause a deserialization error
         assert!(result.is_err());
@@ -408,7 +884,7 @@ methods = ["GET"]
 "#;
         fs::write(&config_path, config_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_ok()); // Should be valid even with missing cert files
     }
 
@@ -579,6 +1055,7 @@ methods = ["GET"]
                 config: PathBuf::from("nonexistent_config.toml"),
                 host: None,
                 port: None,
+                instance_name: None,
             },
         };
 
@@ -609,6 +1086,7 @@ methods = ["GET"]
                 config: config_path,
                 host: Some("0.0.0.0".to_string()),
                 port: Some(9090),
+                instance_name: None,
             },
         };
 
@@ -623,6 +1101,7 @@ methods = ["GET"]
                 config: _,
                 host,
                 port,
+                ..
             } => {
                 assert_eq!(host, Some("0.0.0.0".to_string()));
                 assert_eq!(port, Some(9090));
@@ -652,6 +1131,9 @@ methods = ["GET", "POST"]
         let cli = Cli {
             command: Commands::Validate {
                 config: config_path,
+                print_effective: None,
+                format: ReportFormat::Text,
+                watch: false,
             },
         };
 
@@ -664,6 +1146,9 @@ methods = ["GET", "POST"]
         let cli = Cli {
             command: Commands::Validate {
                 config: PathBuf::from("nonexistent.toml"),
+                print_effective: None,
+                format: ReportFormat::Text,
+                watch: false,
             },
         };
 
@@ -768,6 +1253,9 @@ methods = ["GET", "POST"]
         let cli = Cli {
             command: Commands::Validate {
                 config: PathBuf::from("test.toml"),
+                print_effective: None,
+                format: ReportFormat::Text,
+                watch: false,
             },
         };
 
@@ -782,11 +1270,15 @@ methods = ["GET", "POST"]
             config: PathBuf::from("test.toml"),
             host: Some("localhost".to_string()),
             port: Some(8080),
+            instance_name: None,
         };
         assert!(matches!(start_cmd, Commands::Start { .. }));
 
         let validate_cmd = Commands::Validate {
             config: PathBuf::from("test.toml"),
+            print_effective: None,
+            format: ReportFormat::Text,
+            watch: false,
         };
         assert!(matches!(validate_cmd, Commands::Validate { .. }));
 
@@ -806,8 +1298,102 @@ methods = ["GET", "POST"]
         let stop_cmd = Commands::Stop {
             config: PathBuf::from("test.toml"),
             force: false,
+            timeout: None,
+            host: None,
+            port: None,
+            instance_name: None,
         };
         assert!(matches!(stop_cmd, Commands::Stop { .. }));
+
+        let completions_cmd = Commands::Completions { shell: Shell::Bash };
+        assert!(matches!(completions_cmd, Commands::Completions { .. }));
+    }
+
+    #[test]
+    fn test_completions_for_every_shell() {
+        let cli = Cli::try_parse_from(["ferragate", "completions", "zsh"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Completions { shell: Shell::Zsh }
+        ));
+        assert!(cli.writes_script_to_stdout());
+        assert!(Cli::try_parse_from(["ferragate", "completions", "tcsh"]).is_err());
+
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let mut script = Vec::new();
+            write_completions(shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(!script.is_empty(), "{shell}");
+            assert!(script.contains("route-test"), "{shell}");
+            assert!(script.contains("print-effective"), "{shell}");
+        }
+    }
+
+    // Helper to load the config used by the route-test tests
+    fn route_test_config() -> GatewayConfig {
+        toml::from_str(
+            r#"
+[server]
+timeout_ms = 30000
+
+[[routes]]
+path = "/api/v1/users/*"
+upstream = "http://user-service:8080"
+methods = ["GET", "POST"]
+strip_path = true
+timeout_ms = 15000
+
+[[routes]]
+path = "/api/*"
+upstream = "http://tenant-service:8080"
+
+[[routes.header_match]]
+name = "x-tenant"
+regex = "^acme$"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_route_test_reports_match() {
+        let output = render_route_test(
+            route_test_config(),
+            "get",
+            "/api/v1/users/42?expand=true",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "Matched route 1: /api/v1/users/* -> http://user-service:8080\n\
+             Target URL: http://user-service:8080/42?expand=true\n\
+             Effective timeout: 15000ms"
+        );
+
+        let output = render_route_test(
+            route_test_config(),
+            "DELETE",
+            "/api/orders",
+            &["X-Tenant: acme".to_string()],
+        )
+        .unwrap();
+        assert!(output.starts_with("Matched route 2: /api/* -> http://tenant-service:8080"));
+        assert!(output.ends_with("Effective timeout: 30000ms"));
+    }
+
+    #[test]
+    fn test_route_test_reports_no_match() {
+        let output = render_route_test(route_test_config(), "DELETE", "/api/orders", &[]).unwrap();
+        assert_eq!(output, "No route matches DELETE /api/orders");
+
+        let result = render_route_test(
+            route_test_config(),
+            "GET",
+            "/api/orders",
+            &["missing-colon".to_string()],
+        );
+        assert!(result.is_err());
     }
 
     #[tokio::test]
@@ -918,7 +1504,7 @@ methods = ["GET", "HEAD"]
 "#;
         fs::write(&config_path, config_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_ok());
     }
 
@@ -940,7 +1526,7 @@ methods = ["GET", "INVALID_METHOD"]
 "#;
         fs::write(&config_path, config_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
@@ -966,7 +1552,7 @@ methods = ["GET"]
 "#;
         fs::write(&config_path, config_content).unwrap();
 
-        let result = validate_config(config_path);
+        let result = validate_config(config_path, None, ReportFormat::Text);
         assert!(result.is_err());
         let error_msg = result.unwrap_err().to_string();
         assert!(error_msg.contains("Route path cannot be empty"));
@@ -1029,6 +1615,9 @@ methods = ["GET"]
         let validate_cli = Cli {
             command: Commands::Validate {
                 config: config_path.clone(),
+                print_effective: None,
+                format: ReportFormat::Text,
+                watch: false,
             },
         };
         let result = validate_cli.execute().await;
@@ -1062,6 +1651,10 @@ methods = ["GET"]
             command: Commands::Stop {
                 config: config_path.clone(),
                 force: false,
+                timeout: None,
+                host: None,
+                port: None,
+                instance_name: None,
             },
         };
         let result = stop_cli.execute().await;
@@ -1075,7 +1668,7 @@ methods = ["GET"]
         let temp_dir = tempdir().unwrap();
         let config_path = temp_dir.path().join("stop_test.toml");
 
-        let result = stop_server(config_path, false).await;
+        let result = stop_server(Instance::new(config_path.to_str(), None), false, None).await;
         // Should succeed even if no processes found
         assert!(result.is_ok());
     }
@@ -1086,7 +1679,7 @@ methods = ["GET"]
         let temp_dir = tempdir().unwrap();
         let config_path = temp_dir.path().join("force_stop_test.toml");
 
-        let result = stop_server(config_path, true).await;
+        let result = stop_server(Instance::new(config_path.to_str(), None), true, None).await;
         // Should succeed even with force flag when no processes found
         assert!(result.is_ok());
     }
@@ -1101,7 +1694,7 @@ methods = ["GET"]
         // Create a fake PID file with a non-existent process ID
         fs::write(&pid_file, "99999").unwrap();
 
-        let result = stop_server(config_path, false).await;
+        let result = stop_server(Instance::new(config_path.to_str(), None), false, None).await;
         // Should handle non-existent process gracefully
         // The exact behavior depends on the system, but it shouldn't panic
         assert!(result.is_ok() || result.is_err());
@@ -1121,6 +1714,10 @@ methods = ["GET"]
             command: Commands::Stop {
                 config: config_path,
                 force: false,
+                timeout: None,
+                host: None,
+                port: None,
+                instance_name: None,
             },
         };
 
@@ -1137,10 +1734,274 @@ methods = ["GET"]
             command: Commands::Stop {
                 config: config_path,
                 force: true,
+                timeout: None,
+                host: None,
+                port: None,
+                instance_name: None,
             },
         };
 
         let result = cli.execute().await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_render_effective_config_includes_defaults() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+"#,
+        )
+        .unwrap();
+
+        let toml_output = render_effective_config(&config, OutputFormat::Toml).unwrap();
+        assert!(toml_output.contains("host = \"0.0.0.0\""));
+        assert!(toml_output.contains("timeout_ms = 30000"));
+        assert!(toml_output.contains("retry_backoff_ms = 100"));
+
+        let json_output = render_effective_config(&config, OutputFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json_output).unwrap();
+        assert_eq!(parsed["server"]["port"], 3000);
+        assert_eq!(parsed["routes"][0]["timeout_ms"], 30000);
+    }
+
+    #[test]
+    fn test_validation_report_json_for_valid_config() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://api:8080"
+methods = ["GET"]
+
+[server.tls]
+enabled = true
+port = 8443
+cert_file = "/nonexistent/server.crt"
+key_file = "/nonexistent/server.key"
+"#,
+        )
+        .unwrap();
+
+        let report = validation_report(file.path().to_str().unwrap());
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["valid"], true);
+        assert!(json["error"].is_null());
+        assert_eq!(
+            json["routes"],
+            serde_json::json!([{"path": "/api/*", "upstream": "http://api:8080", "methods": ["GET"]}])
+        );
+        let warnings = json["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0]
+            .as_str()
+            .unwrap()
+            .starts_with("TLS certificate file not found"));
+    }
+
+    #[test]
+    fn test_validation_report_json_for_invalid_config() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            "[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"not a url\"\n",
+        )
+        .unwrap();
+
+        let report = validation_report(file.path().to_str().unwrap());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["valid"], false);
+        assert!(json["error"]
+            .as_str()
+            .unwrap()
+            .contains("Invalid upstream URL"));
+        assert_eq!(json["routes"][0]["upstream"], "not a url");
+
+        // The command fails so CI sees a non-zero exit code
+        let result = validate_config(file.path().to_path_buf(), None, ReportFormat::Json);
+        assert!(result.is_err());
+
+        std::fs::write(file.path(), "not toml [").unwrap();
+        let json = serde_json::to_value(validation_report(file.path().to_str().unwrap())).unwrap();
+        assert_eq!(json["valid"], false);
+        assert!(json["error"].as_str().unwrap().contains("Failed to parse"));
+        assert_eq!(json["routes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_validate_print_effective_flag_parsing() {
+        let cli = Cli::try_parse_from(["ferragate", "validate", "--print-effective"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Validate {
+                print_effective: Some(OutputFormat::Toml),
+                ..
+            }
+        ));
+
+        let cli =
+            Cli::try_parse_from(["ferragate", "validate", "--print-effective", "json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Validate {
+                print_effective: Some(OutputFormat::Json),
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["ferragate", "validate", "--format", "json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Validate {
+                print_effective: None,
+                format: ReportFormat::Json,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_stop_timeout_flag_parsing() {
+        let cli = Cli::try_parse_from(["ferragate", "stop", "--timeout", "3"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Stop {
+                timeout: Some(3),
+                force: false,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["ferragate", "stop"]).unwrap();
+        assert!(matches!(cli.command, Commands::Stop { timeout: None, .. }));
+    }
+
+    #[test]
+    fn test_instances_from_same_config_do_not_collide() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("gateway.toml");
+        fs::write(
+            &config_path,
+            "[server]\nhost = \"127.0.0.1\"\nport = 8080\n\n[[routes]]\npath = \"/*\"\nupstream = \"http://localhost:3000\"\n",
+        )
+        .unwrap();
+        let path = config_path.to_str();
+
+        let default = select_instance(path, None, None);
+        let first = select_instance(path, None, Some(("127.0.0.1", 8081)));
+        let second = select_instance(path, None, Some(("127.0.0.1", 8082)));
+        let named = select_instance(path, Some("canary"), Some(("127.0.0.1", 8081)));
+        let instances = [&default, &first, &second, &named];
+        for (i, a) in instances.iter().enumerate() {
+            for b in &instances[i + 1..] {
+                assert_ne!(a.pid_file(), b.pid_file());
+                assert_ne!(a.control_socket_path(), b.control_socket_path());
+            }
+        }
+
+        // Stop fills in the host from the config file, matching the started instance
+        assert_eq!(
+            stopped_instance(&config_path, None, Some(8081), None),
+            first
+        );
+        assert_eq!(stopped_instance(&config_path, None, None, None), default);
+        assert_eq!(
+            stopped_instance(&config_path, None, None, Some("canary".to_string())),
+            named
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_revalidates_when_file_changes() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("gateway.toml");
+        let path = config_path.to_str().unwrap().to_string();
+        let write_upstream = |upstream: &str| {
+            fs::write(
+                &config_path,
+                format!("[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"{upstream}\"\n"),
+            )
+            .unwrap();
+        };
+        write_upstream("http://backend:3000");
+
+        let shutdown = CancellationToken::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                watch_validation(
+                    &path,
+                    ReportFormat::Text,
+                    Duration::from_millis(20),
+                    Duration::from_millis(20),
+                    &shutdown,
+                    |output| tx.send(output).unwrap(),
+                )
+                .await
+            })
+        };
+
+        async fn next(rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>) -> String {
+            tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("no validation output")
+                .unwrap()
+        }
+        assert_eq!(next(&mut rx).await, "✅ Configuration is valid (1 routes)");
+
+        // Breaking the file reports the error
+        write_upstream("not a url");
+        assert!(next(&mut rx)
+            .await
+            .starts_with("❌ Configuration is invalid: "));
+
+        // Fixing it reports it valid again
+        write_upstream("http://fixed:3000");
+        assert_eq!(next(&mut rx).await, "✅ Configuration is valid (1 routes)");
+
+        shutdown.cancel();
+        watcher.await.unwrap();
+    }
+
+    #[test]
+    fn test_watch_flag_parsing() {
+        let cli = Cli::try_parse_from(["ferragate", "validate", "--watch"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Validate { watch: true, .. }
+        ));
+        assert!(
+            Cli::try_parse_from(["ferragate", "validate", "--watch", "--print-effective"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_stop_uses_configured_socket_dir() {
+        let temp_dir = tempdir().unwrap();
+        let socket_dir = temp_dir.path().join("sockets");
+        let config_path = temp_dir.path().join("gateway.toml");
+        fs::write(
+            &config_path,
+            format!(
+                "routes = []\n\n[server]\ncontrol_socket_dir = \"{}\"\n",
+                socket_dir.display()
+            ),
+        )
+        .unwrap();
+
+        let instance = stopped_instance(&config_path, None, None, None);
+        assert!(instance
+            .control_socket_path()
+            .starts_with(socket_dir.to_str().unwrap()));
+    }
 }
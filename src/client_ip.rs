@@ -0,0 +1,409 @@
+/// Client IP resolution
+///
+/// Forwarded client IP headers are only honored when the connection comes from
+/// a trusted proxy, so clients cannot spoof their address. Proxies are trusted
+/// by address (`trusted_proxies`) or by position, as the nearest `trusted_hops`
+/// hops of the forwarding chain. Everything that
+/// needs the client's address (canary bucketing, X-Forwarded-For and Forwarded
+/// injection) goes through the single resolver in this module.
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+use tracing::warn;
+
+use crate::config::ServerConfig;
+use crate::constants::DEFAULT_CLIENT_IP_HEADER;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const FORWARDED: &str = "forwarded";
+
+/// Parse a CIDR, accepting bare IP addresses as single-host networks
+pub fn parse_cidr(value: &str) -> Option<IpNet> {
+    value
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| value.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+/// Resolves the real client IP for a request
+#[derive(Debug, Clone)]
+pub struct ClientIpResolver {
+    header: HeaderName,
+    trusted_proxies: Vec<IpNet>,
+    trusted_hops: usize,
+}
+
+impl ClientIpResolver {
+    /// Create a resolver from the server configuration
+    ///
+    /// Invalid entries are skipped with a warning; they are rejected by config validation.
+    pub fn new(config: &ServerConfig) -> Self {
+        let header = config
+            .client_ip_header
+            .as_deref()
+            .and_then(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+            .unwrap_or(HeaderName::from_static(DEFAULT_CLIENT_IP_HEADER));
+
+        let trusted_proxies = config
+            .trusted_proxies
+            .iter()
+            .filter_map(|cidr| {
+                let parsed = parse_cidr(cidr);
+                if parsed.is_none() {
+                    warn!("Ignoring invalid trusted proxy: {}", cidr);
+                }
+                parsed
+            })
+            .collect();
+
+        Self {
+            header,
+            trusted_proxies,
+            trusted_hops: config.trusted_hops,
+        }
+    }
+
+    /// Check whether an address belongs to a trusted proxy
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Check whether a hop of the forwarding chain is a trusted proxy
+    ///
+    /// Hops are counted from the gateway: the socket peer is hop 0, the
+    /// rightmost forwarded address hop 1, and so on.
+    fn trusts_hop(&self, hop: usize, ip: IpAddr) -> bool {
+        hop < self.trusted_hops || self.is_trusted(ip)
+    }
+
+    /// Resolve the client IP for a request
+    ///
+    /// The configured header is only consulted when the socket peer is a trusted
+    /// proxy. Its addresses are walked right to left, skipping trusted proxies,
+    /// and the first untrusted address is the client; entries left of it may
+    /// have been forged by the client. When every hop is trusted the leftmost
+    /// address is used. Otherwise the socket peer itself is the client.
+    pub fn resolve(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        let peer_ip = peer?.ip();
+        if !self.trusts_hop(0, peer_ip) {
+            return Some(peer_ip);
+        }
+
+        let forwarded: Vec<IpAddr> = headers
+            .get_all(&self.header)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|entry| entry.trim().parse().ok())
+            .collect();
+
+        forwarded
+            .iter()
+            .rev()
+            .enumerate()
+            .find(|(hop, ip)| !self.trusts_hop(hop + 1, **ip))
+            .map(|(_, ip)| ip)
+            .or(forwarded.first())
+            .copied()
+            .or(Some(peer_ip))
+    }
+
+    /// Build the headers to forward upstream, with X-Forwarded-For injected
+    ///
+    /// The socket peer is appended to the existing chain when it is a trusted
+    /// proxy; otherwise any client-supplied chain is replaced by the peer.
+    pub fn with_forwarded_for(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> HeaderMap {
+        let mut headers = headers.clone();
+        let Some(peer_ip) = peer.map(|addr| addr.ip()) else {
+            return headers;
+        };
+
+        let existing = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let chain = if self.trusts_hop(0, peer_ip) && !existing.is_empty() {
+            format!("{existing}, {peer_ip}")
+        } else {
+            peer_ip.to_string()
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&chain) {
+            headers.insert(X_FORWARDED_FOR, value);
+        }
+        headers
+    }
+
+    /// Add an RFC 7239 `Forwarded` element for this hop to the headers
+    ///
+    /// The element records the socket peer, the host the client requested and
+    /// the scheme it used. As with X-Forwarded-For, an existing `Forwarded`
+    /// header is kept only when the peer is a trusted proxy.
+    pub fn insert_forwarded(
+        &self,
+        headers: &mut HeaderMap,
+        peer: Option<SocketAddr>,
+        host: Option<&str>,
+        proto: &str,
+    ) {
+        let node = match peer.map(|addr| addr.ip()) {
+            Some(IpAddr::V4(ip)) => ip.to_string(),
+            Some(IpAddr::V6(ip)) => format!("\"[{ip}]\""),
+            None => "unknown".to_string(),
+        };
+        let mut element = format!("for={node}");
+        if let Some(host) = host {
+            element.push_str(";host=");
+            element.push_str(&forwarded_value(host));
+        }
+        element.push_str(";proto=");
+        element.push_str(&forwarded_value(proto));
+
+        let existing = headers
+            .get_all(FORWARDED)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let trusted = peer.is_some_and(|addr| self.trusts_hop(0, addr.ip()));
+        let forwarded = if trusted && !existing.is_empty() {
+            format!("{existing}, {element}")
+        } else {
+            element
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&forwarded) {
+            headers.insert(FORWARDED, value);
+        }
+    }
+}
+
+/// Format a `Forwarded` parameter value, quoting it unless it is a plain token
+fn forwarded_value(value: &str) -> String {
+    let is_token = !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+    if is_token {
+        return value.to_string();
+    }
+
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(header: Option<&str>, trusted: &[&str]) -> ClientIpResolver {
+        ClientIpResolver::new(&ServerConfig {
+            client_ip_header: header.map(str::to_string),
+            trusted_proxies: trusted.iter().map(|cidr| cidr.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    fn hops_resolver(trusted_hops: usize, trusted: &[&str]) -> ClientIpResolver {
+        ClientIpResolver::new(&ServerConfig {
+            trusted_proxies: trusted.iter().map(|cidr| cidr.to_string()).collect(),
+            trusted_hops,
+            ..Default::default()
+        })
+    }
+
+    fn forwarded_for(chain: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", chain.parse().unwrap());
+        headers
+    }
+
+    fn peer(ip: &str) -> Option<SocketAddr> {
+        Some(SocketAddr::new(ip.parse().unwrap(), 40000))
+    }
+
+    #[test]
+    fn test_untrusted_peer_header_is_ignored() {
+        let resolver = resolver(None, &["10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        assert_eq!(
+            resolver.resolve(&headers, peer("203.0.113.9")),
+            Some("203.0.113.9".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_trusted_peer_header_is_used() {
+        let resolver = resolver(None, &["10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "6.6.6.6, 1.2.3.4, 10.0.0.2".parse().unwrap(),
+        );
+
+        // The rightmost untrusted hop is the client; earlier entries may be spoofed
+        assert_eq!(
+            resolver.resolve(&headers, peer("10.0.0.1")),
+            Some("1.2.3.4".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_trusted_hops_skip_nearest_proxies() {
+        // client -> CDN (198.51.100.20) -> load balancer (203.0.113.5) -> gateway
+        let resolver = hops_resolver(2, &[]);
+        let headers = forwarded_for("192.0.2.10, 198.51.100.20");
+        assert_eq!(
+            resolver.resolve(&headers, peer("203.0.113.5")),
+            Some("192.0.2.10".parse().unwrap())
+        );
+
+        // Entries the client forged are left of its real address and never reached
+        let spoofed = forwarded_for("1.1.1.1, 10.0.0.1, 192.0.2.10, 198.51.100.20");
+        assert_eq!(
+            resolver.resolve(&spoofed, peer("203.0.113.5")),
+            Some("192.0.2.10".parse().unwrap())
+        );
+
+        // One hop trusts only the peer, so the rightmost entry is the client
+        let resolver = hops_resolver(1, &[]);
+        assert_eq!(
+            resolver.resolve(&spoofed, peer("203.0.113.5")),
+            Some("198.51.100.20".parse().unwrap())
+        );
+
+        // A chain shorter than the hop count falls back to its leftmost address
+        let resolver = hops_resolver(3, &[]);
+        assert_eq!(
+            resolver.resolve(&forwarded_for("192.0.2.10"), peer("203.0.113.5")),
+            Some("192.0.2.10".parse().unwrap())
+        );
+        assert_eq!(
+            resolver.resolve(&HeaderMap::new(), peer("203.0.113.5")),
+            Some("203.0.113.5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_trusted_hops_combined_with_trusted_proxies() {
+        // The peer is trusted by position, internal proxies by address
+        let resolver = hops_resolver(1, &["10.0.0.0/8"]);
+        let headers = forwarded_for("6.6.6.6, 192.0.2.10, 10.0.0.3, 10.0.0.2");
+        assert_eq!(
+            resolver.resolve(&headers, peer("203.0.113.5")),
+            Some("192.0.2.10".parse().unwrap())
+        );
+
+        // Without hops the untrusted peer is the client and the chain is ignored
+        let resolver = hops_resolver(0, &["10.0.0.0/8"]);
+        assert_eq!(
+            resolver.resolve(&headers, peer("203.0.113.5")),
+            Some("203.0.113.5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_trusted_hops_keep_forwarding_chain() {
+        let resolver = hops_resolver(1, &[]);
+        let headers = forwarded_for("192.0.2.10");
+
+        let forwarded = resolver.with_forwarded_for(&headers, peer("203.0.113.5"));
+        assert_eq!(forwarded["x-forwarded-for"], "192.0.2.10, 203.0.113.5");
+    }
+
+    #[test]
+    fn test_custom_client_ip_header() {
+        let resolver = resolver(Some("CF-Connecting-IP"), &["173.245.48.0/20"]);
+        let mut headers = HeaderMap::new();
+        headers.insert("cf-connecting-ip", "198.51.100.7".parse().unwrap());
+        headers.insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        assert_eq!(
+            resolver.resolve(&headers, peer("173.245.48.1")),
+            Some("198.51.100.7".parse().unwrap())
+        );
+        // A trusted peer without the header falls back to the peer address
+        assert_eq!(
+            resolver.resolve(&HeaderMap::new(), peer("173.245.48.1")),
+            Some("173.245.48.1".parse().unwrap())
+        );
+        assert_eq!(resolver.resolve(&headers, None), None);
+    }
+
+    #[test]
+    fn test_forwarded_for_injection() {
+        let resolver = resolver(None, &["10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted = resolver.with_forwarded_for(&headers, peer("10.0.0.1"));
+        assert_eq!(trusted["x-forwarded-for"], "1.2.3.4, 10.0.0.1");
+
+        let untrusted = resolver.with_forwarded_for(&headers, peer("203.0.113.9"));
+        assert_eq!(untrusted["x-forwarded-for"], "203.0.113.9");
+    }
+
+    #[test]
+    fn test_forwarded_element_format() {
+        let resolver = resolver(None, &[]);
+
+        let mut headers = HeaderMap::new();
+        resolver.insert_forwarded(
+            &mut headers,
+            peer("203.0.113.9"),
+            Some("example.com"),
+            "https",
+        );
+        assert_eq!(
+            headers["forwarded"],
+            "for=203.0.113.9;host=example.com;proto=https"
+        );
+
+        // IPv6 nodes and hosts with a port are not tokens and must be quoted
+        let mut headers = HeaderMap::new();
+        resolver.insert_forwarded(
+            &mut headers,
+            peer("2001:db8::1"),
+            Some("example.com:8443"),
+            "http",
+        );
+        assert_eq!(
+            headers["forwarded"],
+            "for=\"[2001:db8::1]\";host=\"example.com:8443\";proto=http"
+        );
+
+        let mut headers = HeaderMap::new();
+        resolver.insert_forwarded(&mut headers, None, None, "http");
+        assert_eq!(headers["forwarded"], "for=unknown;proto=http");
+    }
+
+    #[test]
+    fn test_forwarded_chain_kept_only_from_trusted_peer() {
+        let resolver = resolver(None, &["10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.insert("forwarded", "for=1.2.3.4;proto=https".parse().unwrap());
+
+        let mut trusted = headers.clone();
+        resolver.insert_forwarded(&mut trusted, peer("10.0.0.1"), Some("example.com"), "http");
+        assert_eq!(
+            trusted["forwarded"],
+            "for=1.2.3.4;proto=https, for=10.0.0.1;host=example.com;proto=http"
+        );
+
+        let mut untrusted = headers;
+        resolver.insert_forwarded(&mut untrusted, peer("203.0.113.9"), None, "http");
+        assert_eq!(untrusted["forwarded"], "for=203.0.113.9;proto=http");
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert!(parse_cidr("10.0.0.0/8").is_some());
+        assert!(parse_cidr("192.168.1.1").is_some());
+        assert!(parse_cidr("2001:db8::/32").is_some());
+        assert!(parse_cidr("10.0.0.0/33").is_none());
+    }
+}
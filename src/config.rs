@@ -1,19 +1,44 @@
+use axum::http::{HeaderMap, HeaderName};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
 
+use crate::canary::CanaryKey;
+use crate::client_ip::parse_cidr;
+use crate::config_builder::{GatewayConfigBuilder, RouteConfigBuilder};
 use crate::constants::{
-    DEFAULT_HOST, DEFAULT_HTTPS_PORT, DEFAULT_HTTP_PORT, DEFAULT_LOG_LEVEL, DEFAULT_TIMEOUT_MS,
-    LOG_CONFIG_LOADED,
+    BODY_LOG_REDACTED, CONFIG_PROFILE_ENV, DEFAULT_BODY_LOG_MAX_BYTES, DEFAULT_DRAIN_TIMEOUT_SECS,
+    DEFAULT_HEALTH_WEIGHT_EJECT_SECS, DEFAULT_HEALTH_WEIGHT_ERROR_SENSITIVITY,
+    DEFAULT_HEALTH_WEIGHT_LATENCY_SENSITIVITY, DEFAULT_HEALTH_WEIGHT_MIN_REQUESTS,
+    DEFAULT_HEALTH_WEIGHT_MIN_WEIGHT, DEFAULT_HOST, DEFAULT_HTTPS_PORT, DEFAULT_HTTP_PORT,
+    DEFAULT_IDLE_TIMEOUT_SECS, DEFAULT_INTERRUPT_DRAIN_TIMEOUT_SECS, DEFAULT_LISTEN_BACKLOG,
+    DEFAULT_LOG_LEVEL, DEFAULT_MAX_HEALTH_CHECKS, DEFAULT_MAX_REDIRECTS,
+    DEFAULT_MAX_REQUEST_HEADERS, DEFAULT_MAX_REQUEST_HEADER_BYTES, DEFAULT_MAX_RESPONSE_BODY_BYTES,
+    DEFAULT_MAX_RETRY_AFTER_MS, DEFAULT_MAX_RETRY_BODY_BYTES, DEFAULT_MIRROR_SAMPLE_RATE,
+    DEFAULT_QUEUE_TIMEOUT_MS, DEFAULT_RETRY_BACKOFF_MS, DEFAULT_RETRY_BUDGET_RATIO,
+    DEFAULT_RETRY_BUDGET_WINDOW_SECS, DEFAULT_SHUTDOWN_STATUS, DEFAULT_TIMEOUT_MS,
+    DEFAULT_UPSTREAM_HEALTH_CHECK_INTERVAL_SECS, DEFAULT_UPSTREAM_HEALTH_CHECK_PATH,
+    DEFAULT_UPSTREAM_HEALTH_CHECK_TIMEOUT_MS, LOG_CONFIG_LOADED, METHOD_WILDCARD,
+    METRICS_LATENCY_BUCKETS, MSG_MAINTENANCE_MODE, MSG_SHUTTING_DOWN, READ_METHODS,
+    REDACTED_QUERY_VALUE, WRITE_METHODS,
 };
 use crate::error::{FerragateError, FerragateResult};
+use crate::tls::TlsInfo;
 
 /// Main gateway configuration structure
 ///
 /// This represents the complete configuration for the Ferragate API Gateway,
 /// including server settings, routing rules, and logging configuration.
+/// A `route_defaults` table, if present, is merged into every route while
+/// deserializing.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawGatewayConfig")]
 pub struct GatewayConfig {
     /// Server configuration (host, port, workers, etc.)
     pub server: ServerConfig,
@@ -24,6 +49,195 @@ pub struct GatewayConfig {
     pub logging: LoggingConfig,
 }
 
+/// Gateway configuration as written in the file, before route defaults are applied
+///
+/// Values in `route_defaults` apply to every route that does not set them itself.
+/// The route's own value always wins, except for tables such as `headers`, which
+/// are merged key by key (again with the route's entries winning).
+#[derive(Debug, Deserialize)]
+struct RawGatewayConfig {
+    server: ServerConfig,
+    #[serde(default)]
+    route_defaults: Option<toml::Table>,
+    /// Required unless the routes live in `server.routes_file`
+    #[serde(default)]
+    routes: Option<Vec<toml::Table>>,
+    #[serde(default)]
+    logging: LoggingConfig,
+}
+
+/// Contents of a separate routes file named by `routes_file`
+///
+/// The file has its own `route_defaults`; those of the main file do not apply.
+#[derive(Debug, Deserialize)]
+struct RawRoutesFile {
+    #[serde(default)]
+    route_defaults: Option<toml::Table>,
+    #[serde(default)]
+    routes: Vec<toml::Table>,
+}
+
+impl TryFrom<RawGatewayConfig> for GatewayConfig {
+    type Error = String;
+
+    fn try_from(raw: RawGatewayConfig) -> Result<Self, Self::Error> {
+        let routes = match raw.routes {
+            Some(routes) => routes,
+            None if raw.server.routes_file.is_some() => Vec::new(),
+            None => return Err("missing field `routes`".to_string()),
+        };
+        Ok(Self {
+            server: raw.server,
+            routes: build_routes(routes, raw.route_defaults)?,
+            logging: raw.logging,
+        })
+    }
+}
+
+/// Deserialize route tables after merging the route defaults into each
+fn build_routes(
+    routes: Vec<toml::Table>,
+    defaults: Option<toml::Table>,
+) -> Result<Vec<RouteConfig>, String> {
+    let defaults = defaults.unwrap_or_default();
+    routes
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut route)| {
+            merge_route_defaults(&mut route, &defaults);
+            toml::Value::Table(route)
+                .try_into::<RouteConfig>()
+                .map_err(|e| format!("invalid route {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Apply route defaults to a single route table, with the route's values winning
+fn merge_route_defaults(route: &mut toml::Table, defaults: &toml::Table) {
+    for (key, default_value) in defaults {
+        match (route.get_mut(key), default_value) {
+            (None, _) => {
+                route.insert(key.clone(), default_value.clone());
+            }
+            (Some(toml::Value::Table(route_table)), toml::Value::Table(default_table)) => {
+                merge_route_defaults(route_table, default_table);
+            }
+            (Some(_), _) => {}
+        }
+    }
+}
+
+/// Parse a configuration file, overlaying the named profile from its `profiles` table
+///
+/// A profile has the same layout as the file itself. Its tables are merged into
+/// the base configuration key by key, and any other value, including arrays such
+/// as `routes`, replaces the base value. Without a profile the `profiles` table
+/// is ignored.
+fn parse_config(content: &str, profile: Option<&str>) -> Result<GatewayConfig, String> {
+    let mut table: toml::Table = toml::from_str(content).map_err(|e| e.to_string())?;
+    let profiles = match table.remove("profiles") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => return Err("`profiles` must be a table of named profiles".to_string()),
+        None => toml::Table::new(),
+    };
+
+    if let Some(name) = profile {
+        let overlay = match profiles.get(name) {
+            Some(toml::Value::Table(overlay)) => overlay.clone(),
+            Some(_) => return Err(format!("profile '{name}' must be a table")),
+            None => {
+                let defined: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                return Err(format!(
+                    "profile '{name}' is not defined (defined profiles: {})",
+                    if defined.is_empty() {
+                        "none".to_string()
+                    } else {
+                        defined.join(", ")
+                    }
+                ));
+            }
+        };
+        apply_profile(&mut table, overlay);
+    }
+
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| e.to_string())
+}
+
+/// Overlay a profile onto a configuration table, with the profile's values winning
+fn apply_profile(base: &mut toml::Table, profile: toml::Table) {
+    for (key, value) in profile {
+        let value = match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(profile_table)) => {
+                apply_profile(base_table, profile_table);
+                continue;
+            }
+            (_, value) => value,
+        };
+        base.insert(key, value);
+    }
+}
+
+/// Profile selected through the environment, if any
+fn active_profile() -> Option<String> {
+    std::env::var(CONFIG_PROFILE_ENV)
+        .ok()
+        .filter(|profile| !profile.is_empty())
+}
+
+/// Non-fatal problem found while validating a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// No routes are configured, so only the health endpoints are served
+    NoRoutes,
+    /// The TLS certificate file does not exist and will be auto-generated
+    MissingCertFile(String),
+    /// The TLS private key file does not exist and will be auto-generated
+    MissingKeyFile(String),
+    /// A route matches exactly the same requests as an earlier one, so it never matches
+    DuplicateRoute {
+        first: usize,
+        duplicate: usize,
+        path: String,
+    },
+    /// A route writes request and response bodies to the log
+    BodyLogging(String),
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoRoutes => {
+                write!(
+                    f,
+                    "No routes configured - gateway will only serve health endpoints"
+                )
+            }
+            Self::MissingCertFile(path) => write!(
+                f,
+                "TLS certificate file not found: {path} (will be auto-generated)"
+            ),
+            Self::MissingKeyFile(path) => write!(
+                f,
+                "TLS private key file not found: {path} (will be auto-generated)"
+            ),
+            Self::DuplicateRoute {
+                first,
+                duplicate,
+                path,
+            } => write!(
+                f,
+                "Route {duplicate} ({path}) duplicates route {first} and will never match"
+            ),
+            Self::BodyLogging(path) => write!(
+                f,
+                "Route {path} logs request and response bodies; enable log_bodies only while debugging"
+            ),
+        }
+    }
+}
+
 /// Server configuration structure
 ///
 /// Defines how the gateway server should be configured, including
@@ -42,9 +256,212 @@ pub struct ServerConfig {
     /// Request timeout in milliseconds (default: 30000)
     #[serde(default)]
     pub timeout_ms: Option<u64>,
+    /// Header in which clients may send a shorter upstream timeout in milliseconds (disabled if not specified)
+    #[serde(default)]
+    pub client_timeout_header: Option<String>,
     /// TLS configuration (optional)
     #[serde(default)]
     pub tls: Option<TlsConfig>,
+    /// Maximum retries as a fraction of requests over the budget window (default: 0.2)
+    #[serde(default = "default_retry_budget_ratio")]
+    pub retry_budget_ratio: f64,
+    /// Sliding window for the retry budget in seconds (default: 10)
+    #[serde(default = "default_retry_budget_window_secs")]
+    pub retry_budget_window_secs: u64,
+    /// Longest upstream Retry-After the gateway will wait before retrying, in milliseconds (default: 5000)
+    #[serde(default = "default_max_retry_after_ms")]
+    pub max_retry_after_ms: u64,
+    /// Largest request body buffered to allow retries; larger bodies are streamed in a single attempt (default: 1 MiB)
+    #[serde(default = "default_max_retry_body_bytes")]
+    pub max_retry_body_bytes: u64,
+    /// Largest request body accepted; larger ones are rejected with 413 (unlimited if not specified)
+    #[serde(default)]
+    pub max_request_body_bytes: Option<u64>,
+    /// Most headers a request may carry; more are rejected with 431 (default: 100)
+    #[serde(default = "default_max_request_headers")]
+    pub max_request_headers: usize,
+    /// Largest total size of a request's header names and values in bytes; larger
+    /// header sets are rejected with 431 (default: 65536)
+    #[serde(default = "default_max_request_header_bytes")]
+    pub max_request_header_bytes: usize,
+    /// Largest upstream response body in bytes in any response mode; larger ones are
+    /// aborted, with a 502 if nothing has been sent yet (0 = unlimited, the default)
+    #[serde(default)]
+    pub max_response_bytes: u64,
+    /// Load shedding thresholds (disabled if not specified)
+    #[serde(default)]
+    pub load_shedding: Option<LoadSheddingConfig>,
+    /// Upstream receiving requests that match no route, instead of a 404 (disabled if not specified)
+    #[serde(default)]
+    pub default_upstream: Option<String>,
+    /// Time in seconds in-flight requests may finish after SIGTERM or a stop request (default: 30)
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+    /// Time in seconds in-flight requests may finish after SIGINT (Ctrl+C) (default: 5)
+    #[serde(default = "default_interrupt_drain_timeout_secs")]
+    pub interrupt_drain_timeout_secs: u64,
+    /// Status returned to proxied requests arriving once draining has started (default: 503)
+    #[serde(default = "default_shutdown_status")]
+    pub shutdown_status: u16,
+    /// Response body returned to proxied requests arriving once draining has started
+    #[serde(default = "default_shutdown_body")]
+    pub shutdown_body: String,
+    /// Request priority header and the priorities exempt from load shedding (disabled if not specified)
+    #[serde(default)]
+    pub priority: Option<PriorityConfig>,
+    /// Requests rejected with 403 before routing, by method and path pattern
+    #[serde(default)]
+    pub deny_rules: Vec<DenyRule>,
+    /// Reject duplicate routes instead of warning about them (default: false)
+    #[serde(default)]
+    pub strict_validation: bool,
+    /// Reject routes forwarding to any upstream that does not use https:// (default: false)
+    #[serde(default)]
+    pub require_https_upstreams: bool,
+    /// Header carrying the real client IP when set by a trusted proxy (default: "x-forwarded-for")
+    #[serde(default)]
+    pub client_ip_header: Option<String>,
+    /// CIDRs of proxies whose forwarded client IP headers are trusted
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    /// Number of proxies in front of the gateway trusted regardless of address (default: 0)
+    #[serde(default)]
+    pub trusted_hops: usize,
+    /// Send an RFC 7239 Forwarded header with the client address, host and scheme upstream
+    #[serde(default)]
+    pub forwarded_header: bool,
+    /// Name the gateway records in a Via header appended to proxied responses (disabled if not specified)
+    #[serde(default)]
+    pub via: Option<String>,
+    /// Replaces the upstream's Server header on proxied responses; empty removes it (passed through if not specified)
+    #[serde(default)]
+    pub server_header: Option<String>,
+    /// Maximum number of open connections on the HTTP listener (unlimited if not specified)
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    /// Maximum new connections accepted per second on the HTTP listener (unlimited if not specified)
+    #[serde(default)]
+    pub max_accept_rate: Option<f64>,
+    /// Set TCP_NODELAY on connections accepted by the HTTP listener
+    #[serde(default)]
+    pub tcp_nodelay: bool,
+    /// Idle seconds before TCP keepalive probes are sent on accepted connections (disabled if not specified)
+    #[serde(default)]
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Length of the HTTP listener's pending connection queue (default: 1024)
+    #[serde(default = "default_listen_backlog")]
+    pub listen_backlog: u32,
+    /// Seconds without any bytes read or written before an HTTP client connection is closed (default: 300)
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// Periodically request the gateway's own liveness endpoint and report the result as a health check
+    #[serde(default)]
+    pub loopback_health_check: bool,
+    /// Directory for the control socket; FERRAGATE_CONTROL_SOCKET_DIR takes precedence (default: /tmp)
+    #[serde(default)]
+    pub control_socket_dir: Option<String>,
+    /// File holding the `[[routes]]` tables, relative to the configuration file; reloadable on its own
+    #[serde(default)]
+    pub routes_file: Option<String>,
+    /// Most health checks that can be registered; further registrations are rejected (default: 256)
+    #[serde(default = "default_max_health_checks")]
+    pub max_health_checks: usize,
+    /// Interval in seconds for polling the config file and reloading it on change (disabled if not specified)
+    #[serde(default)]
+    pub config_poll_interval_secs: Option<u64>,
+    /// Response body returned to proxied requests while in maintenance mode
+    #[serde(default = "default_maintenance_body")]
+    pub maintenance_body: String,
+    /// Answer /favicon.ico at the gateway instead of routing it (default: false)
+    #[serde(default)]
+    pub serve_favicon: bool,
+    /// Icon served at /favicon.ico; without one the response is 204 No Content
+    #[serde(default)]
+    pub favicon_file: Option<String>,
+    /// Answer /robots.txt at the gateway instead of routing it (default: false)
+    #[serde(default)]
+    pub serve_robots_txt: bool,
+    /// File served at /robots.txt (default: disallow all crawling)
+    #[serde(default)]
+    pub robots_txt_file: Option<String>,
+    /// Upper bounds of the request latency histogram buckets, in seconds
+    #[serde(default = "default_metrics_latency_buckets")]
+    pub metrics_latency_buckets: Vec<f64>,
+    /// HTTP versions accepted by the listeners: "h1", "h1.1", "h2", "h2c" (default: ["h1.1", "h2"])
+    #[serde(default = "default_http_versions")]
+    pub http_versions: Vec<HttpVersion>,
+}
+
+/// HTTP protocol version a listener may accept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HttpVersion {
+    /// HTTP/1.0
+    #[serde(rename = "h1")]
+    Http10,
+    /// HTTP/1.1
+    #[serde(rename = "h1.1")]
+    Http11,
+    /// HTTP/2 over TLS
+    #[serde(rename = "h2")]
+    H2,
+    /// HTTP/2 over cleartext TCP (prior knowledge)
+    #[serde(rename = "h2c")]
+    H2c,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+            workers: default_workers(),
+            timeout_ms: None,
+            client_timeout_header: None,
+            tls: None,
+            retry_budget_ratio: default_retry_budget_ratio(),
+            retry_budget_window_secs: default_retry_budget_window_secs(),
+            max_retry_after_ms: default_max_retry_after_ms(),
+            max_retry_body_bytes: default_max_retry_body_bytes(),
+            max_request_body_bytes: None,
+            max_request_headers: default_max_request_headers(),
+            max_request_header_bytes: default_max_request_header_bytes(),
+            max_response_bytes: 0,
+            load_shedding: None,
+            default_upstream: None,
+            drain_timeout_secs: default_drain_timeout_secs(),
+            interrupt_drain_timeout_secs: default_interrupt_drain_timeout_secs(),
+            shutdown_status: default_shutdown_status(),
+            shutdown_body: default_shutdown_body(),
+            priority: None,
+            deny_rules: Vec::new(),
+            strict_validation: false,
+            require_https_upstreams: false,
+            client_ip_header: None,
+            trusted_proxies: Vec::new(),
+            trusted_hops: 0,
+            forwarded_header: false,
+            via: None,
+            server_header: None,
+            max_connections: None,
+            max_accept_rate: None,
+            tcp_nodelay: false,
+            tcp_keepalive_secs: None,
+            listen_backlog: default_listen_backlog(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            loopback_health_check: false,
+            control_socket_dir: None,
+            routes_file: None,
+            max_health_checks: default_max_health_checks(),
+            config_poll_interval_secs: None,
+            maintenance_body: default_maintenance_body(),
+            serve_favicon: false,
+            favicon_file: None,
+            serve_robots_txt: false,
+            robots_txt_file: None,
+            metrics_latency_buckets: default_metrics_latency_buckets(),
+            http_versions: default_http_versions(),
+        }
+    }
 }
 
 /// TLS/SSL configuration structure
@@ -64,8 +481,148 @@ pub struct TlsConfig {
     /// Whether to redirect HTTP requests to HTTPS
     #[serde(default)]
     pub redirect_http: bool,
+    /// Generate a self-signed certificate when the certificate files are missing
+    #[serde(default = "default_auto_generate_cert")]
+    pub auto_generate_cert: bool,
+    /// CA bundle used to verify client certificates, enabling mutual TLS (disabled if not specified)
+    #[serde(default)]
+    pub client_ca_file: Option<String>,
+    /// Refuse the handshake for clients without a valid certificate (default: false)
+    #[serde(default)]
+    pub require_client_cert: bool,
+}
+
+/// Load shedding configuration structure
+///
+/// Proxied requests are rejected with 503 once either threshold is exceeded.
+/// Health endpoints are never shed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadSheddingConfig {
+    /// Maximum number of concurrently proxied requests
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// Maximum average proxy latency in milliseconds before shedding starts
+    #[serde(default)]
+    pub max_latency_ms: Option<u64>,
+}
+
+/// Request priority classification
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriorityConfig {
+    /// Header clients set to the request's priority (e.g. "x-priority")
+    pub header: String,
+    /// Priority values admitted even while load shedding rejects other requests
+    #[serde(default)]
+    pub bypass_shedding: Vec<String>,
+}
+
+impl PriorityConfig {
+    /// Priority of a request, from the value of the priority header
+    pub fn priority_of<'a>(&self, headers: &'a HeaderMap) -> Option<&'a str> {
+        headers
+            .get(self.header.as_str())?
+            .to_str()
+            .ok()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Check whether requests with the given priority bypass load shedding
+    pub fn bypasses_shedding(&self, priority: &str) -> bool {
+        self.bypass_shedding
+            .iter()
+            .any(|value| value.eq_ignore_ascii_case(priority))
+    }
+}
+
+/// Request pattern rejected before routing
+///
+/// A minimal guard against obviously malicious requests, such as path
+/// traversal or TRACE; it is no replacement for a web application firewall.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DenyRule {
+    /// Methods the rule applies to (empty = all methods)
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Regex searched for in the request path as sent by the client (default: any path)
+    #[serde(default)]
+    pub path: Option<PathRegex>,
+}
+
+impl DenyRule {
+    /// Check whether a request matches the rule
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        (self.methods.is_empty()
+            || self
+                .methods
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(method)))
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|regex| regex.0.is_match(path))
+    }
+}
+
+/// Path regular expression compiled when the configuration is loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PathRegex(Regex);
+
+impl TryFrom<String> for PathRegex {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value)
+            .map(Self)
+            .map_err(|e| format!("invalid path regex '{value}': {e}"))
+    }
+}
+
+impl From<PathRegex> for String {
+    fn from(value: PathRegex) -> Self {
+        value.0.as_str().to_string()
+    }
+}
+
+/// Body redaction regular expression compiled when the configuration is loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RedactPattern(Regex);
+
+impl RedactPattern {
+    /// Replace every match in the text with `***`
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.0.replace_all(text, BODY_LOG_REDACTED)
+    }
+}
+
+impl TryFrom<String> for RedactPattern {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value)
+            .map(Self)
+            .map_err(|e| format!("invalid redact pattern '{value}': {e}"))
+    }
+}
+
+impl From<RedactPattern> for String {
+    fn from(value: RedactPattern) -> Self {
+        value.0.as_str().to_string()
+    }
 }
 
+/// Path, methods, header, query, TLS and Content-Type rules identifying the requests a route matches
+type RouteMatchKey = (
+    String,
+    Vec<String>,
+    Vec<(String, String)>,
+    Vec<String>,
+    Vec<(TlsField, String)>,
+    Vec<String>,
+);
+
 /// Route configuration structure
 ///
 /// Defines a single routing rule that maps incoming requests to upstream services.
@@ -75,405 +632,4298 @@ pub struct RouteConfig {
     pub path: String,
     /// Upstream service URL (e.g., "http://localhost:8080")
     pub upstream: String,
-    /// Allowed HTTP methods (empty = all methods allowed)
-    #[serde(default)]
+    /// Whether the route takes part in matching; disabled routes are skipped (default: true)
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Allowed HTTP methods (empty = all methods allowed); `*`, `READ` and `WRITE` are expanded on load
+    #[serde(default, deserialize_with = "deserialize_methods")]
     pub methods: Vec<String>,
     /// Additional headers to add to upstream requests
     #[serde(default)]
     pub headers: HashMap<String, String>,
+    /// Header rules that must all match for a request to use this route
+    #[serde(default)]
+    pub header_match: Vec<HeaderMatch>,
+    /// Query parameter rules that must all match for a request to use this route
+    #[serde(default)]
+    pub query_match: Vec<QueryMatch>,
+    /// TLS connection rules (SNI, client certificate) that must all match for a request to use this route
+    #[serde(default)]
+    pub tls_match: Vec<TlsMatch>,
+    /// Media types, e.g. "application/json" or "application/*", one of which the request's Content-Type must match (empty = any)
+    #[serde(default)]
+    pub content_type_match: Vec<String>,
+    /// Client headers forwarded upstream: "all", "none", or a list of header names (default: "all")
+    #[serde(default)]
+    pub forward_headers: ForwardHeaders,
     /// Whether to strip the matched path prefix before forwarding
     #[serde(default)]
     pub strip_path: bool,
     /// Whether to preserve the original Host header
     #[serde(default)]
     pub preserve_host: bool,
-    /// Route-specific timeout in milliseconds (overrides server default)
-    #[serde(default)]
+    /// Timeout for each upstream attempt in milliseconds (overrides server default); also accepted as `attempt_timeout_ms`
+    #[serde(default, alias = "attempt_timeout_ms")]
     pub timeout_ms: Option<u64>,
+    /// Wall-clock budget in milliseconds across all attempts and retry backoffs (unbounded if not specified)
+    #[serde(default)]
+    pub total_timeout_ms: Option<u64>,
+    /// Time to wait for the upstream's response headers in milliseconds (bounded only by the timeout if not specified)
+    #[serde(default)]
+    pub headers_timeout_ms: Option<u64>,
+    /// Longest gap between response body chunks in milliseconds (bounded only by the timeout if not specified)
+    #[serde(default)]
+    pub body_timeout_ms: Option<u64>,
+    /// Number of retries for failed idempotent requests (default: 0)
+    #[serde(default)]
+    pub retries: u32,
+    /// Base backoff between retries in milliseconds, doubled on each attempt (default: 100)
+    #[serde(default)]
+    pub retry_backoff_ms: Option<u64>,
+    /// User-Agent sent upstream: "passthrough", "gateway-default", or a literal value
+    #[serde(default)]
+    pub upstream_user_agent: Option<UpstreamUserAgent>,
+    /// Accept-Encoding sent upstream: "passthrough", "strip", or a literal value
+    #[serde(default)]
+    pub upstream_accept_encoding: Option<UpstreamAcceptEncoding>,
+    /// Share one upstream call between identical concurrent GET requests (default: false)
+    #[serde(default)]
+    pub coalesce_requests: bool,
+    /// Replace a fetched 200 with a bodiless 304 when the client's If-None-Match or If-Modified-Since
+    /// matches its validators; the upstream is still called (default: false)
+    #[serde(default)]
+    pub strip_conditional_responses: bool,
+    /// Accept gRPC-Web requests and forward them to the upstream as gRPC over HTTP/2 (default: false)
+    #[serde(default)]
+    pub grpc_web: bool,
+    /// Decode gzip, deflate and brotli request bodies before forwarding them upstream
+    #[serde(default)]
+    pub decompress_request: bool,
+    /// Largest decoded request or response body in bytes when decompression is enabled (default: 10 MiB)
+    #[serde(default)]
+    pub max_decompressed_bytes: Option<u64>,
+    /// Decode gzip, deflate and brotli upstream responses before transforming them
+    #[serde(default)]
+    pub decompress_response: bool,
+    /// Gzip responses decoded by `decompress_response` again for clients that accept gzip
+    #[serde(default)]
+    pub recompress_response: bool,
+    /// Log request and response bodies for debugging, with redaction (disabled if not specified)
+    #[serde(default)]
+    pub log_bodies: Option<BodyLoggingConfig>,
+    /// Additional upstreams that share the route's traffic with `upstream`
+    #[serde(default)]
+    pub upstreams: Vec<String>,
+    /// Upstream pools serving requests by priority header value, instead of `upstream`
+    #[serde(default)]
+    pub priority_upstreams: HashMap<String, Vec<String>>,
+    /// Shift traffic away from upstreams with rising error rates or latency (equal weights if not specified)
+    #[serde(default)]
+    pub health_weighting: Option<HealthWeightingConfig>,
+    /// Actively probe the route's upstreams and stop sending traffic to failing ones (disabled if not specified)
+    #[serde(default)]
+    pub health_check: Option<UpstreamHealthCheckConfig>,
+    /// Secondary upstream that receives a fire-and-forget copy of matched requests
+    #[serde(default)]
+    pub mirror_upstream: Option<String>,
+    /// Fraction of matched requests copied to the mirror upstream (0.0 - 1.0, default: 1.0)
+    #[serde(default)]
+    pub mirror_sample_rate: Option<f64>,
+    /// Canary upstream that receives a sticky percentage of requests
+    #[serde(default)]
+    pub canary_upstream: Option<String>,
+    /// Percentage of requests sent to the canary upstream (0.0 - 100.0, default: 0.0)
+    #[serde(default)]
+    pub canary_percent: Option<f64>,
+    /// Request attribute used to bucket clients: "client_ip" or "header:<name>" (default: "client_ip")
+    #[serde(default)]
+    pub canary_key: Option<String>,
+    /// Token-bucket rate limit for this route (disabled if not specified)
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Limit on requests forwarded at once, with a bounded queue (unlimited if not specified)
+    #[serde(default)]
+    pub concurrency: Option<ConcurrencyConfig>,
+    /// Follow upstream redirects instead of forwarding them to the client (default: false)
+    #[serde(default)]
+    pub follow_redirects: bool,
+    /// Maximum redirects to follow when `follow_redirects` is enabled (default: 10)
+    #[serde(default)]
+    pub max_redirects: Option<u32>,
+    /// HTTP version used to reach the upstream: "http1" or "http2" (default: negotiated)
+    #[serde(default)]
+    pub upstream_protocol: Option<UpstreamProtocol>,
+    /// Local IP address upstream connections originate from (default: chosen by the OS)
+    #[serde(default)]
+    pub local_address: Option<IpAddr>,
+    /// Upstream status codes to replace in client responses (e.g. 418 -> 400)
+    #[serde(default, with = "status_map")]
+    pub status_map: HashMap<u16, u16>,
+    /// Find-and-replace rules applied to response bodies of matching content types
+    #[serde(default)]
+    pub body_replacements: Vec<BodyReplacement>,
+    /// Largest response body in bytes that `body_replacements` rewrites (default: 1 MiB)
+    #[serde(default)]
+    pub max_replacement_body_bytes: Option<u64>,
+    /// Stream response bodies to the client or buffer them first: "stream" or "buffer"
+    /// (default: "buffer" with `body_replacements`, otherwise "stream")
+    #[serde(default)]
+    pub response_mode: Option<ResponseMode>,
+    /// Former name of `max_response_bytes`, still accepted; setting both is an error
+    #[serde(default)]
+    pub max_response_body_bytes: Option<u64>,
+    /// Largest upstream response body in bytes in any response mode (0 = unlimited, default: server setting)
+    #[serde(default)]
+    pub max_response_bytes: Option<u64>,
+    /// Media types clients may send; others get 415 (empty = any)
+    #[serde(default)]
+    pub allowed_request_content_types: Vec<String>,
+    /// Media types the upstream may return; others become 502 (empty = any)
+    #[serde(default)]
+    pub allowed_response_content_types: Vec<String>,
 }
 
-/// Logging configuration structure
+impl Default for RouteConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            upstream: String::new(),
+            enabled: true,
+            methods: Vec::new(),
+            headers: HashMap::new(),
+            header_match: Vec::new(),
+            query_match: Vec::new(),
+            tls_match: Vec::new(),
+            content_type_match: Vec::new(),
+            forward_headers: Default::default(),
+            strip_path: false,
+            preserve_host: false,
+            timeout_ms: None,
+            total_timeout_ms: None,
+            headers_timeout_ms: None,
+            body_timeout_ms: None,
+            retries: 0,
+            retry_backoff_ms: None,
+            upstream_user_agent: None,
+            upstream_accept_encoding: None,
+            coalesce_requests: false,
+            strip_conditional_responses: false,
+            grpc_web: false,
+            decompress_request: false,
+            max_decompressed_bytes: None,
+            decompress_response: false,
+            recompress_response: false,
+            log_bodies: None,
+            upstreams: Vec::new(),
+            priority_upstreams: HashMap::new(),
+            health_weighting: None,
+            health_check: None,
+            mirror_upstream: None,
+            mirror_sample_rate: None,
+            canary_upstream: None,
+            canary_percent: None,
+            canary_key: None,
+            rate_limit: None,
+            concurrency: None,
+            follow_redirects: false,
+            max_redirects: None,
+            upstream_protocol: None,
+            local_address: None,
+            status_map: HashMap::new(),
+            body_replacements: Vec::new(),
+            allowed_request_content_types: Vec::new(),
+            allowed_response_content_types: Vec::new(),
+            max_replacement_body_bytes: None,
+            response_mode: None,
+            max_response_body_bytes: None,
+            max_response_bytes: None,
+        }
+    }
+}
+
+/// (De)serialize `status_map`, whose TOML keys are strings
+mod status_map {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<u16, u16>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        map.iter()
+            .map(|(from, to)| (from.to_string(), *to))
+            .collect::<HashMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<u16, u16>, D::Error> {
+        HashMap::<String, u16>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(from, to)| {
+                from.parse()
+                    .map(|from| (from, to))
+                    .map_err(|_| D::Error::custom(format!("invalid status_map key '{from}'")))
+            })
+            .collect()
+    }
+}
+
+/// Rate limit configuration structure
 ///
-/// Controls how the gateway handles logging output, including levels and formats.
+/// Requests are limited with a token bucket shared by all clients of the route.
+/// Methods listed in a tier draw from that tier's bucket; all other methods use
+/// the default bucket.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Sustained requests per second for the default tier
+    pub requests_per_second: f64,
+    /// Maximum burst size for the default tier (default: requests_per_second, at least 1)
+    #[serde(default)]
+    pub burst: Option<u32>,
+    /// Method-specific tiers, checked in order
+    #[serde(default)]
+    pub tiers: Vec<RateLimitTier>,
+}
+
+/// Concurrency limit for a route, with a bounded FIFO queue
+///
+/// Requests beyond `max_concurrent` wait in line for a slot. Once `queue_size`
+/// requests are waiting, or a request has waited `queue_timeout_ms`, it is
+/// answered with 503 instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LoggingConfig {
-    /// Log level (trace, debug, info, warn, error) - default: "info"
-    #[serde(default = "default_log_level")]
-    pub level: String,
-    /// Whether to output logs in JSON format
+pub struct ConcurrencyConfig {
+    /// Requests forwarded upstream at once
+    pub max_concurrent: usize,
+    /// Requests that may wait for a slot (default: 0, rejecting immediately)
     #[serde(default)]
-    pub json: bool,
-    /// Whether to write logs to files
+    pub queue_size: usize,
+    /// Longest a request waits for a slot in milliseconds (default: 1000)
+    #[serde(default = "default_queue_timeout_ms")]
+    pub queue_timeout_ms: u64,
+}
+
+/// Adaptive weighting of a route's upstreams by their recent health
+///
+/// Each upstream's weight falls as its error rate and latency over the rolling
+/// stats window rise, and recovers as they improve. With `eject_error_rate`, an
+/// upstream failing at least that often is also taken out of the pool for a while.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthWeightingConfig {
+    /// Exponent applied to the success rate; higher values back off faster (default: 2.0)
+    #[serde(default = "default_error_sensitivity")]
+    pub error_sensitivity: f64,
+    /// p90 latency in milliseconds above which the weight is reduced (latency is ignored if not specified)
     #[serde(default)]
-    pub file: bool,
-    /// Directory for log files (if file logging is enabled)
+    pub latency_threshold_ms: Option<u64>,
+    /// Exponent applied to the ratio of the latency threshold to the p90 latency (default: 1.0)
+    #[serde(default = "default_latency_sensitivity")]
+    pub latency_sensitivity: f64,
+    /// Lowest weight relative to a healthy upstream, so degraded upstreams can still recover (default: 0.05)
+    #[serde(default = "default_min_weight")]
+    pub min_weight: f64,
+    /// Requests an upstream must have served in the stats window before its weight adapts (default: 10)
+    #[serde(default = "default_min_requests")]
+    pub min_requests: usize,
+    /// Error rate at or above which an upstream is ejected from the pool (never ejected if not specified)
     #[serde(default)]
-    pub dir: Option<String>,
+    pub eject_error_rate: Option<f64>,
+    /// Seconds an ejected upstream stays out of the pool before it is tried again (default: 30)
+    #[serde(default = "default_eject_secs")]
+    pub eject_secs: u64,
 }
 
-impl Default for LoggingConfig {
+impl Default for HealthWeightingConfig {
     fn default() -> Self {
         Self {
-            level: default_log_level(),
-            json: false,
-            file: false,
-            dir: None,
+            error_sensitivity: default_error_sensitivity(),
+            latency_threshold_ms: None,
+            latency_sensitivity: default_latency_sensitivity(),
+            min_weight: default_min_weight(),
+            min_requests: default_min_requests(),
+            eject_error_rate: None,
+            eject_secs: default_eject_secs(),
         }
     }
 }
 
-fn default_host() -> String {
-    DEFAULT_HOST.to_string()
+/// Active health check for the upstreams in a route's pool
+///
+/// Every `interval_secs`, `upstream` and each of `upstreams` is probed. An
+/// upstream whose check fails or times out receives no traffic until a later
+/// check passes; upstreams count as healthy until their first check completes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamHealthCheckConfig {
+    /// How upstreams are probed: "http" or "grpc" (default: "http")
+    #[serde(rename = "type", default)]
+    pub check_type: UpstreamHealthCheckType,
+    /// Path requested by HTTP checks, which pass on any 2xx status (default: "/health")
+    #[serde(default = "default_upstream_health_check_path")]
+    pub path: String,
+    /// Service named in gRPC checks; empty asks about the server as a whole (default: "")
+    #[serde(default)]
+    pub service: String,
+    /// Seconds between checks of each upstream (default: 10)
+    #[serde(default = "default_upstream_health_check_interval_secs")]
+    pub interval_secs: u64,
+    /// Milliseconds to wait for a check's response (default: 2000)
+    #[serde(default = "default_upstream_health_check_timeout_ms")]
+    pub timeout_ms: u64,
 }
 
-fn default_port() -> u16 {
-    DEFAULT_HTTP_PORT
+impl Default for UpstreamHealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            check_type: UpstreamHealthCheckType::default(),
+            path: default_upstream_health_check_path(),
+            service: String::new(),
+            interval_secs: default_upstream_health_check_interval_secs(),
+            timeout_ms: default_upstream_health_check_timeout_ms(),
+        }
+    }
 }
 
-fn default_workers() -> Option<usize> {
-    None
+/// Protocol of an upstream health check
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamHealthCheckType {
+    /// GET `path`, passing on a 2xx status
+    #[default]
+    Http,
+    /// Call the standard `grpc.health.v1.Health/Check` RPC, passing on SERVING
+    Grpc,
 }
 
-fn default_log_level() -> String {
-    DEFAULT_LOG_LEVEL.to_string()
+/// Debug logging of the bodies a route forwards
+///
+/// Bodies are logged at info level as text, after the configured redactions,
+/// and cut to `max_bytes`. Responses are buffered so they can be logged;
+/// request bodies too large to buffer for retries, and binary bodies, are
+/// reported by size only. Never leave this on in production.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyLoggingConfig {
+    /// Dot-separated JSON field paths whose values are logged as `***`, such as "user.password"
+    #[serde(default)]
+    pub redact_fields: Vec<String>,
+    /// Regular expressions whose matches are logged as `***`
+    #[serde(default)]
+    pub redact_patterns: Vec<RedactPattern>,
+    /// Bytes of each body written to the log after redaction (default: 4096)
+    #[serde(default = "default_body_log_max_bytes")]
+    pub max_bytes: usize,
 }
 
-fn default_https_port() -> u16 {
-    DEFAULT_HTTPS_PORT
+impl Default for BodyLoggingConfig {
+    fn default() -> Self {
+        Self {
+            redact_fields: Vec::new(),
+            redact_patterns: Vec::new(),
+            max_bytes: default_body_log_max_bytes(),
+        }
+    }
 }
 
-impl GatewayConfig {
-    /// Load configuration from a TOML file
-    ///
-    /// Reads and parses a TOML configuration file, validates the configuration,
-    /// and returns a GatewayConfig instance.
-    pub fn from_file(path: &str) -> FerragateResult<Self> {
-        info!("Loading configuration from: {}", path);
-
-        let content = fs::read_to_string(path).map_err(|e| {
-            FerragateError::config(format!("Failed to read config file '{path}': {e}"))
-        })?;
+/// Rate limit for a group of HTTP methods
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitTier {
+    /// Methods that draw from this tier's bucket
+    pub methods: Vec<String>,
+    /// Sustained requests per second for this tier
+    pub requests_per_second: f64,
+    /// Maximum burst size for this tier (default: requests_per_second, at least 1)
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
 
-        let config: GatewayConfig = toml::from_str(&content).map_err(|e| {
-            FerragateError::config(format!("Failed to parse config file '{path}': {e}"))
-        })?;
+/// Request header rule for route matching
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderMatch {
+    /// Name of the header to inspect (case-insensitive)
+    pub name: String,
+    /// Regular expression the header value must match
+    pub regex: HeaderRegex,
+}
 
-        info!("{} from: {}", LOG_CONFIG_LOADED, path);
-        debug!("Loaded config: {:#?}", config);
+impl HeaderMatch {
+    /// Check whether any value of the header matches the regex
+    ///
+    /// Requests without the header, or with a non-UTF-8 value, do not match.
+    pub fn matches(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get_all(self.name.as_str())
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| self.regex.0.is_match(value))
+    }
+}
 
-        config.validate()?;
+/// Regular expression compiled when the configuration is loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HeaderRegex(Regex);
 
-        Ok(config)
+impl TryFrom<String> for HeaderRegex {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value)
+            .map(Self)
+            .map_err(|e| format!("invalid header_match regex '{value}': {e}"))
     }
+}
 
-    /// Validate the configuration
+impl From<HeaderRegex> for String {
+    fn from(value: HeaderRegex) -> Self {
+        value.0.as_str().to_string()
+    }
+}
+
+/// Attribute of a request's TLS connection that routes can match on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsField {
+    /// Server name the client requested through SNI
+    Sni,
+    /// Subject of the client certificate, such as `CN=admin, O=Example`
+    ClientCertSubject,
+    /// Any DNS subject alternative name of the client certificate
+    ClientCertSan,
+}
+
+impl TlsField {
+    /// Whether the attribute comes from a client certificate
+    pub fn is_client_cert(self) -> bool {
+        matches!(self, Self::ClientCertSubject | Self::ClientCertSan)
+    }
+}
+
+/// TLS connection rule for route matching
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsMatch {
+    /// Connection attribute to inspect
+    pub field: TlsField,
+    /// Regular expression the attribute must match
+    pub regex: TlsRegex,
+}
+
+impl TlsMatch {
+    /// Check whether any value of the attribute matches the regex
     ///
-    /// Performs comprehensive validation of the configuration including
-    /// route validation, TLS setup, and basic sanity checks.
-    pub fn validate(&self) -> FerragateResult<()> {
-        if self.routes.is_empty() {
-            warn!("No routes configured - gateway will only serve health endpoints");
+    /// Requests that did not arrive over TLS, or whose connection lacks the
+    /// attribute, do not match.
+    pub fn matches(&self, tls: Option<&TlsInfo>) -> bool {
+        tls.is_some_and(|tls| {
+            tls.values(self.field)
+                .into_iter()
+                .any(|value| self.regex.0.is_match(value))
+        })
+    }
+}
+
+/// TLS rule regular expression compiled when the configuration is loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TlsRegex(Regex);
+
+impl TryFrom<String> for TlsRegex {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value)
+            .map(Self)
+            .map_err(|e| format!("invalid tls_match regex '{value}': {e}"))
+    }
+}
+
+impl From<TlsRegex> for String {
+    fn from(value: TlsRegex) -> Self {
+        value.0.as_str().to_string()
+    }
+}
+
+/// Find-and-replace rule for response bodies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BodyReplacement {
+    /// Text to find in the body
+    pub from: String,
+    /// Replacement text
+    pub to: String,
+    /// Media types the rule applies to, e.g. "text/html" or "text/*"
+    pub content_types: Vec<String>,
+}
+
+impl BodyReplacement {
+    /// Check whether the rule applies to a response's Content-Type
+    ///
+    /// Parameters such as `charset` are ignored and matching is case-insensitive.
+    pub fn applies_to(&self, content_type: &str) -> bool {
+        media_type_matches(&self.content_types, content_type)
+    }
+}
+
+/// Check a Content-Type against media type patterns such as `text/html` or `text/*`
+///
+/// Parameters such as `charset` are ignored and matching is case-insensitive.
+fn media_type_matches(patterns: &[String], content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some(prefix) => media_type
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/')),
+            None => media_type == pattern,
         }
+    })
+}
 
-        // Validate TLS configuration if enabled
-        if let Some(tls) = &self.server.tls {
-            if tls.enabled {
-                // Check if certificate files exist (but allow auto-generation)
-                if !std::path::Path::new(&tls.cert_file).exists() {
-                    warn!(
-                        "TLS certificate file not found: {} (will be auto-generated)",
-                        tls.cert_file
-                    );
-                }
-                if !std::path::Path::new(&tls.key_file).exists() {
-                    warn!(
-                        "TLS private key file not found: {} (will be auto-generated)",
-                        tls.key_file
-                    );
-                }
-                info!(
-                    "TLS configuration validated: cert={}, key={}",
-                    tls.cert_file, tls.key_file
-                );
+/// Check a message's Content-Type against an allowlist (empty = anything)
+///
+/// A message without a Content-Type is only allowed when it has no body.
+fn content_type_allowed(allowed: &[String], content_type: Option<&str>, has_body: bool) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    match content_type {
+        Some(content_type) => media_type_matches(allowed, content_type),
+        None => !has_body,
+    }
+}
+
+/// Query parameter rule for route matching
+///
+/// Written as `name` (parameter present), `name=value` (parameter has the value)
+/// or `!name` (parameter absent).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum QueryMatch {
+    /// The parameter appears in the query string, with any value
+    Present(String),
+    /// One of the parameter's values equals the given value
+    Equals(String, String),
+    /// The parameter does not appear in the query string
+    Absent(String),
+}
+
+impl QueryMatch {
+    /// Check the rule against a raw query string (without the leading `?`)
+    pub fn matches(&self, query: &str) -> bool {
+        let mut params = url::form_urlencoded::parse(query.as_bytes());
+        match self {
+            Self::Present(name) => params.any(|(key, _)| key == name.as_str()),
+            Self::Equals(name, value) => {
+                params.any(|(key, actual)| key == name.as_str() && actual == value.as_str())
             }
+            Self::Absent(name) => !params.any(|(key, _)| key == name.as_str()),
         }
+    }
+}
 
-        // Validate each route
-        for (i, route) in self.routes.iter().enumerate() {
-            route
-                .validate()
-                .map_err(|e| FerragateError::config(format!("Route {i}: {e}")))?;
+impl TryFrom<String> for QueryMatch {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid query_match rule '{value}'");
+        let rule = if let Some(name) = value.strip_prefix('!') {
+            if name.contains('=') {
+                return Err(invalid());
+            }
+            Self::Absent(name.to_string())
+        } else if let Some((name, expected)) = value.split_once('=') {
+            Self::Equals(name.to_string(), expected.to_string())
+        } else {
+            Self::Present(value.clone())
+        };
+
+        match &rule {
+            Self::Present(name) | Self::Equals(name, _) | Self::Absent(name) if name.is_empty() => {
+                Err(invalid())
+            }
+            _ => Ok(rule),
         }
+    }
+}
 
-        info!("Configuration validation completed successfully");
-        Ok(())
+impl From<QueryMatch> for String {
+    fn from(value: QueryMatch) -> Self {
+        match value {
+            QueryMatch::Present(name) => name,
+            QueryMatch::Equals(name, value) => format!("{name}={value}"),
+            QueryMatch::Absent(name) => format!("!{name}"),
+        }
     }
+}
 
-    pub fn default_config() -> Self {
-        Self {
-            server: ServerConfig {
-                host: default_host(),
-                port: default_port(),
-                workers: default_workers(),
-                timeout_ms: Some(DEFAULT_TIMEOUT_MS), // 30 seconds
-                tls: Some(TlsConfig {
-                    enabled: true,
-                    port: default_https_port(),
-                    cert_file: "/etc/ssl/certs/ssl-cert-snakeoil.pem".to_string(),
-                    key_file: "/etc/ssl/private/ssl-cert-snakeoil.key".to_string(),
-                    redirect_http: true,
-                }),
+/// Which client headers are forwarded to a route's upstream
+///
+/// Written as `"all"`, `"none"` or a list of header names. Hop-by-hop headers
+/// are dropped in every mode, and the route's own `headers` are always added.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "ForwardHeadersValue", into = "ForwardHeadersValue")]
+pub enum ForwardHeaders {
+    /// Forward every client header
+    #[default]
+    All,
+    /// Forward no client headers
+    None,
+    /// Forward only the listed client headers (lowercase names)
+    Allowlist(Vec<String>),
+}
+
+impl ForwardHeaders {
+    /// Check whether a client header may be forwarded
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::None => false,
+            Self::Allowlist(names) => names
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(name)),
+        }
+    }
+}
+
+/// TOML representation of `forward_headers`: a mode name or a list of headers
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ForwardHeadersValue {
+    Mode(String),
+    Allowlist(Vec<String>),
+}
+
+impl TryFrom<ForwardHeadersValue> for ForwardHeaders {
+    type Error = String;
+
+    fn try_from(value: ForwardHeadersValue) -> Result<Self, Self::Error> {
+        match value {
+            ForwardHeadersValue::Mode(mode) => match mode.as_str() {
+                "all" => Ok(Self::All),
+                "none" => Ok(Self::None),
+                _ => Err(format!(
+                    "invalid forward_headers mode '{mode}': expected \"all\", \"none\" or a list of header names"
+                )),
             },
-            routes: vec![
-                RouteConfig {
-                    path: "/get/*".to_string(),
-                    upstream: "https://httpbin.org".to_string(),
-                    methods: vec!["GET".to_string()],
-                    headers: HashMap::new(),
-                    strip_path: true,
-                    preserve_host: false,
-                    timeout_ms: Some(30000),
-                },
-                RouteConfig {
-                    path: "/post/*".to_string(),
-                    upstream: "https://httpbin.org".to_string(),
-                    methods: vec!["POST".to_string()],
-                    headers: HashMap::new(),
-                    strip_path: true,
-                    preserve_host: false,
-                    timeout_ms: Some(30000),
-                },
-                RouteConfig {
-                    path: "/json/*".to_string(),
-                    upstream: "https://httpbin.org".to_string(),
-                    methods: vec!["GET".to_string()],
-                    headers: HashMap::new(),
-                    strip_path: true,
-                    preserve_host: false,
-                    timeout_ms: Some(30000),
-                },
-                RouteConfig {
-                    path: "/status/*".to_string(),
-                    upstream: "https://httpbin.org".to_string(),
-                    methods: vec!["GET".to_string()],
-                    headers: HashMap::new(),
-                    strip_path: true,
-                    preserve_host: false,
-                    timeout_ms: Some(30000),
-                },
-            ],
-            logging: LoggingConfig::default(),
+            ForwardHeadersValue::Allowlist(names) => names
+                .into_iter()
+                .map(|name| {
+                    reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                        .map(|header| header.as_str().to_string())
+                        .map_err(|_| format!("invalid forward_headers entry '{name}'"))
+                })
+                .collect::<Result<_, _>>()
+                .map(Self::Allowlist),
+        }
+    }
+}
+
+impl From<ForwardHeaders> for ForwardHeadersValue {
+    fn from(value: ForwardHeaders) -> Self {
+        match value {
+            ForwardHeaders::All => Self::Mode("all".to_string()),
+            ForwardHeaders::None => Self::Mode("none".to_string()),
+            ForwardHeaders::Allowlist(names) => Self::Allowlist(names),
+        }
+    }
+}
+
+/// User-Agent policy for requests forwarded to a route's upstream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum UpstreamUserAgent {
+    /// Forward the client's original User-Agent
+    Passthrough,
+    /// Replace the client's User-Agent with the gateway's own
+    GatewayDefault,
+    /// Replace the client's User-Agent with a fixed value
+    Literal(String),
+}
+
+impl From<String> for UpstreamUserAgent {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "passthrough" => Self::Passthrough,
+            "gateway-default" => Self::GatewayDefault,
+            _ => Self::Literal(value),
+        }
+    }
+}
+
+impl From<UpstreamUserAgent> for String {
+    fn from(value: UpstreamUserAgent) -> Self {
+        match value {
+            UpstreamUserAgent::Passthrough => "passthrough".to_string(),
+            UpstreamUserAgent::GatewayDefault => "gateway-default".to_string(),
+            UpstreamUserAgent::Literal(value) => value,
+        }
+    }
+}
+
+/// Accept-Encoding policy for requests forwarded to a route's upstream
+///
+/// Stripping the header makes upstreams respond with uncompressed bodies that
+/// the gateway can inspect or rewrite.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum UpstreamAcceptEncoding {
+    /// Forward the client's original Accept-Encoding
+    Passthrough,
+    /// Remove Accept-Encoding so the upstream responds uncompressed
+    Strip,
+    /// Replace the client's Accept-Encoding with a fixed value
+    Literal(String),
+}
+
+impl From<String> for UpstreamAcceptEncoding {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "passthrough" => Self::Passthrough,
+            "strip" => Self::Strip,
+            _ => Self::Literal(value),
+        }
+    }
+}
+
+impl From<UpstreamAcceptEncoding> for String {
+    fn from(value: UpstreamAcceptEncoding) -> Self {
+        match value {
+            UpstreamAcceptEncoding::Passthrough => "passthrough".to_string(),
+            UpstreamAcceptEncoding::Strip => "strip".to_string(),
+            UpstreamAcceptEncoding::Literal(value) => value,
+        }
+    }
+}
+
+/// HTTP version the gateway speaks to a route's upstream
+///
+/// Without one, HTTP/2 is used only when a TLS upstream offers it during the
+/// handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamProtocol {
+    /// Always HTTP/1.1, even when the upstream offers HTTP/2
+    Http1,
+    /// Always HTTP/2 with prior knowledge, including over cleartext
+    Http2,
+}
+
+/// How a route's upstream response bodies reach the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseMode {
+    /// Forward the body as it arrives from the upstream
+    Stream,
+    /// Read the whole body first, so it can be checked and rewritten
+    Buffer,
+}
+
+/// Logging configuration structure
+///
+/// Controls how the gateway handles logging output, including levels and formats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Log level (trace, debug, info, warn, error) - default: "info"
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Whether to output logs in JSON format
+    #[serde(default)]
+    pub json: bool,
+    /// Whether to write logs to files
+    #[serde(default)]
+    pub file: bool,
+    /// Directory for log files (if file logging is enabled)
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Request fields recorded on tracing spans
+    #[serde(default)]
+    pub span_fields: SpanFieldsConfig,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            json: false,
+            file: false,
+            dir: None,
+            span_fields: SpanFieldsConfig::default(),
+        }
+    }
+}
+
+/// Request fields recorded on the spans of proxied requests
+///
+/// The full URI is recorded by default; query strings often carry tokens or
+/// personal data, so they can be redacted or left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanFieldsConfig {
+    /// How the request URI is recorded (default: "full")
+    #[serde(default)]
+    pub uri: SpanUri,
+    /// Request headers recorded in the span's `headers` field (none by default)
+    #[serde(default)]
+    pub headers: Vec<String>,
+}
+
+impl SpanFieldsConfig {
+    /// Configured headers present on the request, as `name=value` pairs
+    ///
+    /// Returns `None` when no header is configured or none is present.
+    pub fn render_headers(&self, headers: &HeaderMap) -> Option<String> {
+        let recorded: Vec<String> = self
+            .headers
+            .iter()
+            .filter_map(|name| {
+                let value = headers.get(name.as_str())?.to_str().ok()?;
+                Some(format!("{}={}", name.to_ascii_lowercase(), value))
+            })
+            .collect();
+        (!recorded.is_empty()).then(|| recorded.join(", "))
+    }
+}
+
+/// How a request URI is recorded on tracing spans
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanUri {
+    /// Path and query string as received
+    #[default]
+    Full,
+    /// Path and query parameter names, with every value replaced
+    RedactQuery,
+    /// Path only
+    Path,
+}
+
+impl SpanUri {
+    /// Render a request URI for a span
+    pub fn render(&self, uri: &axum::http::Uri) -> String {
+        match (self, uri.query()) {
+            (Self::Full, _) => uri.to_string(),
+            (Self::RedactQuery, Some(query)) => {
+                let redacted: Vec<String> = query
+                    .split('&')
+                    .map(|pair| match pair.split_once('=') {
+                        Some((name, _)) => format!("{name}={REDACTED_QUERY_VALUE}"),
+                        None => REDACTED_QUERY_VALUE.to_string(),
+                    })
+                    .collect();
+                format!("{}?{}", uri.path(), redacted.join("&"))
+            }
+            (Self::RedactQuery, None) | (Self::Path, _) => uri.path().to_string(),
         }
     }
+}
+
+fn default_host() -> String {
+    DEFAULT_HOST.to_string()
+}
+
+fn default_port() -> u16 {
+    DEFAULT_HTTP_PORT
+}
+
+fn default_workers() -> Option<usize> {
+    None
+}
+
+fn default_log_level() -> String {
+    DEFAULT_LOG_LEVEL.to_string()
+}
+
+fn default_https_port() -> u16 {
+    DEFAULT_HTTPS_PORT
+}
+
+fn default_auto_generate_cert() -> bool {
+    true
+}
+
+fn default_retry_budget_ratio() -> f64 {
+    DEFAULT_RETRY_BUDGET_RATIO
+}
+
+fn default_error_sensitivity() -> f64 {
+    DEFAULT_HEALTH_WEIGHT_ERROR_SENSITIVITY
+}
+
+fn default_latency_sensitivity() -> f64 {
+    DEFAULT_HEALTH_WEIGHT_LATENCY_SENSITIVITY
+}
+
+fn default_eject_secs() -> u64 {
+    DEFAULT_HEALTH_WEIGHT_EJECT_SECS
+}
+
+fn default_min_weight() -> f64 {
+    DEFAULT_HEALTH_WEIGHT_MIN_WEIGHT
+}
+
+fn default_min_requests() -> usize {
+    DEFAULT_HEALTH_WEIGHT_MIN_REQUESTS
+}
+
+fn default_retry_budget_window_secs() -> u64 {
+    DEFAULT_RETRY_BUDGET_WINDOW_SECS
+}
+
+fn default_max_retry_after_ms() -> u64 {
+    DEFAULT_MAX_RETRY_AFTER_MS
+}
+
+fn default_max_retry_body_bytes() -> u64 {
+    DEFAULT_MAX_RETRY_BODY_BYTES
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    DEFAULT_DRAIN_TIMEOUT_SECS
+}
+
+fn default_interrupt_drain_timeout_secs() -> u64 {
+    DEFAULT_INTERRUPT_DRAIN_TIMEOUT_SECS
+}
+
+fn default_listen_backlog() -> u32 {
+    DEFAULT_LISTEN_BACKLOG
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    DEFAULT_IDLE_TIMEOUT_SECS
+}
+
+fn default_max_request_headers() -> usize {
+    DEFAULT_MAX_REQUEST_HEADERS
+}
+
+fn default_max_request_header_bytes() -> usize {
+    DEFAULT_MAX_REQUEST_HEADER_BYTES
+}
+
+fn default_queue_timeout_ms() -> u64 {
+    DEFAULT_QUEUE_TIMEOUT_MS
+}
+
+fn default_upstream_health_check_path() -> String {
+    DEFAULT_UPSTREAM_HEALTH_CHECK_PATH.to_string()
+}
+
+fn default_upstream_health_check_interval_secs() -> u64 {
+    DEFAULT_UPSTREAM_HEALTH_CHECK_INTERVAL_SECS
+}
+
+fn default_upstream_health_check_timeout_ms() -> u64 {
+    DEFAULT_UPSTREAM_HEALTH_CHECK_TIMEOUT_MS
+}
+
+fn default_body_log_max_bytes() -> usize {
+    DEFAULT_BODY_LOG_MAX_BYTES
+}
+
+fn default_max_health_checks() -> usize {
+    DEFAULT_MAX_HEALTH_CHECKS
+}
+
+fn default_shutdown_status() -> u16 {
+    DEFAULT_SHUTDOWN_STATUS
+}
+
+fn default_shutdown_body() -> String {
+    MSG_SHUTTING_DOWN.to_string()
+}
+
+fn default_maintenance_body() -> String {
+    MSG_MAINTENANCE_MODE.to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_metrics_latency_buckets() -> Vec<f64> {
+    METRICS_LATENCY_BUCKETS.to_vec()
+}
+
+fn default_http_versions() -> Vec<HttpVersion> {
+    vec![HttpVersion::Http11, HttpVersion::H2]
+}
+
+fn deserialize_methods<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer).map(expand_methods)
+}
+
+/// Expand the wildcard and method groups in a route's methods
+///
+/// `*` allows every method, so a list containing it becomes empty. `READ`
+/// (GET, HEAD, OPTIONS) and `WRITE` (POST, PUT, PATCH, DELETE) are replaced by
+/// their methods. Other entries are kept as written for validation to check;
+/// repeated methods are dropped.
+pub fn expand_methods(methods: Vec<String>) -> Vec<String> {
+    if methods.iter().any(|method| method == METHOD_WILDCARD) {
+        return Vec::new();
+    }
+    let mut expanded: Vec<String> = Vec::with_capacity(methods.len());
+    for method in methods {
+        let group = match method.to_uppercase().as_str() {
+            "READ" => READ_METHODS,
+            "WRITE" => WRITE_METHODS,
+            _ => &[],
+        };
+        let members = if group.is_empty() {
+            vec![method]
+        } else {
+            group.iter().map(|m| m.to_string()).collect()
+        };
+        for member in members {
+            if !expanded.iter().any(|m| m.eq_ignore_ascii_case(&member)) {
+                expanded.push(member);
+            }
+        }
+    }
+    expanded
+}
+
+impl GatewayConfig {
+    /// Start building a configuration in code
+    ///
+    /// ```
+    /// use ferragate::config::{GatewayConfig, RouteConfig};
+    ///
+    /// let config = GatewayConfig::builder()
+    ///     .port(8080)
+    ///     .route(
+    ///         RouteConfig::builder("/api/*", "http://backend:3000")
+    ///             .methods(["GET", "POST"])
+    ///             .strip_path(true),
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(config.routes[0].methods, ["GET", "POST"]);
+    /// ```
+    #[allow(dead_code)] // Public API method
+    pub fn builder() -> GatewayConfigBuilder {
+        GatewayConfigBuilder::new()
+    }
+
+    /// Load configuration from a TOML file
+    ///
+    /// Reads and parses a TOML configuration file, validates the configuration,
+    /// and returns a GatewayConfig instance.
+    pub fn from_file(path: &str) -> FerragateResult<Self> {
+        let config = Self::read_file(path)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Load a configuration file with the named profile applied, then validate it
+    #[allow(dead_code)] // Public API method
+    pub fn from_file_with_profile(path: &str, profile: Option<&str>) -> FerragateResult<Self> {
+        let config = Self::read_file_with_profile(path, profile)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Load a configuration file without validating it
+    ///
+    /// The profile named by the `FERRAGATE_PROFILE` environment variable, if set,
+    /// is applied; see [`GatewayConfig::read_file_with_profile`].
+    pub fn read_file(path: &str) -> FerragateResult<Self> {
+        Self::read_file_with_profile(path, active_profile().as_deref())
+    }
+
+    /// Load a configuration file with the named profile applied, without validating it
+    ///
+    /// The profile's values, from the file's `[profiles.<name>]` table, override
+    /// the base configuration. Naming a profile the file does not define is an error.
+    pub fn read_file_with_profile(path: &str, profile: Option<&str>) -> FerragateResult<Self> {
+        info!("Loading configuration from: {}", path);
+
+        let content = fs::read_to_string(path).map_err(|e| {
+            FerragateError::config(format!("Failed to read config file '{path}': {e}"))
+        })?;
+
+        if let Some(profile) = profile {
+            info!("Using configuration profile: {}", profile);
+        }
+        let mut config = parse_config(&content, profile).map_err(|e| {
+            FerragateError::config(format!("Failed to parse config file '{path}': {e}"))
+        })?;
+
+        if let Some(routes_path) = config.routes_file_path(path) {
+            if !config.routes.is_empty() {
+                return Err(FerragateError::config(
+                    "Routes must be defined either in the config file or in routes_file, not both",
+                ));
+            }
+            config.routes = Self::read_routes_file(&routes_path)?;
+        }
+
+        info!("{} from: {}", LOG_CONFIG_LOADED, path);
+        debug!("Loaded config: {:#?}", config);
+
+        Ok(config)
+    }
+
+    /// Location of the separate routes file, resolved against the configuration file's directory
+    pub fn routes_file_path(&self, config_path: &str) -> Option<PathBuf> {
+        let routes_file = self.server.routes_file.as_deref()?;
+        let base = Path::new(config_path).parent().unwrap_or(Path::new(""));
+        Some(base.join(routes_file))
+    }
+
+    /// Load the routes from a separate routes file without validating them
+    pub fn read_routes_file(path: &Path) -> FerragateResult<Vec<RouteConfig>> {
+        info!("Loading routes from: {}", path.display());
+
+        let content = fs::read_to_string(path).map_err(|e| {
+            FerragateError::config(format!(
+                "Failed to read routes file '{}': {e}",
+                path.display()
+            ))
+        })?;
+
+        let raw: RawRoutesFile = toml::from_str(&content).map_err(|e| {
+            FerragateError::config(format!(
+                "Failed to parse routes file '{}': {e}",
+                path.display()
+            ))
+        })?;
+
+        build_routes(raw.routes, raw.route_defaults).map_err(|e| {
+            FerragateError::config(format!(
+                "Failed to parse routes file '{}': {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Problems that do not make the configuration invalid but are worth reporting
+    fn collect_warnings(&self) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+        if self.routes.is_empty() {
+            warnings.push(ConfigWarning::NoRoutes);
+        }
+        if !self.server.strict_validation {
+            warnings.extend(self.duplicate_routes());
+        }
+        for route in self
+            .routes
+            .iter()
+            .filter(|route| route.log_bodies.is_some())
+        {
+            warnings.push(ConfigWarning::BodyLogging(route.path.clone()));
+        }
+
+        if let Some(tls) = self.server.tls.as_ref().filter(|tls| tls.enabled) {
+            // Missing certificate files are allowed when they can be auto-generated
+            if !std::path::Path::new(&tls.cert_file).exists() {
+                warnings.push(ConfigWarning::MissingCertFile(tls.cert_file.clone()));
+            }
+            if !std::path::Path::new(&tls.key_file).exists() {
+                warnings.push(ConfigWarning::MissingKeyFile(tls.key_file.clone()));
+            }
+        }
+
+        warnings
+    }
+
+    /// Validate the configuration
+    ///
+    /// Performs comprehensive validation of the configuration including
+    /// route validation, TLS setup, and basic sanity checks. Returns the
+    /// warnings found in a valid configuration; they are also logged.
+    pub fn validate(&self) -> FerragateResult<Vec<ConfigWarning>> {
+        let warnings = self.collect_warnings();
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+
+        if let Some(tls) = self.server.tls.as_ref().filter(|tls| tls.enabled) {
+            info!(
+                "TLS configuration validated: cert={}, key={}",
+                tls.cert_file, tls.key_file
+            );
+        }
+
+        if !(0.0..=1.0).contains(&self.server.retry_budget_ratio) {
+            return Err(FerragateError::config(format!(
+                "retry_budget_ratio must be between 0.0 and 1.0, got {}",
+                self.server.retry_budget_ratio
+            )));
+        }
+        if self.server.retry_budget_window_secs == 0 {
+            return Err(FerragateError::config(
+                "retry_budget_window_secs must be greater than 0",
+            ));
+        }
+
+        if let Some(shedding) = &self.server.load_shedding {
+            if shedding.max_in_flight == Some(0) || shedding.max_latency_ms == Some(0) {
+                return Err(FerragateError::config(
+                    "load_shedding thresholds must be greater than 0",
+                ));
+            }
+        }
+
+        if let Some(upstream) = &self.server.default_upstream {
+            url::Url::parse(upstream).map_err(|e| {
+                FerragateError::config(format!("Invalid default_upstream URL '{upstream}': {e}"))
+            })?;
+        }
+
+        for (i, rule) in self.server.deny_rules.iter().enumerate() {
+            if rule.methods.is_empty() && rule.path.is_none() {
+                return Err(FerragateError::config(format!(
+                    "deny_rules[{i}] must set methods or path, or it would deny every request"
+                )));
+            }
+            if let Some(method) = rule
+                .methods
+                .iter()
+                .find(|method| axum::http::Method::from_bytes(method.as_bytes()).is_err())
+            {
+                return Err(FerragateError::config(format!(
+                    "deny_rules[{i}] has an invalid HTTP method: '{method}'"
+                )));
+            }
+        }
+
+        if let Some(priority) = &self.server.priority {
+            reqwest::header::HeaderName::from_bytes(priority.header.as_bytes()).map_err(|_| {
+                FerragateError::config(format!("Invalid priority header: '{}'", priority.header))
+            })?;
+        }
+
+        if let Some(via) = &self.server.via {
+            let is_token = !via.is_empty()
+                && via
+                    .bytes()
+                    .all(|b| b.is_ascii_graphic() && !matches!(b, b',' | b'(' | b')'));
+            if !is_token {
+                return Err(FerragateError::config(format!(
+                    "Invalid via: '{via}' (must be a single name without spaces or commas)"
+                )));
+            }
+        }
+        if let Some(server_header) = &self.server.server_header {
+            reqwest::header::HeaderValue::from_str(server_header).map_err(|_| {
+                FerragateError::config(format!("Invalid server_header: '{server_header}'"))
+            })?;
+        }
+
+        if let Some(header) = &self.server.client_timeout_header {
+            reqwest::header::HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                FerragateError::config(format!("Invalid client_timeout_header: '{header}'"))
+            })?;
+        }
+
+        if let Some(header) = &self.server.client_ip_header {
+            reqwest::header::HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                FerragateError::config(format!("Invalid client_ip_header: '{header}'"))
+            })?;
+        }
+        for cidr in &self.server.trusted_proxies {
+            parse_cidr(cidr).ok_or_else(|| {
+                FerragateError::config(format!("Invalid trusted_proxies entry: '{cidr}'"))
+            })?;
+        }
+
+        if self.server.max_connections == Some(0) {
+            return Err(FerragateError::config(
+                "max_connections must be greater than 0",
+            ));
+        }
+        if let Some(rate) = self.server.max_accept_rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(FerragateError::config(
+                    "max_accept_rate must be greater than 0",
+                ));
+            }
+        }
+        if !(400..=599).contains(&self.server.shutdown_status) {
+            return Err(FerragateError::config(format!(
+                "shutdown_status must be a 4xx or 5xx status, got {}",
+                self.server.shutdown_status
+            )));
+        }
+        if self.server.tcp_keepalive_secs == Some(0) {
+            return Err(FerragateError::config(
+                "tcp_keepalive_secs must be greater than 0",
+            ));
+        }
+        if self.server.max_request_body_bytes == Some(0) {
+            return Err(FerragateError::config(
+                "max_request_body_bytes must be greater than 0",
+            ));
+        }
+        if self.server.max_request_headers == 0 {
+            return Err(FerragateError::config(
+                "max_request_headers must be greater than 0",
+            ));
+        }
+        if self.server.max_request_header_bytes == 0 {
+            return Err(FerragateError::config(
+                "max_request_header_bytes must be greater than 0",
+            ));
+        }
+
+        if self.server.listen_backlog == 0 {
+            return Err(FerragateError::config(
+                "listen_backlog must be greater than 0",
+            ));
+        }
+        if self.server.idle_timeout_secs == 0 {
+            return Err(FerragateError::config(
+                "idle_timeout_secs must be greater than 0",
+            ));
+        }
+        if self.server.max_health_checks == 0 {
+            return Err(FerragateError::config(
+                "max_health_checks must be greater than 0",
+            ));
+        }
+        if self.server.config_poll_interval_secs == Some(0) {
+            return Err(FerragateError::config(
+                "config_poll_interval_secs must be greater than 0",
+            ));
+        }
+
+        for name in &self.logging.span_fields.headers {
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                FerragateError::config(format!("Invalid span_fields header: '{name}'"))
+            })?;
+        }
+
+        let buckets = &self.server.metrics_latency_buckets;
+        if buckets
+            .iter()
+            .any(|bound| !(bound.is_finite() && *bound > 0.0))
+            || buckets.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(FerragateError::config(
+                "metrics_latency_buckets must be positive and sorted in ascending order",
+            ));
+        }
+
+        for file in [&self.server.favicon_file, &self.server.robots_txt_file]
+            .into_iter()
+            .flatten()
+        {
+            if !std::path::Path::new(file).is_file() {
+                return Err(FerragateError::config(format!(
+                    "Built-in file not found: {file}"
+                )));
+            }
+        }
+
+        if self.server.http_versions.is_empty() {
+            return Err(FerragateError::config(
+                "http_versions must allow at least one HTTP version",
+            ));
+        }
+
+        self.validate_exclusive_options()?;
+
+        // Validate each route
+        for (i, route) in self.routes.iter().enumerate() {
+            route
+                .validate()
+                .and_then(|()| {
+                    if self.server.require_https_upstreams {
+                        route.validate_https_upstreams()
+                    } else {
+                        Ok(())
+                    }
+                })
+                .map_err(|e| FerragateError::config(format!("Route {i}: {e}")))?;
+        }
+        if self.server.strict_validation {
+            if let Some(duplicate) = self.duplicate_routes().into_iter().next() {
+                return Err(FerragateError::config(duplicate.to_string()));
+            }
+        }
+
+        info!("Configuration validation completed successfully");
+        Ok(warnings)
+    }
+
+    /// Enabled routes shadowed by an earlier enabled route with the same path and match rules
+    ///
+    /// Routes are compared on their path, methods, `header_match` (which also
+    /// covers the Host header), `query_match`, `tls_match` and `content_type_match`;
+    /// the order and case of methods and media types do not matter.
+    fn duplicate_routes(&self) -> Vec<ConfigWarning> {
+        let mut seen = HashMap::new();
+        let mut duplicates = Vec::new();
+        for (i, route) in self
+            .routes
+            .iter()
+            .enumerate()
+            .filter(|(_, route)| route.enabled)
+        {
+            match seen.entry(route.match_key()) {
+                std::collections::hash_map::Entry::Occupied(first) => {
+                    duplicates.push(ConfigWarning::DuplicateRoute {
+                        first: *first.get(),
+                        duplicate: i,
+                        path: route.path.clone(),
+                    });
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(i);
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// Reject server options that contradict each other
+    fn validate_exclusive_options(&self) -> FerragateResult<()> {
+        let tls_enabled = self.server.tls.as_ref().is_some_and(|tls| tls.enabled);
+
+        if let Some(tls) = &self.server.tls {
+            if tls.enabled && tls.port == self.server.port {
+                return Err(FerragateError::config(format!(
+                    "tls.port and port cannot both be {}",
+                    tls.port
+                )));
+            }
+            if tls.redirect_http && !tls.enabled {
+                return Err(FerragateError::config(
+                    "tls.redirect_http requires tls.enabled = true",
+                ));
+            }
+            if tls.require_client_cert && tls.client_ca_file.is_none() {
+                return Err(FerragateError::config(
+                    "tls.require_client_cert requires tls.client_ca_file",
+                ));
+            }
+            if let Some(ca_file) = tls.client_ca_file.as_ref().filter(|_| tls.enabled) {
+                if !std::path::Path::new(ca_file).is_file() {
+                    return Err(FerragateError::config(format!(
+                        "tls.client_ca_file not found: {ca_file}"
+                    )));
+                }
+            }
+        }
+
+        let client_ca = self
+            .server
+            .tls
+            .as_ref()
+            .is_some_and(|tls| tls.enabled && tls.client_ca_file.is_some());
+        for route in &self.routes {
+            if !tls_enabled && !route.tls_match.is_empty() {
+                return Err(FerragateError::config(format!(
+                    "tls_match on route {} requires tls.enabled = true",
+                    route.path
+                )));
+            }
+            if !client_ca
+                && route
+                    .tls_match
+                    .iter()
+                    .any(|rule| rule.field.is_client_cert())
+            {
+                return Err(FerragateError::config(format!(
+                    "tls_match on client certificates (route {}) requires tls.client_ca_file",
+                    route.path
+                )));
+            }
+        }
+
+        if self.server.client_ip_header.is_some()
+            && self.server.trusted_proxies.is_empty()
+            && self.server.trusted_hops == 0
+        {
+            return Err(FerragateError::config(
+                "client_ip_header requires trusted_proxies or trusted_hops; the header is ignored otherwise",
+            ));
+        }
+
+        if self.server.priority.is_none()
+            && self
+                .routes
+                .iter()
+                .any(|route| !route.priority_upstreams.is_empty())
+        {
+            return Err(FerragateError::config(
+                "priority_upstreams requires a server priority header",
+            ));
+        }
+
+        if self.server.favicon_file.is_some() && !self.server.serve_favicon {
+            return Err(FerragateError::config(
+                "favicon_file requires serve_favicon = true",
+            ));
+        }
+        if self.server.robots_txt_file.is_some() && !self.server.serve_robots_txt {
+            return Err(FerragateError::config(
+                "robots_txt_file requires serve_robots_txt = true",
+            ));
+        }
+
+        if self.server.loopback_health_check
+            && !self.server.http_versions.contains(&HttpVersion::Http11)
+        {
+            return Err(FerragateError::config(
+                "loopback_health_check requires h1.1 in http_versions",
+            ));
+        }
+
+        if !tls_enabled && self.server.http_versions == [HttpVersion::H2] {
+            return Err(FerragateError::config(
+                "http_versions only allows h2, which requires tls.enabled = true",
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn default_config() -> Self {
+        Self {
+            server: ServerConfig {
+                host: default_host(),
+                port: default_port(),
+                workers: default_workers(),
+                timeout_ms: Some(DEFAULT_TIMEOUT_MS), // 30 seconds
+                tls: Some(TlsConfig {
+                    enabled: true,
+                    port: default_https_port(),
+                    cert_file: "/etc/ssl/certs/ssl-cert-snakeoil.pem".to_string(),
+                    key_file: "/etc/ssl/private/ssl-cert-snakeoil.key".to_string(),
+                    redirect_http: true,
+                    auto_generate_cert: true,
+                    client_ca_file: None,
+                    require_client_cert: false,
+                }),
+                ..Default::default()
+            },
+            routes: vec![
+                RouteConfig {
+                    path: "/get/*".to_string(),
+                    upstream: "https://httpbin.org".to_string(),
+                    methods: vec!["GET".to_string()],
+                    headers: HashMap::new(),
+                    strip_path: true,
+                    preserve_host: false,
+                    timeout_ms: Some(30000),
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/post/*".to_string(),
+                    upstream: "https://httpbin.org".to_string(),
+                    methods: vec!["POST".to_string()],
+                    headers: HashMap::new(),
+                    strip_path: true,
+                    preserve_host: false,
+                    timeout_ms: Some(30000),
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/json/*".to_string(),
+                    upstream: "https://httpbin.org".to_string(),
+                    methods: vec!["GET".to_string()],
+                    headers: HashMap::new(),
+                    strip_path: true,
+                    preserve_host: false,
+                    timeout_ms: Some(30000),
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/status/*".to_string(),
+                    upstream: "https://httpbin.org".to_string(),
+                    methods: vec!["GET".to_string()],
+                    headers: HashMap::new(),
+                    strip_path: true,
+                    preserve_host: false,
+                    timeout_ms: Some(30000),
+                    ..Default::default()
+                },
+            ],
+            logging: LoggingConfig::default(),
+        }
+    }
+
+    pub fn save_example(path: &str) -> FerragateResult<()> {
+        let config = Self::default_config();
+        let content = toml::to_string_pretty(&config)?;
+
+        fs::write(path, &content)?;
+
+        info!("Example configuration saved to: {}", path);
+        Ok(())
+    }
+
+    /// Get the fully-resolved configuration, with implicit defaults filled in
+    ///
+    /// Every route setting with an `effective_*` value is replaced by it, and the
+    /// routes are listed in the order requests are matched against them, so the
+    /// result shows exactly how each route will behave.
+    pub fn effective(&self) -> Self {
+        let mut config = self.clone();
+        let server_timeout = config.server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+        config.server.timeout_ms = Some(server_timeout);
+
+        for route in &mut config.routes {
+            route.total_timeout_ms = Some(route.effective_total_timeout(&config.server));
+            route.headers_timeout_ms = Some(route.effective_headers_timeout(server_timeout));
+            route.body_timeout_ms = Some(route.effective_body_timeout(server_timeout));
+            route.timeout_ms = Some(route.effective_timeout(server_timeout));
+            route.retry_backoff_ms = Some(route.retry_backoff(1));
+            if route.mirror_upstream.is_some() {
+                route.mirror_sample_rate = Some(route.effective_mirror_sample_rate());
+            }
+            route.max_redirects = route.effective_max_redirects();
+            route.upstream_protocol = route.effective_upstream_protocol();
+            route.response_mode = Some(route.effective_response_mode());
+            route.max_response_bytes = Some(
+                route
+                    .effective_max_response_bytes(&config.server)
+                    .unwrap_or(0),
+            );
+            route.max_response_body_bytes = None;
+        }
+        // Stable, so equally specific routes keep their configuration order
+        config
+            .routes
+            .sort_by_key(|route| std::cmp::Reverse(route.specificity()));
+
+        config
+    }
+}
+
+impl RouteConfig {
+    /// Start building a route forwarding `path` to `upstream`
+    #[allow(dead_code)] // Public API method
+    pub fn builder(path: impl Into<String>, upstream: impl Into<String>) -> RouteConfigBuilder {
+        RouteConfigBuilder::new(path, upstream)
+    }
+
+    /// Check if this route matches the given path
+    ///
+    /// Supports wildcard matching with "/*" suffix for prefix matching.
+    /// Returns true if the path matches this route's pattern.
+    #[allow(dead_code)] // Public API method
+    pub fn matches_path(&self, path: &str) -> bool {
+        if self.path.ends_with("/*") {
+            let prefix = &self.path[..self.path.len() - 2];
+            path.starts_with(prefix)
+        } else {
+            path == self.path
+        }
+    }
+
+    /// How specific this route's path is; higher values are matched first
+    ///
+    /// An exact path outranks any prefix route that also matches it, and a
+    /// longer prefix outranks a shorter one.
+    pub fn specificity(&self) -> usize {
+        match self.path.strip_suffix("/*") {
+            Some(prefix) => prefix.len() * 2,
+            None => self.path.len() * 2 + 1,
+        }
+    }
+
+    /// Check if this route allows the given HTTP method
+    ///
+    /// Returns true if:
+    /// - No methods are specified (allows all methods)
+    /// - The method is explicitly listed in allowed methods
+    pub fn matches_method(&self, method: &str) -> bool {
+        if self.methods.is_empty() {
+            return true; // No method restriction
+        }
+        self.methods
+            .iter()
+            .any(|m| m.to_uppercase() == method.to_uppercase())
+    }
+
+    /// Check whether a request's Content-Type is allowed by `allowed_request_content_types`
+    pub fn allows_request_content_type(&self, content_type: Option<&str>, has_body: bool) -> bool {
+        content_type_allowed(&self.allowed_request_content_types, content_type, has_body)
+    }
+
+    /// Check whether a response's Content-Type is allowed by `allowed_response_content_types`
+    pub fn allows_response_content_type(&self, content_type: Option<&str>, has_body: bool) -> bool {
+        content_type_allowed(&self.allowed_response_content_types, content_type, has_body)
+    }
+
+    /// Get the client-facing status for an upstream status, applying `status_map`
+    pub fn map_status(&self, status: u16) -> u16 {
+        self.status_map.get(&status).copied().unwrap_or(status)
+    }
+
+    /// Check if the request headers satisfy every `header_match` rule
+    pub fn matches_headers(&self, headers: &HeaderMap) -> bool {
+        self.header_match.iter().all(|rule| rule.matches(headers))
+    }
+
+    /// The requests a route matches, in a form that compares equal between duplicates
+    fn match_key(&self) -> RouteMatchKey {
+        let mut methods: Vec<String> = self.methods.iter().map(|m| m.to_uppercase()).collect();
+        methods.sort();
+        methods.dedup();
+        let mut headers: Vec<(String, String)> = self
+            .header_match
+            .iter()
+            .map(|rule| (rule.name.to_lowercase(), rule.regex.0.as_str().to_string()))
+            .collect();
+        headers.sort();
+        let mut query: Vec<String> = self.query_match.iter().cloned().map(String::from).collect();
+        query.sort();
+        let mut tls: Vec<(TlsField, String)> = self
+            .tls_match
+            .iter()
+            .map(|rule| (rule.field, rule.regex.0.as_str().to_string()))
+            .collect();
+        tls.sort();
+        let mut content_types: Vec<String> = self
+            .content_type_match
+            .iter()
+            .map(|media_type| media_type.trim().to_ascii_lowercase())
+            .collect();
+        content_types.sort();
+        content_types.dedup();
+        (
+            self.path.clone(),
+            methods,
+            headers,
+            query,
+            tls,
+            content_types,
+        )
+    }
+
+    /// Check if the request's Content-Type satisfies `content_type_match`
+    ///
+    /// Requests without a Content-Type only match routes that accept any.
+    pub fn matches_content_type(&self, headers: &HeaderMap) -> bool {
+        if self.content_type_match.is_empty() {
+            return true;
+        }
+        headers
+            .get(axum::http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| media_type_matches(&self.content_type_match, content_type))
+    }
+
+    /// Check if the request's TLS connection satisfies every `tls_match` rule
+    pub fn matches_tls(&self, tls: Option<&TlsInfo>) -> bool {
+        self.tls_match.iter().all(|rule| rule.matches(tls))
+    }
+
+    /// Check if the query string satisfies every `query_match` rule
+    pub fn matches_query(&self, query: &str) -> bool {
+        self.query_match.iter().all(|rule| rule.matches(query))
+    }
+
+    /// Transform the original request path for upstream forwarding
+    ///
+    /// If `strip_path` is enabled and the route uses wildcard matching,
+    /// the matched prefix will be removed from the path before forwarding.
+    pub fn transform_path(&self, original_path: &str) -> String {
+        if !self.strip_path {
+            return original_path.to_string();
+        }
+
+        if self.path.ends_with("/*") {
+            let prefix = &self.path[..self.path.len() - 2];
+            if let Some(remaining) = original_path.strip_prefix(prefix) {
+                if remaining.is_empty() {
+                    "/".to_string()
+                } else {
+                    remaining.to_string()
+                }
+            } else {
+                original_path.to_string()
+            }
+        } else {
+            original_path.to_string()
+        }
+    }
+
+    /// Largest upstream response body for the route in any response mode, or None if unlimited
+    pub fn effective_max_response_bytes(&self, server: &ServerConfig) -> Option<u64> {
+        Some(
+            self.route_max_response_bytes()
+                .unwrap_or(server.max_response_bytes),
+        )
+        .filter(|&bytes| bytes > 0)
+    }
+
+    /// Largest response body the route reads into memory in buffer mode
+    ///
+    /// Buffering is never unlimited: a route without a limit of its own buffers
+    /// up to the server's limit, capped at `DEFAULT_MAX_RESPONSE_BODY_BYTES`.
+    pub fn effective_max_buffered_response_bytes(&self, server: &ServerConfig) -> u64 {
+        match self.route_max_response_bytes().filter(|&bytes| bytes > 0) {
+            Some(max_bytes) => max_bytes,
+            None => self
+                .effective_max_response_bytes(server)
+                .map_or(DEFAULT_MAX_RESPONSE_BODY_BYTES, |max_bytes| {
+                    max_bytes.min(DEFAULT_MAX_RESPONSE_BODY_BYTES)
+                }),
+        }
+    }
+
+    /// The route's own response size limit, under either of its names
+    fn route_max_response_bytes(&self) -> Option<u64> {
+        self.max_response_bytes.or(self.max_response_body_bytes)
+    }
+
+    /// Response mode of the route; buffering is the default only when bodies are decoded, rewritten or logged
+    pub fn effective_response_mode(&self) -> ResponseMode {
+        self.response_mode.unwrap_or(
+            if self.body_replacements.is_empty()
+                && !self.decompress_response
+                && self.log_bodies.is_none()
+            {
+                ResponseMode::Stream
+            } else {
+                ResponseMode::Buffer
+            },
+        )
+    }
+
+    /// Every upstream the route can send requests to, including priority, mirror and canary upstreams
+    pub fn all_upstreams(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.upstream)
+            .chain(&self.upstreams)
+            .chain(self.priority_upstreams.values().flatten())
+            .chain(&self.mirror_upstream)
+            .chain(&self.canary_upstream)
+    }
+
+    /// Reject any upstream of the route, including mirror and canary upstreams, that is not HTTPS
+    pub fn validate_https_upstreams(&self) -> FerragateResult<()> {
+        for upstream in self.all_upstreams() {
+            let is_https = url::Url::parse(upstream).is_ok_and(|url| url.scheme() == "https");
+            if !is_https {
+                return Err(FerragateError::validation(format!(
+                    "Upstream '{upstream}' must use https:// because require_https_upstreams is enabled"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate this route configuration
+    ///
+    /// Checks that the route has valid path, upstream URL, and HTTP methods.
+    pub fn validate(&self) -> FerragateResult<()> {
+        // Validate path
+        if self.path.is_empty() {
+            return Err(FerragateError::validation("Route path cannot be empty"));
+        }
+
+        // Validate upstream URL
+        url::Url::parse(&self.upstream).map_err(|e| {
+            FerragateError::validation(format!("Invalid upstream URL '{}': {}", self.upstream, e))
+        })?;
+
+        // Validate methods
+        for method in &self.methods {
+            match method.to_uppercase().as_str() {
+                "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" | "OPTIONS" => {}
+                _ => {
+                    return Err(FerragateError::validation(format!(
+                        "Invalid HTTP method or method group: {method}"
+                    )))
+                }
+            }
+        }
+
+        // Validate header match rules
+        for rule in &self.header_match {
+            if HeaderName::from_bytes(rule.name.as_bytes()).is_err() {
+                return Err(FerragateError::validation(format!(
+                    "Invalid header_match name: '{}'",
+                    rule.name
+                )));
+            }
+        }
+
+        // Validate status code mappings
+        for (from, to) in &self.status_map {
+            for code in [from, to] {
+                if !(100..=599).contains(code) {
+                    return Err(FerragateError::validation(format!(
+                        "Invalid status code in status_map: {code}"
+                    )));
+                }
+            }
+        }
+
+        // Validate the source address for upstream connections
+        if let Some(address) = self.local_address {
+            std::net::UdpSocket::bind((address, 0)).map_err(|e| {
+                FerragateError::validation(format!(
+                    "local_address {address} is not an address of this host: {e}"
+                ))
+            })?;
+        }
+
+        // Validate mirror settings
+        if let Some(mirror) = &self.mirror_upstream {
+            url::Url::parse(mirror).map_err(|e| {
+                FerragateError::validation(format!("Invalid mirror_upstream URL '{mirror}': {e}"))
+            })?;
+        }
+        if !(0.0..=1.0).contains(&self.effective_mirror_sample_rate()) {
+            return Err(FerragateError::validation(
+                "mirror_sample_rate must be between 0.0 and 1.0",
+            ));
+        }
+
+        // Validate load balancing settings
+        for upstream in &self.upstreams {
+            url::Url::parse(upstream).map_err(|e| {
+                FerragateError::validation(format!("Invalid upstreams URL '{upstream}': {e}"))
+            })?;
+        }
+        for (priority, pool) in &self.priority_upstreams {
+            if pool.is_empty() {
+                return Err(FerragateError::validation(format!(
+                    "priority_upstreams pool '{priority}' must list at least one upstream"
+                )));
+            }
+            for upstream in pool {
+                url::Url::parse(upstream).map_err(|e| {
+                    FerragateError::validation(format!(
+                        "Invalid priority_upstreams URL '{upstream}': {e}"
+                    ))
+                })?;
+            }
+        }
+        if let Some(weighting) = &self.health_weighting {
+            for sensitivity in [weighting.error_sensitivity, weighting.latency_sensitivity] {
+                if !(sensitivity.is_finite() && sensitivity >= 0.0) {
+                    return Err(FerragateError::validation(
+                        "health_weighting sensitivities must be 0 or greater",
+                    ));
+                }
+            }
+            if !(weighting.min_weight > 0.0 && weighting.min_weight <= 1.0) {
+                return Err(FerragateError::validation(
+                    "health_weighting min_weight must be greater than 0.0 and at most 1.0",
+                ));
+            }
+            if weighting.latency_threshold_ms == Some(0) {
+                return Err(FerragateError::validation(
+                    "health_weighting latency_threshold_ms must be greater than 0",
+                ));
+            }
+            if let Some(rate) = weighting.eject_error_rate {
+                if !(rate > 0.0 && rate <= 1.0) {
+                    return Err(FerragateError::validation(
+                        "health_weighting eject_error_rate must be greater than 0.0 and at most 1.0",
+                    ));
+                }
+            }
+            if weighting.eject_secs == 0 {
+                return Err(FerragateError::validation(
+                    "health_weighting eject_secs must be greater than 0",
+                ));
+            }
+        }
+
+        if self.total_timeout_ms == Some(0) {
+            return Err(FerragateError::validation(
+                "total_timeout_ms must be greater than 0",
+            ));
+        }
+
+        for replacement in &self.body_replacements {
+            if replacement.from.is_empty() {
+                return Err(FerragateError::validation(
+                    "body_replacements from must not be empty",
+                ));
+            }
+            if replacement.content_types.is_empty() {
+                return Err(FerragateError::validation(
+                    "body_replacements content_types must list at least one media type",
+                ));
+            }
+        }
+        if self.max_replacement_body_bytes == Some(0) {
+            return Err(FerragateError::validation(
+                "max_replacement_body_bytes must be greater than 0",
+            ));
+        }
+        for (option, media_types) in [
+            ("content_type_match", &self.content_type_match),
+            (
+                "allowed_request_content_types",
+                &self.allowed_request_content_types,
+            ),
+            (
+                "allowed_response_content_types",
+                &self.allowed_response_content_types,
+            ),
+        ] {
+            if let Some(invalid) = media_types.iter().find(|media_type| {
+                let mut parts = media_type.trim().splitn(2, '/');
+                let (kind, subtype) = (parts.next().unwrap_or_default(), parts.next());
+                kind.is_empty() || subtype.is_none_or(str::is_empty)
+            }) {
+                return Err(FerragateError::validation(format!(
+                    "Invalid {option} entry: '{invalid}' (expected type/subtype or type/*)"
+                )));
+            }
+        }
+        if self.max_decompressed_bytes == Some(0) {
+            return Err(FerragateError::validation(
+                "max_decompressed_bytes must be greater than 0",
+            ));
+        }
+
+        // gRPC-Web calls reach the upstream over cleartext HTTP/2
+        if self.grpc_web {
+            if self.upstream_protocol == Some(UpstreamProtocol::Http1) {
+                return Err(FerragateError::validation(
+                    "grpc_web requires HTTP/2 and cannot be combined with upstream_protocol = \"http1\"",
+                ));
+            }
+            let upstreams = std::iter::once(&self.upstream)
+                .chain(&self.upstreams)
+                .chain(self.priority_upstreams.values().flatten())
+                .chain(&self.canary_upstream);
+            for upstream in upstreams {
+                if !url::Url::parse(upstream).is_ok_and(|url| url.scheme() == "http") {
+                    return Err(FerragateError::validation(format!(
+                        "Upstream '{upstream}' must use http:// because grpc_web calls it over cleartext HTTP/2"
+                    )));
+                }
+            }
+        }
+
+        if let Some(check) = &self.health_check {
+            if check.interval_secs == 0 {
+                return Err(FerragateError::validation(
+                    "health_check interval_secs must be greater than 0",
+                ));
+            }
+            if check.timeout_ms == 0 {
+                return Err(FerragateError::validation(
+                    "health_check timeout_ms must be greater than 0",
+                ));
+            }
+            match check.check_type {
+                UpstreamHealthCheckType::Http => {
+                    if !check.path.starts_with('/') {
+                        return Err(FerragateError::validation(
+                            "health_check path must start with '/'",
+                        ));
+                    }
+                }
+                // gRPC checks reach the upstream over cleartext HTTP/2, like grpc_web
+                UpstreamHealthCheckType::Grpc => {
+                    for upstream in std::iter::once(&self.upstream).chain(&self.upstreams) {
+                        if !url::Url::parse(upstream).is_ok_and(|url| url.scheme() == "http") {
+                            return Err(FerragateError::validation(format!(
+                                "Upstream '{upstream}' must use http:// because gRPC health checks call it over cleartext HTTP/2"
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Rewriting bodies needs them whole, and the size cap only applies when buffering
+        if self.response_mode == Some(ResponseMode::Stream) && !self.body_replacements.is_empty() {
+            return Err(FerragateError::validation(
+                "body_replacements require response_mode = \"buffer\"",
+            ));
+        }
+        if self.response_mode == Some(ResponseMode::Stream) && self.decompress_response {
+            return Err(FerragateError::validation(
+                "decompress_response requires response_mode = \"buffer\"",
+            ));
+        }
+        if self.recompress_response && !self.decompress_response {
+            return Err(FerragateError::validation(
+                "recompress_response requires decompress_response",
+            ));
+        }
+        if let Some(log_bodies) = &self.log_bodies {
+            if self.response_mode == Some(ResponseMode::Stream) {
+                return Err(FerragateError::validation(
+                    "log_bodies requires response_mode = \"buffer\"",
+                ));
+            }
+            if log_bodies.max_bytes == 0 {
+                return Err(FerragateError::validation(
+                    "log_bodies.max_bytes must be greater than 0",
+                ));
+            }
+            if let Some(field) = log_bodies
+                .redact_fields
+                .iter()
+                .find(|field| field.split('.').any(str::is_empty))
+            {
+                return Err(FerragateError::validation(format!(
+                    "Invalid log_bodies.redact_fields entry: '{field}'"
+                )));
+            }
+        }
+        if self.max_response_body_bytes.is_some() && self.max_response_bytes.is_some() {
+            return Err(FerragateError::validation(
+                "max_response_body_bytes is another name for max_response_bytes; set only one",
+            ));
+        }
+
+        // Validate the concurrency limit
+        if let Some(concurrency) = &self.concurrency {
+            if concurrency.max_concurrent == 0 {
+                return Err(FerragateError::validation(
+                    "concurrency max_concurrent must be greater than 0",
+                ));
+            }
+            if concurrency.queue_timeout_ms == 0 {
+                return Err(FerragateError::validation(
+                    "concurrency queue_timeout_ms must be greater than 0",
+                ));
+            }
+        }
+
+        // Validate rate limits
+        if let Some(rate_limit) = &self.rate_limit {
+            let rates = std::iter::once(rate_limit.requests_per_second)
+                .chain(rate_limit.tiers.iter().map(|tier| tier.requests_per_second));
+            for rate in rates {
+                if !(rate.is_finite() && rate > 0.0) {
+                    return Err(FerragateError::validation(
+                        "rate_limit requests_per_second must be greater than 0",
+                    ));
+                }
+            }
+            if rate_limit.tiers.iter().any(|tier| tier.methods.is_empty()) {
+                return Err(FerragateError::validation(
+                    "rate_limit tiers must list at least one method",
+                ));
+            }
+        }
+
+        // Validate canary settings
+        if let Some(canary) = &self.canary_upstream {
+            url::Url::parse(canary).map_err(|e| {
+                FerragateError::validation(format!("Invalid canary_upstream URL '{canary}': {e}"))
+            })?;
+        }
+        if let Some(percent) = self.canary_percent {
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(FerragateError::validation(
+                    "canary_percent must be between 0.0 and 100.0",
+                ));
+            }
+        }
+        if let Some(key) = &self.canary_key {
+            CanaryKey::parse(key).ok_or_else(|| {
+                FerragateError::validation(format!(
+                    "Invalid canary_key '{key}': expected \"client_ip\" or \"header:<name>\""
+                ))
+            })?;
+        }
+
+        // Validate literal User-Agent values
+        if let Some(UpstreamUserAgent::Literal(value)) = &self.upstream_user_agent {
+            if value.is_empty() || reqwest::header::HeaderValue::from_str(value).is_err() {
+                return Err(FerragateError::validation(format!(
+                    "Invalid upstream_user_agent: '{value}'"
+                )));
+            }
+        }
+
+        // Validate literal Accept-Encoding values
+        if let Some(UpstreamAcceptEncoding::Literal(value)) = &self.upstream_accept_encoding {
+            if value.is_empty() || reqwest::header::HeaderValue::from_str(value).is_err() {
+                return Err(FerragateError::validation(format!(
+                    "Invalid upstream_accept_encoding: '{value}'"
+                )));
+            }
+        }
+
+        self.validate_exclusive_options()
+    }
+
+    /// Reject options that contradict each other or depend on an unset option
+    fn validate_exclusive_options(&self) -> FerragateResult<()> {
+        if self.max_redirects.is_some() && !self.follow_redirects {
+            return Err(FerragateError::validation(
+                "max_redirects requires follow_redirects = true",
+            ));
+        }
+
+        if self.mirror_upstream.is_none() && self.mirror_sample_rate.is_some() {
+            return Err(FerragateError::validation(
+                "mirror_sample_rate requires mirror_upstream",
+            ));
+        }
+        if self.mirror_upstream.as_deref() == Some(self.upstream.as_str()) {
+            return Err(FerragateError::validation(
+                "mirror_upstream must differ from upstream",
+            ));
+        }
+
+        if self.health_weighting.is_some() && self.upstreams.is_empty() {
+            return Err(FerragateError::validation(
+                "health_weighting requires upstreams",
+            ));
+        }
+        if self.upstreams.contains(&self.upstream) {
+            return Err(FerragateError::validation(
+                "upstreams must not repeat upstream",
+            ));
+        }
+
+        if self.canary_upstream.is_none()
+            && (self.canary_percent.is_some() || self.canary_key.is_some())
+        {
+            return Err(FerragateError::validation(
+                "canary_percent and canary_key require canary_upstream",
+            ));
+        }
+        if self.canary_upstream.as_deref() == Some(self.upstream.as_str()) {
+            return Err(FerragateError::validation(
+                "canary_upstream must differ from upstream",
+            ));
+        }
+
+        if let Some(rate_limit) = &self.rate_limit {
+            let mut tiered_methods = std::collections::HashSet::new();
+            for method in rate_limit.tiers.iter().flat_map(|tier| &tier.methods) {
+                if !tiered_methods.insert(method.to_uppercase()) {
+                    return Err(FerragateError::validation(format!(
+                        "Method {method} is listed in more than one rate_limit tier"
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the upstream pool for a request priority, matched case-insensitively
+    pub fn priority_pool(&self, priority: &str) -> Option<&[String]> {
+        self.priority_upstreams
+            .iter()
+            .find(|(value, _)| value.eq_ignore_ascii_case(priority))
+            .map(|(_, pool)| pool.as_slice())
+    }
+
+    /// Get the effective timeout for a single upstream attempt
+    ///
+    /// Returns the route-specific timeout if set, otherwise the provided default,
+    /// capped by the route's total timeout budget.
+    pub fn effective_timeout(&self, default_timeout_ms: u64) -> u64 {
+        let attempt_timeout_ms = self.timeout_ms.unwrap_or(default_timeout_ms);
+        self.total_timeout_ms
+            .map_or(attempt_timeout_ms, |total| attempt_timeout_ms.min(total))
+    }
+
+    /// Get the longest time a request may spend upstream, across all attempts
+    ///
+    /// Returns the route's total timeout if set, otherwise the worst case of
+    /// every attempt timing out with the longest permitted wait between them.
+    pub fn effective_total_timeout(&self, server: &ServerConfig) -> u64 {
+        if let Some(total_timeout_ms) = self.total_timeout_ms {
+            return total_timeout_ms;
+        }
+        let attempt_timeout_ms =
+            self.effective_timeout(server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+        (1..=self.retries).fold(attempt_timeout_ms, |total, retry| {
+            let wait_ms = self.retry_backoff(retry).max(server.max_retry_after_ms);
+            total
+                .saturating_add(wait_ms)
+                .saturating_add(attempt_timeout_ms)
+        })
+    }
+
+    /// Get the time to wait for response headers, which the attempt timeout also bounds
+    pub fn effective_headers_timeout(&self, default_timeout_ms: u64) -> u64 {
+        let attempt_timeout_ms = self.effective_timeout(default_timeout_ms);
+        self.headers_timeout_ms
+            .map_or(attempt_timeout_ms, |headers| {
+                headers.min(attempt_timeout_ms)
+            })
+    }
+
+    /// Get the longest gap between body chunks, which the attempt timeout also bounds
+    pub fn effective_body_timeout(&self, default_timeout_ms: u64) -> u64 {
+        let attempt_timeout_ms = self.effective_timeout(default_timeout_ms);
+        self.body_timeout_ms
+            .map_or(attempt_timeout_ms, |body| body.min(attempt_timeout_ms))
+    }
+
+    /// Get the HTTP version used to reach the route's upstreams
+    ///
+    /// Returns None when the version is negotiated over TLS. Cleartext upstreams
+    /// never negotiate HTTP/2, so routes whose upstreams are all `http://` use
+    /// HTTP/1.1 unless HTTP/2 is forced.
+    pub fn effective_upstream_protocol(&self) -> Option<UpstreamProtocol> {
+        self.upstream_protocol.or_else(|| {
+            self.all_upstreams()
+                .all(|upstream| upstream.starts_with("http://"))
+                .then_some(UpstreamProtocol::Http1)
+        })
+    }
+
+    /// Get the fraction of requests to copy to the mirror upstream
+    pub fn effective_mirror_sample_rate(&self) -> f64 {
+        self.mirror_sample_rate
+            .unwrap_or(DEFAULT_MIRROR_SAMPLE_RATE)
+    }
+
+    /// Get the number of upstream redirects to follow
+    ///
+    /// Returns None when redirects are forwarded to the client untouched.
+    pub fn effective_max_redirects(&self) -> Option<u32> {
+        self.follow_redirects
+            .then(|| self.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS))
+    }
+
+    /// Get the backoff to wait before the given retry attempt (1-based)
+    ///
+    /// The configured base backoff is doubled for every subsequent retry.
+    pub fn retry_backoff(&self, attempt: u32) -> u64 {
+        let base = self.retry_backoff_ms.unwrap_or(DEFAULT_RETRY_BACKOFF_MS);
+        base.saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+    }
+}
+
+/// Machine-readable result of validating a configuration
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    /// Why the configuration is invalid, if it is
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+    pub routes: Vec<RouteSummary>,
+}
+
+/// Summary of a configured route in a validation report
+#[derive(Debug, Serialize)]
+pub struct RouteSummary {
+    pub path: String,
+    pub upstream: String,
+    pub methods: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Validate a loaded configuration, or report why it could not be loaded
+    pub fn new(loaded: FerragateResult<GatewayConfig>) -> Self {
+        let config = match loaded {
+            Ok(config) => config,
+            Err(e) => {
+                return Self {
+                    valid: false,
+                    error: Some(e.to_string()),
+                    warnings: Vec::new(),
+                    routes: Vec::new(),
+                }
+            }
+        };
+
+        let (error, warnings) = match config.validate() {
+            Ok(warnings) => (None, warnings.iter().map(ToString::to_string).collect()),
+            Err(e) => (Some(e.to_string()), Vec::new()),
+        };
+        Self {
+            valid: error.is_none(),
+            error,
+            warnings,
+            routes: config
+                .routes
+                .iter()
+                .map(|route| RouteSummary {
+                    path: route.path.clone(),
+                    upstream: route.upstream.clone(),
+                    methods: route.methods.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_matching() {
+        let route = RouteConfig {
+            path: "/get/*".to_string(),
+            upstream: "https://httpbin.org".to_string(),
+            methods: vec!["GET".to_string()],
+            headers: HashMap::new(),
+            strip_path: true,
+            preserve_host: false,
+            timeout_ms: None,
+            ..Default::default()
+        };
+
+        assert!(route.matches_path("/get/anything"));
+        assert!(route.matches_path("/get/"));
+        assert!(!route.matches_path("/post/anything"));
+        assert!(!route.matches_path("/health"));
+
+        assert!(route.matches_method("GET"));
+        assert!(route.matches_method("get"));
+        assert!(!route.matches_method("POST"));
+    }
+
+    #[test]
+    fn test_path_transformation() {
+        let route = RouteConfig {
+            path: "/status/*".to_string(),
+            upstream: "https://httpbin.org".to_string(),
+            methods: vec![],
+            headers: HashMap::new(),
+            strip_path: true,
+            preserve_host: false,
+            timeout_ms: None,
+            ..Default::default()
+        };
+
+        assert_eq!(route.transform_path("/status/200"), "/200");
+        assert_eq!(route.transform_path("/status/"), "/");
+        assert_eq!(route.transform_path("/status"), "/");
+    }
+
+    #[test]
+    fn test_effective_timeout_with_route_specific() {
+        let route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://example.com".to_string(),
+            methods: vec![],
+            headers: std::collections::HashMap::new(),
+            strip_path: false,
+            preserve_host: false,
+            timeout_ms: Some(5000), // Route-specific timeout
+            ..Default::default()
+        };
+
+        // Should return route-specific timeout, ignoring default
+        assert_eq!(route.effective_timeout(30000), 5000);
+        assert_eq!(route.effective_timeout(10000), 5000);
+    }
+
+    #[test]
+    fn test_effective_timeout_with_server_default() {
+        let route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://example.com".to_string(),
+            methods: vec![],
+            headers: std::collections::HashMap::new(),
+            strip_path: false,
+            preserve_host: false,
+            timeout_ms: None, // No route-specific timeout
+            ..Default::default()
+        };
+
+        // Should return server default timeout
+        assert_eq!(route.effective_timeout(30000), 30000);
+        assert_eq!(route.effective_timeout(15000), 15000);
+        assert_eq!(
+            route.effective_timeout(DEFAULT_TIMEOUT_MS),
+            DEFAULT_TIMEOUT_MS
+        );
+    }
+
+    #[test]
+    fn test_effective_timeout_capped_by_total_timeout() {
+        let route: RouteConfig = toml::from_str(
+            r#"
+path = "/api/*"
+upstream = "http://example.com"
+attempt_timeout_ms = 5000
+total_timeout_ms = 2000
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(route.timeout_ms, Some(5000));
+        assert_eq!(route.effective_timeout(30000), 2000);
+
+        let route = RouteConfig {
+            total_timeout_ms: Some(60000),
+            ..route
+        };
+        assert_eq!(route.effective_timeout(30000), 5000);
+    }
+
+    #[test]
+    fn test_effective_timeout_zero_values() {
+        let route_with_zero = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://example.com".to_string(),
+            methods: vec![],
+            headers: std::collections::HashMap::new(),
+            strip_path: false,
+            preserve_host: false,
+            timeout_ms: Some(0), // Zero timeout (valid but unusual)
+            ..Default::default()
+        };
+
+        // Should return zero if explicitly set
+        assert_eq!(route_with_zero.effective_timeout(30000), 0);
+
+        let route_no_timeout = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://example.com".to_string(),
+            methods: vec![],
+            headers: std::collections::HashMap::new(),
+            strip_path: false,
+            preserve_host: false,
+            timeout_ms: None,
+            ..Default::default()
+        };
+
+        // Should handle zero default gracefully
+        assert_eq!(route_no_timeout.effective_timeout(0), 0);
+    }
+
+    #[test]
+    fn test_retry_backoff_doubles_per_attempt() {
+        let route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://example.com".to_string(),
+            retries: 3,
+            retry_backoff_ms: Some(50),
+            ..Default::default()
+        };
+
+        assert_eq!(route.retry_backoff(1), 50);
+        assert_eq!(route.retry_backoff(2), 100);
+        assert_eq!(route.retry_backoff(3), 200);
+
+        let default_route = RouteConfig::default();
+        assert_eq!(default_route.retry_backoff(1), DEFAULT_RETRY_BACKOFF_MS);
+    }
+
+    #[test]
+    fn test_retry_budget_validation() {
+        let mut config = GatewayConfig::default_config();
+        config.server.tls = None;
+        assert!(config.validate().is_ok());
+
+        config.server.retry_budget_ratio = 1.5;
+        assert!(config.validate().is_err());
+
+        config.server.retry_budget_ratio = 0.2;
+        config.server.retry_budget_window_secs = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_span_fields_rendering_and_validation() {
+        let uri: axum::http::Uri = "/api/items?token=abc&flag&page=2".parse().unwrap();
+        assert_eq!(
+            SpanUri::Full.render(&uri),
+            "/api/items?token=abc&flag&page=2"
+        );
+        assert_eq!(
+            SpanUri::RedactQuery.render(&uri),
+            "/api/items?token=REDACTED&REDACTED&page=REDACTED"
+        );
+        assert_eq!(SpanUri::Path.render(&uri), "/api/items");
+        let plain: axum::http::Uri = "/api/items".parse().unwrap();
+        assert_eq!(SpanUri::RedactQuery.render(&plain), "/api/items");
+
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[logging.span_fields]
+uri = "redact_query"
+headers = ["X-Request-Id", "user-agent"]
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        let span_fields = &config.logging.span_fields;
+        assert_eq!(span_fields.uri, SpanUri::RedactQuery);
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(span_fields.render_headers(&headers), None);
+        headers.insert("x-request-id", "req-1".parse().unwrap());
+        headers.insert("user-agent", "curl/8.0".parse().unwrap());
+        headers.insert("cookie", "session=secret".parse().unwrap());
+        assert_eq!(
+            span_fields.render_headers(&headers).as_deref(),
+            Some("x-request-id=req-1, user-agent=curl/8.0")
+        );
+
+        config.logging.span_fields.headers = vec!["bad header".to_string()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_upstream_accept_encoding_parsing() {
+        let toml_content = r#"
+[server]
+
+[[routes]]
+path = "/a/*"
+upstream = "http://localhost:3000"
+upstream_accept_encoding = "passthrough"
+
+[[routes]]
+path = "/b/*"
+upstream = "http://localhost:3000"
+upstream_accept_encoding = "strip"
+
+[[routes]]
+path = "/c/*"
+upstream = "http://localhost:3000"
+upstream_accept_encoding = "identity"
+"#;
+        let mut config: GatewayConfig = toml::from_str(toml_content).unwrap();
+        assert_eq!(
+            config.routes[0].upstream_accept_encoding,
+            Some(UpstreamAcceptEncoding::Passthrough)
+        );
+        assert_eq!(
+            config.routes[1].upstream_accept_encoding,
+            Some(UpstreamAcceptEncoding::Strip)
+        );
+        assert_eq!(
+            config.routes[2].upstream_accept_encoding,
+            Some(UpstreamAcceptEncoding::Literal("identity".to_string()))
+        );
+        assert!(config.validate().is_ok());
+
+        config.routes[2].upstream_accept_encoding =
+            Some(UpstreamAcceptEncoding::Literal(String::new()));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_upstream_user_agent_parsing() {
+        let toml_content = r#"
+[server]
+
+[[routes]]
+path = "/a/*"
+upstream = "http://localhost:3000"
+upstream_user_agent = "passthrough"
+
+[[routes]]
+path = "/b/*"
+upstream = "http://localhost:3000"
+upstream_user_agent = "gateway-default"
+
+[[routes]]
+path = "/c/*"
+upstream = "http://localhost:3000"
+upstream_user_agent = "my-agent/1.0"
+"#;
+        let config: GatewayConfig = toml::from_str(toml_content).unwrap();
+        assert_eq!(
+            config.routes[0].upstream_user_agent,
+            Some(UpstreamUserAgent::Passthrough)
+        );
+        assert_eq!(
+            config.routes[1].upstream_user_agent,
+            Some(UpstreamUserAgent::GatewayDefault)
+        );
+        assert_eq!(
+            config.routes[2].upstream_user_agent,
+            Some(UpstreamUserAgent::Literal("my-agent/1.0".to_string()))
+        );
+
+        let mut route = config.routes[2].clone();
+        route.upstream_user_agent = Some(UpstreamUserAgent::Literal("bad\nagent".to_string()));
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_upstream_protocol_parsing() {
+        let route = |protocol: &str| {
+            format!(
+                "[server]\n\n[[routes]]\npath = \"/a/*\"\nupstream = \"http://localhost:3000\"\n{protocol}"
+            )
+        };
+
+        let config: GatewayConfig = toml::from_str(&route("")).unwrap();
+        assert_eq!(config.routes[0].upstream_protocol, None);
+        let config: GatewayConfig =
+            toml::from_str(&route("upstream_protocol = \"http1\"")).unwrap();
+        assert_eq!(
+            config.routes[0].upstream_protocol,
+            Some(UpstreamProtocol::Http1)
+        );
+        let config: GatewayConfig =
+            toml::from_str(&route("upstream_protocol = \"http2\"")).unwrap();
+        assert_eq!(
+            config.routes[0].upstream_protocol,
+            Some(UpstreamProtocol::Http2)
+        );
+
+        let error = toml::from_str::<GatewayConfig>(&route("upstream_protocol = \"h3\""))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("http1"), "{error}");
+    }
+
+    #[test]
+    fn test_local_address_validation() {
+        let route = |address: &str| {
+            format!(
+                "[server]\n\n[[routes]]\npath = \"/a/*\"\nupstream = \"http://localhost:3000\"\nlocal_address = \"{address}\""
+            )
+        };
+
+        let config: GatewayConfig = toml::from_str(&route("127.0.0.1")).unwrap();
+        assert_eq!(
+            config.routes[0].local_address,
+            Some(IpAddr::from([127, 0, 0, 1]))
+        );
+        assert!(config.validate().is_ok());
+
+        // Addresses not assigned to this host are rejected
+        let config: GatewayConfig = toml::from_str(&route("192.0.2.1")).unwrap();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("local_address 192.0.2.1"), "{error}");
+
+        assert!(toml::from_str::<GatewayConfig>(&route("not-an-ip")).is_err());
+    }
+
+    #[test]
+    fn test_mirror_validation() {
+        let mut route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://localhost:3000".to_string(),
+            mirror_upstream: Some("http://localhost:4000".to_string()),
+            ..Default::default()
+        };
+        assert!(route.validate().is_ok());
+        assert_eq!(route.effective_mirror_sample_rate(), 1.0);
+
+        route.mirror_sample_rate = Some(1.5);
+        assert!(route.validate().is_err());
+
+        route.mirror_sample_rate = Some(0.5);
+        route.mirror_upstream = Some("not a url".to_string());
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_effective_config_fills_defaults() {
+        let mut config = GatewayConfig::default_config();
+        config.routes[0].timeout_ms = None;
+        config.routes[0].retry_backoff_ms = None;
+
+        let effective = config.effective();
+        assert_eq!(effective.server.timeout_ms, Some(DEFAULT_TIMEOUT_MS));
+        assert_eq!(effective.routes[0].timeout_ms, Some(DEFAULT_TIMEOUT_MS));
+        assert_eq!(
+            effective.routes[0].retry_backoff_ms,
+            Some(DEFAULT_RETRY_BACKOFF_MS)
+        );
+    }
+
+    #[test]
+    fn test_effective_config_resolves_every_route_setting() {
+        let route = |path: &str| RouteConfig {
+            path: path.to_string(),
+            upstream: "http://backend:3000".to_string(),
+            ..Default::default()
+        };
+        let mut config = GatewayConfig::builder()
+            .route(route("/*"))
+            .route(RouteConfig {
+                timeout_ms: Some(1000),
+                headers_timeout_ms: Some(5000),
+                body_timeout_ms: Some(200),
+                retries: 1,
+                retry_backoff_ms: Some(50),
+                max_response_body_bytes: Some(4096),
+                body_replacements: vec![BodyReplacement {
+                    from: "a".to_string(),
+                    to: "b".to_string(),
+                    content_types: vec!["text/html".to_string()],
+                }],
+                ..route("/api/*")
+            })
+            .route(route("/api/health"))
+            .build()
+            .unwrap();
+        config.server.max_response_bytes = 0;
+        config.server.max_retry_after_ms = 0;
+
+        let effective = config.effective();
+        let paths: Vec<_> = effective.routes.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["/api/health", "/api/*", "/*"]);
+
+        let api = &effective.routes[1];
+        assert_eq!(api.response_mode, Some(ResponseMode::Buffer));
+        assert_eq!(api.max_response_bytes, Some(4096));
+        assert_eq!(api.max_response_body_bytes, None);
+        assert_eq!(api.total_timeout_ms, Some(2050));
+        assert_eq!(api.headers_timeout_ms, Some(1000));
+        assert_eq!(api.body_timeout_ms, Some(200));
+        assert_eq!(api.upstream_protocol, Some(UpstreamProtocol::Http1));
+
+        let fallback = &effective.routes[2];
+        assert_eq!(fallback.response_mode, Some(ResponseMode::Stream));
+        assert_eq!(fallback.max_response_bytes, Some(0));
+        assert_eq!(fallback.total_timeout_ms, Some(DEFAULT_TIMEOUT_MS));
+
+        // Resolved values describe the same behavior when loaded again
+        assert!(effective.validate().is_ok());
+        assert_eq!(
+            toml::to_string(&effective.effective()).unwrap(),
+            toml::to_string(&effective).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_redirect_settings() {
+        let mut route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://localhost:3000".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(route.effective_max_redirects(), None);
+
+        route.max_redirects = Some(3);
+        assert!(route.validate().is_err());
+
+        route.follow_redirects = true;
+        assert!(route.validate().is_ok());
+        assert_eq!(route.effective_max_redirects(), Some(3));
+
+        route.max_redirects = None;
+        assert_eq!(route.effective_max_redirects(), Some(DEFAULT_MAX_REDIRECTS));
+    }
+
+    #[test]
+    fn test_health_weighting_ejection_validation() {
+        let route = |eject_error_rate, eject_secs| RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://a:3000".to_string(),
+            upstreams: vec!["http://b:3000".to_string()],
+            health_weighting: Some(HealthWeightingConfig {
+                eject_error_rate,
+                eject_secs,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(route(None, DEFAULT_HEALTH_WEIGHT_EJECT_SECS)
+            .validate()
+            .is_ok());
+        assert!(route(Some(0.5), 10).validate().is_ok());
+        assert!(route(Some(1.0), 10).validate().is_ok());
+        for rate in [0.0, 1.5, f64::NAN] {
+            assert!(route(Some(rate), 10).validate().is_err());
+        }
+        assert!(route(Some(0.5), 0).validate().is_err());
+    }
+
+    #[test]
+    fn test_route_exclusive_options() {
+        let base = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://localhost:3000".to_string(),
+            ..Default::default()
+        };
+        let error = |route: RouteConfig| route.validate().unwrap_err().to_string();
+
+        let route = RouteConfig {
+            mirror_sample_rate: Some(0.5),
+            ..base.clone()
+        };
+        assert!(error(route).contains("mirror_sample_rate requires mirror_upstream"));
+
+        let route = RouteConfig {
+            mirror_upstream: Some(base.upstream.clone()),
+            ..base.clone()
+        };
+        assert!(error(route).contains("mirror_upstream must differ from upstream"));
+
+        let route = RouteConfig {
+            health_weighting: Some(HealthWeightingConfig::default()),
+            ..base.clone()
+        };
+        assert!(error(route).contains("health_weighting requires upstreams"));
+
+        let route = RouteConfig {
+            upstreams: vec![base.upstream.clone()],
+            ..base.clone()
+        };
+        assert!(error(route).contains("upstreams must not repeat upstream"));
+
+        let route = RouteConfig {
+            canary_percent: Some(10.0),
+            ..base.clone()
+        };
+        assert!(error(route).contains("require canary_upstream"));
+
+        let route = RouteConfig {
+            canary_key: Some("client_ip".to_string()),
+            ..base.clone()
+        };
+        assert!(error(route).contains("require canary_upstream"));
+
+        let route = RouteConfig {
+            canary_upstream: Some(base.upstream.clone()),
+            ..base.clone()
+        };
+        assert!(error(route).contains("canary_upstream must differ from upstream"));
+
+        let tier = |methods: &[&str]| RateLimitTier {
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            requests_per_second: 1.0,
+            burst: None,
+        };
+        let route = RouteConfig {
+            rate_limit: Some(RateLimitConfig {
+                requests_per_second: 10.0,
+                burst: None,
+                tiers: vec![tier(&["POST", "PUT"]), tier(&["put"])],
+            }),
+            ..base.clone()
+        };
+        assert!(error(route).contains("more than one rate_limit tier"));
+    }
+
+    #[test]
+    fn test_validate_returns_warnings() {
+        let mut config = GatewayConfig::default_config();
+        let tls = config.server.tls.take().unwrap();
+        assert_eq!(config.validate().unwrap(), []);
+
+        config.routes.clear();
+        let tls = config.server.tls.insert(tls);
+        tls.enabled = true;
+        tls.cert_file = "/nonexistent/server.crt".to_string();
+        tls.key_file = "/nonexistent/server.key".to_string();
+        let warnings = config.validate().unwrap();
+        assert_eq!(
+            warnings,
+            [
+                ConfigWarning::NoRoutes,
+                ConfigWarning::MissingCertFile("/nonexistent/server.crt".to_string()),
+                ConfigWarning::MissingKeyFile("/nonexistent/server.key".to_string()),
+            ]
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            "TLS certificate file not found: /nonexistent/server.crt (will be auto-generated)"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_routes_warn_or_fail_in_strict_mode() {
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+methods = ["GET", "POST"]
+header_match = [{ name = "Host", regex = "^api\\.example\\.com$" }]
+
+# Same methods in another order and case, same host rule: a duplicate
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:4000"
+methods = ["post", "GET"]
+header_match = [{ name = "host", regex = "^api\\.example\\.com$" }]
+
+# Near duplicates that match different requests
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:5000"
+methods = ["GET"]
+header_match = [{ name = "Host", regex = "^api\\.example\\.com$" }]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:5000"
+methods = ["GET", "POST"]
+header_match = [{ name = "Host", regex = "^internal\\.example\\.com$" }]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:5000"
+methods = ["GET", "POST"]
+header_match = [{ name = "Host", regex = "^api\\.example\\.com$" }]
+query_match = ["beta"]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:5000"
+methods = ["GET", "POST"]
+header_match = [{ name = "Host", regex = "^api\\.example\\.com$" }]
+content_type_match = ["application/xml"]
+"#,
+        )
+        .unwrap();
+
+        let warnings = config.validate().unwrap();
+        assert_eq!(
+            warnings,
+            [ConfigWarning::DuplicateRoute {
+                first: 0,
+                duplicate: 1,
+                path: "/api/*".to_string(),
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "Route 1 (/api/*) duplicates route 0 and will never match"
+        );
+
+        config.server.strict_validation = true;
+        let error = config.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("Route 1 (/api/*) duplicates route 0"),
+            "{error}"
+        );
+
+        config.routes.remove(1);
+        assert_eq!(config.validate().unwrap(), []);
+    }
+
+    #[test]
+    fn test_content_type_match_validation() {
+        let mut route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://localhost:3000".to_string(),
+            content_type_match: vec!["application/json".to_string(), "text/*".to_string()],
+            ..Default::default()
+        };
+        assert!(route.validate().is_ok());
+
+        route.content_type_match.push("json".to_string());
+        let error = route.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("Invalid content_type_match entry: 'json'"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_require_https_upstreams() {
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/secure/*"
+upstream = "https://secure.example.com"
+upstreams = ["https://secure-2.example.com"]
+
+[[routes]]
+path = "/legacy/*"
+upstream = "https://legacy.example.com"
+mirror_upstream = "http://shadow.example.com"
+"#,
+        )
+        .unwrap();
+
+        // Plaintext upstreams are allowed unless the flag is set
+        assert!(config.validate().is_ok());
+
+        config.server.require_https_upstreams = true;
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("Route 1"), "{error}");
+        assert!(error.contains("http://shadow.example.com"), "{error}");
+
+        config.routes[1].mirror_upstream = None;
+        assert!(config.validate().is_ok());
+
+        config.routes[0]
+            .upstreams
+            .push("http://secure-3.example.com".to_string());
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("Route 0"), "{error}");
+        assert!(error.contains("must use https://"), "{error}");
+    }
+
+    #[test]
+    fn test_route_enabled_by_default() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:4000"
+enabled = false
+"#,
+        )
+        .unwrap();
+        assert!(config.routes[0].enabled);
+        assert!(!config.routes[1].enabled);
+        assert!(RouteConfig::default().enabled);
+
+        // A disabled copy of a route is not reported as a duplicate
+        assert_eq!(config.validate().unwrap(), []);
+    }
+
+    #[test]
+    fn test_server_exclusive_options() {
+        let error = |config: &GatewayConfig| config.validate().unwrap_err().to_string();
+
+        let mut config = GatewayConfig::default_config();
+        let tls = config.server.tls.as_mut().unwrap();
+        tls.port = config.server.port;
+        assert!(error(&config).contains("tls.port and port cannot both be"));
+
+        let mut config = GatewayConfig::default_config();
+        let tls = config.server.tls.as_mut().unwrap();
+        tls.enabled = false;
+        tls.redirect_http = true;
+        assert!(error(&config).contains("tls.redirect_http requires tls.enabled"));
+
+        let mut config = GatewayConfig::default_config();
+        config.server.client_ip_header = Some("x-real-ip".to_string());
+        assert!(error(&config).contains("client_ip_header requires trusted_proxies"));
+
+        let mut config = GatewayConfig::default_config();
+        config.server.tls = None;
+        config.server.http_versions = vec![HttpVersion::H2];
+        assert!(error(&config).contains("requires tls.enabled"));
+    }
+
+    #[test]
+    fn test_canary_validation() {
+        let mut route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://localhost:3000".to_string(),
+            canary_upstream: Some("http://localhost:3001".to_string()),
+            canary_percent: Some(5.0),
+            canary_key: Some("header:x-user-id".to_string()),
+            ..Default::default()
+        };
+        assert!(route.validate().is_ok());
+
+        route.canary_percent = Some(150.0);
+        assert!(route.validate().is_err());
+
+        route.canary_percent = Some(5.0);
+        route.canary_key = Some("cookie".to_string());
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_client_ip_settings_validation() {
+        let mut config = GatewayConfig::default_config();
+        config.server.tls = None;
+        config.server.client_ip_header = Some("CF-Connecting-IP".to_string());
+        config.server.trusted_proxies = vec!["10.0.0.0/8".to_string(), "::1".to_string()];
+        assert!(config.validate().is_ok());
+
+        config.server.trusted_proxies.push("not-a-cidr".to_string());
+        assert!(config.validate().is_err());
+
+        config.server.trusted_proxies.pop();
+        config.server.client_ip_header = Some("bad header".to_string());
+        assert!(config.validate().is_err());
+
+        // A hop count alone is enough to trust the header
+        config.server.client_ip_header = Some("x-real-ip".to_string());
+        config.server.trusted_proxies.clear();
+        assert!(config.validate().is_err());
+        config.server.trusted_hops = 1;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_metrics_latency_buckets_validation() {
+        let mut config = GatewayConfig::default_config();
+        config.server.tls = None;
+        config.server.metrics_latency_buckets = vec![0.001, 0.01, 0.1];
+        assert!(config.validate().is_ok());
+
+        config.server.metrics_latency_buckets = vec![0.1, 0.01];
+        assert!(config.validate().is_err());
+
+        config.server.metrics_latency_buckets = vec![0.01, 0.01];
+        assert!(config.validate().is_err());
+
+        config.server.metrics_latency_buckets = vec![0.0, 0.1];
+        assert!(config.validate().is_err());
+
+        config.server.metrics_latency_buckets = vec![0.1, f64::INFINITY];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_routes_loaded_from_separate_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("conf.d")).unwrap();
+        let config_path = dir.path().join("gateway.toml");
+        std::fs::write(
+            &config_path,
+            "[server]\nroutes_file = \"conf.d/routes.toml\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("conf.d/routes.toml"),
+            r#"
+[route_defaults]
+retries = 2
+
+[[routes]]
+path = "/api/*"
+upstream = "http://backend:3000"
+
+[[routes]]
+path = "/users/*"
+upstream = "http://users:8000"
+retries = 0
+"#,
+        )
+        .unwrap();
+
+        let config = GatewayConfig::from_file(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(config.routes.len(), 2);
+        assert_eq!(config.routes[0].upstream, "http://backend:3000");
+        assert_eq!(config.routes[0].retries, 2);
+        assert_eq!(config.routes[1].retries, 0);
+        assert_eq!(
+            config.routes_file_path(config_path.to_str().unwrap()),
+            Some(dir.path().join("conf.d/routes.toml"))
+        );
+    }
+
+    #[test]
+    fn test_routes_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("gateway.toml");
+        let config_path_str = config_path.to_str().unwrap();
+
+        // A missing routes file fails to load
+        std::fs::write(&config_path, "[server]\nroutes_file = \"routes.toml\"\n").unwrap();
+        let err = GatewayConfig::read_file(config_path_str).unwrap_err();
+        assert!(err.to_string().contains("routes file"));
+
+        // Routes cannot be split between both files
+        std::fs::write(dir.path().join("routes.toml"), "routes = []\n").unwrap();
+        std::fs::write(
+            &config_path,
+            "[server]\nroutes_file = \"routes.toml\"\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"http://backend:3000\"\n",
+        )
+        .unwrap();
+        assert!(GatewayConfig::read_file(config_path_str).is_err());
+    }
+
+    #[test]
+    fn test_profiles_override_base_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("gateway.toml");
+        let config_path = config_path.to_str().unwrap();
+        std::fs::write(
+            config_path,
+            r#"
+[server]
+port = 8080
+timeout_ms = 30000
+
+[logging]
+level = "info"
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[profiles.dev.logging]
+level = "debug"
+
+[profiles.prod.server]
+port = 80
+workers = 8
+
+[[profiles.prod.routes]]
+path = "/api/*"
+upstream = "http://api-service:8080"
+
+[[profiles.prod.routes]]
+path = "/admin/*"
+upstream = "http://admin-service:8080"
+"#,
+        )
+        .unwrap();
+
+        let base = GatewayConfig::read_file_with_profile(config_path, None).unwrap();
+        assert_eq!(base.server.port, 8080);
+        assert_eq!(base.logging.level, "info");
+        assert_eq!(base.routes.len(), 1);
+
+        // Tables merge key by key, keeping base values the profile does not set
+        let dev = GatewayConfig::from_file_with_profile(config_path, Some("dev")).unwrap();
+        assert_eq!(dev.logging.level, "debug");
+        assert_eq!(dev.server.port, 8080);
+        assert_eq!(dev.routes[0].upstream, "http://localhost:3000");
+
+        // Arrays such as routes are replaced as a whole
+        let prod = GatewayConfig::from_file_with_profile(config_path, Some("prod")).unwrap();
+        assert_eq!(prod.server.port, 80);
+        assert_eq!(prod.server.workers, Some(8));
+        assert_eq!(prod.server.timeout_ms, Some(30000));
+        assert_eq!(prod.logging.level, "info");
+        let upstreams: Vec<&str> = prod.routes.iter().map(|r| r.upstream.as_str()).collect();
+        assert_eq!(
+            upstreams,
+            ["http://api-service:8080", "http://admin-service:8080"]
+        );
+    }
+
+    #[test]
+    fn test_undefined_profile_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("gateway.toml");
+        let config_path = config_path.to_str().unwrap();
+        std::fs::write(
+            config_path,
+            r#"
+routes = []
+
+[server]
+
+[profiles.dev.server]
+port = 3000
+"#,
+        )
+        .unwrap();
+
+        let err = GatewayConfig::read_file_with_profile(config_path, Some("staging")).unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("profile 'staging' is not defined"),
+            "{message}"
+        );
+        assert!(message.contains("dev"), "{message}");
+
+        // `profiles` must be a table of named profiles
+        std::fs::write(config_path, "profiles = 1\n[server]\n").unwrap();
+        assert!(GatewayConfig::read_file_with_profile(config_path, None).is_err());
+    }
+
+    #[test]
+    fn test_route_defaults_applied_to_every_route() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[route_defaults]
+methods = ["GET", "POST"]
+timeout_ms = 2500
+strip_path = true
+
+[[routes]]
+path = "/users/*"
+upstream = "http://users:8080"
+
+[[routes]]
+path = "/orders/*"
+upstream = "http://orders:8080"
+"#,
+        )
+        .unwrap();
+
+        for route in &config.routes {
+            assert_eq!(route.methods, vec!["GET", "POST"]);
+            assert_eq!(route.timeout_ms, Some(2500));
+            assert!(route.strip_path);
+        }
+    }
+
+    #[test]
+    fn test_route_overrides_defaults_and_headers_merge() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[route_defaults]
+timeout_ms = 2500
+strip_path = true
+
+[route_defaults.headers]
+X-Gateway = "ferragate"
+X-Team = "platform"
+
+[[routes]]
+path = "/users/*"
+upstream = "http://users:8080"
+timeout_ms = 100
+strip_path = false
+
+[routes.headers]
+X-Team = "users"
+"#,
+        )
+        .unwrap();
+
+        let route = &config.routes[0];
+        assert_eq!(route.timeout_ms, Some(100));
+        assert!(!route.strip_path);
+        assert_eq!(route.headers["X-Gateway"], "ferragate");
+        assert_eq!(route.headers["X-Team"], "users");
+    }
+
+    #[test]
+    fn test_invalid_route_after_defaults_reports_route() {
+        let result: Result<GatewayConfig, _> = toml::from_str(
+            r#"
+[server]
+
+[route_defaults]
+timeout_ms = "slow"
+
+[[routes]]
+path = "/users/*"
+upstream = "http://users:8080"
+"#,
+        );
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("invalid route 1"), "{error}");
+    }
+
+    #[test]
+    fn test_header_match_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[[routes.header_match]]
+name = "User-Agent"
+regex = "(?i)(android|iphone)"
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.routes[0].header_match[0].name, "User-Agent");
+
+        let result: Result<GatewayConfig, _> = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[[routes.header_match]]
+name = "User-Agent"
+regex = "(unclosed"
+"#,
+        );
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("invalid header_match regex"), "{error}");
+
+        let mut route = config.routes[0].clone();
+        route.header_match[0].name = "bad header".to_string();
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_query_match_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+query_match = ["v=2", "debug", "!legacy"]
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.routes[0].query_match,
+            [
+                QueryMatch::Equals("v".to_string(), "2".to_string()),
+                QueryMatch::Present("debug".to_string()),
+                QueryMatch::Absent("legacy".to_string()),
+            ]
+        );
+
+        for rule in ["", "=2", "!", "!legacy=1"] {
+            let result: Result<GatewayConfig, _> = toml::from_str(&format!(
+                "[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"http://localhost:3000\"\nquery_match = [\"{rule}\"]\n"
+            ));
+            let error = result.unwrap_err().to_string();
+            assert!(error.contains("invalid query_match rule"), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_content_type_allowlists() {
+        let mut route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: "http://backend:3000".to_string(),
+            ..Default::default()
+        };
+        assert!(route.allows_request_content_type(Some("application/xml"), true));
+        assert!(route.allows_response_content_type(None, true));
+
+        route.allowed_request_content_types = vec!["application/json".to_string()];
+        route.allowed_response_content_types = vec!["text/*".to_string()];
+        assert!(route.validate().is_ok());
+        assert!(route.allows_request_content_type(Some("application/json; charset=utf-8"), true));
+        assert!(!route.allows_request_content_type(Some("application/xml"), true));
+        assert!(route.allows_request_content_type(None, false));
+        assert!(!route.allows_request_content_type(None, true));
+        assert!(route.allows_response_content_type(Some("TEXT/plain"), true));
+        assert!(!route.allows_response_content_type(Some("textual/plain"), true));
+
+        for invalid in ["json", "application/", "/json", ""] {
+            route.allowed_request_content_types = vec![invalid.to_string()];
+            assert!(route.validate().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_via_and_server_header_validation() {
+        let mut config = GatewayConfig::builder()
+            .route(RouteConfig::builder("/api/*", "http://backend:3000"))
+            .build()
+            .unwrap();
+        config.server.via = Some("ferragate".to_string());
+        config.server.server_header = Some("FerraGate/1.0 (edge)".to_string());
+        assert!(config.validate().is_ok());
+
+        for via in ["", "ferra gate", "a,b"] {
+            config.server.via = Some(via.to_string());
+            assert!(config.validate().is_err(), "via {via:?}");
+        }
+        config.server.via = None;
+        config.server.server_header = Some("bad\nvalue".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_method_groups_expand_on_load() {
+        let route = |methods: &str| {
+            toml::from_str::<GatewayConfig>(&format!(
+                "[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"http://backend:3000\"\nmethods = {methods}\n"
+            ))
+            .unwrap()
+            .routes
+            .remove(0)
+        };
+
+        assert_eq!(route(r#"["READ"]"#).methods, ["GET", "HEAD", "OPTIONS"]);
+        assert_eq!(
+            route(r#"["write"]"#).methods,
+            ["POST", "PUT", "PATCH", "DELETE"]
+        );
+        assert_eq!(
+            route(r#"["get", "READ", "DELETE"]"#).methods,
+            ["get", "HEAD", "OPTIONS", "DELETE"]
+        );
+
+        let all = route(r#"["*", "GET"]"#);
+        assert!(all.methods.is_empty());
+        assert!(all.matches_method("PATCH"));
+
+        let read = route(r#"["READ"]"#);
+        assert!(read.matches_method("HEAD"));
+        assert!(!read.matches_method("POST"));
+
+        let unknown = route(r#"["READS"]"#);
+        let error = unknown.validate().unwrap_err().to_string();
+        assert!(error.contains("Invalid HTTP method or method group: READS"));
+    }
+
+    #[test]
+    fn test_tls_match_parsing_and_validation() {
+        let parse = |tls: &str| {
+            toml::from_str::<GatewayConfig>(&format!(
+                r#"
+[server]
+{tls}
+
+[[routes]]
+path = "/admin/*"
+upstream = "http://localhost:3000"
+tls_match = [
+    {{ field = "sni", regex = "^admin\\." }},
+    {{ field = "client_cert_san", regex = "\\.internal$" }},
+]
+"#
+            ))
+            .unwrap()
+        };
+
+        let config = parse(
+            "[server.tls]\nenabled = true\ncert_file = \"Cargo.toml\"\nkey_file = \"Cargo.toml\"\nclient_ca_file = \"Cargo.toml\"",
+        );
+        assert!(config.validate().is_ok());
+        let route = &config.routes[0];
+        assert_eq!(route.tls_match[0].field, TlsField::Sni);
+
+        let mut tls = TlsInfo {
+            sni: Some("admin.example.com".to_string()),
+            client_cert_subject: Some("CN=ops".to_string()),
+            client_cert_sans: vec!["ops.example.com".to_string(), "ops.internal".to_string()],
+        };
+        assert!(route.matches_tls(Some(&tls)));
+        tls.client_cert_sans.pop();
+        assert!(!route.matches_tls(Some(&tls)));
+        assert!(!route.matches_tls(None));
+        assert!(RouteConfig::default().matches_tls(None));
+
+        let error = parse("").validate().unwrap_err().to_string();
+        assert!(error.contains("requires tls.enabled = true"), "{error}");
+        let error = parse("[server.tls]\nenabled = true\ncert_file = \"a\"\nkey_file = \"b\"")
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("requires tls.client_ca_file"), "{error}");
+
+        let result: Result<GatewayConfig, _> = toml::from_str(
+            "[server]\n\n[[routes]]\npath = \"/\"\nupstream = \"http://localhost:3000\"\ntls_match = [{ field = \"sni\", regex = \"(\" }]\n",
+        );
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("invalid tls_match regex"), "{error}");
+    }
+
+    #[test]
+    fn test_forward_headers_parsing() {
+        let parse = |value: &str| {
+            toml::from_str::<GatewayConfig>(&format!(
+                "[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"http://localhost:3000\"\nforward_headers = {value}\n"
+            ))
+            .map(|config| config.routes[0].forward_headers.clone())
+        };
+
+        assert_eq!(parse("\"all\"").unwrap(), ForwardHeaders::All);
+        assert_eq!(parse("\"none\"").unwrap(), ForwardHeaders::None);
+        assert_eq!(
+            parse("[\"Authorization\", \"accept\"]").unwrap(),
+            ForwardHeaders::Allowlist(vec!["authorization".to_string(), "accept".to_string()])
+        );
+        assert_eq!(RouteConfig::default().forward_headers, ForwardHeaders::All);
+
+        let error = parse("\"some\"").unwrap_err().to_string();
+        assert!(error.contains("invalid forward_headers mode"), "{error}");
+        let error = parse("[\"bad header\"]").unwrap_err().to_string();
+        assert!(error.contains("invalid forward_headers entry"), "{error}");
+    }
 
-    pub fn save_example(path: &str) -> FerragateResult<()> {
-        let config = Self::default_config();
-        let content = toml::to_string_pretty(&config)?;
+    #[test]
+    fn test_forward_headers_allows() {
+        let allowlist = ForwardHeaders::Allowlist(vec!["authorization".to_string()]);
+        assert!(allowlist.allows("Authorization"));
+        assert!(!allowlist.allows("cookie"));
+        assert!(ForwardHeaders::All.allows("cookie"));
+        assert!(!ForwardHeaders::None.allows("authorization"));
+    }
 
-        fs::write(path, &content)?;
+    #[test]
+    fn test_priority_parsing_and_validation() {
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+priority = { header = "x-priority", bypass_shedding = ["high"] }
 
-        info!("Example configuration saved to: {}", path);
-        Ok(())
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+priority_upstreams = { high = ["http://fast-1:3000", "http://fast-2:3000"] }
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let priority = config.server.priority.clone().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(priority.priority_of(&headers), None);
+        headers.insert("x-priority", " High ".parse().unwrap());
+        assert_eq!(priority.priority_of(&headers), Some("High"));
+        assert!(priority.bypasses_shedding("High"));
+        assert!(!priority.bypasses_shedding("low"));
+        assert_eq!(config.routes[0].priority_pool("HIGH").unwrap().len(), 2);
+        assert!(config.routes[0].priority_pool("low").is_none());
+
+        config.routes[0]
+            .priority_upstreams
+            .insert("low".to_string(), vec![]);
+        assert!(config.validate().is_err());
+        config.routes[0].priority_upstreams.remove("low");
+
+        config.server.priority = None;
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("priority_upstreams requires"), "{error}");
     }
-}
 
-impl RouteConfig {
-    /// Check if this route matches the given path
-    ///
-    /// Supports wildcard matching with "/*" suffix for prefix matching.
-    /// Returns true if the path matches this route's pattern.
-    pub fn matches_path(&self, path: &str) -> bool {
-        if self.path.ends_with("/*") {
-            let prefix = &self.path[..self.path.len() - 2];
-            path.starts_with(prefix)
-        } else {
-            path == self.path
-        }
+    #[test]
+    fn test_builtin_file_validation() {
+        let mut config = GatewayConfig::default_config();
+        config.server.tls = None;
+        config.server.favicon_file = Some("Cargo.toml".to_string());
+        let error = config.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("favicon_file requires serve_favicon"),
+            "{error}"
+        );
+
+        config.server.serve_favicon = true;
+        assert!(config.validate().is_ok());
+
+        config.server.serve_robots_txt = true;
+        config.server.robots_txt_file = Some("/nonexistent/robots.txt".to_string());
+        let error = config.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("not found: /nonexistent/robots.txt"),
+            "{error}"
+        );
     }
 
-    /// Check if this route allows the given HTTP method
-    ///
-    /// Returns true if:
-    /// - No methods are specified (allows all methods)
-    /// - The method is explicitly listed in allowed methods
-    pub fn matches_method(&self, method: &str) -> bool {
-        if self.methods.is_empty() {
-            return true; // No method restriction
-        }
-        self.methods
-            .iter()
-            .any(|m| m.to_uppercase() == method.to_uppercase())
+    #[test]
+    fn test_deny_rules_parsing_and_validation() {
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+deny_rules = [
+    { path = '\.\./' },
+    { methods = ["TRACE", "connect"] },
+    { methods = ["DELETE"], path = "^/admin" },
+]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let rules = &config.server.deny_rules;
+        assert!(rules[0].matches("GET", "/api/../secret"));
+        assert!(!rules[0].matches("GET", "/api/file..txt"));
+        assert!(rules[1].matches("CONNECT", "/anything"));
+        assert!(!rules[1].matches("GET", "/anything"));
+        assert!(rules[2].matches("delete", "/admin/users"));
+        assert!(!rules[2].matches("DELETE", "/api/admin"));
+        assert!(!rules[2].matches("GET", "/admin/users"));
+
+        config.server.deny_rules.push(DenyRule::default());
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("deny_rules[3] must set"), "{error}");
+
+        config.server.deny_rules[3].methods = vec!["NOT A METHOD".to_string()];
+        assert!(config.validate().is_err());
+
+        let error = toml::from_str::<GatewayConfig>(
+            "[server]\ndeny_rules = [{ path = \"(\" }]\n\n[[routes]]\npath = \"/*\"\nupstream = \"http://localhost:3000\"\n",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("invalid path regex"), "{error}");
     }
 
-    /// Transform the original request path for upstream forwarding
-    ///
-    /// If `strip_path` is enabled and the route uses wildcard matching,
-    /// the matched prefix will be removed from the path before forwarding.
-    pub fn transform_path(&self, original_path: &str) -> String {
-        if !self.strip_path {
-            return original_path.to_string();
-        }
+    #[test]
+    fn test_shutdown_response_validation() {
+        let mut config = GatewayConfig::default_config();
+        assert_eq!(config.server.shutdown_status, 503);
+        assert_eq!(config.server.shutdown_body, MSG_SHUTTING_DOWN);
+        assert!(config.validate().is_ok());
 
-        if self.path.ends_with("/*") {
-            let prefix = &self.path[..self.path.len() - 2];
-            if let Some(remaining) = original_path.strip_prefix(prefix) {
-                if remaining.is_empty() {
-                    "/".to_string()
-                } else {
-                    remaining.to_string()
-                }
-            } else {
-                original_path.to_string()
-            }
-        } else {
-            original_path.to_string()
-        }
+        config.server.shutdown_status = 200;
+        assert!(config.validate().is_err());
+        config.server.shutdown_status = 600;
+        assert!(config.validate().is_err());
+        config.server.shutdown_status = 429;
+        assert!(config.validate().is_ok());
     }
 
-    /// Validate this route configuration
-    ///
-    /// Checks that the route has valid path, upstream URL, and HTTP methods.
-    pub fn validate(&self) -> FerragateResult<()> {
-        // Validate path
-        if self.path.is_empty() {
-            return Err(FerragateError::validation("Route path cannot be empty"));
-        }
+    #[test]
+    fn test_listener_socket_options_parsing_and_validation() {
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+tcp_nodelay = true
+tcp_keepalive_secs = 75
+listen_backlog = 4096
+idle_timeout_secs = 30
 
-        // Validate upstream URL
-        url::Url::parse(&self.upstream).map_err(|e| {
-            FerragateError::validation(format!("Invalid upstream URL '{}': {}", self.upstream, e))
-        })?;
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.server.tcp_nodelay);
+        assert_eq!(config.server.tcp_keepalive_secs, Some(75));
+        assert_eq!(config.server.listen_backlog, 4096);
+        assert_eq!(config.server.idle_timeout_secs, 30);
 
-        // Validate methods
-        for method in &self.methods {
-            match method.to_uppercase().as_str() {
-                "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" | "OPTIONS" => {}
-                _ => {
-                    return Err(FerragateError::validation(format!(
-                        "Invalid HTTP method: {method}"
-                    )))
-                }
-            }
-        }
+        let defaults = ServerConfig::default();
+        assert!(!defaults.tcp_nodelay);
+        assert_eq!(defaults.tcp_keepalive_secs, None);
+        assert_eq!(defaults.listen_backlog, DEFAULT_LISTEN_BACKLOG);
+        assert_eq!(defaults.idle_timeout_secs, DEFAULT_IDLE_TIMEOUT_SECS);
 
-        Ok(())
+        config.server.tcp_keepalive_secs = Some(0);
+        assert!(config.validate().is_err());
+        config.server.tcp_keepalive_secs = None;
+        config.server.listen_backlog = 0;
+        assert!(config.validate().is_err());
+        config.server.listen_backlog = 4096;
+        config.server.idle_timeout_secs = 0;
+        assert!(config.validate().is_err());
     }
 
-    /// Get the effective timeout for this route
-    ///
-    /// Returns the route-specific timeout if set, otherwise the provided default.
-    pub fn effective_timeout(&self, default_timeout_ms: u64) -> u64 {
-        self.timeout_ms.unwrap_or(default_timeout_ms)
+    #[test]
+    fn test_request_header_limits_validation() {
+        let mut config = GatewayConfig::default_config();
+        assert_eq!(
+            config.server.max_request_headers,
+            DEFAULT_MAX_REQUEST_HEADERS
+        );
+        assert_eq!(
+            config.server.max_request_header_bytes,
+            DEFAULT_MAX_REQUEST_HEADER_BYTES
+        );
+        assert!(config.validate().is_ok());
+
+        config.server.max_request_headers = 0;
+        assert!(config.validate().is_err());
+        config.server.max_request_headers = 50;
+        config.server.max_request_header_bytes = 0;
+        assert!(config.validate().is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_max_health_checks_validation() {
+        let mut config = GatewayConfig::default_config();
+        assert_eq!(config.server.max_health_checks, DEFAULT_MAX_HEALTH_CHECKS);
+        config.server.max_health_checks = 0;
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("max_health_checks"), "{error}");
+    }
 
     #[test]
-    fn test_route_matching() {
-        let route = RouteConfig {
-            path: "/get/*".to_string(),
-            upstream: "https://httpbin.org".to_string(),
-            methods: vec!["GET".to_string()],
-            headers: HashMap::new(),
-            strip_path: true,
-            preserve_host: false,
-            timeout_ms: None,
-        };
+    fn test_body_replacements_parsing_and_validation() {
+        let mut config: GatewayConfig = toml::from_str(
+            r#"
+[server]
 
-        assert!(route.matches_path("/get/anything"));
-        assert!(route.matches_path("/get/"));
-        assert!(!route.matches_path("/post/anything"));
-        assert!(!route.matches_path("/health"));
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
 
-        assert!(route.matches_method("GET"));
-        assert!(route.matches_method("get"));
-        assert!(!route.matches_method("POST"));
+[[routes.body_replacements]]
+from = "http://internal:8080"
+to = "https://example.com"
+content_types = ["text/html", "application/*"]
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let replacement = &config.routes[0].body_replacements[0];
+        assert!(replacement.applies_to("text/html"));
+        assert!(replacement.applies_to("Text/HTML; charset=utf-8"));
+        assert!(replacement.applies_to("application/json"));
+        assert!(!replacement.applies_to("text/plain"));
+        assert!(!replacement.applies_to("applications/json"));
+        assert!(!replacement.applies_to(""));
+
+        config.routes[0].body_replacements[0].content_types.clear();
+        assert!(config.validate().is_err());
+        config.routes[0].body_replacements[0].content_types = vec!["text/html".to_string()];
+        config.routes[0].body_replacements[0].from.clear();
+        assert!(config.validate().is_err());
     }
 
     #[test]
-    fn test_path_transformation() {
-        let route = RouteConfig {
-            path: "/status/*".to_string(),
-            upstream: "https://httpbin.org".to_string(),
-            methods: vec![],
-            headers: HashMap::new(),
-            strip_path: true,
-            preserve_host: false,
-            timeout_ms: None,
-        };
+    fn test_response_mode_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
 
-        assert_eq!(route.transform_path("/status/200"), "/200");
-        assert_eq!(route.transform_path("/status/"), "/");
-        assert_eq!(route.transform_path("/status"), "/");
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+response_mode = "buffer"
+max_response_body_bytes = 65536
+"#,
+        )
+        .unwrap();
+        let mut route = config.routes[0].clone();
+        assert_eq!(route.response_mode, Some(ResponseMode::Buffer));
+        assert!(route.validate().is_ok());
+
+        // The size cap is a limit on the response in any mode
+        route.response_mode = Some(ResponseMode::Stream);
+        assert!(route.validate().is_ok());
+        route.max_response_body_bytes = None;
+        route.response_mode = None;
+        assert_eq!(route.effective_response_mode(), ResponseMode::Stream);
+
+        // Body replacements buffer by default and cannot stream
+        route.body_replacements = vec![BodyReplacement {
+            from: "a".to_string(),
+            to: "b".to_string(),
+            content_types: vec!["text/html".to_string()],
+        }];
+        assert_eq!(route.effective_response_mode(), ResponseMode::Buffer);
+        assert!(route.validate().is_ok());
+        route.response_mode = Some(ResponseMode::Stream);
+        assert!(route.validate().is_err());
     }
 
     #[test]
-    fn test_effective_timeout_with_route_specific() {
-        let route = RouteConfig {
-            path: "/api/*".to_string(),
-            upstream: "http://example.com".to_string(),
-            methods: vec![],
-            headers: std::collections::HashMap::new(),
-            strip_path: false,
-            preserve_host: false,
-            timeout_ms: Some(5000), // Route-specific timeout
-        };
+    fn test_effective_max_response_bytes() {
+        let mut server = ServerConfig::default();
+        let mut route = RouteConfig::default();
+        assert_eq!(server.max_response_bytes, 0);
+        assert_eq!(route.effective_max_response_bytes(&server), None);
 
-        // Should return route-specific timeout, ignoring default
-        assert_eq!(route.effective_timeout(30000), 5000);
-        assert_eq!(route.effective_timeout(10000), 5000);
+        server.max_response_bytes = 1024;
+        assert_eq!(route.effective_max_response_bytes(&server), Some(1024));
+        route.max_response_bytes = Some(4096);
+        assert_eq!(route.effective_max_response_bytes(&server), Some(4096));
+        route.max_response_bytes = Some(0);
+        assert_eq!(route.effective_max_response_bytes(&server), None);
     }
 
     #[test]
-    fn test_effective_timeout_with_server_default() {
-        let route = RouteConfig {
+    fn test_max_response_body_bytes_is_an_alias() {
+        let server = ServerConfig {
+            max_response_bytes: 1024,
+            ..ServerConfig::default()
+        };
+        let mut route = RouteConfig {
             path: "/api/*".to_string(),
-            upstream: "http://example.com".to_string(),
-            methods: vec![],
-            headers: std::collections::HashMap::new(),
-            strip_path: false,
-            preserve_host: false,
-            timeout_ms: None, // No route-specific timeout
+            upstream: "http://localhost:3000".to_string(),
+            max_response_body_bytes: Some(4096),
+            ..Default::default()
         };
+        assert!(route.validate().is_ok());
+        assert_eq!(route.effective_max_response_bytes(&server), Some(4096));
+        assert_eq!(route.effective_max_buffered_response_bytes(&server), 4096);
 
-        // Should return server default timeout
-        assert_eq!(route.effective_timeout(30000), 30000);
-        assert_eq!(route.effective_timeout(15000), 15000);
+        route.max_response_bytes = Some(4096);
+        let err = route.validate().unwrap_err().to_string();
+        assert!(err.contains("set only one"), "{err}");
+    }
+
+    #[test]
+    fn test_effective_max_buffered_response_bytes() {
+        let mut server = ServerConfig::default();
+        let mut route = RouteConfig::default();
         assert_eq!(
-            route.effective_timeout(DEFAULT_TIMEOUT_MS),
-            DEFAULT_TIMEOUT_MS
+            route.effective_max_buffered_response_bytes(&server),
+            DEFAULT_MAX_RESPONSE_BODY_BYTES
+        );
+
+        // The server limit applies up to the buffering default
+        server.max_response_bytes = 1024;
+        assert_eq!(route.effective_max_buffered_response_bytes(&server), 1024);
+        server.max_response_bytes = u64::MAX;
+        assert_eq!(
+            route.effective_max_buffered_response_bytes(&server),
+            DEFAULT_MAX_RESPONSE_BODY_BYTES
+        );
+
+        // A route's own limit applies as set; unlimited still buffers up to the default
+        route.max_response_bytes = Some(u64::MAX);
+        assert_eq!(
+            route.effective_max_buffered_response_bytes(&server),
+            u64::MAX
+        );
+        route.max_response_bytes = Some(0);
+        assert_eq!(
+            route.effective_max_buffered_response_bytes(&server),
+            DEFAULT_MAX_RESPONSE_BODY_BYTES
         );
     }
 
     #[test]
-    fn test_effective_timeout_zero_values() {
-        let route_with_zero = RouteConfig {
+    fn test_response_decompression_validation() {
+        let mut route = RouteConfig {
             path: "/api/*".to_string(),
-            upstream: "http://example.com".to_string(),
-            methods: vec![],
-            headers: std::collections::HashMap::new(),
-            strip_path: false,
-            preserve_host: false,
-            timeout_ms: Some(0), // Zero timeout (valid but unusual)
+            upstream: "http://backend:8080".to_string(),
+            decompress_response: true,
+            ..Default::default()
         };
+        assert_eq!(route.effective_response_mode(), ResponseMode::Buffer);
+        route.recompress_response = true;
+        assert!(route.validate().is_ok());
 
-        // Should return zero if explicitly set
-        assert_eq!(route_with_zero.effective_timeout(30000), 0);
+        route.response_mode = Some(ResponseMode::Stream);
+        assert!(route.validate().is_err());
+        route.response_mode = None;
+        route.decompress_response = false;
+        let error = route.validate().unwrap_err().to_string();
+        assert!(error.contains("requires decompress_response"), "{error}");
+    }
 
-        let route_no_timeout = RouteConfig {
-            path: "/api/*".to_string(),
-            upstream: "http://example.com".to_string(),
-            methods: vec![],
-            headers: std::collections::HashMap::new(),
-            strip_path: false,
-            preserve_host: false,
-            timeout_ms: None,
+    #[test]
+    fn test_log_bodies_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://backend:8080"
+
+[routes.log_bodies]
+redact_fields = ["user.password"]
+redact_patterns = ["Bearer [A-Za-z0-9._-]+"]
+"#,
+        )
+        .unwrap();
+        let route = &config.routes[0];
+        let log_bodies = route.log_bodies.as_ref().unwrap();
+        assert_eq!(log_bodies.max_bytes, 4096);
+        assert_eq!(log_bodies.redact_patterns[0].redact("Bearer abc"), "***");
+        assert_eq!(route.effective_response_mode(), ResponseMode::Buffer);
+        // Enabling body logging is always reported
+        assert_eq!(
+            config.validate().unwrap(),
+            [ConfigWarning::BodyLogging("/api/*".to_string())]
+        );
+
+        let invalid = toml::from_str::<GatewayConfig>(
+            "[server]\n[[routes]]\npath = \"/\"\nupstream = \"http://backend\"\n[routes.log_bodies]\nredact_patterns = [\"(\"]\n",
+        );
+        assert!(invalid.is_err());
+
+        let mut route = route.clone();
+        route.response_mode = Some(ResponseMode::Stream);
+        assert!(route.validate().is_err());
+        route.response_mode = None;
+        route.log_bodies.as_mut().unwrap().redact_fields = vec!["user..password".to_string()];
+        assert!(route.validate().is_err());
+        route.log_bodies.as_mut().unwrap().redact_fields.clear();
+        route.log_bodies.as_mut().unwrap().max_bytes = 0;
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_query_match_rules() {
+        let present = QueryMatch::try_from("debug".to_string()).unwrap();
+        assert!(present.matches("debug"));
+        assert!(present.matches("a=1&debug=true"));
+        assert!(!present.matches("debugging=1"));
+        assert!(!present.matches(""));
+
+        let equals = QueryMatch::try_from("v=2".to_string()).unwrap();
+        assert!(equals.matches("v=2"));
+        assert!(equals.matches("v=1&v=2"));
+        assert!(!equals.matches("v=3"));
+        assert!(!equals.matches("v"));
+
+        let encoded = QueryMatch::try_from("name=a b".to_string()).unwrap();
+        assert!(encoded.matches("name=a%20b"));
+
+        let absent = QueryMatch::try_from("!legacy".to_string()).unwrap();
+        assert!(absent.matches("v=2"));
+        assert!(!absent.matches("legacy"));
+        assert!(!absent.matches("legacy=0"));
+    }
+
+    #[test]
+    fn test_status_map_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[routes.status_map]
+418 = 400
+"500" = 502
+"#,
+        )
+        .unwrap();
+        let route = &config.routes[0];
+        assert!(route.validate().is_ok());
+        assert_eq!(route.map_status(418), 400);
+        assert_eq!(route.map_status(500), 502);
+        assert_eq!(route.map_status(200), 200);
+
+        let result: Result<GatewayConfig, _> = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[routes.status_map]
+teapot = 400
+"#,
+        );
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("invalid status_map key 'teapot'"), "{error}");
+
+        let mut route = route.clone();
+        route.status_map.insert(404, 999);
+        assert!(route.validate().is_err());
+        route.status_map.clear();
+        route.status_map.insert(42, 400);
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_tiers_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[routes.rate_limit]
+requests_per_second = 100
+burst = 200
+
+[[routes.rate_limit.tiers]]
+methods = ["POST", "PUT", "PATCH", "DELETE"]
+requests_per_second = 10
+"#,
+        )
+        .unwrap();
+
+        let mut route = config.routes[0].clone();
+        let rate_limit = route.rate_limit.as_ref().unwrap();
+        assert_eq!(rate_limit.burst, Some(200));
+        assert_eq!(rate_limit.tiers[0].methods.len(), 4);
+        assert!(route.validate().is_ok());
+
+        route.rate_limit.as_mut().unwrap().tiers[0].requests_per_second = 0.0;
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_concurrency_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/api/*"
+upstream = "http://localhost:3000"
+
+[routes.concurrency]
+max_concurrent = 8
+queue_size = 32
+"#,
+        )
+        .unwrap();
+
+        let mut route = config.routes[0].clone();
+        let concurrency = route.concurrency.as_ref().unwrap();
+        assert_eq!(concurrency.max_concurrent, 8);
+        assert_eq!(concurrency.queue_size, 32);
+        assert_eq!(concurrency.queue_timeout_ms, DEFAULT_QUEUE_TIMEOUT_MS);
+        assert!(route.validate().is_ok());
+
+        route.concurrency.as_mut().unwrap().queue_timeout_ms = 0;
+        assert!(route.validate().is_err());
+        route.concurrency.as_mut().unwrap().queue_timeout_ms = 500;
+        route.concurrency.as_mut().unwrap().max_concurrent = 0;
+        assert!(route.validate().is_err());
+    }
+
+    #[test]
+    fn test_grpc_web_validation() {
+        let mut route = RouteConfig {
+            path: "/grpc/*".to_string(),
+            upstream: "http://grpc-service:50051".to_string(),
+            grpc_web: true,
+            ..Default::default()
         };
+        assert!(route.validate().is_ok());
 
-        // Should handle zero default gracefully
-        assert_eq!(route_no_timeout.effective_timeout(0), 0);
+        route.upstream_protocol = Some(UpstreamProtocol::Http1);
+        assert!(route.validate().is_err());
+        route.upstream_protocol = Some(UpstreamProtocol::Http2);
+        assert!(route.validate().is_ok());
+
+        route.upstreams = vec!["https://grpc-service-2:50051".to_string()];
+        let error = route.validate().unwrap_err().to_string();
+        assert!(error.contains("grpc-service-2"), "{error}");
+    }
+
+    #[test]
+    fn test_upstream_health_check_parsing_and_validation() {
+        let config: GatewayConfig = toml::from_str(
+            r#"
+            [server]
+            host = "127.0.0.1"
+            port = 8080
+
+            [[routes]]
+            path = "/grpc/*"
+            upstream = "http://grpc-service:50051"
+
+            [routes.health_check]
+            type = "grpc"
+            service = "echo.Echo"
+            "#,
+        )
+        .unwrap();
+        let mut route = config.routes[0].clone();
+        let check = route.health_check.clone().unwrap();
+        assert_eq!(check.check_type, UpstreamHealthCheckType::Grpc);
+        assert_eq!(check.service, "echo.Echo");
+        assert_eq!(check.interval_secs, 10);
+        assert_eq!(check.timeout_ms, 2000);
+        assert!(route.validate().is_ok());
+
+        route.upstreams = vec!["https://grpc-service-2:50051".to_string()];
+        let error = route.validate().unwrap_err().to_string();
+        assert!(error.contains("grpc-service-2"), "{error}");
+
+        // HTTP checks may use any scheme but need an absolute path
+        let check = route.health_check.as_mut().unwrap();
+        check.check_type = UpstreamHealthCheckType::Http;
+        assert_eq!(check.path, "/health");
+        assert!(route.validate().is_ok());
+        route.health_check.as_mut().unwrap().path = "health".to_string();
+        assert!(route.validate().is_err());
+
+        let mut route = RouteConfig {
+            health_check: Some(UpstreamHealthCheckConfig::default()),
+            ..route
+        };
+        route.health_check.as_mut().unwrap().interval_secs = 0;
+        assert!(route.validate().is_err());
+        route.health_check.as_mut().unwrap().interval_secs = 5;
+        route.health_check.as_mut().unwrap().timeout_ms = 0;
+        assert!(route.validate().is_err());
     }
 }
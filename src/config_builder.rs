@@ -0,0 +1,279 @@
+/// Builders for constructing gateway configurations in code
+///
+/// Embedding applications and tests can assemble a [`GatewayConfig`] without
+/// spelling out every field: unset options keep the same defaults as an empty
+/// configuration file, and `build` runs the same validation as loading a file.
+use crate::config::{
+    expand_methods, GatewayConfig, LoggingConfig, RouteConfig, ServerConfig, TlsConfig,
+};
+use crate::error::FerragateResult;
+
+/// Chainable builder for [`GatewayConfig`]
+#[allow(dead_code)] // Public API for embedding
+#[derive(Debug, Clone)]
+pub struct GatewayConfigBuilder {
+    config: GatewayConfig,
+}
+
+impl Default for GatewayConfigBuilder {
+    fn default() -> Self {
+        Self {
+            config: GatewayConfig {
+                server: ServerConfig::default(),
+                routes: Vec::new(),
+                logging: LoggingConfig::default(),
+            },
+        }
+    }
+}
+
+#[allow(dead_code)] // Public API for embedding
+impl GatewayConfigBuilder {
+    /// Start from the default server and logging settings, with no routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the address the HTTP listener binds to
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.config.server.host = host.into();
+        self
+    }
+
+    /// Set the HTTP listener port
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.server.port = port;
+        self
+    }
+
+    /// Set the number of worker threads
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.config.server.workers = Some(workers);
+        self
+    }
+
+    /// Set the default upstream request timeout in milliseconds
+    pub fn timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.config.server.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Enable the HTTPS listener with the given TLS settings
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.config.server.tls = Some(tls);
+        self
+    }
+
+    /// Adjust any other server option
+    pub fn server(mut self, configure: impl FnOnce(&mut ServerConfig)) -> Self {
+        configure(&mut self.config.server);
+        self
+    }
+
+    /// Append a route; routes are matched in the order they are added
+    pub fn route(mut self, route: impl Into<RouteConfig>) -> Self {
+        self.config.routes.push(route.into());
+        self
+    }
+
+    /// Append several routes
+    pub fn routes<R: Into<RouteConfig>>(mut self, routes: impl IntoIterator<Item = R>) -> Self {
+        self.config
+            .routes
+            .extend(routes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Replace the logging settings
+    pub fn logging(mut self, logging: LoggingConfig) -> Self {
+        self.config.logging = logging;
+        self
+    }
+
+    /// Set the log level
+    pub fn log_level(mut self, level: impl Into<String>) -> Self {
+        self.config.logging.level = level.into();
+        self
+    }
+
+    /// Validate and return the configuration
+    pub fn build(self) -> FerragateResult<GatewayConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+
+    /// Return the configuration without validating it
+    pub fn build_unvalidated(self) -> GatewayConfig {
+        self.config
+    }
+}
+
+/// Chainable builder for a single [`RouteConfig`]
+///
+/// A builder can be passed to [`GatewayConfigBuilder::route`] directly; the
+/// route is then validated along with the rest of the configuration.
+#[allow(dead_code)] // Public API for embedding
+#[derive(Debug, Clone)]
+pub struct RouteConfigBuilder {
+    route: RouteConfig,
+}
+
+#[allow(dead_code)] // Public API for embedding
+impl RouteConfigBuilder {
+    /// Start a route forwarding requests matching `path` to `upstream`
+    pub fn new(path: impl Into<String>, upstream: impl Into<String>) -> Self {
+        Self {
+            route: RouteConfig {
+                path: path.into(),
+                upstream: upstream.into(),
+                ..RouteConfig::default()
+            },
+        }
+    }
+
+    /// Restrict the route to the given HTTP methods or method groups
+    pub fn methods<M: Into<String>>(mut self, methods: impl IntoIterator<Item = M>) -> Self {
+        self.route.methods = expand_methods(methods.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add a header to upstream requests
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.route.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Strip the matched path prefix before forwarding
+    pub fn strip_path(mut self, strip_path: bool) -> Self {
+        self.route.strip_path = strip_path;
+        self
+    }
+
+    /// Forward the client's Host header instead of the upstream's
+    pub fn preserve_host(mut self, preserve_host: bool) -> Self {
+        self.route.preserve_host = preserve_host;
+        self
+    }
+
+    /// Set the timeout for each upstream attempt in milliseconds
+    pub fn timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.route.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Set the number of retries for failed idempotent requests
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.route.retries = retries;
+        self
+    }
+
+    /// Balance requests across additional upstreams
+    pub fn upstreams<U: Into<String>>(mut self, upstreams: impl IntoIterator<Item = U>) -> Self {
+        self.route.upstreams = upstreams.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Enable or disable the route
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.route.enabled = enabled;
+        self
+    }
+
+    /// Adjust any other route option
+    pub fn configure(mut self, configure: impl FnOnce(&mut RouteConfig)) -> Self {
+        configure(&mut self.route);
+        self
+    }
+
+    /// Validate and return the route
+    pub fn build(self) -> FerragateResult<RouteConfig> {
+        self.route.validate()?;
+        Ok(self.route)
+    }
+}
+
+impl From<RouteConfigBuilder> for RouteConfig {
+    fn from(builder: RouteConfigBuilder) -> Self {
+        builder.route
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FerragateError;
+
+    #[test]
+    fn test_gateway_builder_defaults_match_empty_config() {
+        let built = GatewayConfigBuilder::new().build_unvalidated();
+        let parsed: GatewayConfig = toml::from_str("routes = []\n\n[server]\n").unwrap();
+
+        assert_eq!(built.server.host, parsed.server.host);
+        assert_eq!(built.server.port, parsed.server.port);
+        assert_eq!(built.server.listen_backlog, parsed.server.listen_backlog);
+        assert_eq!(built.logging.level, parsed.logging.level);
+        assert!(built.routes.is_empty());
+    }
+
+    #[test]
+    fn test_gateway_builder_sets_options_and_routes() {
+        let config = GatewayConfig::builder()
+            .host("127.0.0.1")
+            .port(9000)
+            .timeout_ms(2500)
+            .server(|server| server.max_connections = Some(64))
+            .log_level("debug")
+            .route(
+                RouteConfig::builder("/api/*", "http://backend:3000")
+                    .methods(["GET", "POST"])
+                    .header("X-Gateway", "ferragate")
+                    .strip_path(true)
+                    .retries(2),
+            )
+            .routes([RouteConfig::builder("/users/*", "http://users:8000").preserve_host(true)])
+            .build()
+            .unwrap();
+
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.server.timeout_ms, Some(2500));
+        assert_eq!(config.server.max_connections, Some(64));
+        assert_eq!(config.logging.level, "debug");
+        assert_eq!(config.routes.len(), 2);
+        assert_eq!(config.routes[0].methods, ["GET", "POST"]);
+        assert_eq!(config.routes[0].headers["X-Gateway"], "ferragate");
+        assert!(config.routes[0].strip_path);
+        assert_eq!(config.routes[0].retries, 2);
+        assert!(config.routes[1].enabled);
+        assert!(config.routes[1].preserve_host);
+    }
+
+    #[test]
+    fn test_builders_validate() {
+        let result = GatewayConfig::builder()
+            .route(RouteConfig::builder("/api/*", "not a url"))
+            .build();
+        assert!(matches!(result, Err(FerragateError::Config { .. })));
+
+        let result = GatewayConfig::builder()
+            .server(|server| server.listen_backlog = 0)
+            .build();
+        assert!(matches!(result, Err(FerragateError::Config { .. })));
+
+        assert!(RouteConfig::builder("/api/*", "not a url").build().is_err());
+        let route = RouteConfig::builder("/api/*", "http://backend:3000")
+            .configure(|route| route.total_timeout_ms = Some(1000))
+            .build()
+            .unwrap();
+        assert_eq!(route.total_timeout_ms, Some(1000));
+    }
+
+    #[test]
+    fn test_route_builder_expands_method_groups() {
+        let route = RouteConfig::builder("/api/*", "http://backend:3000")
+            .methods(["READ", "POST"])
+            .build()
+            .unwrap();
+        assert_eq!(route.methods, ["GET", "HEAD", "OPTIONS", "POST"]);
+    }
+}
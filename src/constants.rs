@@ -8,17 +8,119 @@ pub const DEFAULT_HTTP_PORT: u16 = 3000;
 pub const DEFAULT_HTTPS_PORT: u16 = 443;
 pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
 pub const DEFAULT_LOG_LEVEL: &str = "info";
+/// Pending connection queue length, matching tokio's `TcpListener::bind`
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
+/// Seconds without any bytes read or written before a client connection is closed
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+/// Most headers accepted on a request, matching the HTTP/1.1 parser's own limit
+pub const DEFAULT_MAX_REQUEST_HEADERS: usize = 100;
+pub const DEFAULT_MAX_REQUEST_HEADER_BYTES: usize = 65_536;
 
 // Client configuration
 pub const CLIENT_USER_AGENT: &str = concat!("FerraGate/", env!("CARGO_PKG_VERSION"));
 pub const CLIENT_POOL_IDLE_TIMEOUT_SECS: u64 = 60;
 pub const CLIENT_POOL_MAX_IDLE_PER_HOST: usize = 10;
+pub const DEFAULT_MAX_REDIRECTS: u32 = 10;
+/// Client-requested timeouts above this are ignored as bogus
+pub const MAX_CLIENT_TIMEOUT_MS: u64 = 3_600_000;
+
+// Retry configuration
+pub const DEFAULT_RETRY_BACKOFF_MS: u64 = 100;
+pub const DEFAULT_RETRY_BUDGET_RATIO: f64 = 0.2;
+pub const DEFAULT_RETRY_BUDGET_WINDOW_SECS: u64 = 10;
+pub const RETRY_BUDGET_MIN_RETRIES: u64 = 10;
+pub const DEFAULT_MAX_RETRY_AFTER_MS: u64 = 5000;
+pub const DEFAULT_MAX_RETRY_BODY_BYTES: u64 = 1_048_576;
+
+// Request decompression configuration
+pub const DEFAULT_MAX_DECOMPRESSED_BYTES: u64 = 10_485_760;
+
+// Response body rewriting configuration
+pub const DEFAULT_MAX_REPLACEMENT_BODY_BYTES: u64 = 1_048_576;
+
+// Response buffering configuration
+pub const DEFAULT_MAX_RESPONSE_BODY_BYTES: u64 = 10_485_760;
+
+// Body logging configuration
+pub const DEFAULT_BODY_LOG_MAX_BYTES: usize = 4096;
+pub const BODY_LOG_REDACTED: &str = "***";
+
+// Expect: 100-continue relay
+pub const EXPECT_CONTINUE_TIMEOUT_MS: u64 = 1000;
+
+// Route concurrency queue configuration
+pub const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 1000;
+
+// gRPC-Web translation
+pub const GRPC_CONTENT_TYPE: &str = "application/grpc";
+pub const GRPC_WEB_CONTENT_TYPE: &str = "application/grpc-web";
+pub const GRPC_WEB_TEXT_CONTENT_TYPE: &str = "application/grpc-web-text";
+/// First byte of the gRPC-Web frame carrying the trailers
+pub const GRPC_WEB_TRAILER_FLAG: u8 = 0x80;
+pub const GRPC_STATUS_DEADLINE_EXCEEDED: u16 = 4;
+pub const GRPC_STATUS_UNAVAILABLE: u16 = 14;
+
+// Load shedding configuration
+pub const LOAD_SHEDDING_LATENCY_SMOOTHING: f64 = 0.2;
+
+// Health-weighted load balancing configuration
+pub const DEFAULT_HEALTH_WEIGHT_ERROR_SENSITIVITY: f64 = 2.0;
+pub const DEFAULT_HEALTH_WEIGHT_LATENCY_SENSITIVITY: f64 = 1.0;
+pub const DEFAULT_HEALTH_WEIGHT_MIN_WEIGHT: f64 = 0.05;
+pub const DEFAULT_HEALTH_WEIGHT_MIN_REQUESTS: usize = 10;
+pub const DEFAULT_HEALTH_WEIGHT_EJECT_SECS: u64 = 30;
+
+// Active upstream health check configuration
+pub const DEFAULT_UPSTREAM_HEALTH_CHECK_PATH: &str = "/health";
+pub const DEFAULT_UPSTREAM_HEALTH_CHECK_INTERVAL_SECS: u64 = 10;
+pub const DEFAULT_UPSTREAM_HEALTH_CHECK_TIMEOUT_MS: u64 = 2000;
+/// How often the checker looks for upstreams that are due a check
+pub const UPSTREAM_HEALTH_CHECK_TICK_MS: u64 = 1000;
+pub const GRPC_HEALTH_CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+/// `HealthCheckResponse.ServingStatus.SERVING`
+pub const GRPC_HEALTH_SERVING: u64 = 1;
+
+// Traffic mirroring configuration
+pub const DEFAULT_MIRROR_SAMPLE_RATE: f64 = 1.0;
+/// Mirror copies in flight across all routes; further copies are dropped
+pub const MAX_IN_FLIGHT_MIRROR_REQUESTS: usize = 256;
+
+// Metrics configuration
+pub const METRICS_ENDPOINT: &str = "/metrics";
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+pub const METRICS_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+pub const METRICS_BYTE_BUCKETS: &[u64] = &[
+    64, 256, 1_024, 4_096, 16_384, 65_536, 262_144, 1_048_576, 4_194_304, 16_777_216,
+];
+
+// Admin endpoints
+pub const ADMIN_UPSTREAMS_ENDPOINT: &str = "/admin/upstreams";
+pub const ADMIN_VALIDATE_ENDPOINT: &str = "/admin/validate";
+pub const ADMIN_ROUTE_ORDER_ENDPOINT: &str = "/admin/route-order";
+pub const UPSTREAM_STATS_WINDOW: usize = 256;
 
 // Health check endpoints
 pub const HEALTH_ENDPOINT: &str = "/health";
 pub const LIVENESS_ENDPOINT: &str = "/health/live";
 pub const READINESS_ENDPOINT: &str = "/health/ready";
 
+// Built-in files
+pub const FAVICON_ENDPOINT: &str = "/favicon.ico";
+pub const ROBOTS_TXT_ENDPOINT: &str = "/robots.txt";
+pub const DEFAULT_ROBOTS_TXT: &str = "User-agent: *\nDisallow: /\n";
+pub const BUILTIN_FILE_CACHE_CONTROL: &str = "public, max-age=86400";
+
+// Background health check configuration
+pub const HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+pub const HEALTH_TASK_RESTART_DELAY_MS: u64 = 1000;
+pub const LOOPBACK_HEALTH_CHECK_NAME: &str = "loopback";
+pub const LOOPBACK_HEALTH_CHECK_TIMEOUT_MS: u64 = 5000;
+pub const DEFAULT_MAX_HEALTH_CHECKS: usize = 256;
+
 // File paths and extensions
 pub const DEFAULT_CONFIG_FILE: &str = "gateway.toml";
 pub const DEFAULT_LOG_DIR: &str = "logs";
@@ -27,17 +129,68 @@ pub const DEFAULT_LOG_FILE_PREFIX: &str = "ferragate";
 pub const CERT_FILE_EXTENSION: &str = ".crt";
 pub const KEY_FILE_EXTENSION: &str = ".key";
 
+// Configuration profiles
+pub const CONFIG_PROFILE_ENV: &str = "FERRAGATE_PROFILE";
+
+// Configuration file polling
+pub const CONFIG_POLL_JITTER_RATIO: f64 = 0.1;
+pub const CONFIG_POLL_DEBOUNCE_MS: u64 = 500;
+pub const VALIDATE_WATCH_INTERVAL_MS: u64 = 500;
+
+// Bench subcommand defaults
+pub const DEFAULT_BENCH_REQUESTS: usize = 1000;
+pub const DEFAULT_BENCH_CONCURRENCY: usize = 50;
+
 // Control socket configuration
 #[cfg(unix)]
 pub const CONTROL_SOCKET_PREFIX: &str = "/tmp/ferragate_";
 #[cfg(windows)]
 pub const CONTROL_SOCKET_PREFIX: &str = "ferragate_";
+pub const CONTROL_SOCKET_FILE_PREFIX: &str = "ferragate_";
+pub const CONTROL_SOCKET_DIR_ENV: &str = "FERRAGATE_CONTROL_SOCKET_DIR";
+
+// Systemd socket activation
+#[cfg(target_os = "linux")]
+pub const LISTEN_FDS_ENV: &str = "LISTEN_FDS";
+#[cfg(target_os = "linux")]
+pub const LISTEN_PID_ENV: &str = "LISTEN_PID";
+#[cfg(target_os = "linux")]
+pub const LISTEN_FDS_START: i32 = 3;
+
+// Shutdown configuration
+pub const DEFAULT_SHUTDOWN_STATUS: u16 = 503;
+#[cfg(unix)]
+pub const DEFAULT_STOP_TIMEOUT_SECS: u64 = 10;
+#[cfg(windows)]
+pub const DEFAULT_STOP_TIMEOUT_SECS: u64 = 5;
+pub const DEFAULT_SHUTDOWN_HOOK_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_INTERRUPT_DRAIN_TIMEOUT_SECS: u64 = 5;
 
 // Certificate configuration
 pub const CERT_ORGANIZATION: &str = "FerraGate";
 pub const CERT_COUNTRY: &str = "US";
 pub const DEFAULT_HOSTNAME: &str = "localhost";
 
+// Client IP resolution
+pub const DEFAULT_CLIENT_IP_HEADER: &str = "x-forwarded-for";
+
+// Tracing span fields
+pub const REDACTED_QUERY_VALUE: &str = "REDACTED";
+
+// Request identification
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Trace context propagation
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACEPARENT_VERSION: &str = "00";
+pub const TRACE_FLAG_SAMPLED: u8 = 0x01;
+
+// Route method groups
+pub const METHOD_WILDCARD: &str = "*";
+pub const READ_METHODS: &[&str] = &["GET", "HEAD", "OPTIONS"];
+pub const WRITE_METHODS: &[&str] = &["POST", "PUT", "PATCH", "DELETE"];
+
 // HTTP headers that should not be forwarded to upstream
 pub const FILTERED_HEADERS: &[&str] = &[
     "connection",
@@ -55,9 +208,35 @@ pub const MSG_ROUTE_NOT_FOUND: &str = "No matching route found";
 pub const MSG_HEALTH_CHECK_FAILED: &str = "Health check failed";
 pub const MSG_SERVER_NOT_READY: &str = "Server not ready";
 pub const MSG_INVALID_REQUEST_BODY: &str = "Failed to read request body";
+pub const MSG_REQUEST_BODY_TOO_LARGE: &str = "Request body too large";
+pub const MSG_REQUEST_HEADERS_TOO_LARGE: &str = "Request header fields too large";
+pub const MSG_UNSUPPORTED_CONTENT_TYPE: &str = "Unsupported request content type";
+pub const MSG_UPSTREAM_CONTENT_TYPE_NOT_ALLOWED: &str =
+    "Upstream response content type not allowed";
+/// Status recorded (as by nginx) for requests whose client went away; it is never delivered
+pub const CLIENT_CLOSED_REQUEST_STATUS: u16 = 499;
+pub const MSG_INVALID_REQUEST_ENCODING: &str = "Failed to decompress request body";
+pub const MSG_DECOMPRESSED_BODY_TOO_LARGE: &str = "Decompressed request body too large";
+pub const MSG_GATEWAY_OVERLOADED: &str = "Gateway overloaded";
+pub const MSG_NO_HEALTHY_UPSTREAM: &str = "No healthy upstream available";
+pub const MSG_REQUEST_DENIED: &str = "Request denied";
+pub const MSG_MAINTENANCE_MODE: &str = "Service is under maintenance";
+pub const MSG_SHUTTING_DOWN: &str = "Service is shutting down";
+pub const MSG_RATE_LIMITED: &str = "Rate limit exceeded";
+pub const MSG_ROUTE_AT_CAPACITY: &str = "Route is at capacity";
+pub const MSG_INVALID_GRPC_WEB_BODY: &str = "Invalid gRPC-Web request body";
+pub const MSG_INTERNAL_ERROR: &str = "Internal server error";
+pub const MSG_HTTP_VERSION_NOT_SUPPORTED: &str = "HTTP version not supported";
+pub const MSG_UPSTREAM_TIMEOUT: &str = "Upstream request timed out";
+pub const MSG_UPSTREAM_CONNECT_FAILED: &str = "Failed to connect to upstream";
+pub const MSG_UPSTREAM_TLS_FAILED: &str = "TLS handshake with upstream failed";
+pub const MSG_UPSTREAM_INVALID_RESPONSE: &str = "Invalid response from upstream";
+pub const MSG_UPSTREAM_REQUEST_FAILED: &str = "Upstream request failed";
+pub const MSG_UPSTREAM_RESPONSE_TOO_LARGE: &str = "Upstream response body too large";
 
 // Buffer sizes
 pub const CONTROL_SOCKET_BUFFER_SIZE: usize = 1024;
+pub const REQUEST_BODY_STREAM_CHANNEL_SIZE: usize = 16;
 
 // Log messages
 pub const LOG_SERVER_STARTING: &str = "Starting Ferragate API Gateway";
@@ -0,0 +1,200 @@
+/// Decompression of encoded request and response bodies
+///
+/// Routes with `decompress_request` decode gzip, deflate and brotli request
+/// bodies before forwarding them, for upstreams that cannot handle a
+/// `Content-Encoding` themselves. Routes with `decompress_response` decode
+/// upstream responses the same way so body transformations see plain content,
+/// and can gzip them again for clients that accept it. Decoding stops as soon
+/// as the output exceeds the route's size cap, so a small compressed body
+/// cannot expand without bound.
+use axum::http::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+    HeaderMap,
+};
+use bytes::Bytes;
+use std::io::{Read, Write};
+
+/// Buffer size used by the brotli decoder
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// Content coding the gateway can decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+/// Reason a request body could not be decompressed
+#[derive(Debug)]
+pub enum DecompressError {
+    /// The decoded body is larger than the allowed size
+    TooLarge,
+    /// The body is not valid for its declared coding
+    Invalid(std::io::Error),
+}
+
+impl ContentCoding {
+    /// Parse a single content coding name
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "br" => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+
+    /// Coding of a body from its `Content-Encoding` header
+    ///
+    /// Only a single supported coding is recognised; bodies with several
+    /// codings or an unknown one are forwarded as they are.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut values = headers.get_all(CONTENT_ENCODING).iter();
+        let value = values.next()?.to_str().ok()?;
+        if values.next().is_some() || value.contains(',') {
+            return None;
+        }
+        Self::parse(value)
+    }
+}
+
+/// Decode a body, failing once the output exceeds `max_bytes`
+pub fn decompress(
+    body: &[u8],
+    coding: ContentCoding,
+    max_bytes: u64,
+) -> Result<Bytes, DecompressError> {
+    let decoder: Box<dyn Read + '_> = match coding {
+        ContentCoding::Gzip => Box::new(flate2::read::MultiGzDecoder::new(body)),
+        ContentCoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(body)),
+        ContentCoding::Brotli => Box::new(brotli_decompressor::Decompressor::new(
+            body,
+            BROTLI_BUFFER_SIZE,
+        )),
+    };
+
+    let mut decoded = Vec::new();
+    decoder
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut decoded)
+        .map_err(DecompressError::Invalid)?;
+    if decoded.len() as u64 > max_bytes {
+        return Err(DecompressError::TooLarge);
+    }
+    Ok(Bytes::from(decoded))
+}
+
+/// Gzip a body
+pub fn gzip(body: &[u8]) -> Bytes {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(body)
+        .expect("writing to a Vec cannot fail");
+    Bytes::from(encoder.finish().expect("writing to a Vec cannot fail"))
+}
+
+/// Whether a request's `Accept-Encoding` allows a gzip response
+///
+/// `gzip`, `x-gzip` and `*` are accepted unless given a quality of zero.
+pub fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|entry| {
+            let mut params = entry.split(';');
+            let coding = params.next().unwrap_or_default().trim();
+            let rejected = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    == Some(0.0)
+            });
+            ["gzip", "x-gzip", "*"]
+                .iter()
+                .any(|name| coding.eq_ignore_ascii_case(name))
+                && !rejected
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use flate2::{write::ZlibEncoder, Compression};
+
+    #[test]
+    fn test_coding_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(ContentCoding::from_headers(&headers), None);
+
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("GZIP"));
+        assert_eq!(
+            ContentCoding::from_headers(&headers),
+            Some(ContentCoding::Gzip)
+        );
+
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip, br"));
+        assert_eq!(ContentCoding::from_headers(&headers), None);
+
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+        assert_eq!(ContentCoding::from_headers(&headers), None);
+    }
+
+    #[test]
+    fn test_decompress_gzip_and_deflate() {
+        let data = b"hello, decompressed world";
+        let decoded = decompress(&gzip(data), ContentCoding::Gzip, 1024).unwrap();
+        assert_eq!(&decoded[..], data);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        let deflated = encoder.finish().unwrap();
+        let decoded = decompress(&deflated, ContentCoding::Deflate, 1024).unwrap();
+        assert_eq!(&decoded[..], data);
+    }
+
+    #[test]
+    fn test_decompress_stops_at_size_cap() {
+        let bomb = gzip(&vec![0u8; 1_000_000]);
+        assert!(bomb.len() < 10_000);
+
+        let result = decompress(&bomb, ContentCoding::Gzip, 64 * 1024);
+        assert!(matches!(result, Err(DecompressError::TooLarge)));
+
+        let decoded = decompress(&bomb, ContentCoding::Gzip, 1_000_000).unwrap();
+        assert_eq!(decoded.len(), 1_000_000);
+    }
+
+    #[test]
+    fn test_accepts_gzip() {
+        let accepts = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(value));
+            accepts_gzip(&headers)
+        };
+        assert!(accepts("gzip"));
+        assert!(accepts("br, GZIP;q=0.5"));
+        assert!(accepts("*"));
+        assert!(!accepts("gzip;q=0"));
+        assert!(!accepts("br, deflate"));
+        assert!(!accepts("identity"));
+        assert!(!accepts_gzip(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let data = br#"{"items":[1,2,3]}"#;
+        let decoded = decompress(&gzip(data), ContentCoding::Gzip, 1024).unwrap();
+        assert_eq!(&decoded[..], data);
+    }
+
+    #[test]
+    fn test_decompress_rejects_invalid_body() {
+        let result = decompress(b"not gzip", ContentCoding::Gzip, 1024);
+        assert!(matches!(result, Err(DecompressError::Invalid(_))));
+    }
+}
@@ -2,8 +2,14 @@
 ///
 /// This module provides structured error types that improve error handling
 /// and debugging throughout the application.
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
 use thiserror::Error;
 
+use crate::constants::{MSG_HEALTH_CHECK_FAILED, MSG_INTERNAL_ERROR, MSG_UPSTREAM_REQUEST_FAILED};
+
 /// Main error type for Ferragate operations
 #[derive(Error, Debug)]
 pub enum FerragateError {
@@ -38,6 +44,22 @@ pub enum FerragateError {
     /// Server startup/shutdown errors
     #[error("Server error: {message}")]
     Server { message: String },
+
+    /// Request rejected by a rate limit
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        message: String,
+        /// Seconds the client should wait before retrying, sent as `Retry-After`
+        retry_after_secs: Option<u64>,
+    },
+
+    /// Request rejected for missing or invalid credentials
+    #[error("Unauthorized: {message}")]
+    Unauthorized { message: String },
+
+    /// Request rejected because the client is not allowed to access the resource
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
 }
 
 /// Result type alias for Ferragate operations
@@ -99,6 +121,73 @@ impl FerragateError {
             message: message.into(),
         }
     }
+
+    /// Create a new rate limit rejection
+    pub fn rate_limited<S: Into<String>>(message: S, retry_after_secs: Option<u64>) -> Self {
+        Self::RateLimited {
+            message: message.into(),
+            retry_after_secs,
+        }
+    }
+
+    /// Create a new unauthorized rejection
+    #[allow(dead_code)] // Public API method
+    pub fn unauthorized<S: Into<String>>(message: S) -> Self {
+        Self::Unauthorized {
+            message: message.into(),
+        }
+    }
+
+    /// Create a new forbidden rejection
+    #[allow(dead_code)] // Public API method
+    pub fn forbidden<S: Into<String>>(message: S) -> Self {
+        Self::Forbidden {
+            message: message.into(),
+        }
+    }
+
+    /// HTTP status code returned to clients for this error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            Self::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Self::Validation { .. } => StatusCode::BAD_REQUEST,
+            Self::Network { .. } | Self::Proxy { .. } => StatusCode::BAD_GATEWAY,
+            Self::Health { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Config { .. } | Self::Tls { .. } | Self::Io { .. } | Self::Server { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+/// Convert errors into client responses
+///
+/// Request rejections and validation errors return their message to the client.
+/// Internal and upstream errors return a generic message so that details such as
+/// file paths or upstream addresses are not leaked.
+impl IntoResponse for FerragateError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        match self {
+            Self::RateLimited {
+                message,
+                retry_after_secs: Some(secs),
+            } => (status, [(header::RETRY_AFTER, secs.to_string())], message).into_response(),
+            Self::RateLimited { message, .. }
+            | Self::Unauthorized { message }
+            | Self::Forbidden { message }
+            | Self::Validation { message } => (status, message).into_response(),
+            Self::Network { .. } | Self::Proxy { .. } => {
+                (status, MSG_UPSTREAM_REQUEST_FAILED).into_response()
+            }
+            Self::Health { .. } => (status, MSG_HEALTH_CHECK_FAILED).into_response(),
+            Self::Config { .. } | Self::Tls { .. } | Self::Io { .. } | Self::Server { .. } => {
+                (status, MSG_INTERNAL_ERROR).into_response()
+            }
+        }
+    }
 }
 
 /// Convert standard I/O errors to Ferragate errors
@@ -145,3 +234,88 @@ impl From<reqwest::Error> for FerragateError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code_mapping() {
+        let cases = [
+            (
+                FerragateError::rate_limited("slow down", None),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                FerragateError::unauthorized("missing token"),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (FerragateError::forbidden("denied"), StatusCode::FORBIDDEN),
+            (FerragateError::validation("bad"), StatusCode::BAD_REQUEST),
+            (FerragateError::network("refused"), StatusCode::BAD_GATEWAY),
+            (FerragateError::proxy("failed"), StatusCode::BAD_GATEWAY),
+            (
+                FerragateError::health("down"),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                FerragateError::config("bad"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                FerragateError::tls("bad"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (FerragateError::io("bad"), StatusCode::INTERNAL_SERVER_ERROR),
+            (
+                FerragateError::server("bad"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (error, status) in cases {
+            assert_eq!(error.status_code(), status, "{error}");
+            assert_eq!(error.into_response().status(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejection_response_body_and_headers() {
+        use http_body_util::BodyExt;
+
+        let response = FerragateError::rate_limited("Rate limit exceeded", Some(3)).into_response();
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Rate limit exceeded");
+
+        let response = FerragateError::rate_limited("Rate limit exceeded", None).into_response();
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_internal_error_details_are_not_exposed() {
+        use http_body_util::BodyExt;
+
+        let response = FerragateError::config("secret path /etc/gateway.toml").into_response();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, MSG_INTERNAL_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_handlers_can_propagate_errors() {
+        use tower::ServiceExt;
+
+        async fn handler() -> Result<&'static str, FerragateError> {
+            Err(FerragateError::forbidden("not allowed"))?;
+            Ok("unreachable")
+        }
+
+        let app = axum::Router::new().route("/", axum::routing::get(handler));
+        let request = axum::http::Request::builder()
+            .uri("/")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
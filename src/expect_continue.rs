@@ -0,0 +1,270 @@
+/// Relaying `Expect: 100-continue` between clients and upstreams
+///
+/// A client sending `Expect: 100-continue` holds its body back until it gets an
+/// interim `100 Continue`, so a server that would refuse the request can say so
+/// before a large upload. hyper answers the client with `100 Continue` as soon
+/// as the request body is first read, so the gateway leaves the body unread
+/// until the upstream sends its own `100 Continue`, then streams it through.
+/// An upstream that answers with a final status instead never receives the
+/// body, and the client is never asked to send it. Upstreams that ignore the
+/// expectation get the body after `EXPECT_CONTINUE_TIMEOUT_MS`, as RFC 9110
+/// allows.
+///
+/// reqwest does not report interim responses, so these requests are sent with
+/// a hyper HTTP/1.1 client, which reaches cleartext `http://` upstreams only.
+/// The request is prepared exactly as for reqwest, including its default
+/// headers, and its timeout bounds the whole exchange as reqwest's does. Like
+/// any upload that is not buffered, it is sent in a single attempt, without
+/// retries or mirroring. Routes that follow redirects are not relayed, since
+/// only reqwest follows them.
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
+use axum::response::IntoResponse;
+use futures_util::future::Either;
+use futures_util::StreamExt;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use crate::config::{RouteConfig, UpstreamProtocol};
+use crate::constants::{
+    EXPECT_CONTINUE_TIMEOUT_MS, MSG_UPSTREAM_CONNECT_FAILED, MSG_UPSTREAM_REQUEST_FAILED,
+    MSG_UPSTREAM_TIMEOUT,
+};
+use crate::proxy::UpstreamResponse;
+use crate::tracked_body::TrackedBody;
+
+/// HTTP/1.1 client for requests whose client expects `100 Continue`
+pub type ContinueClient = Client<HttpConnector, Body>;
+
+/// Build the client used to relay `100 Continue` from upstreams
+pub fn build_continue_client() -> ContinueClient {
+    Client::builder(TokioExecutor::new()).build_http()
+}
+
+/// Check whether the client waits for `100 Continue` before sending its body
+pub fn expects_continue(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::EXPECT)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"100-continue"))
+}
+
+/// Check whether a request to the target can go through the relay client
+///
+/// The relay client speaks HTTP/1.1 over cleartext only, from the default local
+/// address, and never follows redirects.
+pub fn can_relay(route: &RouteConfig, target_url: &str) -> bool {
+    target_url.starts_with("http://")
+        && route.upstream_protocol != Some(UpstreamProtocol::Http2)
+        && route.local_address.is_none()
+        && !route.follow_redirects
+}
+
+/// Send a request upstream, holding the client's body back until the upstream asks for it
+///
+/// `request` carries the method, URL, headers and timeout prepared for the
+/// upstream; its body is ignored in favour of `body`, the client's body, which
+/// is only read once the upstream sends `100 Continue`. The request's timeout
+/// bounds the whole exchange, up to the end of the response body, and the wait
+/// for response headers is also bounded by `headers_timeout`. Failures are
+/// returned as the client's response.
+pub async fn forward(
+    client: &ContinueClient,
+    request: reqwest::Request,
+    body: Body,
+    headers_timeout: Option<Duration>,
+) -> Result<UpstreamResponse, axum::response::Response> {
+    let started = Instant::now();
+    let target_url = request.url().clone();
+    let continued = Arc::new(Notify::new());
+    let responded = CancellationToken::new();
+    let finished = CancellationToken::new();
+
+    let body = held_body(
+        body,
+        Arc::clone(&continued),
+        responded.clone(),
+        finished.clone(),
+    );
+    let mut upstream = Request::new(body);
+    *upstream.method_mut() = Method::from_bytes(request.method().as_str().as_bytes())
+        .expect("reqwest methods are valid HTTP methods");
+    *upstream.uri_mut() = match target_url.as_str().parse() {
+        Ok(uri) => uri,
+        Err(e) => {
+            error!("Invalid upstream URL {}: {}", target_url, e);
+            return Err((StatusCode::BAD_GATEWAY, MSG_UPSTREAM_REQUEST_FAILED).into_response());
+        }
+    };
+    for (name, value) in request.headers() {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            upstream.headers_mut().append(name, value);
+        }
+    }
+    hyper::ext::on_informational(&mut upstream, move |response| {
+        if response.status() == StatusCode::CONTINUE {
+            continued.notify_one();
+        }
+    });
+
+    let limit = [request.timeout().copied(), headers_timeout]
+        .into_iter()
+        .flatten()
+        .min();
+    let send = client.request(upstream);
+    let response = match limit {
+        Some(limit) => match tokio::time::timeout(limit, send).await {
+            Ok(response) => response,
+            Err(_) => {
+                error!(
+                    "Upstream {} did not send response headers within {:?}",
+                    target_url, limit
+                );
+                return Err((StatusCode::GATEWAY_TIMEOUT, MSG_UPSTREAM_TIMEOUT).into_response());
+            }
+        },
+        None => send.await,
+    };
+    responded.cancel();
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed to proxy request to {}: {}", target_url, e);
+            let message = if e.is_connect() {
+                MSG_UPSTREAM_CONNECT_FAILED
+            } else {
+                MSG_UPSTREAM_REQUEST_FAILED
+            };
+            return Err((StatusCode::BAD_GATEWAY, message).into_response());
+        }
+    };
+    debug!("Upstream response status: {}", response.status());
+    let deadline = request.timeout().map(|timeout| started + *timeout);
+    Ok(into_upstream_response(
+        response,
+        &target_url,
+        deadline,
+        finished,
+    ))
+}
+
+/// The client's body, read only once the upstream asks for it
+///
+/// If the upstream answers before asking for the body, the body is never read.
+/// The request to the upstream was then never completed, so its connection is
+/// aborted once the answer has been read in full.
+fn held_body(
+    body: Body,
+    continued: Arc<Notify>,
+    responded: CancellationToken,
+    finished: CancellationToken,
+) -> Body {
+    let start = async move {
+        let proceed = tokio::select! {
+            biased;
+            _ = continued.notified() => true,
+            _ = responded.cancelled() => false,
+            _ = tokio::time::sleep(Duration::from_millis(EXPECT_CONTINUE_TIMEOUT_MS)) => {
+                debug!("No 100 Continue from the upstream, sending the request body");
+                true
+            }
+        };
+        if proceed {
+            Either::Left(body.into_data_stream())
+        } else {
+            debug!("Upstream answered without asking for the request body");
+            finished.cancelled().await;
+            Either::Right(futures_util::stream::once(async {
+                Err(axum::Error::new(
+                    "upstream answered before the request body was sent",
+                ))
+            }))
+        }
+    };
+    Body::from_stream(futures_util::stream::once(start).flatten())
+}
+
+/// Hand a response from the relay client to the rest of the proxy
+///
+/// `finished` is cancelled once the body has been read in full, has failed or
+/// has been dropped. A body still being read at `deadline` fails then.
+fn into_upstream_response(
+    response: hyper::Response<hyper::body::Incoming>,
+    url: &reqwest::Url,
+    deadline: Option<Instant>,
+    finished: CancellationToken,
+) -> UpstreamResponse {
+    let (parts, incoming) = response.into_parts();
+    let content_length = hyper::body::Body::size_hint(&incoming).exact();
+    let body = Body::new(TrackedBody::new(Body::new(incoming), move |_| {
+        finished.cancel()
+    }));
+    UpstreamResponse {
+        status: parts.status,
+        version: parts.version,
+        headers: parts.headers,
+        origin: url.origin().ascii_serialization(),
+        content_length,
+        body: match deadline {
+            Some(deadline) => body_until(body, deadline),
+            None => body,
+        },
+    }
+}
+
+/// A body that fails if it has not ended by `deadline`
+fn body_until(body: Body, deadline: Instant) -> Body {
+    let chunks = Box::pin(body.into_data_stream());
+    Body::from_stream(futures_util::stream::unfold(
+        Some(chunks),
+        move |chunks| async move {
+            let mut chunks = chunks?;
+            match tokio::time::timeout_at(deadline, chunks.next()).await {
+                Ok(chunk) => chunk.map(|chunk| (chunk, Some(chunks))),
+                Err(_) => {
+                    error!("Upstream response body not complete by the request timeout");
+                    let timed_out = axum::Error::new("upstream response timed out");
+                    Some((Err(timed_out), None))
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expects_continue() {
+        let mut headers = HeaderMap::new();
+        assert!(!expects_continue(&headers));
+        headers.insert(header::EXPECT, HeaderValue::from_static("100-Continue"));
+        assert!(expects_continue(&headers));
+    }
+
+    #[test]
+    fn test_can_relay_only_cleartext_http1_without_redirects() {
+        let mut route = RouteConfig::default();
+        assert!(can_relay(&route, "http://backend:8080/upload"));
+        assert!(!can_relay(&route, "https://backend/upload"));
+
+        route.upstream_protocol = Some(UpstreamProtocol::Http2);
+        assert!(!can_relay(&route, "http://backend:8080/upload"));
+        route.upstream_protocol = None;
+        route.local_address = Some("127.0.0.1".parse().unwrap());
+        assert!(!can_relay(&route, "http://backend:8080/upload"));
+        route.local_address = None;
+        route.follow_redirects = true;
+        assert!(!can_relay(&route, "http://backend:8080/upload"));
+    }
+}
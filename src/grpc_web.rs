@@ -0,0 +1,304 @@
+/// gRPC-Web translation for browser clients of gRPC upstreams
+///
+/// gRPC-Web frames messages exactly like gRPC, but browsers cannot read HTTP
+/// trailers, so the gRPC status travels in a final length-prefixed frame of the
+/// response body instead, flagged by the high bit of its first byte. The text
+/// variant (`application/grpc-web-text`) additionally base64-encodes the body.
+///
+/// Routes with `grpc_web = true` accept either variant, call the upstream over
+/// HTTP/2 as a standard gRPC request and translate the response back. Browsers
+/// only make unary and server-streaming calls over gRPC-Web, so request bodies
+/// are read in full while responses are streamed.
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::response::IntoResponse;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::{BufMut, Bytes, BytesMut};
+use http_body_util::BodyExt;
+use hyper::body::Frame;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::constants::{
+    GRPC_CONTENT_TYPE, GRPC_STATUS_DEADLINE_EXCEEDED, GRPC_STATUS_UNAVAILABLE,
+    GRPC_WEB_CONTENT_TYPE, GRPC_WEB_TEXT_CONTENT_TYPE, GRPC_WEB_TRAILER_FLAG,
+    MSG_INVALID_GRPC_WEB_BODY, MSG_UPSTREAM_CONNECT_FAILED, MSG_UPSTREAM_REQUEST_FAILED,
+    MSG_UPSTREAM_TIMEOUT,
+};
+
+/// HTTP/2 client for gRPC upstreams, which keeps response trailers intact
+pub type GrpcClient = Client<HttpConnector, Body>;
+
+/// Build the client used to call the upstreams of gRPC-Web routes
+///
+/// gRPC requires HTTP/2; upstreams are reached over cleartext with prior knowledge.
+pub fn build_grpc_client() -> GrpcClient {
+    Client::builder(TokioExecutor::new())
+        .http2_only(true)
+        .build_http()
+}
+
+/// How a gRPC-Web body is encoded on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrpcWebEncoding {
+    /// `application/grpc-web`: gRPC framing as-is
+    Binary,
+    /// `application/grpc-web-text`: gRPC framing, base64-encoded
+    Text,
+}
+
+/// A gRPC-Web call being translated to gRPC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcWebRequest {
+    encoding: GrpcWebEncoding,
+    /// Message format suffix of the content type, such as `+proto`
+    format: String,
+}
+
+impl GrpcWebRequest {
+    /// Recognize a gRPC-Web request by its content type
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        let essence = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        let (encoding, format) = match essence.strip_prefix(GRPC_WEB_TEXT_CONTENT_TYPE) {
+            Some(format) => (GrpcWebEncoding::Text, format),
+            None => (
+                GrpcWebEncoding::Binary,
+                essence.strip_prefix(GRPC_WEB_CONTENT_TYPE)?,
+            ),
+        };
+        if !format.is_empty() && !format.starts_with('+') {
+            return None;
+        }
+        Some(Self {
+            encoding,
+            format: format.to_string(),
+        })
+    }
+
+    /// Body encoding of the call
+    #[allow(dead_code)] // Public API method
+    pub fn encoding(&self) -> GrpcWebEncoding {
+        self.encoding
+    }
+
+    /// Content type of the call toward the client
+    fn web_content_type(&self) -> String {
+        let base = match self.encoding {
+            GrpcWebEncoding::Binary => GRPC_WEB_CONTENT_TYPE,
+            GrpcWebEncoding::Text => GRPC_WEB_TEXT_CONTENT_TYPE,
+        };
+        format!("{base}{}", self.format)
+    }
+
+    /// Decode the client's request body into gRPC framing
+    pub fn decode_body(&self, body: Bytes) -> Result<Bytes, base64::DecodeError> {
+        match self.encoding {
+            GrpcWebEncoding::Binary => Ok(body),
+            GrpcWebEncoding::Text => decode_text(&body).map(Bytes::from),
+        }
+    }
+
+    /// Send the call to a gRPC upstream and translate its response for the client
+    ///
+    /// `headers` are the headers to send upstream; the content type is switched to
+    /// gRPC and `te: trailers` is added, as gRPC servers require. The wait for
+    /// response headers is bounded by `headers_timeout`.
+    pub async fn forward(
+        &self,
+        client: &GrpcClient,
+        target_url: &str,
+        mut headers: HeaderMap,
+        body: Bytes,
+        headers_timeout: Duration,
+    ) -> axum::response::Response {
+        let body = match self.decode_body(body) {
+            Ok(body) => body,
+            Err(e) => {
+                debug!("Invalid gRPC-Web text body: {}", e);
+                return (StatusCode::BAD_REQUEST, MSG_INVALID_GRPC_WEB_BODY).into_response();
+            }
+        };
+
+        headers.remove(header::CONTENT_LENGTH);
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_str(&format!("{GRPC_CONTENT_TYPE}{}", self.format))
+                .expect("content type suffix comes from a header value"),
+        );
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+
+        let mut request = Request::new(Body::from(body));
+        *request.method_mut() = Method::POST;
+        *request.headers_mut() = headers;
+        *request.uri_mut() = match target_url.parse() {
+            Ok(uri) => uri,
+            Err(e) => {
+                error!("Invalid gRPC upstream URL {}: {}", target_url, e);
+                return unavailable(StatusCode::BAD_GATEWAY, MSG_UPSTREAM_REQUEST_FAILED);
+            }
+        };
+
+        let response = match tokio::time::timeout(headers_timeout, client.request(request)).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                error!("Failed to proxy gRPC-Web request to {}: {}", target_url, e);
+                let message = if e.is_connect() {
+                    MSG_UPSTREAM_CONNECT_FAILED
+                } else {
+                    MSG_UPSTREAM_REQUEST_FAILED
+                };
+                return unavailable(StatusCode::BAD_GATEWAY, message);
+            }
+            Err(_) => {
+                error!(
+                    "gRPC upstream {} did not send response headers within {:?}",
+                    target_url, headers_timeout
+                );
+                let mut response =
+                    (StatusCode::GATEWAY_TIMEOUT, MSG_UPSTREAM_TIMEOUT).into_response();
+                insert_grpc_status(
+                    response.headers_mut(),
+                    GRPC_STATUS_DEADLINE_EXCEEDED,
+                    MSG_UPSTREAM_TIMEOUT,
+                );
+                return response;
+            }
+        };
+        debug!("gRPC upstream response status: {}", response.status());
+
+        let (mut parts, body) = response.into_parts();
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_str(&self.web_content_type())
+                .expect("content type suffix comes from a header value"),
+        );
+        let encoding = self.encoding;
+        let body = body.map_frame(move |frame| {
+            let data = match frame.into_data() {
+                Ok(data) => data,
+                Err(frame) => frame
+                    .into_trailers()
+                    .map(|trailers| trailer_frame(&trailers))
+                    .unwrap_or_default(),
+            };
+            Frame::data(match encoding {
+                GrpcWebEncoding::Binary => data,
+                GrpcWebEncoding::Text => Bytes::from(STANDARD.encode(&data)),
+            })
+        });
+        axum::response::Response::from_parts(parts, Body::new(body))
+    }
+}
+
+/// Encode gRPC trailers as the final frame of a gRPC-Web response body
+///
+/// The frame holds the trailers as `name:value` lines, each ended by CRLF.
+pub fn trailer_frame(trailers: &HeaderMap) -> Bytes {
+    let mut block = Vec::new();
+    for (name, value) in trailers {
+        block.extend_from_slice(name.as_str().as_bytes());
+        block.push(b':');
+        block.extend_from_slice(value.as_bytes());
+        block.extend_from_slice(b"\r\n");
+    }
+    let mut frame = BytesMut::with_capacity(5 + block.len());
+    frame.put_u8(GRPC_WEB_TRAILER_FLAG);
+    frame.put_u32(block.len() as u32);
+    frame.extend_from_slice(&block);
+    frame.freeze()
+}
+
+/// Decode a base64 gRPC-Web text body
+///
+/// Clients may concatenate separately padded chunks, so each padded segment is
+/// decoded on its own.
+pub fn decode_text(body: &[u8]) -> Result<Vec<u8>, base64::DecodeError> {
+    let text: Vec<u8> = body
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut start = 0;
+    for (index, quad) in text.chunks(4).enumerate() {
+        let end = index * 4 + quad.len();
+        if quad.contains(&b'=') {
+            STANDARD.decode_vec(&text[start..end], &mut decoded)?;
+            start = end;
+        }
+    }
+    STANDARD.decode_vec(&text[start..], &mut decoded)?;
+    Ok(decoded)
+}
+
+/// Gateway error response that gRPC-Web clients report as UNAVAILABLE
+fn unavailable(status: StatusCode, message: &'static str) -> axum::response::Response {
+    let mut response = (status, message).into_response();
+    insert_grpc_status(response.headers_mut(), GRPC_STATUS_UNAVAILABLE, message);
+    response
+}
+
+fn insert_grpc_status(headers: &mut HeaderMap, status: u16, message: &'static str) {
+    headers.insert("grpc-status", HeaderValue::from(status));
+    headers.insert("grpc-message", HeaderValue::from_static(message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(content_type: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_grpc_web_content_types() {
+        let binary = GrpcWebRequest::from_headers(&headers("application/grpc-web+proto")).unwrap();
+        assert_eq!(binary.encoding(), GrpcWebEncoding::Binary);
+        assert_eq!(binary.web_content_type(), "application/grpc-web+proto");
+
+        let text = GrpcWebRequest::from_headers(&headers("application/grpc-web-text")).unwrap();
+        assert_eq!(text.encoding(), GrpcWebEncoding::Text);
+        assert_eq!(text.web_content_type(), "application/grpc-web-text");
+
+        assert!(GrpcWebRequest::from_headers(&headers("application/grpc")).is_none());
+        assert!(GrpcWebRequest::from_headers(&headers("application/grpc-webby")).is_none());
+        assert!(GrpcWebRequest::from_headers(&headers("application/json")).is_none());
+        assert!(GrpcWebRequest::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_trailer_frame() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        trailers.insert("grpc-message", HeaderValue::from_static("OK"));
+
+        let frame = trailer_frame(&trailers);
+        let block = b"grpc-status:0\r\ngrpc-message:OK\r\n";
+        assert_eq!(frame[0], 0x80);
+        assert_eq!(&frame[1..5], (block.len() as u32).to_be_bytes());
+        assert_eq!(&frame[5..], block);
+    }
+
+    #[test]
+    fn test_decode_text_body() {
+        let message = b"\x00\x00\x00\x00\x03abc";
+        let encoded = STANDARD.encode(message);
+        assert_eq!(decode_text(encoded.as_bytes()).unwrap(), message);
+
+        // Separately padded chunks, with a line break between them
+        let chunked = format!("{}\r\n{}", STANDARD.encode(b"ab"), STANDARD.encode(b"cde"));
+        assert_eq!(decode_text(chunked.as_bytes()).unwrap(), b"abcde");
+
+        assert!(decode_text(b"not base64!").is_err());
+        assert!(decode_text(b"YWJ").is_err());
+    }
+}
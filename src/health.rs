@@ -1,11 +1,16 @@
 use axum::{extract::State, http::StatusCode, response::Json};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::constants::{MSG_HEALTH_CHECK_FAILED, MSG_SERVER_NOT_READY};
+use crate::constants::{
+    DEFAULT_MAX_HEALTH_CHECKS, HEALTH_CHECK_INTERVAL_SECS, HEALTH_TASK_RESTART_DELAY_MS,
+    LOOPBACK_HEALTH_CHECK_NAME, LOOPBACK_HEALTH_CHECK_TIMEOUT_MS, MSG_HEALTH_CHECK_FAILED,
+    MSG_SERVER_NOT_READY,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthCheck {
@@ -46,11 +51,81 @@ pub struct ReadinessResponse {
     pub checks: Vec<HealthCheck>,
 }
 
+/// Registered health checks, indexed by name
+///
+/// Checks are looked up by name, and a second index keyed by registration
+/// number keeps them in registration order. Lookups, updates and removals do
+/// not scan the other checks, and listings walk the order index without sorting.
+#[derive(Debug, Default)]
+struct HealthChecks {
+    by_name: HashMap<String, RegisteredCheck>,
+    /// Names of the registered checks by registration number
+    order: BTreeMap<u64, String>,
+    next_order: u64,
+}
+
+#[derive(Debug)]
+struct RegisteredCheck {
+    order: u64,
+    check: HealthCheck,
+}
+
+impl HealthChecks {
+    fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name)
+    }
+
+    fn get(&self, name: &str) -> Option<&HealthCheck> {
+        self.by_name.get(name).map(|registered| &registered.check)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut HealthCheck> {
+        self.by_name
+            .get_mut(name)
+            .map(|registered| &mut registered.check)
+    }
+
+    fn insert(&mut self, check: HealthCheck) {
+        let order = self.next_order;
+        self.next_order += 1;
+        self.order.insert(order, check.name.clone());
+        self.by_name
+            .insert(check.name.clone(), RegisteredCheck { order, check });
+    }
+
+    fn remove(&mut self, name: &str) -> Option<HealthCheck> {
+        let registered = self.by_name.remove(name)?;
+        self.order.remove(&registered.order);
+        Some(registered.check)
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut HealthCheck> {
+        self.by_name
+            .values_mut()
+            .map(|registered| &mut registered.check)
+    }
+
+    /// Copies of all checks in registration order
+    fn to_vec(&self) -> Vec<HealthCheck> {
+        self.order
+            .values()
+            .filter_map(|name| self.get(name).cloned())
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     startup_time: SystemTime,
     ready: Arc<RwLock<bool>>,
-    health_checks: Arc<RwLock<Vec<HealthCheck>>>,
+    maintenance: Arc<RwLock<bool>>,
+    health_checks: Arc<RwLock<HealthChecks>>,
+    max_health_checks: usize,
+    loopback_url: Arc<RwLock<Option<String>>>,
 }
 
 impl Default for AppState {
@@ -61,10 +136,18 @@ impl Default for AppState {
 
 impl AppState {
     pub fn new() -> Self {
+        Self::with_max_health_checks(DEFAULT_MAX_HEALTH_CHECKS)
+    }
+
+    /// Create a state that accepts at most `max_health_checks` registered checks
+    pub fn with_max_health_checks(max_health_checks: usize) -> Self {
         Self {
             startup_time: SystemTime::now(),
             ready: Arc::new(RwLock::new(true)),
-            health_checks: Arc::new(RwLock::new(vec![])), // Start with no health checks
+            maintenance: Arc::new(RwLock::new(false)),
+            health_checks: Arc::new(RwLock::new(HealthChecks::default())), // Start with no health checks
+            max_health_checks,
+            loopback_url: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -83,15 +166,34 @@ impl AppState {
         *self.ready.write().unwrap() = ready;
     }
 
+    /// Check whether the gateway is in maintenance mode
+    pub fn in_maintenance(&self) -> bool {
+        *self.maintenance.read().unwrap()
+    }
+
+    /// Enter or leave maintenance mode
+    ///
+    /// While in maintenance mode proxied requests are rejected with 503 and the
+    /// readiness endpoint reports not ready; liveness and health stay up.
+    pub fn set_maintenance(&self, maintenance: bool) {
+        *self.maintenance.write().unwrap() = maintenance;
+    }
+
     pub fn get_health_checks(&self) -> Vec<HealthCheck> {
-        self.health_checks.read().unwrap().clone()
+        self.health_checks.read().unwrap().to_vec()
+    }
+
+    /// Look up a single health check by name
+    #[allow(dead_code)] // Public API method
+    pub fn get_health_check(&self, name: &str) -> Option<HealthCheck> {
+        self.health_checks.read().unwrap().get(name).cloned()
     }
 
     /// Update the status of an existing health check
     /// This is part of the public health API and is used by the background health checker
     pub fn update_health_check(&self, name: &str, status: HealthStatus, message: Option<String>) {
         let mut checks = self.health_checks.write().unwrap();
-        if let Some(check) = checks.iter_mut().find(|c| c.name == name) {
+        if let Some(check) = checks.get_mut(name) {
             let old_status = check.status.clone();
             check.status = status.clone();
             check.last_checked = Utc::now();
@@ -125,28 +227,68 @@ impl AppState {
 
     /// Register a new health check
     /// This is part of the public health API and is used by the background health checker
+    ///
+    /// Returns whether the check was registered. Duplicates, and checks beyond
+    /// the configured maximum, are rejected with a warning.
     pub fn register_health_check(
         &self,
         name: String,
         status: HealthStatus,
         message: Option<String>,
-    ) {
+    ) -> bool {
         let mut checks = self.health_checks.write().unwrap();
-        // Check if health check already exists
-        if !checks.iter().any(|c| c.name == name) {
-            info!(
-                "Registering new health check: {} with status: {:?}",
-                name, status
-            );
-            checks.push(HealthCheck {
-                name,
-                status,
-                last_checked: Utc::now(),
-                message,
-            });
-        } else {
+        if checks.contains(&name) {
             warn!("Attempted to register duplicate health check: {}", name);
+            return false;
+        }
+        if checks.len() >= self.max_health_checks {
+            warn!(
+                "Not registering health check {}: limit of {} health checks reached",
+                name, self.max_health_checks
+            );
+            return false;
         }
+
+        info!(
+            "Registering new health check: {} with status: {:?}",
+            name, status
+        );
+        checks.insert(HealthCheck {
+            name,
+            status,
+            last_checked: Utc::now(),
+            message,
+        });
+        true
+    }
+
+    /// Register the loopback health check against a URL served by the gateway
+    ///
+    /// The background task requests the URL on every run, so the check fails
+    /// when the gateway stops answering requests even though the process is alive.
+    pub fn register_loopback_check(&self, url: String) {
+        if self.register_health_check(
+            LOOPBACK_HEALTH_CHECK_NAME.to_string(),
+            HealthStatus::Healthy,
+            Some(format!("Waiting for the first request to {url}")),
+        ) {
+            *self.loopback_url.write().unwrap() = Some(url);
+        }
+    }
+
+    /// Request the loopback URL once and record the result, if the check is registered
+    pub async fn run_loopback_check(&self) {
+        let Some(url) = self.loopback_url.read().unwrap().clone() else {
+            return;
+        };
+        let (status, message) = match probe_loopback(&url).await {
+            Ok(()) => (HealthStatus::Healthy, Some(format!("{url} answered"))),
+            Err(reason) => (
+                HealthStatus::Unhealthy,
+                Some(format!("{url} failed: {reason}")),
+            ),
+        };
+        self.update_health_check(LOOPBACK_HEALTH_CHECK_NAME, status, message);
     }
 
     /// Remove a health check
@@ -154,9 +296,7 @@ impl AppState {
     #[allow(dead_code)] // Public API method
     pub fn unregister_health_check(&self, name: &str) {
         let mut checks = self.health_checks.write().unwrap();
-        let initial_count = checks.len();
-        checks.retain(|c| c.name != name);
-        if checks.len() < initial_count {
+        if checks.remove(name).is_some() {
             info!("Unregistered health check: {}", name);
         } else {
             warn!(
@@ -172,7 +312,7 @@ impl AppState {
     #[allow(dead_code)] // Public API method - can be called manually to trigger health checks
     pub async fn run_all_health_checks(&self) {
         let mut checks = self.health_checks.write().unwrap();
-        for check in checks.iter_mut() {
+        for check in checks.values_mut() {
             check.last_checked = Utc::now();
             // Future: Here you could call actual health check functions
             // based on the check name or type
@@ -308,7 +448,7 @@ pub async fn readiness_handler(
             .all(|check| matches!(check.status, HealthStatus::Healthy))
     };
 
-    let is_ready = state.is_ready() && checks_healthy;
+    let is_ready = state.is_ready() && !state.in_maintenance() && checks_healthy;
 
     let status = if is_ready {
         HealthStatus::Healthy
@@ -338,10 +478,13 @@ pub async fn readiness_handler(
     (status_code, Json(response))
 }
 
-/// Execute a single health check and update its status
-fn execute_health_check(state: &AppState, name: &str) {
+/// Probe deciding whether a named health check passes
+pub type HealthProbe = fn(&str) -> bool;
+
+/// Built-in probe for the default health checks
+fn probe_health_check(name: &str) -> bool {
     // Simulate health check logic (in real implementation, this would check actual services)
-    let is_healthy = match name {
+    match name {
         "system" => {
             // Simulate system health check - always healthy if AppState exists
             true
@@ -354,42 +497,112 @@ fn execute_health_check(state: &AppState, name: &str) {
             // Default health check
             true
         }
-    };
+    }
+}
 
-    let (status, message) = if is_healthy {
-        (HealthStatus::Healthy, Some(format!("{name} check passed")))
+/// Send one request to the gateway's own listener
+///
+/// Redirects count as answered, since a listener redirecting to HTTPS is still
+/// serving requests.
+async fn probe_loopback(url: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(LOOPBACK_HEALTH_CHECK_TIMEOUT_MS))
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        Ok(())
     } else {
-        (
+        Err(format!("status {status}"))
+    }
+}
+
+/// Execute a single health check with the given probe and update its status
+///
+/// A panicking probe is caught and marks its check unhealthy, so one faulty
+/// check cannot take down the background task and freeze every other status.
+fn execute_health_check(state: &AppState, name: &str, probe: HealthProbe) {
+    let (status, message) = match std::panic::catch_unwind(|| probe(name)) {
+        Ok(true) => (HealthStatus::Healthy, Some(format!("{name} check passed"))),
+        Ok(false) => (
             HealthStatus::Unhealthy,
             Some(format!("{name} check failed")),
-        )
+        ),
+        Err(panic) => {
+            let reason = panic_message(panic.as_ref());
+            error!("Health check '{}' panicked: {}", name, reason);
+            (
+                HealthStatus::Unhealthy,
+                Some(format!("{name} check panicked: {reason}")),
+            )
+        }
     };
 
     state.update_health_check(name, status, message);
 }
 
+/// Text of a caught panic payload
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
 /// Background task to periodically run health checks
 #[instrument(skip(state))]
 pub async fn health_check_background_task(state: AppState) {
-    let mut interval = tokio::time::interval(Duration::from_secs(30));
+    run_health_checks(
+        state,
+        Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS),
+        probe_health_check,
+    )
+    .await;
+}
+
+/// Run the background health checks, restarting the task if it ever exits
+///
+/// Individual checks already recover from panics; this guards against the
+/// loop itself dying, which would otherwise leave every status frozen.
+pub async fn supervise_health_check_task(state: AppState) {
+    loop {
+        match tokio::spawn(health_check_background_task(state.clone())).await {
+            Err(e) if e.is_cancelled() => return,
+            Err(e) => error!("Background health check task panicked, restarting: {}", e),
+            Ok(()) => warn!("Background health check task exited unexpectedly, restarting"),
+        }
+        tokio::time::sleep(Duration::from_millis(HEALTH_TASK_RESTART_DELAY_MS)).await;
+    }
+}
+
+/// Run every registered health check with `probe` once per `period`
+async fn run_health_checks(state: AppState, period: Duration, probe: HealthProbe) {
+    let mut interval = tokio::time::interval(period);
     info!("Background health check task started");
 
     loop {
         interval.tick().await;
-        let checks_count = state.get_health_checks().len();
+        state.run_loopback_check().await;
+
+        // The loopback check is not probed by name, and on its own does not
+        // stand in for the defaults
+        let check_names: Vec<String> = state
+            .get_health_checks()
+            .into_iter()
+            .map(|check| check.name)
+            .filter(|name| name != LOOPBACK_HEALTH_CHECK_NAME)
+            .collect();
 
-        if checks_count > 0 {
-            info!("Running {} background health checks...", checks_count);
+        if !check_names.is_empty() {
+            info!("Running {} background health checks...", check_names.len());
 
             // Simulate health checks and update their status
-            let check_names: Vec<String> = state
-                .get_health_checks()
-                .into_iter()
-                .map(|check| check.name)
-                .collect();
-
             for name in check_names {
-                execute_health_check(&state, &name);
+                execute_health_check(&state, &name, probe);
             }
 
             debug!("Background health checks completed");
@@ -573,6 +786,83 @@ mod tests {
         assert_eq!(checks[0].name, "service2");
     }
 
+    #[test]
+    fn test_registration_is_capped() {
+        let state = AppState::with_max_health_checks(2);
+
+        assert!(state.register_health_check("a".to_string(), HealthStatus::Healthy, None));
+        assert!(state.register_health_check("b".to_string(), HealthStatus::Healthy, None));
+        assert!(!state.register_health_check("c".to_string(), HealthStatus::Healthy, None));
+        assert!(!state.register_health_check("a".to_string(), HealthStatus::Unhealthy, None));
+        assert_eq!(state.get_health_checks().len(), 2);
+        assert!(state.get_health_check("c").is_none());
+
+        // Unregistering frees a slot
+        state.unregister_health_check("a");
+        assert!(state.register_health_check("c".to_string(), HealthStatus::Healthy, None));
+        let names: Vec<_> = state
+            .get_health_checks()
+            .into_iter()
+            .map(|check| check.name)
+            .collect();
+        assert_eq!(names, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_lookup_and_update_by_name() {
+        let state = AppState::with_max_health_checks(10_000);
+        for i in 0..10_000 {
+            state.register_health_check(format!("check-{i}"), HealthStatus::Healthy, None);
+        }
+
+        state.update_health_check(
+            "check-9999",
+            HealthStatus::Unhealthy,
+            Some("down".to_string()),
+        );
+        let check = state.get_health_check("check-9999").unwrap();
+        assert_eq!(check.status, HealthStatus::Unhealthy);
+        assert_eq!(check.message.as_deref(), Some("down"));
+        assert_eq!(
+            state.get_health_check("check-0").unwrap().status,
+            HealthStatus::Healthy
+        );
+        assert!(state.get_health_check("check-10000").is_none());
+
+        // Registration order is kept
+        let checks = state.get_health_checks();
+        assert_eq!(checks[0].name, "check-0");
+        assert_eq!(checks[9_999].name, "check-9999");
+    }
+
+    #[test]
+    fn test_many_checks_keep_registration_order() {
+        let state = AppState::with_max_health_checks(1_000);
+        for i in 0..1_000 {
+            state.register_health_check(format!("check-{i}"), HealthStatus::Healthy, None);
+        }
+        for i in (0..1_000).step_by(2) {
+            state.unregister_health_check(&format!("check-{i}"));
+        }
+        state.register_health_check("check-0".to_string(), HealthStatus::Unknown, None);
+
+        let names: Vec<_> = state
+            .get_health_checks()
+            .into_iter()
+            .map(|check| check.name)
+            .collect();
+        let mut expected: Vec<_> = (1..1_000)
+            .step_by(2)
+            .map(|i| format!("check-{i}"))
+            .collect();
+        expected.push("check-0".to_string());
+        assert_eq!(names, expected);
+        assert_eq!(
+            state.get_health_check("check-0").unwrap().status,
+            HealthStatus::Unknown
+        );
+    }
+
     #[test]
     fn test_unregister_nonexistent_health_check() {
         let state = AppState::new();
@@ -762,6 +1052,21 @@ mod tests {
         assert!(!response.ready);
     }
 
+    #[tokio::test]
+    async fn test_readiness_handler_not_ready_in_maintenance() {
+        let state = AppState::new();
+
+        state.set_maintenance(true);
+        let (status, response) = readiness_handler(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!response.ready);
+
+        state.set_maintenance(false);
+        let (status, response) = readiness_handler(State(state)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.ready);
+    }
+
     #[tokio::test]
     async fn test_readiness_handler_not_ready_due_to_unhealthy_checks() {
         let state = AppState::new();
@@ -800,7 +1105,7 @@ mod tests {
         state.register_health_check("system".to_string(), HealthStatus::Unknown, None);
 
         // Execute the system health check
-        super::execute_health_check(&state, "system");
+        super::execute_health_check(&state, "system", super::probe_health_check);
 
         let checks = state.get_health_checks();
         assert_eq!(checks[0].status, HealthStatus::Healthy);
@@ -819,7 +1124,7 @@ mod tests {
         state.register_health_check("memory".to_string(), HealthStatus::Unknown, None);
 
         // Execute the memory health check
-        super::execute_health_check(&state, "memory");
+        super::execute_health_check(&state, "memory", super::probe_health_check);
 
         let checks = state.get_health_checks();
         assert_eq!(checks[0].status, HealthStatus::Healthy);
@@ -838,7 +1143,7 @@ mod tests {
         state.register_health_check("custom-service".to_string(), HealthStatus::Unknown, None);
 
         // Execute the custom health check (should use default logic)
-        super::execute_health_check(&state, "custom-service");
+        super::execute_health_check(&state, "custom-service", super::probe_health_check);
 
         let checks = state.get_health_checks();
         assert_eq!(checks[0].status, HealthStatus::Healthy);
@@ -1018,6 +1323,93 @@ mod tests {
         assert!(check_names.contains(&"memory".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_panicking_check_is_marked_unhealthy_and_loop_continues() {
+        fn probe(name: &str) -> bool {
+            if name == "flaky" {
+                panic!("probe exploded");
+            }
+            true
+        }
+
+        let state = AppState::new();
+        state.register_health_check("flaky".to_string(), HealthStatus::Healthy, None);
+        state.register_health_check("system".to_string(), HealthStatus::Unknown, None);
+
+        let handle = tokio::spawn(super::run_health_checks(
+            state.clone(),
+            Duration::from_millis(20),
+            probe,
+        ));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let first_checked = state.get_health_checks()[1].last_checked;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        assert!(!handle.is_finished());
+        handle.abort();
+
+        let checks = state.get_health_checks();
+        assert_eq!(checks[0].status, HealthStatus::Unhealthy);
+        assert_eq!(
+            checks[0].message.as_deref(),
+            Some("flaky check panicked: probe exploded")
+        );
+        assert_eq!(checks[1].status, HealthStatus::Healthy);
+        assert!(checks[1].last_checked > first_checked);
+    }
+
+    #[tokio::test]
+    async fn test_background_task_runs_loopback_check() {
+        use axum::{routing::get, Router};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/health/live", get(|| async { "alive" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let state = AppState::new();
+        state.register_loopback_check(format!("http://{addr}/health/live"));
+        let handle = tokio::spawn(super::run_health_checks(
+            state.clone(),
+            Duration::from_millis(20),
+            probe_health_check,
+        ));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.abort();
+
+        let checks = state.get_health_checks();
+        let loopback = checks
+            .iter()
+            .find(|check| check.name == LOOPBACK_HEALTH_CHECK_NAME)
+            .unwrap();
+        assert_eq!(loopback.status, HealthStatus::Healthy);
+        assert!(loopback.message.as_deref().unwrap().ends_with("answered"));
+        // The defaults are still registered alongside the loopback check
+        assert!(checks.iter().any(|check| check.name == "system"));
+    }
+
+    #[tokio::test]
+    async fn test_loopback_check_fails_when_listener_is_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let state = AppState::new();
+        state.register_loopback_check(format!("http://{addr}/health/live"));
+        state.run_loopback_check().await;
+
+        let checks = state.get_health_checks();
+        assert_eq!(checks[0].status, HealthStatus::Unhealthy);
+        assert!(checks[0].message.as_deref().unwrap().contains("failed"));
+    }
+
+    #[tokio::test]
+    async fn test_loopback_check_is_a_no_op_when_not_registered() {
+        let state = AppState::new();
+        state.run_loopback_check().await;
+        assert!(state.get_health_checks().is_empty());
+    }
+
     #[test]
     fn test_multiple_health_check_updates() {
         let state = AppState::new();
@@ -1,9 +1,35 @@
+pub mod admin;
+pub mod balancer;
+pub mod bench;
+pub mod body_log;
+pub mod builtin_files;
+pub mod canary;
 pub mod cli;
+pub mod client_ip;
 pub mod config;
+pub mod config_builder;
 pub mod constants;
+pub mod decompress;
 pub mod error;
+pub mod expect_continue;
+pub mod grpc_web;
 pub mod health;
+pub mod listener;
 pub mod logging;
+pub mod metrics;
 pub mod proxy;
+pub mod rate_limit;
+pub mod reload;
+pub mod retry;
+pub mod route_queue;
+pub mod route_trie;
 pub mod server;
+pub mod shedding;
+pub mod shutdown;
+pub mod single_flight;
+pub mod socket_activation;
 pub mod tls;
+pub mod trace_context;
+pub mod tracked_body;
+pub mod upstream_health;
+pub mod upstream_stats;
@@ -0,0 +1,586 @@
+/// Connection-level limits for the HTTP listener
+///
+/// Request rate limits only apply once a connection has been accepted, so they do
+/// not protect against floods of new connections. This listener bounds how many
+/// connections are open at once and how fast new ones are accepted. Excess
+/// connections are not refused outright; they wait in the kernel's accept backlog
+/// until a slot or an accept token frees up, and are refused by the kernel once
+/// that backlog is full. The listener also applies the configured socket options
+/// to each accepted connection, and closes connections that stay idle for longer
+/// than the configured idle timeout. A connection with a request in flight is
+/// never idle, however long the upstream takes to answer.
+use axum::body::Body;
+use axum::extract::Request;
+use axum::response::Response;
+use axum::serve::{IncomingStream, Listener};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{Instant, Sleep};
+use tracing::debug;
+
+use crate::config::ServerConfig;
+use crate::rate_limit::TokenBucket;
+use crate::tracked_body::TrackedBody;
+
+/// Socket options for accepted connections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Disable Nagle's algorithm, sending small writes immediately
+    pub nodelay: bool,
+    /// Idle time before TCP keepalive probes start, also used as the probe interval
+    pub keepalive: Option<Duration>,
+}
+
+impl SocketOptions {
+    /// Socket options from the server configuration
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            nodelay: config.tcp_nodelay,
+            keepalive: config.tcp_keepalive_secs.map(Duration::from_secs),
+        }
+    }
+
+    /// Apply the options to an accepted connection
+    fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        if self.nodelay {
+            stream.set_nodelay(true)?;
+        }
+        if let Some(keepalive) = self.keepalive {
+            let params = socket2::TcpKeepalive::new()
+                .with_time(keepalive)
+                .with_interval(keepalive);
+            socket2::SockRef::from(stream).set_tcp_keepalive(&params)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bind a TCP listener with the given accept backlog
+pub fn bind_listener(addr: SocketAddr, backlog: u32) -> io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    // Match TcpListener::bind, which allows rebinding a port left in TIME_WAIT
+    #[cfg(unix)]
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(backlog)
+}
+
+/// TCP listener that enforces `max_connections` and `max_accept_rate`
+pub struct LimitedListener {
+    inner: TcpListener,
+    connections: Option<Arc<Semaphore>>,
+    accept_rate: Option<(TokenBucket, Duration)>,
+    options: SocketOptions,
+    idle_timeout: Option<Duration>,
+}
+
+impl LimitedListener {
+    /// Wrap a listener with the limits, socket options and idle timeout from the server configuration
+    pub fn new(inner: TcpListener, config: &ServerConfig) -> Self {
+        Self::with_limits(inner, config.max_connections, config.max_accept_rate)
+            .with_socket_options(SocketOptions::from_config(config))
+            .with_idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+    }
+
+    /// Wrap a listener with explicit limits; `None` disables a limit
+    pub fn with_limits(
+        inner: TcpListener,
+        max_connections: Option<usize>,
+        max_accept_rate: Option<f64>,
+    ) -> Self {
+        Self {
+            inner,
+            connections: max_connections.map(|max| Arc::new(Semaphore::new(max))),
+            accept_rate: max_accept_rate.map(|rate| {
+                let interval = Duration::from_secs_f64(1.0 / rate).max(Duration::from_millis(1));
+                (TokenBucket::new(rate, None), interval)
+            }),
+            options: SocketOptions::default(),
+            idle_timeout: None,
+        }
+    }
+
+    /// Apply the given socket options to accepted connections
+    pub fn with_socket_options(mut self, options: SocketOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Close accepted connections once nothing has been read or written for `timeout`
+    ///
+    /// Served through `TrackInFlight`, connections with requests in flight are kept open.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+}
+
+impl Listener for LimitedListener {
+    type Io = LimitedStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        // Wait for a free connection slot before accepting
+        let permit = match &self.connections {
+            Some(connections) => {
+                if connections.available_permits() == 0 {
+                    debug!("Connection limit reached, delaying accept");
+                }
+                let permit = connections
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("connection semaphore is never closed");
+                Some(permit)
+            }
+            None => None,
+        };
+
+        // Then wait for the accept rate to allow another connection
+        if let Some((bucket, interval)) = &self.accept_rate {
+            while !bucket.try_acquire() {
+                tokio::time::sleep(*interval).await;
+            }
+        }
+
+        let (stream, addr) = Listener::accept(&mut self.inner).await;
+        if let Err(e) = self.options.apply(&stream) {
+            debug!("Failed to set socket options for {}: {}", addr, e);
+        }
+        (
+            LimitedStream {
+                stream,
+                idle: self.idle_timeout.map(IdleTimer::new),
+                requests: InFlightRequests::default(),
+                _permit: permit,
+            },
+            addr,
+        )
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+/// Number of requests in flight on a connection
+#[derive(Debug, Clone, Default)]
+struct InFlightRequests(Arc<AtomicUsize>);
+
+impl InFlightRequests {
+    fn any(&self) -> bool {
+        self.0.load(Ordering::Acquire) > 0
+    }
+
+    /// Count a request until its response body has been sent
+    fn track(&self, response: Response) -> Response {
+        let requests = self.clone();
+        response.map(|body| {
+            Body::new(TrackedBody::new(body, move |_| {
+                requests.0.fetch_sub(1, Ordering::AcqRel);
+            }))
+        })
+    }
+}
+
+/// Deadline after which an idle connection is closed
+struct IdleTimer {
+    timeout: Duration,
+    deadline: Pin<Box<Sleep>>,
+}
+
+impl IdleTimer {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            deadline: Box::pin(tokio::time::sleep(timeout)),
+        }
+    }
+
+    /// Pass through the result of an I/O call, failing it once the connection has been idle too long
+    ///
+    /// Completed calls push the deadline back; pending ones register for a
+    /// wakeup at the deadline so a connection nobody touches is still closed.
+    /// While requests are in flight the deadline keeps moving, so time spent
+    /// waiting on upstreams does not count as idle.
+    fn check<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        poll: Poll<io::Result<T>>,
+        requests: &InFlightRequests,
+    ) -> Poll<io::Result<T>> {
+        if poll.is_ready() || requests.any() {
+            self.deadline.as_mut().reset(Instant::now() + self.timeout);
+        }
+        match poll {
+            Poll::Ready(_) => poll,
+            Poll::Pending => match self.deadline.as_mut().poll(cx) {
+                Poll::Ready(()) if requests.any() => {
+                    // Woken while a request is in flight; wait out another period
+                    self.deadline.as_mut().reset(Instant::now() + self.timeout);
+                    let _ = self.deadline.as_mut().poll(cx);
+                    Poll::Pending
+                }
+                Poll::Ready(()) => {
+                    debug!("Closing connection idle for {:?}", self.timeout);
+                    Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "connection idle timeout",
+                    )))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+}
+
+/// Accepted connection holding its connection slot until it is closed
+pub struct LimitedStream {
+    stream: TcpStream,
+    idle: Option<IdleTimer>,
+    requests: InFlightRequests,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl LimitedStream {
+    /// Apply the idle timeout, if any, to the result of an I/O call
+    fn check_idle<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        poll: Poll<io::Result<T>>,
+    ) -> Poll<io::Result<T>> {
+        match &mut self.idle {
+            Some(idle) => idle.check(cx, poll, &self.requests),
+            None => poll,
+        }
+    }
+}
+
+/// Make-service counting the requests in flight on each connection
+///
+/// Wraps the make-service passed to `axum::serve`, so the idle timeout of
+/// `LimitedListener` connections is paused while they have requests in flight.
+#[derive(Clone)]
+pub struct TrackInFlight<M>(pub M);
+
+impl<'a, L, M, S> tower::Service<IncomingStream<'a, L>> for TrackInFlight<M>
+where
+    L: Listener<Io = LimitedStream>,
+    M: tower::Service<IncomingStream<'a, L>, Response = S, Error = Infallible>,
+    M::Future: Unpin,
+{
+    type Response = InFlightService<S>;
+    type Error = Infallible;
+    type Future = TrackInFlightFuture<M::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, stream: IncomingStream<'a, L>) -> Self::Future {
+        TrackInFlightFuture {
+            requests: stream.io().requests.clone(),
+            future: self.0.call(stream),
+        }
+    }
+}
+
+/// Future resolving to a connection's `InFlightService`
+pub struct TrackInFlightFuture<F> {
+    future: F,
+    requests: InFlightRequests,
+}
+
+impl<F, S> Future for TrackInFlightFuture<F>
+where
+    F: Future<Output = Result<S, Infallible>> + Unpin,
+{
+    type Output = Result<InFlightService<S>, Infallible>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let requests = self.requests.clone();
+        Pin::new(&mut self.future)
+            .poll(cx)
+            .map_ok(|inner| InFlightService { inner, requests })
+    }
+}
+
+/// Connection service counting its requests until their responses have been sent
+#[derive(Clone)]
+pub struct InFlightService<S> {
+    inner: S,
+    requests: InFlightRequests,
+}
+
+impl<S> tower::Service<Request> for InFlightService<S>
+where
+    S: tower::Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.requests.0.fetch_add(1, Ordering::AcqRel);
+        let requests = self.requests.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            Ok(requests.track(response))
+        })
+    }
+}
+
+impl AsyncRead for LimitedStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.stream).poll_read(cx, buf);
+        self.check_idle(cx, poll)
+    }
+}
+
+impl AsyncWrite for LimitedStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.stream).poll_write(cx, buf);
+        self.check_idle(cx, poll)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.stream).poll_write_vectored(cx, bufs);
+        self.check_idle(cx, poll)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.stream).poll_flush(cx);
+        self.check_idle(cx, poll)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Helper to serve a trivial router behind a limited listener
+    async fn serve_limited(
+        max_connections: Option<usize>,
+        max_accept_rate: Option<f64>,
+    ) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = LimitedListener::with_limits(listener, max_connections, max_accept_rate);
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/", get(|| async { "ok" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    // Helper to send a keep-alive request and read the start of the response
+    async fn send_request(stream: &mut TcpStream) -> String {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buffer = [0u8; 256];
+        let n = stream.read(&mut buffer).await.unwrap();
+        String::from_utf8_lossy(&buffer[..n]).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_max_connections_delays_excess_connections() {
+        let addr = serve_limited(Some(1), None).await;
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        assert!(send_request(&mut first).await.starts_with("HTTP/1.1 200"));
+
+        // The second connection is not served while the first holds the only slot
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let delayed =
+            tokio::time::timeout(Duration::from_millis(200), send_request(&mut second)).await;
+        assert!(delayed.is_err());
+
+        // Closing the first connection frees the slot
+        drop(first);
+        let mut buffer = [0u8; 256];
+        let n = tokio::time::timeout(Duration::from_secs(5), second.read(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(String::from_utf8_lossy(&buffer[..n]).starts_with("HTTP/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_max_accept_rate_spreads_connection_bursts() {
+        let addr = serve_limited(None, Some(20.0)).await;
+
+        // 20 connections fit in the initial burst; the next 10 take about 0.5s
+        let started = Instant::now();
+        let mut streams = Vec::new();
+        for _ in 0..30 {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            assert!(send_request(&mut stream).await.starts_with("HTTP/1.1 200"));
+            streams.push(stream);
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_idle_connections_are_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = LimitedListener::with_limits(listener, None, None)
+            .with_idle_timeout(Duration::from_millis(300));
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/", get(|| async { "ok" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Activity keeps the connection open past the idle timeout
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            assert!(send_request(&mut stream).await.starts_with("HTTP/1.1 200"));
+        }
+
+        // Once idle, the server closes it
+        let idle_since = Instant::now();
+        let mut buffer = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer))
+            .await
+            .expect("idle connection was not closed");
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert!(idle_since.elapsed() >= Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn test_requests_in_flight_pause_idle_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = LimitedListener::with_limits(listener, None, None)
+            .with_idle_timeout(Duration::from_millis(300));
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(800)).await;
+                "ok"
+            }),
+        );
+        tokio::spawn(
+            async move { axum::serve(listener, TrackInFlight(app.into_make_service())).await },
+        );
+
+        // A slow response is not cut off by the idle timeout
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        assert!(send_request(&mut stream).await.starts_with("HTTP/1.1 200"));
+
+        // The connection is closed once idle after the response
+        let idle_since = Instant::now();
+        let mut buffer = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer))
+            .await
+            .expect("idle connection was not closed");
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert!(idle_since.elapsed() >= Duration::from_millis(250));
+    }
+
+    // Helper to accept one connection through a listener with the given options
+    async fn accept_with_options(options: SocketOptions) -> (LimitedStream, TcpStream) {
+        let listener = bind_listener("127.0.0.1:0".parse().unwrap(), 16).unwrap();
+        let mut listener =
+            LimitedListener::with_limits(listener, None, None).with_socket_options(options);
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, _) = Listener::accept(&mut listener).await;
+        (stream, client)
+    }
+
+    #[tokio::test]
+    async fn test_socket_options_are_applied_to_accepted_connections() {
+        let (accepted, _client) = accept_with_options(SocketOptions {
+            nodelay: true,
+            keepalive: Some(Duration::from_secs(30)),
+        })
+        .await;
+
+        assert!(accepted.stream.nodelay().unwrap());
+        let socket = socket2::SockRef::from(&accepted.stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            socket.tcp_keepalive_interval().unwrap(),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_socket_options_leave_connections_unchanged() {
+        let (accepted, _client) = accept_with_options(SocketOptions::default()).await;
+
+        assert!(!accepted.stream.nodelay().unwrap());
+        assert!(!socket2::SockRef::from(&accepted.stream)
+            .keepalive()
+            .unwrap());
+    }
+
+    #[test]
+    fn test_socket_options_from_config() {
+        let config = ServerConfig {
+            tcp_nodelay: true,
+            tcp_keepalive_secs: Some(60),
+            ..ServerConfig::default()
+        };
+        assert_eq!(
+            SocketOptions::from_config(&config),
+            SocketOptions {
+                nodelay: true,
+                keepalive: Some(Duration::from_secs(60)),
+            }
+        );
+        assert_eq!(
+            SocketOptions::from_config(&ServerConfig::default()),
+            SocketOptions::default()
+        );
+    }
+}
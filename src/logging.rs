@@ -1,4 +1,8 @@
 use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
 use tracing::info;
 use tracing_appender::{non_blocking, rolling};
 use tracing_subscriber::{fmt::time::UtcTime, EnvFilter};
@@ -24,6 +28,10 @@ pub struct LoggingConfig {
     pub log_file_prefix: String,
     /// Whether to include file and line numbers in logs
     pub include_location: bool,
+    /// Whether to gzip-compress log files once they have been rotated
+    pub compress_rotated_logs: bool,
+    /// Maximum number of log files to keep, including the active one
+    pub log_max_files: Option<usize>,
 }
 
 impl Default for LoggingConfig {
@@ -36,6 +44,11 @@ impl Default for LoggingConfig {
             log_file_prefix: env::var("LOG_FILE_PREFIX")
                 .unwrap_or_else(|_| DEFAULT_LOG_FILE_PREFIX.to_string()),
             include_location: parse_env_bool("LOG_INCLUDE_LOCATION", false),
+            compress_rotated_logs: parse_env_bool("LOG_COMPRESS_ROTATED", false),
+            log_max_files: env::var("LOG_MAX_FILES")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|max_files| *max_files > 0),
         }
     }
 }
@@ -48,11 +61,260 @@ fn parse_env_bool(var_name: &str, default: bool) -> bool {
         .unwrap_or(default)
 }
 
+/// Writer that sends output to a fallback when writes to the primary fail
+///
+/// The log file can become unwritable while the gateway runs, for example when
+/// the disk fills up or the log directory is removed. Records that cannot be
+/// written to the file go to the fallback (stderr) instead of being dropped, and a
+/// warning is written to the fallback the first time this happens. Every record
+/// still tries the file first, so file logging resumes once writes succeed again.
+pub struct FallbackWriter<W, F> {
+    primary: W,
+    fallback: F,
+    warned: bool,
+}
+
+impl<W: Write> FallbackWriter<W, io::Stderr> {
+    /// Fall back to stderr when writes to `primary` fail
+    pub fn stderr(primary: W) -> Self {
+        Self::new(primary, io::stderr())
+    }
+}
+
+impl<W: Write, F: Write> FallbackWriter<W, F> {
+    /// Create a writer that falls back to `fallback` when writes to `primary` fail
+    pub fn new(primary: W, fallback: F) -> Self {
+        Self {
+            primary,
+            fallback,
+            warned: false,
+        }
+    }
+
+    /// Whether a write to the primary writer has failed
+    #[allow(dead_code)] // Public API method
+    pub fn has_fallen_back(&self) -> bool {
+        self.warned
+    }
+
+    fn warn_once(&mut self, error: &io::Error) {
+        if !self.warned {
+            self.warned = true;
+            let _ = writeln!(
+                self.fallback,
+                "Warning: Failed to write to log file ({error}), logging to stderr instead"
+            );
+        }
+    }
+}
+
+impl<W: Write, F: Write> Write for FallbackWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Err(e) = self.primary.write_all(buf) {
+            self.warn_once(&e);
+            self.fallback.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Err(e) = self.primary.flush() {
+            self.warn_once(&e);
+        }
+        self.fallback.flush()
+    }
+}
+
+/// Writer that maintains rotated log files after the daily appender rolls over
+///
+/// The daily appender writes to `<prefix>.<YYYY-MM-DD>` (UTC). After the first
+/// write of a new day, rotated files in the log directory are optionally
+/// compressed to `<name>.gz` and pruned down to `max_files`, on a background
+/// thread. Only files named after the prefix and a date are touched, and the
+/// file for the current day never is. The same maintenance runs when the writer
+/// is created, to catch up on files left by earlier runs.
+pub struct RotationHook<W> {
+    inner: W,
+    log_dir: PathBuf,
+    prefix: String,
+    compress: bool,
+    max_files: Option<usize>,
+    today: Box<dyn Fn() -> String + Send>,
+    current_day: String,
+    maintenance: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl<W: Write> RotationHook<W> {
+    /// Wrap a daily appender using the log directory, prefix and rotation
+    /// settings of the logging configuration
+    pub fn new(inner: W, config: &LoggingConfig) -> Self {
+        Self::with_clock(inner, config, || {
+            chrono::Utc::now().format("%Y-%m-%d").to_string()
+        })
+    }
+
+    fn with_clock(
+        inner: W,
+        config: &LoggingConfig,
+        today: impl Fn() -> String + Send + 'static,
+    ) -> Self {
+        let current_day = today();
+        let mut hook = Self {
+            inner,
+            log_dir: PathBuf::from(&config.log_dir),
+            prefix: config.log_file_prefix.clone(),
+            compress: config.compress_rotated_logs,
+            max_files: config.log_max_files,
+            today: Box::new(today),
+            current_day,
+            maintenance: None,
+        };
+        hook.spawn_maintenance();
+        hook
+    }
+
+    /// Compress and prune rotated files in the background
+    fn spawn_maintenance(&mut self) {
+        // Let a previous run finish first so two threads never touch one file
+        if let Some(previous) = self.maintenance.take() {
+            let _ = previous.join();
+        }
+
+        let log_dir = self.log_dir.clone();
+        let prefix = self.prefix.clone();
+        let active = format!("{}.{}", self.prefix, self.current_day);
+        let (compress, max_files) = (self.compress, self.max_files);
+        self.maintenance = Some(std::thread::spawn(move || {
+            let result = (|| {
+                if compress {
+                    compress_rotated_logs(&log_dir, &prefix, &active)?;
+                }
+                if let Some(max_files) = max_files {
+                    prune_rotated_logs(&log_dir, &prefix, &active, max_files)?;
+                }
+                Ok(())
+            })();
+            if let Err(e) = &result {
+                eprintln!("Warning: Failed to maintain rotated log files: {e}");
+            }
+            result
+        }));
+    }
+}
+
+impl<W: Write> Write for RotationHook<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write first so the appender has rolled over to the new file
+        let result = self.inner.write(buf);
+
+        let today = (self.today)();
+        if today != self.current_day {
+            self.current_day = today;
+            self.spawn_maintenance();
+        }
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Date part of a rotated log file name, `<prefix>.<YYYY-MM-DD>[.gz]`
+fn rotated_log_date<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = name.strip_prefix(prefix)?.strip_prefix('.')?;
+    let date = rest.strip_suffix(".gz").unwrap_or(rest);
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date)
+}
+
+/// Names of the rotated log files in `log_dir`, excluding `active`
+fn rotated_log_files(log_dir: &Path, prefix: &str, active: &str) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(log_dir)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name != active
+            && rotated_log_date(&name, prefix).is_some()
+            && entry.file_type()?.is_file()
+        {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Gzip-compress every rotated log file in `log_dir` except `active`
+///
+/// Each file is replaced by `<name>.gz`. Returns the paths of the compressed files.
+pub fn compress_rotated_logs(
+    log_dir: &Path,
+    prefix: &str,
+    active: &str,
+) -> io::Result<Vec<PathBuf>> {
+    let mut compressed = Vec::new();
+
+    for name in rotated_log_files(log_dir, prefix, active)? {
+        if name.ends_with(".gz") {
+            continue;
+        }
+
+        let path = log_dir.join(&name);
+        let gz_path = log_dir.join(format!("{name}.gz"));
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gz_path)?, flate2::Compression::default());
+        io::copy(&mut File::open(&path)?, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+        fs::remove_file(&path)?;
+        compressed.push(gz_path);
+    }
+
+    Ok(compressed)
+}
+
+/// Delete the oldest rotated log files so at most `max_files` log files remain
+///
+/// The active file counts towards the limit but is never deleted. Returns the
+/// paths of the deleted files.
+pub fn prune_rotated_logs(
+    log_dir: &Path,
+    prefix: &str,
+    active: &str,
+    max_files: usize,
+) -> io::Result<Vec<PathBuf>> {
+    let mut rotated = rotated_log_files(log_dir, prefix, active)?;
+    let keep = max_files.saturating_sub(1);
+    if rotated.len() <= keep {
+        return Ok(Vec::new());
+    }
+
+    // Newest first, by the date in the file name
+    rotated.sort_by(|a, b| rotated_log_date(b, prefix).cmp(&rotated_log_date(a, prefix)));
+    let mut deleted = Vec::new();
+    for name in rotated.split_off(keep) {
+        let path = log_dir.join(name);
+        fs::remove_file(&path)?;
+        deleted.push(path);
+    }
+
+    Ok(deleted)
+}
+
 /// Set up file-based logging subscriber
 fn setup_file_logging(config: &LoggingConfig, env_filter: EnvFilter) -> Result<(), String> {
-    // Create file appender with daily rotation using the prefix
+    // Create file appender with daily rotation using the prefix, optionally
+    // compressing and pruning rotated files, and falling back to stderr if the
+    // file becomes unwritable
     let file_appender = rolling::daily(&config.log_dir, &config.log_file_prefix);
-    let (non_blocking_appender, _guard) = non_blocking(file_appender);
+    let file_writer: Box<dyn Write + Send> =
+        if config.compress_rotated_logs || config.log_max_files.is_some() {
+            Box::new(RotationHook::new(file_appender, config))
+        } else {
+            Box::new(file_appender)
+        };
+    let (non_blocking_appender, _guard) = non_blocking(FallbackWriter::stderr(file_writer));
 
     // We need to keep the guard alive for the lifetime of the program
     // In a real application, you'd want to store this somewhere
@@ -184,7 +446,6 @@ pub fn init_default_logging() -> FerragateResult<()> {
 }
 
 /// Create a structured logging context for request tracing
-#[allow(dead_code)]
 pub fn create_request_id() -> String {
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -205,6 +466,143 @@ mod tests {
     use std::env;
     use tempfile::TempDir;
 
+    /// Writer that records output until it is told to fail like a full disk
+    #[derive(Default)]
+    struct FailingWriter {
+        fail: bool,
+        written: Vec<u8>,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.fail {
+                return Err(io::Error::other("No space left on device"));
+            }
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Helper to build a file logging config for rotation tests
+    fn rotation_config(dir: &TempDir, compress: bool, max_files: Option<usize>) -> LoggingConfig {
+        LoggingConfig {
+            log_dir: dir.path().to_str().unwrap().to_string(),
+            log_file_prefix: "ferragate".to_string(),
+            compress_rotated_logs: compress,
+            log_max_files: max_files,
+            ..LoggingConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_only_newest_log_files_are_retained() {
+        let dir = TempDir::new().unwrap();
+        for name in [
+            "ferragate.2026-10-11.gz",
+            "ferragate.2026-10-12",
+            "ferragate.2026-10-13.gz",
+            "ferragate.2026-10-14",
+            "ferragate.2026-10-15",
+            "ferragate.2026-10-16",
+            "ferragate.notes",
+            "other.2026-10-01",
+        ] {
+            fs::write(dir.path().join(name), "logs\n").unwrap();
+        }
+
+        let config = rotation_config(&dir, false, Some(3));
+        let mut writer = RotationHook::with_clock(Vec::new(), &config, || "2026-10-16".to_string());
+        writer.maintenance.take().unwrap().join().unwrap().unwrap();
+
+        let mut remaining: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            [
+                "ferragate.2026-10-14",
+                "ferragate.2026-10-15",
+                "ferragate.2026-10-16",
+                "ferragate.notes",
+                "other.2026-10-01",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rotated_log_is_compressed_after_rollover() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+        use std::sync::{Arc, Mutex};
+
+        let dir = TempDir::new().unwrap();
+        let day = Arc::new(Mutex::new("2026-10-15".to_string()));
+        let clock = day.clone();
+        let old_file = dir.path().join("ferragate.2026-10-15");
+        fs::write(&old_file, "old logs\n").unwrap();
+
+        let config = rotation_config(&dir, true, None);
+        let mut writer =
+            RotationHook::with_clock(Vec::new(), &config, move || clock.lock().unwrap().clone());
+        writer.maintenance.take().unwrap().join().unwrap().unwrap();
+        assert!(old_file.exists(), "the active file must not be compressed");
+
+        // The appender rolls over to the next day's file
+        *day.lock().unwrap() = "2026-10-16".to_string();
+        let new_file = dir.path().join("ferragate.2026-10-16");
+        fs::write(&new_file, "new logs\n").unwrap();
+        writer.write_all(b"after rollover\n").unwrap();
+
+        writer.maintenance.take().unwrap().join().unwrap().unwrap();
+        let gz_file = dir.path().join("ferragate.2026-10-15.gz");
+        assert!(gz_file.exists());
+        assert!(!old_file.exists());
+        assert_eq!(fs::read_to_string(&new_file).unwrap(), "new logs\n");
+
+        let mut contents = String::new();
+        GzDecoder::new(File::open(&gz_file).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "old logs\n");
+    }
+
+    #[test]
+    fn test_fallback_writer_engages_when_file_writes_fail() {
+        let mut writer = FallbackWriter::new(FailingWriter::default(), Vec::new());
+
+        writer.write_all(b"first\n").unwrap();
+        assert!(!writer.has_fallen_back());
+        assert!(writer.fallback.is_empty());
+
+        writer.primary.fail = true;
+        writer.write_all(b"second\n").unwrap();
+        writer.write_all(b"third\n").unwrap();
+        writer.flush().unwrap();
+        assert!(writer.has_fallen_back());
+
+        let fallback = String::from_utf8(writer.fallback.clone()).unwrap();
+        assert_eq!(
+            fallback
+                .matches("Warning: Failed to write to log file")
+                .count(),
+            1
+        );
+        assert!(fallback.contains("No space left on device"));
+        assert!(fallback.ends_with("second\nthird\n"));
+
+        // File logging resumes once writes succeed again
+        writer.primary.fail = false;
+        writer.write_all(b"fourth\n").unwrap();
+        assert_eq!(writer.primary.written, b"first\nfourth\n");
+        assert!(!String::from_utf8_lossy(&writer.fallback).contains("fourth"));
+    }
+
     #[test]
     #[serial_test::serial]
     fn test_default_config() {
@@ -362,6 +760,8 @@ mod tests {
             log_dir: "test_logs".to_string(),
             log_file_prefix: "test_prefix".to_string(),
             include_location: true,
+            compress_rotated_logs: false,
+            log_max_files: None,
         };
 
         // Since the global subscriber can only be set once,
@@ -384,6 +784,8 @@ mod tests {
             log_dir: log_dir.clone(),
             log_file_prefix: "test".to_string(),
             include_location: false,
+            compress_rotated_logs: false,
+            log_max_files: None,
         };
 
         let result = init_logging(config);
@@ -403,6 +805,8 @@ mod tests {
             log_dir: "logs".to_string(),
             log_file_prefix: "test".to_string(),
             include_location: false,
+            compress_rotated_logs: false,
+            log_max_files: None,
         };
 
         // Should still not panic and either succeed or fail with "already set"
@@ -455,6 +859,8 @@ mod tests {
             log_dir: file_path.to_string_lossy().to_string(),
             log_file_prefix: "test".to_string(),
             include_location: false,
+            compress_rotated_logs: false,
+            log_max_files: None,
         };
 
         let result = init_logging(config);
@@ -477,6 +883,8 @@ mod tests {
                 log_dir: "logs".to_string(),
                 log_file_prefix: "test".to_string(),
                 include_location: false,
+                compress_rotated_logs: false,
+                log_max_files: None,
             };
 
             let result = init_logging(config);
@@ -500,6 +908,8 @@ mod tests {
             log_dir,
             log_file_prefix: "full_test".to_string(),
             include_location: true,
+            compress_rotated_logs: false,
+            log_max_files: None,
         };
 
         let result = init_logging(config);
@@ -520,6 +930,8 @@ mod tests {
             log_dir: log_dir.clone(),
             log_file_prefix: custom_prefix.to_string(),
             include_location: false,
+            compress_rotated_logs: false,
+            log_max_files: None,
         };
 
         let result = init_logging(config);
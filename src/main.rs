@@ -1,12 +1,38 @@
+mod admin;
+mod balancer;
+mod bench;
+mod body_log;
+mod builtin_files;
+mod canary;
 mod cli;
+mod client_ip;
 mod config;
+mod config_builder;
 mod constants;
+mod decompress;
 mod error;
+mod expect_continue;
+mod grpc_web;
 mod health;
+mod listener;
 mod logging;
+mod metrics;
 mod proxy;
+mod rate_limit;
+mod reload;
+mod retry;
+mod route_queue;
+mod route_trie;
 mod server;
+mod shedding;
+mod shutdown;
+mod single_flight;
+mod socket_activation;
 mod tls;
+mod trace_context;
+mod tracked_body;
+mod upstream_health;
+mod upstream_stats;
 
 use cli::Cli;
 use logging::init_default_logging;
@@ -19,16 +45,18 @@ use crate::error::FerragateResult;
 /// Initializes logging, parses CLI arguments, and executes the requested command.
 /// Returns early on any initialization errors.
 pub async fn run_app() -> FerragateResult<()> {
-    // Initialize logging system first
-    if let Err(e) = init_default_logging() {
-        eprintln!("Failed to initialize logging: {e}");
-        return Err(e);
-    }
+    let cli = Cli::parse_args();
 
-    info!("Starting Ferragate API Gateway");
+    // Initialize logging, unless the command's stdout must stay clean
+    if !cli.writes_script_to_stdout() {
+        if let Err(e) = init_default_logging() {
+            eprintln!("Failed to initialize logging: {e}");
+            return Err(e);
+        }
+        info!("Starting Ferragate API Gateway");
+    }
 
-    // Parse CLI arguments and execute the requested command
-    let cli = Cli::parse_args();
+    // Execute the requested command
     cli.execute().await
 }
 
@@ -258,6 +286,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: crate::config::LoggingConfig::default(),
@@ -567,6 +596,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: crate::config::LoggingConfig::default(),
@@ -0,0 +1,442 @@
+/// Prometheus metrics for proxied traffic
+///
+/// Metrics are kept in lock-free atomics where possible and rendered in the
+/// Prometheus text exposition format at the metrics endpoint. Scrapers that
+/// accept OpenMetrics get that format instead, with the latency histogram
+/// linking each bucket to the trace of a recent request as an exemplar.
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, Method, StatusCode},
+    response::IntoResponse,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::constants::{
+    METRICS_BYTE_BUCKETS, METRICS_CONTENT_TYPE, METRICS_LATENCY_BUCKETS, OPENMETRICS_CONTENT_TYPE,
+};
+
+/// Exposition format of the metrics endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// Prometheus text format 0.0.4
+    Prometheus,
+    /// OpenMetrics 1.0 text format, with exemplars
+    OpenMetrics,
+}
+
+impl MetricsFormat {
+    /// OpenMetrics when the scraper's `Accept` header lists it, Prometheus otherwise
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let accepts_openmetrics = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|media_type| {
+                media_type
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .eq_ignore_ascii_case("application/openmetrics-text")
+            });
+        if accepts_openmetrics {
+            Self::OpenMetrics
+        } else {
+            Self::Prometheus
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Prometheus => METRICS_CONTENT_TYPE,
+            Self::OpenMetrics => OPENMETRICS_CONTENT_TYPE,
+        }
+    }
+}
+
+/// Traced observation kept to link a histogram bucket to a trace
+#[derive(Debug, Clone)]
+struct Exemplar {
+    trace_id: String,
+    value: f64,
+    /// Seconds since the Unix epoch
+    timestamp: f64,
+}
+
+/// Cumulative histogram with fixed bucket bounds
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    buckets: Vec<AtomicU64>,
+    /// Latest traced observation per bucket, with a final slot for `+Inf`
+    exemplars: Vec<Mutex<Option<Exemplar>>>,
+    /// Sum of observations, stored as `f64` bits
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// Create a histogram with the given upper bucket bounds (ascending)
+    pub fn new(bounds: Vec<f64>) -> Self {
+        Self {
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            exemplars: (0..=bounds.len()).map(|_| Mutex::new(None)).collect(),
+            bounds,
+            sum: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a single observation
+    pub fn observe(&self, value: f64) {
+        self.observe_with_trace(value, None);
+    }
+
+    /// Record a single observation, keeping its trace id as the bucket's exemplar
+    pub fn observe_with_trace(&self, value: f64, trace_id: Option<&str>) {
+        let index = self.bounds.iter().position(|bound| value <= *bound);
+        if let Some(index) = index {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(trace_id) = trace_id {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            *self.exemplars[index.unwrap_or(self.bounds.len())]
+                .lock()
+                .unwrap() = Some(Exemplar {
+                trace_id: trace_id.to_string(),
+                value,
+                timestamp,
+            });
+        }
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sum of all observed values
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum.load(Ordering::Relaxed))
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str, format: MetricsFormat) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+
+        let mut cumulative = 0;
+        for (index, (bound, bucket)) in self.bounds.iter().zip(&self.buckets).enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = write!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+            self.render_exemplar(out, index, format);
+        }
+        let _ = write!(out, "{name}_bucket{{le=\"+Inf\"}} {}", self.count());
+        self.render_exemplar(out, self.bounds.len(), format);
+        let _ = writeln!(out, "{name}_sum {}", self.sum());
+        let _ = writeln!(out, "{name}_count {}", self.count());
+    }
+
+    /// End a bucket line, with its exemplar in the OpenMetrics format
+    fn render_exemplar(&self, out: &mut String, index: usize, format: MetricsFormat) {
+        if format == MetricsFormat::OpenMetrics {
+            if let Some(exemplar) = self.exemplars[index].lock().unwrap().as_ref() {
+                let _ = write!(
+                    out,
+                    " # {{trace_id=\"{}\"}} {} {:.3}",
+                    exemplar.trace_id, exemplar.value, exemplar.timestamp
+                );
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// Gateway-wide metrics registry
+#[derive(Debug)]
+pub struct Metrics {
+    requests_total: Mutex<BTreeMap<(String, u16), u64>>,
+    /// Request body sizes in bytes
+    pub request_bytes: Histogram,
+    /// Response body sizes in bytes
+    pub response_bytes: Histogram,
+    /// Request durations in seconds
+    pub request_duration: Histogram,
+    /// Requests waiting for a concurrency slot, by route path
+    route_queue_depth: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    /// Create an empty metrics registry with the default latency buckets
+    pub fn new() -> Self {
+        Self::with_latency_buckets(METRICS_LATENCY_BUCKETS.to_vec())
+    }
+
+    /// Create an empty metrics registry with custom latency bucket bounds in seconds
+    pub fn with_latency_buckets(latency_buckets: Vec<f64>) -> Self {
+        let byte_buckets = || METRICS_BYTE_BUCKETS.iter().map(|b| *b as f64).collect();
+        Self {
+            requests_total: Mutex::new(BTreeMap::new()),
+            request_bytes: Histogram::new(byte_buckets()),
+            response_bytes: Histogram::new(byte_buckets()),
+            request_duration: Histogram::new(latency_buckets),
+            route_queue_depth: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Count a request starting to wait in a route's queue
+    pub fn queue_entered(&self, route: &str) {
+        *self
+            .route_queue_depth
+            .lock()
+            .unwrap()
+            .entry(route.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Count a request leaving a route's queue, whether it got a slot or not
+    pub fn queue_left(&self, route: &str) {
+        if let Some(depth) = self.route_queue_depth.lock().unwrap().get_mut(route) {
+            *depth = depth.saturating_sub(1);
+        }
+    }
+
+    /// Record a completed proxied request
+    ///
+    /// The request's trace id, when known, becomes an exemplar of its latency bucket.
+    pub fn record_request(
+        &self,
+        method: &Method,
+        status: StatusCode,
+        request_bytes: u64,
+        response_bytes: u64,
+        duration: Duration,
+        trace_id: Option<&str>,
+    ) {
+        *self
+            .requests_total
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), status.as_u16()))
+            .or_insert(0) += 1;
+        self.request_bytes.observe(request_bytes as f64);
+        self.response_bytes.observe(response_bytes as f64);
+        self.request_duration
+            .observe_with_trace(duration.as_secs_f64(), trace_id);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    #[allow(dead_code)] // Public API method
+    pub fn render(&self) -> String {
+        self.render_as(MetricsFormat::Prometheus)
+    }
+
+    /// Render all metrics in the given exposition format
+    pub fn render_as(&self, format: MetricsFormat) -> String {
+        let mut out = String::new();
+
+        // OpenMetrics names the counter family without its `_total` suffix
+        let requests_family = match format {
+            MetricsFormat::Prometheus => "ferragate_requests_total",
+            MetricsFormat::OpenMetrics => "ferragate_requests",
+        };
+        let _ = writeln!(
+            out,
+            "# HELP {requests_family} Total number of proxied requests"
+        );
+        let _ = writeln!(out, "# TYPE {requests_family} counter");
+        for ((method, status), count) in self.requests_total.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "ferragate_requests_total{{method=\"{method}\",status=\"{status}\"}} {count}"
+            );
+        }
+
+        self.request_bytes.render(
+            &mut out,
+            "ferragate_request_bytes",
+            "Request body size in bytes",
+            format,
+        );
+        self.response_bytes.render(
+            &mut out,
+            "ferragate_response_bytes",
+            "Response body size in bytes",
+            format,
+        );
+        self.request_duration.render(
+            &mut out,
+            "ferragate_request_duration_seconds",
+            "Proxied request duration in seconds",
+            format,
+        );
+
+        let queue_depth = self.route_queue_depth.lock().unwrap();
+        if !queue_depth.is_empty() {
+            let _ = writeln!(
+                out,
+                "# HELP ferragate_route_queue_depth Requests waiting for a route concurrency slot"
+            );
+            let _ = writeln!(out, "# TYPE ferragate_route_queue_depth gauge");
+            for (route, depth) in queue_depth.iter() {
+                let _ = writeln!(
+                    out,
+                    "ferragate_route_queue_depth{{route=\"{route}\"}} {depth}"
+                );
+            }
+        }
+
+        if format == MetricsFormat::OpenMetrics {
+            out.push_str("# EOF\n");
+        }
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Metrics endpoint handler
+///
+/// Returns all gateway metrics in the Prometheus text format, or in the
+/// OpenMetrics format when the scraper asks for it.
+pub async fn metrics_handler(
+    State(metrics): State<Arc<Metrics>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let format = MetricsFormat::from_headers(&headers);
+    (
+        [(header::CONTENT_TYPE, format.content_type())],
+        metrics.render_as(format),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new(vec![10.0, 100.0]);
+        histogram.observe(5.0);
+        histogram.observe(50.0);
+        histogram.observe(500.0);
+
+        let mut out = String::new();
+        histogram.render(&mut out, "test_bytes", "Test", MetricsFormat::Prometheus);
+        assert!(out.contains("test_bytes_bucket{le=\"10\"} 1"));
+        assert!(out.contains("test_bytes_bucket{le=\"100\"} 2"));
+        assert!(out.contains("test_bytes_bucket{le=\"+Inf\"} 3"));
+        assert!(out.contains("test_bytes_sum 555"));
+        assert!(out.contains("test_bytes_count 3"));
+    }
+
+    #[test]
+    fn test_render_includes_request_counts() {
+        let metrics = Metrics::new();
+        metrics.record_request(&Method::GET, StatusCode::OK, 0, 10, Duration::ZERO, None);
+        metrics.record_request(&Method::GET, StatusCode::OK, 0, 10, Duration::ZERO, None);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("ferragate_requests_total{method=\"GET\",status=\"200\"} 2"));
+        assert!(rendered.contains("ferragate_response_bytes_sum 20"));
+    }
+
+    #[test]
+    fn test_custom_latency_buckets_in_scrape_output() {
+        let metrics = Metrics::with_latency_buckets(vec![0.001, 0.05, 2.0]);
+        metrics.record_request(
+            &Method::GET,
+            StatusCode::OK,
+            0,
+            0,
+            Duration::from_millis(20),
+            None,
+        );
+        metrics.record_request(
+            &Method::GET,
+            StatusCode::OK,
+            0,
+            0,
+            Duration::from_secs(3),
+            None,
+        );
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("ferragate_request_duration_seconds_bucket{le=\"0.001\"} 0"));
+        assert!(rendered.contains("ferragate_request_duration_seconds_bucket{le=\"0.05\"} 1"));
+        assert!(rendered.contains("ferragate_request_duration_seconds_bucket{le=\"2\"} 1"));
+        assert!(rendered.contains("ferragate_request_duration_seconds_bucket{le=\"+Inf\"} 2"));
+        assert!(rendered.contains("ferragate_request_duration_seconds_count 2"));
+        assert!(!rendered.contains("le=\"0.005\""));
+    }
+
+    #[tokio::test]
+    async fn test_openmetrics_negotiated_with_trace_exemplars() {
+        use axum::http::HeaderValue;
+
+        let metrics = Arc::new(Metrics::with_latency_buckets(vec![0.1, 1.0]));
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        metrics.record_request(
+            &Method::GET,
+            StatusCode::OK,
+            0,
+            0,
+            Duration::from_millis(50),
+            Some(trace_id),
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static(
+                "application/openmetrics-text; version=1.0.0, text/plain;q=0.5",
+            ),
+        );
+        let response = metrics_handler(State(metrics.clone()), headers)
+            .await
+            .into_response();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            OPENMETRICS_CONTENT_TYPE
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(&format!(
+            "ferragate_request_duration_seconds_bucket{{le=\"0.1\"}} 1 # {{trace_id=\"{trace_id}\"}} 0.05 "
+        )));
+        assert!(body.contains("# TYPE ferragate_requests counter"));
+        assert!(body.ends_with("# EOF\n"));
+
+        // Plain scrapes keep the Prometheus format without exemplars
+        let response = metrics_handler(State(metrics), HeaderMap::new())
+            .await
+            .into_response();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            METRICS_CONTENT_TYPE
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("trace_id"));
+    }
+}
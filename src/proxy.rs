@@ -1,20 +1,64 @@
 use axum::{
     body::Body,
-    extract::State,
-    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    extract::{ConnectInfo, State},
+    http::{
+        header, uri::Scheme, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, Version,
+    },
     response::IntoResponse,
+    routing::any,
+    Extension, Router,
 };
 use bytes::Bytes;
 use http_body_util::BodyExt;
-use std::sync::Arc;
-use std::time::Duration;
-use tracing::{debug, error, instrument, warn};
+use hyper::body::Body as _;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, warn, Span};
 
-use crate::config::{GatewayConfig, RouteConfig};
+use crate::balancer;
+use crate::body_log::{self, BodyDirection};
+use crate::canary::{self, CanaryKey};
+use crate::client_ip::ClientIpResolver;
+use crate::config::{
+    ForwardHeaders, GatewayConfig, ResponseMode, RouteConfig, ServerConfig, SpanFieldsConfig,
+    UpstreamAcceptEncoding, UpstreamProtocol, UpstreamUserAgent,
+};
 use crate::constants::{
-    CLIENT_POOL_IDLE_TIMEOUT_SECS, CLIENT_POOL_MAX_IDLE_PER_HOST, CLIENT_USER_AGENT,
-    DEFAULT_TIMEOUT_MS, FILTERED_HEADERS, MSG_INVALID_REQUEST_BODY, MSG_ROUTE_NOT_FOUND,
+    CLIENT_CLOSED_REQUEST_STATUS, CLIENT_POOL_IDLE_TIMEOUT_SECS, CLIENT_POOL_MAX_IDLE_PER_HOST,
+    CLIENT_USER_AGENT, DEFAULT_MAX_DECOMPRESSED_BYTES, DEFAULT_MAX_REPLACEMENT_BODY_BYTES,
+    DEFAULT_TIMEOUT_MS, FILTERED_HEADERS, MAX_CLIENT_TIMEOUT_MS, MAX_IN_FLIGHT_MIRROR_REQUESTS,
+    MSG_DECOMPRESSED_BODY_TOO_LARGE, MSG_GATEWAY_OVERLOADED, MSG_INVALID_REQUEST_BODY,
+    MSG_INVALID_REQUEST_ENCODING, MSG_NO_HEALTHY_UPSTREAM, MSG_RATE_LIMITED,
+    MSG_REQUEST_BODY_TOO_LARGE, MSG_REQUEST_DENIED, MSG_REQUEST_HEADERS_TOO_LARGE,
+    MSG_ROUTE_AT_CAPACITY, MSG_ROUTE_NOT_FOUND, MSG_UNSUPPORTED_CONTENT_TYPE,
+    MSG_UPSTREAM_CONNECT_FAILED, MSG_UPSTREAM_CONTENT_TYPE_NOT_ALLOWED,
+    MSG_UPSTREAM_INVALID_RESPONSE, MSG_UPSTREAM_REQUEST_FAILED, MSG_UPSTREAM_RESPONSE_TOO_LARGE,
+    MSG_UPSTREAM_TIMEOUT, MSG_UPSTREAM_TLS_FAILED, REQUEST_BODY_STREAM_CHANNEL_SIZE,
+    REQUEST_ID_HEADER, RETRY_BUDGET_MIN_RETRIES,
 };
+use crate::decompress::{self, ContentCoding, DecompressError};
+use crate::error::FerragateError;
+use crate::expect_continue::{self, build_continue_client, ContinueClient};
+use crate::grpc_web::{build_grpc_client, GrpcClient, GrpcWebRequest};
+use crate::logging::create_request_id;
+use crate::metrics::Metrics;
+use crate::rate_limit::RouteRateLimiter;
+use crate::reload::SharedProxyState;
+use crate::retry::RetryBudget;
+use crate::route_queue::RouteQueue;
+use crate::route_trie::RouteTrie;
+use crate::shedding::LoadShedder;
+use crate::single_flight::{FlightKey, SharedResponse, SingleFlight};
+use crate::tls::TlsInfo;
+use crate::trace_context::TraceContext;
+use crate::tracked_body::TrackedBody;
+use crate::upstream_stats::UpstreamStats;
 
 /// State shared across all proxy handlers
 ///
@@ -25,6 +69,37 @@ pub struct ProxyState {
     pub config: Arc<GatewayConfig>,
     /// HTTP client for upstream requests
     pub client: reqwest::Client,
+    /// Global retry budget shared by all routes
+    pub retry_budget: Arc<RetryBudget>,
+    /// Load shedder guarding all proxied requests
+    pub load_shedder: Arc<LoadShedder>,
+    /// Request counters and size histograms exposed at the metrics endpoint
+    pub metrics: Arc<Metrics>,
+    /// Rolling latency and error statistics per upstream, exposed at the admin endpoint
+    pub upstream_stats: Arc<UpstreamStats>,
+    /// Resolver for the real client IP behind trusted proxies
+    pub client_ip: Arc<ClientIpResolver>,
+    /// Rate limiters, indexed like the configured routes
+    pub rate_limiters: Arc<Vec<Option<RouteRateLimiter>>>,
+    /// Concurrency limits and queues, indexed like the configured routes
+    pub route_queues: Arc<Vec<Option<RouteQueue>>>,
+    /// HTTP clients for routes that follow redirects, force an upstream protocol or
+    /// bind a local address, indexed like the configured routes
+    pub route_clients: Arc<Vec<Option<reqwest::Client>>>,
+    /// HTTP/2 client for the gRPC upstreams of `grpc_web` routes
+    pub grpc_client: GrpcClient,
+    /// HTTP/1.1 client relaying `100 Continue` to clients that expect it
+    pub continue_client: ContinueClient,
+    /// Path index over the enabled routes, used to find matching candidates
+    pub route_trie: Arc<RouteTrie>,
+    /// Catch-all route for requests matching no configured route, from `default_upstream`
+    pub default_route: Option<Arc<RouteConfig>>,
+    /// Upstream calls shared by identical concurrent GETs on coalescing routes
+    pub single_flight: Arc<SingleFlight<FlightKey, SharedResponse>>,
+    /// Cancelled once the gateway starts draining for shutdown
+    pub draining: CancellationToken,
+    /// Slots for mirror copies in flight, shared by all routes
+    pub mirror_slots: Arc<Semaphore>,
 }
 
 impl ProxyState {
@@ -34,32 +109,238 @@ impl ProxyState {
     pub fn new(config: GatewayConfig) -> Self {
         let timeout = Duration::from_millis(config.server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
 
-        let client = reqwest::Client::builder()
-            .timeout(timeout)
-            .pool_idle_timeout(Duration::from_secs(CLIENT_POOL_IDLE_TIMEOUT_SECS))
-            .pool_max_idle_per_host(CLIENT_POOL_MAX_IDLE_PER_HOST)
-            .user_agent(CLIENT_USER_AGENT)
-            .build()
-            .expect("Failed to create HTTP client");
+        // Redirects are forwarded to the client unless a route opts into following them
+        let client = build_client(timeout, &ClientIdentity::default());
+        let mut clients = HashMap::new();
+        let route_clients = config
+            .routes
+            .iter()
+            .map(|route| {
+                let identity = ClientIdentity::for_route(route);
+                if identity == ClientIdentity::default() {
+                    return None;
+                }
+                let client = clients
+                    .entry(identity)
+                    .or_insert_with(|| build_client(timeout, &identity));
+                Some(client.clone())
+            })
+            .collect();
+
+        let retry_budget = RetryBudget::new(
+            config.server.retry_budget_ratio,
+            Duration::from_secs(config.server.retry_budget_window_secs),
+            RETRY_BUDGET_MIN_RETRIES,
+        );
+
+        let load_shedder = LoadShedder::new(config.server.load_shedding.as_ref());
+        let client_ip = ClientIpResolver::new(&config.server);
+        let rate_limiters = config
+            .routes
+            .iter()
+            .map(RouteRateLimiter::for_route)
+            .collect();
+        let route_queues = config.routes.iter().map(RouteQueue::for_route).collect();
+        let metrics = Metrics::with_latency_buckets(config.server.metrics_latency_buckets.clone());
+        let default_route = config.server.default_upstream.as_ref().map(|upstream| {
+            Arc::new(RouteConfig {
+                path: "/*".to_string(),
+                upstream: upstream.clone(),
+                ..Default::default()
+            })
+        });
 
         Self {
+            route_trie: Arc::new(RouteTrie::new(&config.routes)),
             config: Arc::new(config),
             client,
+            retry_budget: Arc::new(retry_budget),
+            load_shedder: Arc::new(load_shedder),
+            metrics: Arc::new(metrics),
+            upstream_stats: Arc::new(UpstreamStats::new()),
+            client_ip: Arc::new(client_ip),
+            rate_limiters: Arc::new(rate_limiters),
+            route_queues: Arc::new(route_queues),
+            grpc_client: build_grpc_client(),
+            continue_client: build_continue_client(),
+            route_clients: Arc::new(route_clients),
+            default_route,
+            single_flight: Arc::new(SingleFlight::new()),
+            draining: CancellationToken::new(),
+            mirror_slots: Arc::new(Semaphore::new(MAX_IN_FLIGHT_MIRROR_REQUESTS)),
         }
     }
 
-    /// Find the first route that matches the given path and method
+    /// Build the state for a reloaded configuration
+    ///
+    /// Listener settings cannot change without a restart, so the bind address and
+    /// TLS settings of the running configuration are kept. Metrics carry over so
+    /// counters are not reset by a reload, and so does the draining state. The
+    /// latency histogram keeps its buckets with them, so new
+    /// `metrics_latency_buckets` are kept back with a warning until a restart.
     ///
-    /// Routes are evaluated in the order they appear in the configuration.
-    /// Returns None if no matching route is found.
-    pub fn find_matching_route(&self, path: &str, method: &str) -> Option<&RouteConfig> {
-        self.config
+    /// Per-upstream state (rolling stats, ejections and health check results) is
+    /// keyed by URL and kept for every upstream the new configuration still uses,
+    /// and shared with requests still in flight under the old one. State for
+    /// removed upstreams is dropped.
+    pub fn reconfigure(&self, mut config: GatewayConfig) -> Self {
+        config.server.host = self.config.server.host.clone();
+        config.server.port = self.config.server.port;
+        config.server.tls = self.config.server.tls.clone();
+        if config.server.metrics_latency_buckets != self.config.server.metrics_latency_buckets {
+            warn!("metrics_latency_buckets changed; the new buckets apply after a restart");
+            config.server.metrics_latency_buckets =
+                self.config.server.metrics_latency_buckets.clone();
+        }
+
+        let state = Self {
+            metrics: self.metrics.clone(),
+            upstream_stats: self.upstream_stats.clone(),
+            draining: self.draining.clone(),
+            mirror_slots: self.mirror_slots.clone(),
+            ..Self::new(config)
+        };
+        let upstreams: HashSet<&str> = state
+            .config
             .routes
             .iter()
-            .find(|route| route.matches_path(path) && route.matches_method(method))
+            .flat_map(RouteConfig::all_upstreams)
+            .chain(&state.config.server.default_upstream)
+            .map(String::as_str)
+            .collect();
+        state
+            .upstream_stats
+            .retain_upstreams(|upstream| upstreams.contains(upstream));
+        state
+    }
+
+    /// Find the first route that matches the given path, method, headers
+    /// (including Content-Type), query and TLS connection
+    ///
+    /// The most specific path wins: exact paths before prefixes, and longer
+    /// prefixes before shorter ones, with ties going to the route configured
+    /// first. Disabled routes are skipped. Only the routes whose path matches,
+    /// as found by the route trie, are checked against the other rules. Returns
+    /// None if no matching route is found.
+    #[allow(dead_code)] // Public API method
+    pub fn find_matching_route(
+        &self,
+        path: &str,
+        method: &str,
+        headers: &HeaderMap,
+        query: &str,
+        tls: Option<&TlsInfo>,
+    ) -> Option<&RouteConfig> {
+        self.find_matching_route_index(path, method, headers, query, tls)
+            .map(|(_, route)| route)
+    }
+
+    /// Find the first matching route, as `find_matching_route` does, along with
+    /// its position in the configuration
+    pub fn find_matching_route_index(
+        &self,
+        path: &str,
+        method: &str,
+        headers: &HeaderMap,
+        query: &str,
+        tls: Option<&TlsInfo>,
+    ) -> Option<(usize, &RouteConfig)> {
+        self.route_trie
+            .candidates(path)
+            .into_iter()
+            .map(|index| (index, &self.config.routes[index]))
+            .find(|(_, route)| {
+                route.matches_method(method)
+                    && route.matches_headers(headers)
+                    && route.matches_query(query)
+                    && route.matches_tls(tls)
+                    && route.matches_content_type(headers)
+            })
+    }
+
+    /// Routes in the order `find_matching_route` evaluates them, with their configuration index
+    ///
+    /// Disabled routes are never evaluated and are left out.
+    pub fn evaluation_order(&self) -> impl Iterator<Item = (usize, &RouteConfig)> {
+        self.route_trie
+            .evaluation_order()
+            .iter()
+            .map(|&index| (index, &self.config.routes[index]))
+    }
+
+    /// Get the rate limiter for the route at `route_index` in this state's configuration
+    ///
+    /// The index is `None` for the `default_upstream` route, which has no rate limit.
+    pub fn rate_limiter_for(&self, route_index: Option<usize>) -> Option<&RouteRateLimiter> {
+        self.rate_limiters.get(route_index?)?.as_ref()
+    }
+
+    /// Get the concurrency queue for the route at `route_index` in this state's configuration
+    pub fn route_queue_for(&self, route_index: Option<usize>) -> Option<&RouteQueue> {
+        self.route_queues.get(route_index?)?.as_ref()
+    }
+
+    /// Get the HTTP client to use for a route's upstream requests
+    ///
+    /// Routes that follow redirects or force an upstream protocol get their own
+    /// client; all other routes share the default client, which never follows
+    /// redirects and negotiates the protocol.
+    pub fn client_for(&self, route_index: Option<usize>) -> &reqwest::Client {
+        route_index
+            .and_then(|index| self.route_clients.get(index)?.as_ref())
+            .unwrap_or(&self.client)
+    }
+}
+
+/// Route settings that are baked into an HTTP client
+///
+/// Routes with the same identity share one client, and with it one connection
+/// pool. The default identity is served by the shared default client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ClientIdentity {
+    /// Redirects to follow; `None` forwards redirects to the client
+    pub max_redirects: Option<u32>,
+    /// Forced HTTP version; `None` negotiates it
+    pub protocol: Option<UpstreamProtocol>,
+    /// Source address for upstream connections; `None` lets the OS choose
+    pub local_address: Option<IpAddr>,
+}
+
+impl ClientIdentity {
+    /// Client settings needed by a route
+    pub fn for_route(route: &RouteConfig) -> Self {
+        Self {
+            max_redirects: route.effective_max_redirects(),
+            protocol: route.upstream_protocol,
+            local_address: route.local_address,
+        }
     }
 }
 
+/// Build an HTTP client for upstream requests with the given identity
+///
+/// A forced protocol applies to every connection the client opens; without
+/// one the protocol is negotiated.
+fn build_client(timeout: Duration, identity: &ClientIdentity) -> reqwest::Client {
+    let redirect = match identity.max_redirects {
+        Some(max) => reqwest::redirect::Policy::limited(max as usize),
+        None => reqwest::redirect::Policy::none(),
+    };
+    let builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .pool_idle_timeout(Duration::from_secs(CLIENT_POOL_IDLE_TIMEOUT_SECS))
+        .pool_max_idle_per_host(CLIENT_POOL_MAX_IDLE_PER_HOST)
+        .user_agent(CLIENT_USER_AGENT)
+        .redirect(redirect)
+        .local_address(identity.local_address);
+    let builder = match identity.protocol {
+        Some(UpstreamProtocol::Http1) => builder.http1_only(),
+        Some(UpstreamProtocol::Http2) => builder.http2_prior_knowledge(),
+        None => builder,
+    };
+    builder.build().expect("Failed to create HTTP client")
+}
+
 /// Main proxy handler for incoming requests
 ///
 /// This function:
@@ -67,21 +348,218 @@ impl ProxyState {
 /// 2. Transforms the request for upstream forwarding
 /// 3. Executes the upstream request
 /// 4. Returns the upstream response to the client
-#[instrument(skip(state, body), fields(method = %method, uri = %uri))]
+#[instrument(
+    skip(state, connect_info, listener_scheme, tls_info, body),
+    fields(
+        method = %method,
+        uri = tracing::field::Empty,
+        headers = tracing::field::Empty,
+        trace_id = tracing::field::Empty,
+        effective_timeout_ms = tracing::field::Empty,
+        upstream_duration_ms = tracing::field::Empty
+    )
+)]
+#[allow(clippy::too_many_arguments)] // Each argument is an axum extractor
 pub async fn proxy_handler(
     State(state): State<ProxyState>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    listener_scheme: Option<Extension<Scheme>>,
+    tls_info: Option<Extension<TlsInfo>>,
     method: Method,
     uri: Uri,
-    headers: HeaderMap,
+    mut headers: HeaderMap,
     body: Body,
 ) -> impl IntoResponse {
+    let started = Instant::now();
+    // Check the headers as the client sent them, before the gateway adds any
+    let headers_too_large = exceeds_header_limits(&state.config.server, &headers);
+    record_span_fields(&state.config.logging.span_fields, &uri, &headers);
+    // Start a trace for clients that sent none, so every upstream attempt and
+    // the latency exemplar share it
+    let trace = TraceContext::from_headers(&headers).unwrap_or_else(|| {
+        let root = TraceContext::new_root();
+        root.inject(&mut headers);
+        root
+    });
+    let connection = ClientConnection {
+        addr: connect_info.map(|Extension(ConnectInfo(addr))| addr),
+        // The HTTPS listener tags its requests; HTTP/2 requests also carry a scheme
+        scheme: listener_scheme
+            .map(|Extension(scheme)| scheme)
+            .or_else(|| uri.scheme().cloned())
+            .unwrap_or(Scheme::HTTP),
+        tls: tls_info.map(|Extension(tls)| tls),
+    };
+
+    // Count the request body as it is read, whether it is buffered or streamed
+    let request_bytes = Arc::new(AtomicU64::new(0));
+    let body = Body::new(TrackedBody::counting(body, Arc::clone(&request_bytes)));
+    let response = if headers_too_large {
+        (
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            MSG_REQUEST_HEADERS_TOO_LARGE,
+        )
+            .into_response()
+    } else {
+        forward_request(&state, &connection, &method, &uri, &headers, body).await
+    };
+
+    // Streamed bodies are only sized once sent, so the request is recorded when
+    // the response body ends, or is abandoned by the client
+    let status = response.status();
+    let metrics = Arc::clone(&state.metrics);
+    let trace_id = trace.trace_id();
+    let span = Span::current();
+    response.map(|body| {
+        Body::new(TrackedBody::new(body, move |response_bytes| {
+            let _entered = span.enter();
+            let request_bytes = request_bytes.load(Ordering::Relaxed);
+            metrics.record_request(
+                &method,
+                status,
+                request_bytes,
+                response_bytes,
+                started.elapsed(),
+                Some(&trace_id),
+            );
+            info!(
+                target: "ferragate::access",
+                method = %method,
+                path = %uri.path(),
+                status = status.as_u16(),
+                request_bytes,
+                response_bytes,
+                duration_ms = started.elapsed().as_millis() as u64,
+                "request completed"
+            );
+        }))
+    })
+}
+
+/// Response for requests arriving while the gateway drains for shutdown
+fn shutdown_response(server: &crate::config::ServerConfig) -> axum::response::Response {
+    let status =
+        StatusCode::from_u16(server.shutdown_status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+    (
+        status,
+        [(axum::http::header::CONNECTION, "close")],
+        server.shutdown_body.clone(),
+    )
+        .into_response()
+}
+
+/// Record the configured request fields on the current span
+fn record_span_fields(fields: &SpanFieldsConfig, uri: &Uri, headers: &HeaderMap) {
+    let span = Span::current();
+    span.record("uri", tracing::field::display(fields.uri.render(uri)));
+    if let Some(headers) = fields.render_headers(headers) {
+        span.record("headers", tracing::field::display(headers));
+    }
+}
+
+/// Connection a request arrived on
+struct ClientConnection {
+    /// Socket address of the peer, when known
+    addr: Option<SocketAddr>,
+    /// Scheme the client used to reach the gateway
+    scheme: Scheme,
+    /// TLS details of the connection, on the HTTPS listener
+    tls: Option<TlsInfo>,
+}
+
+/// Whether a request's headers exceed the server's limits on their number or total size
+///
+/// The size counts the bytes of every header name and value.
+fn exceeds_header_limits(server: &ServerConfig, headers: &HeaderMap) -> bool {
+    let count = headers.len();
+    let bytes: usize = headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len())
+        .sum();
+    if count > server.max_request_headers || bytes > server.max_request_header_bytes {
+        warn!(
+            "Rejecting request with {} headers totalling {} bytes",
+            count, bytes
+        );
+        return true;
+    }
+    false
+}
+
+/// Record how long the upstream call took on the current span
+///
+/// Retried requests record the time across all attempts, including backoff.
+fn record_upstream_duration(duration: Duration) {
+    Span::current().record("upstream_duration_ms", duration.as_millis() as u64);
+}
+
+/// Forward a request to the matching upstream and build the client response
+async fn forward_request(
+    state: &ProxyState,
+    connection: &ClientConnection,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: Body,
+) -> axum::response::Response {
     let path = uri.path();
     let query = uri.query().unwrap_or("");
 
     debug!("Processing request: {} {}", method, uri);
 
+    // Turn away requests arriving once shutdown has begun, so clients retry
+    // elsewhere; requests already in flight are unaffected
+    if state.draining.is_cancelled() {
+        debug!("Rejecting {} {} while draining", method, path);
+        return shutdown_response(&state.config.server);
+    }
+
+    // Reject requests matching a deny rule before doing any routing work
+    if let Some(rule) = state
+        .config
+        .server
+        .deny_rules
+        .iter()
+        .position(|rule| rule.matches(method.as_str(), path))
+    {
+        warn!(
+            "Request denied by deny_rules[{}]: {} {}",
+            rule, method, path
+        );
+        return (StatusCode::FORBIDDEN, MSG_REQUEST_DENIED).into_response();
+    }
+
+    // Shed load before doing any work when the gateway is overloaded, unless the
+    // request's priority is exempt
+    let bypass_shedding = state
+        .config
+        .server
+        .priority
+        .as_ref()
+        .is_some_and(|priority| {
+            priority
+                .priority_of(headers)
+                .is_some_and(|value| priority.bypasses_shedding(value))
+        });
+    let in_flight = if bypass_shedding {
+        Some(state.load_shedder.acquire())
+    } else {
+        state.load_shedder.try_acquire()
+    };
+    let Some(in_flight) = in_flight else {
+        warn!("Shedding request due to overload: {} {}", method, path);
+        return (StatusCode::SERVICE_UNAVAILABLE, MSG_GATEWAY_OVERLOADED).into_response();
+    };
+
     // Find matching route
-    let route = match find_route_for_request(&state, path, method.as_str()) {
+    let (route_index, route) = match find_route_for_request(
+        state,
+        path,
+        method.as_str(),
+        headers,
+        query,
+        connection.tls.as_ref(),
+    ) {
         Some(route) => route,
         None => {
             warn!("No matching route found for: {} {}", method, path);
@@ -91,106 +569,921 @@ pub async fn proxy_handler(
 
     debug!("Matched route: {} -> {}", route.path, route.upstream);
 
-    // Build target URL
-    let target_url = build_target_url(route, path, query);
-    debug!("Proxying to: {}", target_url);
+    // Enforce the route's rate limit for this method's tier
+    if let Some(limiter) = state.rate_limiter_for(route_index) {
+        if !limiter.check(method.as_str()) {
+            warn!("Rate limit exceeded for: {} {}", method, path);
+            return FerragateError::rate_limited(MSG_RATE_LIMITED, Some(1)).into_response();
+        }
+    }
+
+    // Only forward content types the route accepts
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let has_body = body.size_hint().exact() != Some(0);
+    if !route.allows_request_content_type(content_type, has_body) {
+        warn!(
+            "Unsupported content type {:?} for: {} {}",
+            content_type, method, path
+        );
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            MSG_UNSUPPORTED_CONTENT_TYPE,
+        )
+            .into_response();
+    }
 
-    // Read request body
-    let body_bytes = match read_request_body(body).await {
-        Ok(bytes) => bytes,
-        Err(err_resp) => return err_resp,
+    // Wait for one of the route's concurrency slots, queueing briefly if all are taken
+    let _slot = match state.route_queue_for(route_index) {
+        Some(queue) => match queue.acquire(&state.metrics, &route.path).await {
+            Ok(permit) => Some(permit),
+            Err(rejection) => {
+                warn!(
+                    "No concurrency slot for {} {} ({:?})",
+                    method, path, rejection
+                );
+                return (StatusCode::SERVICE_UNAVAILABLE, MSG_ROUTE_AT_CAPACITY).into_response();
+            }
+        },
+        None => None,
     };
 
-    // Create and configure upstream request
-    let request_builder =
-        match create_upstream_request(&state, route, &method, &target_url, &headers, body_bytes)
-            .await
-        {
-            Ok(builder) => builder,
+    // The upstream exchange, run directly or shared with identical requests below
+    let proxied = async {
+        // Resolve the real client
+        let client_ip = state.client_ip.resolve(headers, connection.addr);
+
+        let request = RequestMetadata {
+            method,
+            path,
+            query,
+            client_ip,
+            request_id: request_id(headers),
+            route_index,
+        };
+
+        // Build target URL, sending a sticky percentage of clients to the canary
+        let Some(upstream) = select_upstream(state, route, headers, client_ip) else {
+            warn!(
+                "No healthy upstream for route {}: {} {}",
+                route.path, method, path
+            );
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("{MSG_NO_HEALTHY_UPSTREAM} for route {}", route.path),
+            )
+                .into_response();
+        };
+        let target_url = build_target_url(upstream, route, path, query);
+        debug!("Proxying to: {}", target_url);
+
+        let grpc_web = route
+            .grpc_web
+            .then(|| GrpcWebRequest::from_headers(headers))
+            .flatten();
+        let mut headers = upstream_headers(state, route, connection, uri, headers);
+
+        // Read request body, buffering it for retries only while it is small enough
+        let body = match limit_request_body(body, state.config.server.max_request_body_bytes) {
+            Ok(body) => body,
+            Err(error) => return error.into_response(),
+        };
+
+        // gRPC-Web calls go to the upstream as gRPC in a single attempt, without mirroring
+        if let Some(grpc_web) = grpc_web {
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(e) => return BodyReadError::from_error(&e).into_response(),
+            };
+            let headers = grpc_upstream_headers(route, &request, &headers);
+            let headers_timeout = route
+                .headers_timeout_ms
+                .or(route.timeout_ms)
+                .or(state.config.server.timeout_ms)
+                .unwrap_or(DEFAULT_TIMEOUT_MS);
+            Span::current().record("effective_timeout_ms", headers_timeout);
+            let upstream_started = Instant::now();
+            let response = grpc_web
+                .forward(
+                    &state.grpc_client,
+                    &target_url,
+                    headers,
+                    body,
+                    Duration::from_millis(headers_timeout),
+                )
+                .await;
+            let upstream_duration = upstream_started.elapsed();
+            record_upstream_duration(upstream_duration);
+            state.upstream_stats.record(
+                upstream,
+                upstream_duration,
+                response.status().is_server_error(),
+            );
+            return response;
+        }
+
+        let coding = route
+            .decompress_request
+            .then(|| ContentCoding::from_headers(&headers))
+            .flatten();
+        let relay_continue = coding.is_none()
+            && expect_continue::expects_continue(&headers)
+            && expect_continue::can_relay(route, &target_url);
+        let body = match coding {
+            Some(coding) => match read_decompressed_body(route, body, coding).await {
+                Ok(body) => {
+                    headers.remove(axum::http::header::CONTENT_ENCODING);
+                    headers.remove(axum::http::header::CONTENT_LENGTH);
+                    RequestBody::Buffered(body)
+                }
+                Err(err_resp) => return err_resp,
+            },
+            // Leave the body unread until the upstream asks for it
+            None if relay_continue => RequestBody::Held(body),
+            None => match read_request_body(body, state.config.server.max_retry_body_bytes).await {
+                Ok(body) => body,
+                Err(error) => return error.into_response(),
+            },
+        };
+        let headers = &headers;
+
+        // Single attempts count towards the retry budget too
+        state.retry_budget.record_request();
+        let deadline = upstream_deadline(state, route, headers);
+        let upstream_started = Instant::now();
+        let response = match body {
+            RequestBody::Buffered(body_bytes) => {
+                body_log::log_body(route, BodyDirection::Request, &body_bytes);
+
+                // Copy a sample of the traffic to the mirror upstream, if configured
+                spawn_mirror_request(state, route, &request, headers, &body_bytes).await;
+
+                // Execute upstream request, retrying within the route and budget limits
+                execute_with_retries(state, route, &request, &target_url, headers, body_bytes)
+                    .await
+                    .map(UpstreamResponse::from)
+            }
+            RequestBody::Streaming(body, read_error) => {
+                debug!("Request body exceeds the retry buffer, forwarding in a single attempt");
+                body_log::log_unbuffered_body(route, BodyDirection::Request);
+                skip_mirror_request(route);
+                let attempt = async {
+                    let request_builder =
+                        create_upstream_request(state, route, &request, &target_url, headers, body)
+                            .await?;
+                    execute_upstream_request(route, request_builder)
+                        .await
+                        .map(UpstreamResponse::from)
+                        .map_err(|e| upstream_error_response(&e, &target_url))
+                };
+                let response = within_deadline(deadline, &target_url, attempt).await;
+                // A failed upload is the client's doing, not the upstream's
+                if let (Err(_), Some(error)) = (&response, read_error.get()) {
+                    return error.into_response();
+                }
+                response
+            }
+            RequestBody::Held(body) => {
+                debug!("Relaying the upstream's 100 Continue before reading the request body");
+                body_log::log_unbuffered_body(route, BodyDirection::Request);
+                skip_mirror_request(route);
+                let (body, read_error) = track_read_errors(body);
+                let attempt = async {
+                    let upstream_request = create_upstream_request(
+                        state,
+                        route,
+                        &request,
+                        &target_url,
+                        headers,
+                        reqwest::Body::from(Bytes::new()),
+                    )
+                    .await?
+                    .build()
+                    .map_err(|e| {
+                        upstream_error_response(&UpstreamFailure::Request(e), &target_url)
+                    })?;
+                    expect_continue::forward(
+                        &state.continue_client,
+                        upstream_request,
+                        body,
+                        route.headers_timeout_ms.map(Duration::from_millis),
+                    )
+                    .await
+                };
+                let response = within_deadline(deadline, &target_url, attempt).await;
+                if let (Err(_), Some(error)) = (&response, read_error.get()) {
+                    return error.into_response();
+                }
+                response
+            }
+        };
+        let failed = response
+            .as_ref()
+            .map_or(true, |response| response.status.is_server_error());
+        let upstream_duration = upstream_started.elapsed();
+        record_upstream_duration(upstream_duration);
+        state
+            .upstream_stats
+            .record(upstream, upstream_duration, failed);
+        let response = match response {
+            Ok(response) => response,
             Err(err_resp) => return err_resp,
         };
 
-    // Execute upstream request
-    let response = match execute_upstream_request(request_builder, &target_url).await {
-        Ok(response) => response,
-        Err(err_resp) => return err_resp,
+        // Process and return upstream response
+        let accepts_gzip = decompress::accepts_gzip(headers);
+        process_upstream_response(&state.config.server, route, response, accepts_gzip).await
+    };
+
+    // Share one upstream call between identical concurrent GETs
+    let coalesce = route.coalesce_requests && *method == Method::GET;
+    let response = match route_index.filter(|_| coalesce) {
+        Some(index) => {
+            let key = FlightKey::new(index, method, uri, headers);
+            state
+                .single_flight
+                .run(key, || async {
+                    let max_bytes =
+                        route.effective_max_buffered_response_bytes(&state.config.server);
+                    SharedResponse::from_response(proxied.await, max_bytes).await
+                })
+                .await
+                .into_response()
+        }
+        None => proxied.await,
+    };
+
+    let response = if route.strip_conditional_responses {
+        strip_conditional_response(method, headers, response)
+    } else {
+        response
+    };
+
+    // The request counts as in flight until its streamed body has been sent
+    response.map(|body| Body::new(TrackedBody::new(body, move |_| drop(in_flight))))
+}
+
+/// Strip a fetched response down to 304 Not Modified when a conditional GET or HEAD matches it
+///
+/// This is not a cache: the response has already been fetched from the
+/// upstream, and only its body is kept from the client. Only 200 responses are
+/// replaced. The 304 keeps the response's headers apart from those describing
+/// the omitted body.
+fn strip_conditional_response(
+    method: &Method,
+    request_headers: &HeaderMap,
+    response: axum::response::Response,
+) -> axum::response::Response {
+    let conditional_method = *method == Method::GET || *method == Method::HEAD;
+    if !conditional_method
+        || response.status() != StatusCode::OK
+        || !is_not_modified(request_headers, response.headers())
+    {
+        return response;
+    }
+
+    debug!("Answering conditional request with 304 Not Modified");
+    let (mut parts, _) = response.into_parts();
+    for name in [
+        header::CONTENT_TYPE,
+        header::CONTENT_LENGTH,
+        header::CONTENT_ENCODING,
+        header::CONTENT_LANGUAGE,
+        header::CONTENT_RANGE,
+        header::TRANSFER_ENCODING,
+    ] {
+        parts.headers.remove(name);
+    }
+    parts.status = StatusCode::NOT_MODIFIED;
+    axum::response::Response::from_parts(parts, Body::empty())
+}
+
+/// Evaluate If-None-Match, or failing that If-Modified-Since, against a response
+///
+/// Entity tags are compared weakly, as RFC 9110 requires for If-None-Match.
+fn is_not_modified(request_headers: &HeaderMap, response_headers: &HeaderMap) -> bool {
+    let mut if_none_match = request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .peekable();
+    if if_none_match.peek().is_some() {
+        let Some(etag) = response_headers
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return false;
+        };
+        let opaque = |tag: &str| tag.strip_prefix("W/").unwrap_or(tag).to_string();
+        let etag = opaque(etag);
+        return if_none_match.any(|tag| tag == "*" || opaque(tag) == etag);
+    }
+
+    let date = |headers: &HeaderMap, name| {
+        let value = headers.get(name)?.to_str().ok()?;
+        chrono::DateTime::parse_from_rfc2822(value).ok()
+    };
+    let since = date(request_headers, header::IF_MODIFIED_SINCE);
+    let modified = date(response_headers, header::LAST_MODIFIED);
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// Build the headers to send upstream from the client's headers
+///
+/// Only the client headers allowed by the route's `forward_headers` policy are
+/// kept. The gateway's own forwarding headers are then added: X-Forwarded-For
+/// and, when enabled, an RFC 7239 Forwarded element for this hop.
+fn upstream_headers(
+    state: &ProxyState,
+    route: &RouteConfig,
+    connection: &ClientConnection,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> HeaderMap {
+    let allowed: HeaderMap = match &route.forward_headers {
+        ForwardHeaders::All => headers.clone(),
+        policy => headers
+            .iter()
+            .filter(|(name, _)| policy.allows(name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
     };
+    let mut upstream_headers = state
+        .client_ip
+        .with_forwarded_for(&allowed, connection.addr);
+
+    if state.config.server.forwarded_header {
+        let host = headers
+            .get(axum::http::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| uri.authority().map(|authority| authority.to_string()));
+        state.client_ip.insert_forwarded(
+            &mut upstream_headers,
+            connection.addr,
+            host.as_deref(),
+            connection.scheme.as_str(),
+        );
+    }
+    upstream_headers
+}
+
+/// Build a self-contained router that proxies every request using the given state
+///
+/// The returned router is a `tower::Service` that can be mounted on any axum
+/// application, so the gateway's proxying can be embedded without running the
+/// full server. Route paths are matched against the path relative to the mount
+/// point:
+///
+/// ```no_run
+/// use axum::Router;
+/// use ferragate::config::GatewayConfig;
+/// use ferragate::proxy::{proxy_router, ProxyState};
+///
+/// let state = ProxyState::new(GatewayConfig::default_config());
+/// let app: Router = Router::new().nest("/gateway", proxy_router(state));
+/// ```
+#[allow(dead_code)] // Public API method
+pub fn proxy_router(state: ProxyState) -> Router {
+    Router::new()
+        .route("/{*path}", any(proxy_handler))
+        .with_state(state)
+}
 
-    // Process and return upstream response
-    process_upstream_response(response).await
+/// Build a proxy router whose state can be swapped by a configuration reload
+pub fn shared_proxy_router(state: SharedProxyState) -> Router {
+    Router::new()
+        .route("/{*path}", any(shared_proxy_handler))
+        .with_state(state)
 }
 
-/// Find a matching route for the given request
+/// Proxy handler that serves each request from a snapshot of the current state
+#[allow(clippy::too_many_arguments)] // Each argument is an axum extractor
+async fn shared_proxy_handler(
+    State(state): State<SharedProxyState>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    listener_scheme: Option<Extension<Scheme>>,
+    tls_info: Option<Extension<TlsInfo>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    proxy_handler(
+        State(state.current()),
+        connect_info,
+        listener_scheme,
+        tls_info,
+        method,
+        uri,
+        headers,
+        body,
+    )
+    .await
+}
+
+/// Find a matching route for the given request, with its position in the configuration
+///
+/// Requests matching no configured route fall back to the `default_upstream`
+/// route, when one is configured, which has no position.
 fn find_route_for_request<'a>(
     state: &'a ProxyState,
     path: &str,
     method: &str,
-) -> Option<&'a RouteConfig> {
-    state.find_matching_route(path, method)
+    headers: &HeaderMap,
+    query: &str,
+    tls: Option<&TlsInfo>,
+) -> Option<(Option<usize>, &'a RouteConfig)> {
+    state
+        .find_matching_route_index(path, method, headers, query, tls)
+        .map(|(index, route)| (Some(index), route))
+        .or_else(|| {
+            let route = state.default_route.as_deref()?;
+            debug!(
+                "No route matched, using default upstream {}",
+                route.upstream
+            );
+            Some((None, route))
+        })
+}
+
+/// Select the upstream for a request, honoring the route's priority and canary settings
+///
+/// Requests whose priority has a pool in `priority_upstreams` are sent there.
+/// Other clients are bucketed by the route's `canary_key`; requests without a
+/// key value, or outside the canary, are balanced over the route's upstreams.
+/// Returns None when every upstream in the balanced pool is ejected.
+fn select_upstream<'a>(
+    state: &ProxyState,
+    route: &'a RouteConfig,
+    headers: &HeaderMap,
+    client_ip: Option<IpAddr>,
+) -> Option<&'a str> {
+    // Prioritized requests go to their tier's pool, ahead of canary and balancing
+    let priority_pool = state
+        .config
+        .server
+        .priority
+        .as_ref()
+        .and_then(|priority| priority.priority_of(headers))
+        .and_then(|value| route.priority_pool(value));
+    if let Some(pool) = priority_pool {
+        return Some(&pool[fastrand::usize(..pool.len())]);
+    }
+
+    let Some(canary_upstream) = &route.canary_upstream else {
+        return balancer::choose_upstream(route, &state.upstream_stats);
+    };
+
+    let key = CanaryKey::parse(route.canary_key.as_deref().unwrap_or("client_ip"))
+        .and_then(|key| key.extract(headers, client_ip));
+    match key {
+        Some(key) if canary::in_canary(&key, route.canary_percent.unwrap_or(0.0)) => {
+            Some(canary_upstream)
+        }
+        _ => balancer::choose_upstream(route, &state.upstream_stats),
+    }
 }
 
-/// Build the target URL for upstream forwarding
-fn build_target_url(route: &RouteConfig, path: &str, query: &str) -> String {
+/// Build the target URL for the given upstream, applying the route's path transformation
+///
+/// The request path is appended to the upstream's own path with exactly one `/`
+/// between them, whether or not the upstream ends in a slash. A query string on
+/// the upstream is kept ahead of the request's query.
+pub fn build_target_url(upstream: &str, route: &RouteConfig, path: &str, query: &str) -> String {
     let target_path = route.transform_path(path);
-    let mut target_url = format!("{0}{target_path}", route.upstream);
+    let (base, base_query) = upstream.split_once('?').unwrap_or((upstream, ""));
+    let mut target_url = format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        target_path.strip_prefix('/').unwrap_or(&target_path)
+    );
 
-    if !query.is_empty() {
-        target_url.push('?');
-        target_url.push_str(query);
+    for (i, part) in [base_query, query]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .enumerate()
+    {
+        target_url.push(if i == 0 { '?' } else { '&' });
+        target_url.push_str(part);
     }
 
     target_url
 }
 
+/// Send a fire-and-forget copy of the request to the route's mirror upstream
+///
+/// The mirror response is discarded and failures are only logged, so mirroring
+/// never affects the response returned to the client.
+async fn spawn_mirror_request(
+    state: &ProxyState,
+    route: &RouteConfig,
+    request: &RequestMetadata<'_>,
+    headers: &HeaderMap,
+    body_bytes: &Bytes,
+) {
+    let Some(mirror_upstream) = &route.mirror_upstream else {
+        return;
+    };
+    if fastrand::f64() >= route.effective_mirror_sample_rate() {
+        return;
+    }
+    // Mirroring is best effort, so a slow mirror sheds copies rather than piling up tasks
+    let Ok(slot) = Arc::clone(&state.mirror_slots).try_acquire_owned() else {
+        debug!(
+            "{} mirror requests in flight, not mirroring to {}",
+            MAX_IN_FLIGHT_MIRROR_REQUESTS, mirror_upstream
+        );
+        return;
+    };
+
+    let mirror_url = build_target_url(mirror_upstream, route, request.path, request.query);
+    let Ok(request_builder) = create_upstream_request(
+        state,
+        route,
+        request,
+        &mirror_url,
+        headers,
+        body_bytes.clone().into(),
+    )
+    .await
+    else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let _slot = slot;
+        match request_builder.send().await {
+            Ok(response) => debug!("Mirror {} responded with {}", mirror_url, response.status()),
+            Err(e) => warn!("Mirror request to {} failed: {}", mirror_url, e),
+        }
+    });
+}
+
+/// Note that a request whose body is streamed through is not mirrored
+///
+/// Only buffered bodies can be sent twice.
+fn skip_mirror_request(route: &RouteConfig) {
+    if let Some(mirror_upstream) = &route.mirror_upstream {
+        debug!(
+            "Request body is streamed through, not mirroring to {}",
+            mirror_upstream
+        );
+    }
+}
+
+/// Request body prepared for forwarding upstream
+enum RequestBody {
+    /// Fully buffered body that can be replayed for retries and mirroring
+    Buffered(Bytes),
+    /// Body larger than `max_retry_body_bytes`, streamed through in a single attempt,
+    /// with the reason reading it failed part way, if it did
+    Streaming(reqwest::Body, Arc<OnceLock<BodyReadError>>),
+    /// Body of a client expecting `100 Continue`, left unread until the upstream asks for it
+    Held(Body),
+}
+
+/// Why reading a request body from the client failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyReadError {
+    /// The client closed the connection before sending the whole body
+    ClientDisconnected,
+    /// The body exceeds `max_request_body_bytes`
+    TooLarge,
+    /// The body could not be read for any other reason
+    Invalid,
+}
+
+impl BodyReadError {
+    /// Classify a body error by the errors it was caused by
+    fn classify(error: &(dyn std::error::Error + 'static)) -> Self {
+        let mut cause = Some(error);
+        while let Some(error) = cause {
+            if error.is::<http_body_util::LengthLimitError>() {
+                return Self::TooLarge;
+            }
+            if let Some(error) = error.downcast_ref::<hyper::Error>() {
+                if error.is_incomplete_message() || error.is_canceled() || error.is_closed() {
+                    return Self::ClientDisconnected;
+                }
+            }
+            if let Some(error) = error.downcast_ref::<std::io::Error>() {
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::UnexpectedEof
+                        | std::io::ErrorKind::ConnectionReset
+                        | std::io::ErrorKind::ConnectionAborted
+                        | std::io::ErrorKind::BrokenPipe
+                ) {
+                    return Self::ClientDisconnected;
+                }
+            }
+            cause = error.source();
+        }
+        Self::Invalid
+    }
+
+    /// Classify and log a body error
+    fn from_error(error: &axum::Error) -> Self {
+        let classified = Self::classify(error);
+        match classified {
+            Self::ClientDisconnected => {
+                debug!("Client disconnected while sending the body: {}", error)
+            }
+            Self::TooLarge => warn!("Request body exceeds max_request_body_bytes"),
+            Self::Invalid => error!("Failed to read request body: {}", error),
+        }
+        classified
+    }
+}
+
+impl IntoResponse for BodyReadError {
+    /// A client that went away will not see the response; its status only shows
+    /// up in metrics and the access log
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ClientDisconnected => StatusCode::from_u16(CLIENT_CLOSED_REQUEST_STATUS)
+                .expect("valid status code")
+                .into_response(),
+            Self::TooLarge => {
+                (StatusCode::PAYLOAD_TOO_LARGE, MSG_REQUEST_BODY_TOO_LARGE).into_response()
+            }
+            Self::Invalid => (StatusCode::BAD_REQUEST, MSG_INVALID_REQUEST_BODY).into_response(),
+        }
+    }
+}
+
+/// Enforce `max_request_body_bytes` on a request body
+///
+/// Bodies declaring a larger length are rejected outright; others fail with
+/// `TooLarge` once they are read past the limit.
+fn limit_request_body(body: Body, max_bytes: Option<u64>) -> Result<Body, BodyReadError> {
+    let Some(max_bytes) = max_bytes else {
+        return Ok(body);
+    };
+    if body.size_hint().lower() > max_bytes {
+        warn!(
+            "Request body of {} bytes exceeds max_request_body_bytes",
+            body.size_hint().lower()
+        );
+        return Err(BodyReadError::TooLarge);
+    }
+    let max_bytes = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+    Ok(Body::new(http_body_util::Limited::new(body, max_bytes)))
+}
+
 /// Read the request body from the incoming request
-async fn read_request_body(body: Body) -> Result<Bytes, axum::response::Response> {
-    match body.collect().await {
-        Ok(collected) => Ok(collected.to_bytes()),
-        Err(e) => {
-            error!("Failed to read request body: {}", e);
-            Err((StatusCode::BAD_REQUEST, MSG_INVALID_REQUEST_BODY).into_response())
+///
+/// Bodies up to `max_buffered` bytes are buffered so they can be retried. Once a
+/// body is known or found to be larger, it is streamed upstream instead, with any
+/// bytes already read sent first.
+///
+/// For clients sending `Expect: 100-continue`, hyper writes the interim
+/// `100 Continue` when the body is first polled. Routes that can relay the
+/// upstream's own interim response leave the body unread instead; see
+/// `expect_continue`.
+async fn read_request_body(
+    mut body: Body,
+    max_buffered: u64,
+) -> Result<RequestBody, BodyReadError> {
+    let mut buffered = bytes::BytesMut::new();
+
+    if body.size_hint().lower() <= max_buffered {
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(|e| BodyReadError::from_error(&e))?;
+            if let Ok(data) = frame.into_data() {
+                buffered.extend_from_slice(&data);
+            }
+            if buffered.len() as u64 > max_buffered {
+                return Ok(stream_request_body(buffered.freeze(), body));
+            }
+        }
+        return Ok(RequestBody::Buffered(buffered.freeze()));
+    }
+
+    Ok(stream_request_body(buffered.freeze(), body))
+}
+
+/// Read and decode an encoded request body for a route with `decompress_request`
+///
+/// The decoded body is buffered so it can be retried like any other small body.
+/// Bodies that decode past the route's size cap are rejected with 413 before
+/// anything is sent upstream, and malformed ones with 400.
+async fn read_decompressed_body(
+    route: &RouteConfig,
+    body: Body,
+    coding: ContentCoding,
+) -> Result<Bytes, axum::response::Response> {
+    let max_bytes = route
+        .max_decompressed_bytes
+        .unwrap_or(DEFAULT_MAX_DECOMPRESSED_BYTES);
+    let too_large = || {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            MSG_DECOMPRESSED_BODY_TOO_LARGE,
+        )
+            .into_response()
+    };
+
+    let encoded = match read_request_body(body, max_bytes)
+        .await
+        .map_err(IntoResponse::into_response)?
+    {
+        RequestBody::Buffered(encoded) => encoded,
+        RequestBody::Streaming(..) | RequestBody::Held(_) => return Err(too_large()),
+    };
+    match decompress::decompress(&encoded, coding, max_bytes) {
+        Ok(decoded) => {
+            debug!(
+                "Decompressed {:?} request body from {} to {} bytes",
+                coding,
+                encoded.len(),
+                decoded.len()
+            );
+            Ok(decoded)
+        }
+        Err(DecompressError::TooLarge) => {
+            warn!("Decompressed request body exceeds {} bytes", max_bytes);
+            Err(too_large())
+        }
+        Err(DecompressError::Invalid(e)) => {
+            warn!("Failed to decompress {:?} request body: {}", coding, e);
+            Err((StatusCode::BAD_REQUEST, MSG_INVALID_REQUEST_ENCODING).into_response())
         }
     }
 }
 
+/// Stream a partially read body upstream, starting with the bytes already read
+///
+/// reqwest requires a `Sync` stream, which axum's body is not, so the body is
+/// pumped through a channel by a separate task. A read error aborts the upstream
+/// request and is recorded so the client gets a response matching its cause.
+fn stream_request_body(prefix: Bytes, body: Body) -> RequestBody {
+    use futures_util::StreamExt;
+
+    let read_error = Arc::new(OnceLock::new());
+    let pump_error = Arc::clone(&read_error);
+    let (tx, rx) =
+        tokio::sync::mpsc::channel::<Result<Bytes, axum::Error>>(REQUEST_BODY_STREAM_CHANNEL_SIZE);
+    tokio::spawn(async move {
+        if !prefix.is_empty() && tx.send(Ok(prefix)).await.is_err() {
+            return;
+        }
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            if let Err(e) = &chunk {
+                let _ = pump_error.set(BodyReadError::from_error(e));
+            }
+            if tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let body = reqwest::Body::wrap_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    }));
+    RequestBody::Streaming(body, read_error)
+}
+
+/// Record why reading a streamed request body failed, if it does
+fn track_read_errors(body: Body) -> (Body, Arc<OnceLock<BodyReadError>>) {
+    use futures_util::StreamExt;
+
+    let read_error = Arc::new(OnceLock::new());
+    let tracked = Arc::clone(&read_error);
+    let body = Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+        if let Err(e) = chunk {
+            let _ = tracked.set(BodyReadError::from_error(e));
+        }
+    }));
+    (body, read_error)
+}
+
 /// Create and configure the upstream request
 async fn create_upstream_request(
     state: &ProxyState,
     route: &RouteConfig,
-    method: &Method,
+    request: &RequestMetadata<'_>,
     target_url: &str,
     headers: &HeaderMap,
-    body_bytes: Bytes,
+    body: reqwest::Body,
 ) -> Result<reqwest::RequestBuilder, axum::response::Response> {
     // Convert HTTP method
-    let reqwest_method = match convert_http_method(method) {
+    let reqwest_method = match convert_http_method(request.method) {
         Ok(reqwest_method) => reqwest_method,
         Err(err_resp) => return Err(*err_resp),
     };
 
     // Create base request
     let mut request_builder = state
-        .client
+        .client_for(request.route_index)
         .request(reqwest_method, target_url)
-        .body(body_bytes);
+        .body(body);
+
+    // Add headers from original request, applying the route's User-Agent policy
+    let mut headers = apply_upstream_user_agent(route, headers).into_owned();
+    apply_upstream_accept_encoding(route, &mut headers);
+    // reqwest's defaults, set here so the 100-continue relay client sends them too
+    headers
+        .entry(header::USER_AGENT)
+        .or_insert(HeaderValue::from_static(CLIENT_USER_AGENT));
+    headers
+        .entry(header::ACCEPT)
+        .or_insert(HeaderValue::from_static("*/*"));
 
-    // Add headers from original request
-    request_builder = add_forwarded_headers(request_builder, headers);
+    // Continue the client's trace with a new span for this upstream call
+    let trace = TraceContext::for_upstream(&headers);
+    Span::current().record("trace_id", trace.trace_id());
+    debug!(
+        "Upstream span {} in trace {}",
+        trace.span_id(),
+        trace.trace_id()
+    );
+    trace.inject(&mut headers);
+    request_builder = add_forwarded_headers(request_builder, &headers);
 
     // Add custom headers from route configuration
-    request_builder = add_route_headers(request_builder, route);
+    request_builder = add_route_headers(request_builder, route, request);
 
     // Handle Host header
     request_builder = handle_host_header(request_builder, route, target_url);
 
-    // Apply timeout (route-specific or server default)
+    // Apply timeout (route-specific or server default), tightened by the client's own
     let server_default_timeout = state.config.server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
-    let effective_timeout_ms = route.effective_timeout(server_default_timeout);
+    let mut effective_timeout_ms = route.effective_timeout(server_default_timeout);
+    if let Some(client_timeout_ms) = client_requested_timeout(state, &headers) {
+        effective_timeout_ms = effective_timeout_ms.min(client_timeout_ms);
+    }
+    Span::current().record("effective_timeout_ms", effective_timeout_ms);
     request_builder = request_builder.timeout(Duration::from_millis(effective_timeout_ms));
 
     Ok(request_builder)
 }
 
+/// Upstream timeout in milliseconds the client asked for in `client_timeout_header`
+///
+/// Values that are not a positive number, or that exceed
+/// `MAX_CLIENT_TIMEOUT_MS`, are ignored.
+fn client_requested_timeout(state: &ProxyState, headers: &HeaderMap) -> Option<u64> {
+    let header = state.config.server.client_timeout_header.as_deref()?;
+    let value = headers.get(header)?.to_str().ok()?;
+    match value.trim().parse::<u64>() {
+        Ok(timeout_ms) if (1..=MAX_CLIENT_TIMEOUT_MS).contains(&timeout_ms) => Some(timeout_ms),
+        _ => {
+            debug!("Ignoring client timeout {}: {:?}", header, value);
+            None
+        }
+    }
+}
+
+/// Apply the route's `upstream_user_agent` policy to the client's headers
+///
+/// Without a policy the headers are forwarded unchanged; requests without a
+/// User-Agent are then sent with `CLIENT_USER_AGENT`.
+fn apply_upstream_user_agent<'a>(
+    route: &RouteConfig,
+    headers: &'a HeaderMap,
+) -> Cow<'a, HeaderMap> {
+    let user_agent = match &route.upstream_user_agent {
+        None | Some(UpstreamUserAgent::Passthrough) => return Cow::Borrowed(headers),
+        Some(UpstreamUserAgent::GatewayDefault) => HeaderValue::from_static(CLIENT_USER_AGENT),
+        Some(UpstreamUserAgent::Literal(value)) => match HeaderValue::from_str(value) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Invalid upstream_user_agent '{}': {}", value, e);
+                return Cow::Borrowed(headers);
+            }
+        },
+    };
+
+    let mut headers = headers.clone();
+    headers.insert(axum::http::header::USER_AGENT, user_agent);
+    Cow::Owned(headers)
+}
+
+/// Apply the route's `upstream_accept_encoding` policy to the forwarded headers
+fn apply_upstream_accept_encoding(route: &RouteConfig, headers: &mut HeaderMap) {
+    match &route.upstream_accept_encoding {
+        None | Some(UpstreamAcceptEncoding::Passthrough) => {}
+        Some(UpstreamAcceptEncoding::Strip) => {
+            headers.remove(axum::http::header::ACCEPT_ENCODING);
+        }
+        Some(UpstreamAcceptEncoding::Literal(value)) => match HeaderValue::from_str(value) {
+            Ok(value) => {
+                headers.insert(axum::http::header::ACCEPT_ENCODING, value);
+            }
+            Err(e) => warn!("Invalid upstream_accept_encoding '{}': {}", value, e),
+        },
+    }
+}
+
 /// Convert Axum HTTP method to reqwest method
 fn convert_http_method(method: &Method) -> Result<reqwest::Method, Box<axum::response::Response>> {
     match method.as_str() {
@@ -227,16 +1520,111 @@ fn add_forwarded_headers(
 }
 
 /// Add custom headers from route configuration
+///
+/// Header values may contain placeholders that are filled in from the request,
+/// see [`render_header_template`].
 fn add_route_headers(
     mut request_builder: reqwest::RequestBuilder,
     route: &RouteConfig,
+    request: &RequestMetadata<'_>,
 ) -> reqwest::RequestBuilder {
     for (name, value) in &route.headers {
-        request_builder = request_builder.header(name, value);
+        request_builder =
+            request_builder.header(name, render_header_template(value, request).as_ref());
     }
     request_builder
 }
 
+/// Build the headers of a gRPC call from the headers to send upstream
+///
+/// As for other requests, hop-by-hop headers are dropped, the route's custom
+/// headers are added and the client's Host is kept only with `preserve_host`.
+fn grpc_upstream_headers(
+    route: &RouteConfig,
+    request: &RequestMetadata<'_>,
+    headers: &HeaderMap,
+) -> HeaderMap {
+    let mut grpc_headers: HeaderMap = headers
+        .iter()
+        .filter(|(name, _)| should_forward_header(name.as_str()))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    for (name, value) in &route.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::try_from(name.as_str()),
+            HeaderValue::from_str(&render_header_template(value, request)),
+        ) {
+            grpc_headers.insert(name, value);
+        }
+    }
+    if !route.preserve_host {
+        grpc_headers.remove(header::HOST);
+    }
+    grpc_headers
+}
+
+/// Details of the client request, available to route header templates, and the route it matched
+struct RequestMetadata<'a> {
+    method: &'a Method,
+    path: &'a str,
+    query: &'a str,
+    client_ip: Option<IpAddr>,
+    request_id: String,
+    /// Position of the matched route in the configuration, `None` for the default route
+    route_index: Option<usize>,
+}
+
+/// Use the client's request ID, or generate one when it has none
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(create_request_id)
+}
+
+/// Substitute request placeholders in a route header value
+///
+/// Supported placeholders are `{method}`, `{path}`, `{query}`, `{client_ip}` and
+/// `{request_id}`. `{client_ip}` is empty when the client address is unknown.
+/// Unknown placeholders are left as-is.
+fn render_header_template<'v>(value: &'v str, request: &RequestMetadata<'_>) -> Cow<'v, str> {
+    if !value.contains('{') {
+        return Cow::Borrowed(value);
+    }
+
+    let mut rendered = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start + 1..start + len];
+        rendered.push_str(&rest[..start]);
+        match placeholder {
+            "method" => rendered.push_str(request.method.as_str()),
+            "path" => rendered.push_str(request.path),
+            "query" => rendered.push_str(request.query),
+            "client_ip" => {
+                if let Some(client_ip) = request.client_ip {
+                    rendered.push_str(&client_ip.to_string());
+                }
+            }
+            "request_id" => rendered.push_str(&request.request_id),
+            _ => {
+                warn!(
+                    "Unknown placeholder '{{{}}}' in route header value",
+                    placeholder
+                );
+                rendered.push_str(&rest[start..=start + len]);
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+    rendered.push_str(rest);
+    Cow::Owned(rendered)
+}
+
 /// Handle the Host header based on route configuration
 fn handle_host_header(
     mut request_builder: reqwest::RequestBuilder,
@@ -258,49 +1646,389 @@ fn handle_host_header(
     request_builder
 }
 
-/// Execute the upstream request
-async fn execute_upstream_request(
-    request_builder: reqwest::RequestBuilder,
+/// Deadline for the upstream exchange as a whole
+///
+/// The client's own timeout bounds all attempts together, like the route's
+/// `total_timeout_ms`; the sooner of the two applies.
+fn upstream_deadline(
+    state: &ProxyState,
+    route: &RouteConfig,
+    headers: &HeaderMap,
+) -> Option<Instant> {
+    [
+        route.total_timeout_ms,
+        client_requested_timeout(state, headers),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+    .map(|total_ms| Instant::now() + Duration::from_millis(total_ms))
+}
+
+/// Run a single upstream attempt, answering 504 if it is still running at `deadline`
+async fn within_deadline(
+    deadline: Option<Instant>,
+    target_url: &str,
+    attempt: impl std::future::Future<Output = Result<UpstreamResponse, axum::response::Response>>,
+) -> Result<UpstreamResponse, axum::response::Response> {
+    let Some(deadline) = deadline else {
+        return attempt.await;
+    };
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    match tokio::time::timeout(remaining, attempt).await {
+        Ok(result) => result,
+        Err(_) => {
+            error!(
+                "Upstream {} did not respond within the total timeout",
+                target_url
+            );
+            Err((StatusCode::GATEWAY_TIMEOUT, MSG_UPSTREAM_TIMEOUT).into_response())
+        }
+    }
+}
+
+/// Execute the upstream request, retrying failed attempts
+///
+/// Only idempotent methods are retried, at most `route.retries` times, and every
+/// retry must be granted by the global retry budget. When the budget is exhausted
+/// the result of the last attempt is returned as-is.
+async fn execute_with_retries(
+    state: &ProxyState,
+    route: &RouteConfig,
+    request: &RequestMetadata<'_>,
     target_url: &str,
+    headers: &HeaderMap,
+    body_bytes: Bytes,
 ) -> Result<reqwest::Response, axum::response::Response> {
-    match request_builder.send().await {
-        Ok(response) => {
-            debug!("Upstream response status: {}", response.status());
-            Ok(response)
-        }
-        Err(e) => {
-            error!("Failed to proxy request to {}: {}", target_url, e);
-            Err((
-                StatusCode::BAD_GATEWAY,
-                format!("Failed to proxy request: {e}"),
-            )
-                .into_response())
+    let max_attempts = if is_idempotent_method(request.method) {
+        route.retries.saturating_add(1)
+    } else {
+        1
+    };
+
+    let deadline = upstream_deadline(state, route, headers);
+
+    let mut attempt = 1;
+    loop {
+        let mut request_builder = create_upstream_request(
+            state,
+            route,
+            request,
+            target_url,
+            headers,
+            body_bytes.clone().into(),
+        )
+        .await?;
+
+        // Later attempts only get what is left of the route's total budget
+        if let Some(deadline) = deadline {
+            let attempt_timeout =
+                Duration::from_millis(route.effective_timeout(
+                    state.config.server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS),
+                ));
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            request_builder = request_builder.timeout(attempt_timeout.min(remaining));
+        }
+
+        let result = execute_upstream_request(route, request_builder).await;
+        let retry_after = result.as_ref().ok().and_then(parse_retry_after);
+        let retryable = match &result {
+            Ok(response) => {
+                is_retryable_status(response.status())
+                    || (response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                        && retry_after.is_some())
+            }
+            Err(e) => e.is_retryable(),
+        };
+
+        if !retryable || attempt >= max_attempts {
+            return result.map_err(|e| upstream_error_response(&e, target_url));
+        }
+
+        // An upstream asking us to back off for longer than we are willing to wait
+        if let Some(retry_after) = retry_after {
+            if retry_after > Duration::from_millis(state.config.server.max_retry_after_ms) {
+                warn!(
+                    "Upstream {} asked to retry after {:?}, exceeding the limit; not retrying",
+                    target_url, retry_after
+                );
+                return result.map_err(|e| upstream_error_response(&e, target_url));
+            }
+        }
+
+        let backoff_ms = retry_after
+            .map(|retry_after| retry_after.as_millis() as u64)
+            .unwrap_or_else(|| route.retry_backoff(attempt));
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if Duration::from_millis(backoff_ms) >= remaining {
+                warn!(
+                    "Total timeout for {} leaves no time for another attempt; not retrying",
+                    target_url
+                );
+                return result.map_err(|e| upstream_error_response(&e, target_url));
+            }
         }
+
+        if !state.retry_budget.try_withdraw() {
+            warn!(
+                "Retry budget exhausted, not retrying request to {}",
+                target_url
+            );
+            return result.map_err(|e| upstream_error_response(&e, target_url));
+        }
+
+        warn!(
+            "Upstream attempt {} to {} failed, retrying in {}ms",
+            attempt, target_url, backoff_ms
+        );
+        tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
+        attempt += 1;
     }
 }
 
-/// Process the upstream response and prepare it for the client
-async fn process_upstream_response(response: reqwest::Response) -> axum::response::Response {
-    // Convert status code
-    let status = StatusCode::from_u16(response.status().as_u16())
-        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+/// Parse the Retry-After header of a 429 or 503 upstream response
+///
+/// Supports both delay-seconds and HTTP-date values. Dates in the past yield a zero delay.
+fn parse_retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let status = response.status();
+    if status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        && status != reqwest::StatusCode::SERVICE_UNAVAILABLE
+    {
+        return None;
+    }
 
-    // Process response headers
-    let mut response_headers = HeaderMap::new();
-    for (name, value) in response.headers() {
-        let header_name = name.as_str().to_lowercase();
-        if should_forward_response_header(&header_name) {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+    parse_retry_after_value(value)
+}
+
+fn parse_retry_after_value(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Failure to get a response from an upstream
+#[derive(Debug)]
+enum UpstreamFailure {
+    /// The request failed or hit the overall timeout
+    Request(reqwest::Error),
+    /// The upstream did not send response headers within the route's `headers_timeout_ms`
+    HeadersTimeout(Duration),
+}
+
+impl UpstreamFailure {
+    /// Whether another attempt may succeed
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request(e) => e.is_connect() || e.is_timeout(),
+            Self::HeadersTimeout(_) => true,
+        }
+    }
+}
+
+/// Execute a single upstream request attempt
+///
+/// Returns once the response headers arrive; the body is read separately.
+async fn execute_upstream_request(
+    route: &RouteConfig,
+    request_builder: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, UpstreamFailure> {
+    let send = request_builder.send();
+    let response = match route.headers_timeout_ms.map(Duration::from_millis) {
+        Some(limit) => tokio::time::timeout(limit, send)
+            .await
+            .map_err(|_| UpstreamFailure::HeadersTimeout(limit))?,
+        None => send.await,
+    }
+    .map_err(UpstreamFailure::Request)?;
+    debug!("Upstream response status: {}", response.status());
+    Ok(response)
+}
+
+/// Convert a failed upstream request into a client response
+///
+/// The full error is logged, while the client only sees a sanitized message with
+/// a status that reflects the failure: 504 for timeouts and 502 otherwise.
+fn upstream_error_response(e: &UpstreamFailure, target_url: &str) -> axum::response::Response {
+    let e = match e {
+        UpstreamFailure::Request(e) => e,
+        UpstreamFailure::HeadersTimeout(limit) => {
+            error!(
+                "Upstream {} did not send response headers within {:?}",
+                target_url, limit
+            );
+            return (StatusCode::GATEWAY_TIMEOUT, MSG_UPSTREAM_TIMEOUT).into_response();
+        }
+    };
+    let detail = error_chain(e);
+    error!("Failed to proxy request to {}: {}", target_url, detail);
+
+    let (status, message) = classify_upstream_error(e, &detail);
+    (status, message).into_response()
+}
+
+/// Map an upstream error to a client-facing status and message
+fn classify_upstream_error(e: &reqwest::Error, detail: &str) -> (StatusCode, &'static str) {
+    if e.is_timeout() {
+        (StatusCode::GATEWAY_TIMEOUT, MSG_UPSTREAM_TIMEOUT)
+    } else if e.is_connect() && is_tls_error(detail) {
+        (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_TLS_FAILED)
+    } else if e.is_connect() {
+        (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_CONNECT_FAILED)
+    } else if e.is_body() || e.is_decode() {
+        (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_INVALID_RESPONSE)
+    } else {
+        (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_REQUEST_FAILED)
+    }
+}
+
+/// Check an error chain for signs of a TLS failure
+///
+/// The TLS errors come from reqwest's own rustls version, so they cannot be
+/// downcast here and are recognized by their messages instead.
+fn is_tls_error(detail: &str) -> bool {
+    let detail = detail.to_lowercase();
+    [
+        "tls",
+        "certificate",
+        "handshake",
+        "invalidcontenttype",
+        "corrupt message",
+    ]
+    .iter()
+    .any(|marker| detail.contains(marker))
+}
+
+/// Render an error together with all of its sources
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut detail = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        detail.push_str(": ");
+        detail.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    detail
+}
+
+/// Check whether a request method is safe to retry
+fn is_idempotent_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+    )
+}
+
+/// Check whether an upstream status indicates a transient failure worth retrying
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 502..=504)
+}
+
+/// Response from either upstream client, before it is processed for the client
+pub struct UpstreamResponse {
+    pub status: StatusCode,
+    pub version: Version,
+    pub headers: HeaderMap,
+    /// Origin of the URL that answered, after any redirects
+    pub origin: String,
+    /// Exact body size, when known before reading the body
+    pub content_length: Option<u64>,
+    pub body: Body,
+}
+
+impl From<reqwest::Response> for UpstreamResponse {
+    fn from(response: reqwest::Response) -> Self {
+        let version = match response.version() {
+            reqwest::Version::HTTP_09 => Version::HTTP_09,
+            reqwest::Version::HTTP_10 => Version::HTTP_10,
+            reqwest::Version::HTTP_2 => Version::HTTP_2,
+            reqwest::Version::HTTP_3 => Version::HTTP_3,
+            _ => Version::HTTP_11,
+        };
+        let mut headers = HeaderMap::new();
+        for (name, value) in response.headers() {
             if let (Ok(name), Ok(value)) = (
                 HeaderName::try_from(name.as_str()),
                 HeaderValue::try_from(value.as_bytes()),
             ) {
-                response_headers.insert(name, value);
+                headers.append(name, value);
+            }
+        }
+        Self {
+            status: StatusCode::from_u16(response.status().as_u16())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            version,
+            headers,
+            origin: response.url().origin().ascii_serialization(),
+            content_length: response.content_length(),
+            body: Body::from_stream(response.bytes_stream()),
+        }
+    }
+}
+
+/// Process the upstream response and prepare it for the client
+async fn process_upstream_response(
+    server: &ServerConfig,
+    route: &RouteConfig,
+    response: UpstreamResponse,
+    accepts_gzip: bool,
+) -> axum::response::Response {
+    // Convert status code, applying the route's status mapping
+    let upstream_status = response.status.as_u16();
+    let mapped_status = route.map_status(upstream_status);
+    if mapped_status != upstream_status {
+        debug!(
+            "Mapping upstream status {} to {}",
+            upstream_status, mapped_status
+        );
+    }
+    let status = StatusCode::from_u16(mapped_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    // Process response headers
+    let mut response_headers = HeaderMap::new();
+    for (name, value) in &response.headers {
+        if should_forward_response_header(name.as_str()) {
+            response_headers.insert(name, value.clone());
+        }
+    }
+    apply_gateway_response_headers(server, response.version, &mut response_headers);
+
+    // Forward the body as it arrives unless the route needs it whole
+    let body_timeout = route.body_timeout_ms.map(Duration::from_millis);
+    let size_limit = |max_bytes| ResponseSizeLimit {
+        max_bytes,
+        route: route.path.clone(),
+        upstream: response.origin.clone(),
+    };
+    if route.effective_response_mode() == ResponseMode::Stream {
+        let size_limit = route.effective_max_response_bytes(server).map(size_limit);
+        if let Some(limit) = &size_limit {
+            if limit.exceeded_by(response.content_length.unwrap_or_default()) {
+                return (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_RESPONSE_TOO_LARGE).into_response();
             }
         }
+        let has_body = response.content_length != Some(0);
+        if let Some(rejected) = reject_response_content_type(route, &response_headers, has_body) {
+            return rejected;
+        }
+        debug!("Streaming response body");
+        let body = stream_response_body(response, body_timeout, size_limit);
+        return (status, response_headers, body).into_response();
     }
 
     // Read response body
-    let response_body = match response.bytes().await {
+    let size_limit = size_limit(route.effective_max_buffered_response_bytes(server));
+    let response_body = match read_response_body(response, body_timeout, &size_limit).await {
         Ok(bytes) => {
             debug!(
                 "Successfully proxied request, response size: {} bytes",
@@ -308,15 +2036,187 @@ async fn process_upstream_response(response: reqwest::Response) -> axum::respons
             );
             bytes
         }
-        Err(e) => {
-            error!("Failed to read response body: {}", e);
-            return (StatusCode::BAD_GATEWAY, "Failed to read response body").into_response();
-        }
+        Err(err_resp) => return err_resp,
+    };
+
+    // Decode the body so it can be transformed
+    let coding =
+        ContentCoding::from_headers(&response_headers).filter(|_| route.decompress_response);
+    let response_body = match coding {
+        Some(coding) => match decompress_response_body(route, coding, &response_body) {
+            Ok(decoded) => {
+                response_headers.remove(header::CONTENT_ENCODING);
+                response_headers.remove(header::CONTENT_LENGTH);
+                decoded
+            }
+            Err(message) => return (StatusCode::BAD_GATEWAY, message).into_response(),
+        },
+        None => response_body,
     };
+    body_log::log_body(route, BodyDirection::Response, &response_body);
+
+    if let Some(rejected) =
+        reject_response_content_type(route, &response_headers, !response_body.is_empty())
+    {
+        return rejected;
+    }
+    let mut response_body = apply_body_replacements(route, &mut response_headers, response_body);
+
+    if coding.is_some() && route.recompress_response && accepts_gzip {
+        response_body = decompress::gzip(&response_body);
+        response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        response_headers.remove(header::CONTENT_LENGTH);
+    }
 
     (status, response_headers, response_body).into_response()
 }
 
+/// Decode an upstream response body for a route with `decompress_response`
+///
+/// Bodies that decode past the route's size cap are refused rather than passed
+/// on, as are malformed ones; the error is the message for the client's 502.
+fn decompress_response_body(
+    route: &RouteConfig,
+    coding: ContentCoding,
+    body: &[u8],
+) -> Result<Bytes, &'static str> {
+    let max_bytes = route
+        .max_decompressed_bytes
+        .unwrap_or(DEFAULT_MAX_DECOMPRESSED_BYTES);
+    match decompress::decompress(body, coding, max_bytes) {
+        Ok(decoded) => {
+            debug!(
+                "Decompressed {:?} response body from {} to {} bytes",
+                coding,
+                body.len(),
+                decoded.len()
+            );
+            Ok(decoded)
+        }
+        Err(DecompressError::TooLarge) => {
+            warn!("Decompressed response body exceeds {} bytes", max_bytes);
+            Err(MSG_UPSTREAM_RESPONSE_TOO_LARGE)
+        }
+        Err(DecompressError::Invalid(e)) => {
+            warn!("Failed to decompress {:?} response body: {}", coding, e);
+            Err(MSG_UPSTREAM_INVALID_RESPONSE)
+        }
+    }
+}
+
+/// The 502 replacing a response whose content type the route does not allow
+fn reject_response_content_type(
+    route: &RouteConfig,
+    headers: &HeaderMap,
+    has_body: bool,
+) -> Option<axum::response::Response> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    if route.allows_response_content_type(content_type, has_body) {
+        return None;
+    }
+    warn!(
+        "Upstream returned disallowed content type {:?} for route {}",
+        content_type, route.path
+    );
+    Some(
+        (
+            StatusCode::BAD_GATEWAY,
+            MSG_UPSTREAM_CONTENT_TYPE_NOT_ALLOWED,
+        )
+            .into_response(),
+    )
+}
+
+/// Identify the gateway in a response's Via and Server headers, as configured
+///
+/// The Via entry records the protocol version the upstream answered with
+/// (RFC 7230 section 5.7.1) and is appended to any entries already present.
+fn apply_gateway_response_headers(
+    server: &ServerConfig,
+    version: Version,
+    headers: &mut HeaderMap,
+) {
+    if let Some(via) = &server.via {
+        let protocol = match version {
+            Version::HTTP_09 => "0.9",
+            Version::HTTP_10 => "1.0",
+            Version::HTTP_2 => "2",
+            Version::HTTP_3 => "3",
+            _ => "1.1",
+        };
+        let entry = match headers
+            .get(header::VIA)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(existing) => format!("{existing}, {protocol} {via}"),
+            None => format!("{protocol} {via}"),
+        };
+        if let Ok(value) = HeaderValue::from_str(&entry) {
+            headers.insert(header::VIA, value);
+        }
+    }
+
+    match server.server_header.as_deref() {
+        None => {}
+        Some("") => {
+            headers.remove(header::SERVER);
+        }
+        Some(server_header) => {
+            if let Ok(value) = HeaderValue::from_str(server_header) {
+                headers.insert(header::SERVER, value);
+            }
+        }
+    }
+}
+
+/// Apply the route's `body_replacements` to a response body
+///
+/// Only uncompressed UTF-8 bodies of a matching Content-Type are rewritten.
+/// Bodies over `max_replacement_body_bytes` are passed through unchanged, and
+/// Content-Length is dropped from rewritten responses so it is set to the new size.
+fn apply_body_replacements(route: &RouteConfig, headers: &mut HeaderMap, body: Bytes) -> Bytes {
+    if route.body_replacements.is_empty() || headers.contains_key(header::CONTENT_ENCODING) {
+        return body;
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let rules: Vec<_> = route
+        .body_replacements
+        .iter()
+        .filter(|replacement| replacement.applies_to(content_type))
+        .collect();
+    if rules.is_empty() {
+        return body;
+    }
+
+    let max_bytes = route
+        .max_replacement_body_bytes
+        .unwrap_or(DEFAULT_MAX_REPLACEMENT_BODY_BYTES);
+    if body.len() as u64 > max_bytes {
+        warn!(
+            "Response body of {} bytes exceeds the {} byte replacement limit, passing it through unchanged",
+            body.len(),
+            max_bytes
+        );
+        return body;
+    }
+    let Ok(text) = std::str::from_utf8(&body) else {
+        debug!("Response body is not valid UTF-8, skipping body replacements");
+        return body;
+    };
+
+    let mut rewritten = text.to_string();
+    for rule in rules {
+        rewritten = rewritten.replace(&rule.from, &rule.to);
+    }
+    headers.remove(header::CONTENT_LENGTH);
+    Bytes::from(rewritten)
+}
+
 fn should_forward_header(header_name: &str) -> bool {
     // Check if header is in the filtered list
     if FILTERED_HEADERS.contains(&header_name) {
@@ -331,6 +2231,137 @@ fn should_forward_header(header_name: &str) -> bool {
     }
 }
 
+/// Read the upstream response body
+///
+/// With an inactivity limit, the read is abandoned if the upstream sends no data
+/// for that long. Dropping the response closes the upstream connection, and the
+/// client gets a 504 since nothing has been sent to it yet.
+async fn read_response_body(
+    response: UpstreamResponse,
+    inactivity_limit: Option<Duration>,
+    size_limit: &ResponseSizeLimit,
+) -> Result<Bytes, axum::response::Response> {
+    use futures_util::StreamExt;
+
+    let body_error = |e: axum::Error| {
+        error!("Failed to read response body: {}", e);
+        (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_INVALID_RESPONSE).into_response()
+    };
+    let too_large = || (StatusCode::BAD_GATEWAY, MSG_UPSTREAM_RESPONSE_TOO_LARGE).into_response();
+    if size_limit.exceeded_by(response.content_length.unwrap_or_default()) {
+        return Err(too_large());
+    }
+
+    let mut chunks = response.body.into_data_stream();
+    let mut body = bytes::BytesMut::new();
+    loop {
+        let chunk = match inactivity_limit {
+            Some(limit) => match tokio::time::timeout(limit, chunks.next()).await {
+                Ok(chunk) => chunk,
+                Err(_) => {
+                    error!(
+                        "Upstream response body stalled for {:?} after {} bytes, aborting",
+                        limit,
+                        body.len()
+                    );
+                    return Err((StatusCode::GATEWAY_TIMEOUT, MSG_UPSTREAM_TIMEOUT).into_response());
+                }
+            },
+            None => chunks.next().await,
+        };
+        match chunk {
+            Some(Ok(chunk)) => {
+                body.extend_from_slice(&chunk);
+                if size_limit.exceeded_by(body.len() as u64) {
+                    return Err(too_large());
+                }
+            }
+            None => return Ok(body.freeze()),
+            Some(Err(e)) => return Err(body_error(e)),
+        }
+    }
+}
+
+/// Cap on a route's upstream response body, named in the warning when exceeded
+#[derive(Debug, Clone)]
+struct ResponseSizeLimit {
+    max_bytes: u64,
+    route: String,
+    upstream: String,
+}
+
+impl ResponseSizeLimit {
+    /// Check a body size against the cap, logging a warning when it is exceeded
+    fn exceeded_by(&self, bytes: u64) -> bool {
+        if bytes <= self.max_bytes {
+            return false;
+        }
+        warn!(
+            "Response from upstream {} for route {} exceeds {} bytes, aborting",
+            self.upstream, self.route, self.max_bytes
+        );
+        true
+    }
+}
+
+/// Forward an upstream response body to the client as it arrives
+///
+/// The status and headers have already been sent by the time the body fails, so
+/// a body that errors, stalls for longer than `inactivity_limit` or grows past
+/// `size_limit` ends the stream early and the client sees the response cut short.
+fn stream_response_body(
+    response: UpstreamResponse,
+    inactivity_limit: Option<Duration>,
+    size_limit: Option<ResponseSizeLimit>,
+) -> Body {
+    use futures_util::StreamExt;
+
+    if inactivity_limit.is_none() && size_limit.is_none() {
+        return response.body;
+    }
+    let chunks = response
+        .body
+        .into_data_stream()
+        .map(|chunk| chunk.map_err(std::io::Error::other));
+
+    let state = (Box::pin(chunks), 0, size_limit);
+    let chunks = futures_util::stream::unfold(Some(state), move |state| async move {
+        let (mut chunks, received, size_limit) = state?;
+        let next = match inactivity_limit {
+            Some(limit) => match tokio::time::timeout(limit, chunks.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    error!(
+                        "Upstream response body stalled for {:?}, ending the stream",
+                        limit
+                    );
+                    let stalled = std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "upstream response body stalled",
+                    );
+                    return Some((Err(stalled), None));
+                }
+            },
+            None => chunks.next().await,
+        };
+        let chunk = match next? {
+            Ok(chunk) => chunk,
+            Err(e) => return Some((Err(e), None)),
+        };
+
+        let received = received + chunk.len() as u64;
+        if size_limit
+            .as_ref()
+            .is_some_and(|limit| limit.exceeded_by(received))
+        {
+            let too_large = std::io::Error::other("upstream response body too large");
+            return Some((Err(too_large), None));
+        }
+        Some((Ok(chunk), Some((chunks, received, size_limit))))
+    });
+    Body::from_stream(chunks)
+}
+
 fn should_forward_response_header(header_name: &str) -> bool {
     // Check if header is in the filtered list
     if FILTERED_HEADERS.contains(&header_name) {
@@ -358,45 +2389,20 @@ mod tests {
 
     // Helper function to create a test configuration
     fn create_test_config() -> GatewayConfig {
-        GatewayConfig {
-            server: ServerConfig {
-                host: "127.0.0.1".to_string(),
-                port: 8080,
-                workers: None,
-                timeout_ms: Some(5000),
-                tls: None,
-            },
-            routes: vec![
-                RouteConfig {
-                    path: "/api/v1/*".to_string(),
-                    upstream: "http://backend1:3000".to_string(),
-                    methods: vec!["GET".to_string(), "POST".to_string()],
-                    headers: HashMap::new(),
-                    strip_path: true,
-                    preserve_host: false,
-                    timeout_ms: Some(30000),
-                },
-                RouteConfig {
-                    path: "/users/*".to_string(),
-                    upstream: "http://user-service:8000".to_string(),
-                    methods: vec![],
-                    headers: HashMap::new(),
-                    strip_path: false,
-                    preserve_host: true,
-                    timeout_ms: None,
-                },
-                RouteConfig {
-                    path: "/health".to_string(),
-                    upstream: "http://health-service:9000".to_string(),
-                    methods: vec!["GET".to_string()],
-                    headers: HashMap::new(),
-                    strip_path: false,
-                    preserve_host: false,
-                    timeout_ms: None,
-                },
-            ],
-            logging: crate::config::LoggingConfig::default(),
-        }
+        GatewayConfig::builder()
+            .host("127.0.0.1")
+            .port(8080)
+            .timeout_ms(5000)
+            .route(
+                RouteConfig::builder("/api/v1/*", "http://backend1:3000")
+                    .methods(["GET", "POST"])
+                    .strip_path(true)
+                    .timeout_ms(30000),
+            )
+            .route(RouteConfig::builder("/users/*", "http://user-service:8000").preserve_host(true))
+            .route(RouteConfig::builder("/health", "http://health-service:9000").methods(["GET"]))
+            .build()
+            .unwrap()
     }
 
     #[test]
@@ -419,7 +2425,7 @@ mod tests {
         let proxy_state = ProxyState::new(config);
 
         // Test exact path match
-        let route = proxy_state.find_matching_route("/health", "GET");
+        let route = proxy_state.find_matching_route("/health", "GET", &HeaderMap::new(), "", None);
         assert!(route.is_some());
         assert_eq!(route.unwrap().path, "/health");
     }
@@ -430,21 +2436,36 @@ mod tests {
         let proxy_state = ProxyState::new(config);
 
         // Test wildcard path match
-        let route = proxy_state.find_matching_route("/api/v1/test", "GET");
+        let route =
+            proxy_state.find_matching_route("/api/v1/test", "GET", &HeaderMap::new(), "", None);
         assert!(route.is_some());
         assert_eq!(route.unwrap().path, "/api/v1/*");
     }
 
     #[test]
-    fn test_find_matching_route_method_filtering() {
+    fn test_find_matching_route_index_is_configuration_position() {
         let config = create_test_config();
         let proxy_state = ProxyState::new(config);
 
-        // Test method filtering for exact route
-        let route_get = proxy_state.find_matching_route("/health", "GET");
-        assert!(route_get.is_some());
+        let (index, route) = proxy_state
+            .find_matching_route_index("/api/v1/test", "GET", &HeaderMap::new(), "", None)
+            .unwrap();
+        assert!(std::ptr::eq(route, &proxy_state.config.routes[index]));
+        assert_eq!(route.path, "/api/v1/*");
+    }
+
+    #[test]
+    fn test_find_matching_route_method_filtering() {
+        let config = create_test_config();
+        let proxy_state = ProxyState::new(config);
+
+        // Test method filtering for exact route
+        let route_get =
+            proxy_state.find_matching_route("/health", "GET", &HeaderMap::new(), "", None);
+        assert!(route_get.is_some());
 
-        let route_post = proxy_state.find_matching_route("/health", "POST");
+        let route_post =
+            proxy_state.find_matching_route("/health", "POST", &HeaderMap::new(), "", None);
         assert!(route_post.is_none()); // POST not allowed for /health
     }
 
@@ -454,23 +2475,136 @@ mod tests {
         let proxy_state = ProxyState::new(config);
 
         // /users/* has empty methods list, should allow all methods
-        let route_get = proxy_state.find_matching_route("/users/123", "GET");
+        let route_get =
+            proxy_state.find_matching_route("/users/123", "GET", &HeaderMap::new(), "", None);
         assert!(route_get.is_some());
 
-        let route_post = proxy_state.find_matching_route("/users/123", "POST");
+        let route_post =
+            proxy_state.find_matching_route("/users/123", "POST", &HeaderMap::new(), "", None);
         assert!(route_post.is_some());
 
-        let route_delete = proxy_state.find_matching_route("/users/123", "DELETE");
+        let route_delete =
+            proxy_state.find_matching_route("/users/123", "DELETE", &HeaderMap::new(), "", None);
         assert!(route_delete.is_some());
     }
 
+    #[test]
+    fn test_build_target_url_joins_upstream_path() {
+        let route = RouteConfig {
+            path: "/api/*".to_string(),
+            ..Default::default()
+        };
+        let stripped = RouteConfig {
+            strip_path: true,
+            ..route.clone()
+        };
+
+        for (upstream, route, path, query, expected) in [
+            (
+                "http://backend:3000",
+                &route,
+                "/api/users",
+                "",
+                "http://backend:3000/api/users",
+            ),
+            (
+                "http://backend:3000/",
+                &route,
+                "/api/users",
+                "",
+                "http://backend:3000/api/users",
+            ),
+            (
+                "http://backend:3000/base",
+                &route,
+                "/api/users",
+                "",
+                "http://backend:3000/base/api/users",
+            ),
+            (
+                "http://backend:3000/base/",
+                &route,
+                "/api/users",
+                "",
+                "http://backend:3000/base/api/users",
+            ),
+            (
+                "http://backend:3000/base/",
+                &stripped,
+                "/api/users",
+                "",
+                "http://backend:3000/base/users",
+            ),
+            (
+                "http://backend:3000/base",
+                &stripped,
+                "/api",
+                "",
+                "http://backend:3000/base/",
+            ),
+            (
+                "http://backend:3000",
+                &route,
+                "/api/users",
+                "page=2",
+                "http://backend:3000/api/users?page=2",
+            ),
+            (
+                "http://backend:3000/base?key=k",
+                &route,
+                "/api/users",
+                "page=2",
+                "http://backend:3000/base/api/users?key=k&page=2",
+            ),
+            (
+                "http://backend:3000/base?key=k",
+                &route,
+                "/api/users",
+                "",
+                "http://backend:3000/base/api/users?key=k",
+            ),
+        ] {
+            assert_eq!(
+                build_target_url(upstream, route, path, query),
+                expected,
+                "{upstream} + {path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_matching_route_skips_disabled_routes() {
+        let mut config = create_test_config();
+        // A disabled catch-all ahead of the enabled routes never matches
+        config.routes.insert(
+            0,
+            RouteConfig {
+                path: "/*".to_string(),
+                upstream: "http://debug-service:7000".to_string(),
+                enabled: false,
+                ..Default::default()
+            },
+        );
+        config.routes[2].enabled = false;
+        let proxy_state = ProxyState::new(config);
+
+        let route = proxy_state.find_matching_route("/health", "GET", &HeaderMap::new(), "", None);
+        assert_eq!(route.unwrap().path, "/health");
+
+        // With /users/* disabled, its requests match nothing
+        let route =
+            proxy_state.find_matching_route("/users/123", "GET", &HeaderMap::new(), "", None);
+        assert!(route.is_none());
+    }
+
     #[test]
     fn test_find_matching_route_no_match() {
         let config = create_test_config();
         let proxy_state = ProxyState::new(config);
 
         // Test path that doesn't match any route
-        let route = proxy_state.find_matching_route("/nonexistent", "GET");
+        let route =
+            proxy_state.find_matching_route("/nonexistent", "GET", &HeaderMap::new(), "", None);
         assert!(route.is_none());
     }
 
@@ -480,16 +2614,205 @@ mod tests {
         let proxy_state = ProxyState::new(config);
 
         // Test case insensitivity for methods (this should work based on the config implementation)
-        let route_lower = proxy_state.find_matching_route("/health", "get");
+        let route_lower =
+            proxy_state.find_matching_route("/health", "get", &HeaderMap::new(), "", None);
         assert!(
             route_lower.is_some(),
             "Method matching should be case insensitive"
         );
 
-        let route_upper = proxy_state.find_matching_route("/health", "GET");
+        let route_upper =
+            proxy_state.find_matching_route("/health", "GET", &HeaderMap::new(), "", None);
         assert!(route_upper.is_some());
     }
 
+    // Helper to create a config whose first route only matches mobile clients
+    fn create_header_match_config() -> GatewayConfig {
+        toml::from_str(
+            r#"
+[server]
+
+[[routes]]
+path = "/app/*"
+upstream = "http://mobile:3000"
+
+[[routes.header_match]]
+name = "user-agent"
+regex = "(?i)(android|iphone)"
+
+[[routes.header_match]]
+name = "x-app-version"
+regex = "^2\\."
+
+[[routes]]
+path = "/app/*"
+upstream = "http://web:3000"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_matching_route_header_match() {
+        let proxy_state = ProxyState::new(create_header_match_config());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "user-agent",
+            HeaderValue::from_static("Mozilla/5.0 (iPhone)"),
+        );
+        headers.insert("x-app-version", HeaderValue::from_static("2.4.1"));
+        let route = proxy_state.find_matching_route("/app/home", "GET", &headers, "", None);
+        assert_eq!(route.unwrap().upstream, "http://mobile:3000");
+    }
+
+    #[test]
+    fn test_find_matching_route_header_match_requires_all_rules() {
+        let proxy_state = ProxyState::new(create_header_match_config());
+
+        // Mobile User-Agent but an old app version
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", HeaderValue::from_static("Android 14"));
+        headers.insert("x-app-version", HeaderValue::from_static("1.9.0"));
+        let route = proxy_state.find_matching_route("/app/home", "GET", &headers, "", None);
+        assert_eq!(route.unwrap().upstream, "http://web:3000");
+
+        // Desktop User-Agent, and missing headers, fall through as well
+        headers.insert(
+            "user-agent",
+            HeaderValue::from_static("Mozilla/5.0 (X11; Linux)"),
+        );
+        headers.insert("x-app-version", HeaderValue::from_static("2.0.0"));
+        let route = proxy_state.find_matching_route("/app/home", "GET", &headers, "", None);
+        assert_eq!(route.unwrap().upstream, "http://web:3000");
+
+        let route =
+            proxy_state.find_matching_route("/app/home", "GET", &HeaderMap::new(), "", None);
+        assert_eq!(route.unwrap().upstream, "http://web:3000");
+    }
+
+    #[test]
+    fn test_find_matching_route_content_type_match() {
+        let config = GatewayConfig {
+            server: ServerConfig::default(),
+            routes: vec![
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://json:3000".to_string(),
+                    content_type_match: vec!["application/json".to_string()],
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://xml:3000".to_string(),
+                    methods: vec!["POST".to_string()],
+                    content_type_match: vec!["application/xml".to_string(), "text/xml".to_string()],
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://other-application:3000".to_string(),
+                    content_type_match: vec!["application/*".to_string()],
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://default:3000".to_string(),
+                    ..Default::default()
+                },
+            ],
+            logging: crate::config::LoggingConfig::default(),
+        };
+        let proxy_state = ProxyState::new(config);
+        let upstream = |method: &str, content_type: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(content_type) = content_type {
+                headers.insert("content-type", content_type.parse().unwrap());
+            }
+            proxy_state
+                .find_matching_route("/api/orders", method, &headers, "", None)
+                .unwrap()
+                .upstream
+                .clone()
+        };
+
+        // Exact matches ignore parameters and case
+        assert_eq!(
+            upstream("POST", Some("application/json")),
+            "http://json:3000"
+        );
+        assert_eq!(
+            upstream("POST", Some("Application/JSON; charset=utf-8")),
+            "http://json:3000"
+        );
+        assert_eq!(upstream("POST", Some("text/xml")), "http://xml:3000");
+
+        // Combined with the route's method restriction
+        assert_eq!(
+            upstream("PUT", Some("application/xml")),
+            "http://other-application:3000"
+        );
+
+        // Prefix matches
+        assert_eq!(
+            upstream("POST", Some("application/x-www-form-urlencoded")),
+            "http://other-application:3000"
+        );
+        assert_eq!(upstream("POST", Some("text/plain")), "http://default:3000");
+        assert_eq!(upstream("GET", None), "http://default:3000");
+    }
+
+    #[test]
+    fn test_find_matching_route_query_match() {
+        use crate::config::QueryMatch;
+
+        let config = GatewayConfig {
+            server: ServerConfig::default(),
+            routes: vec![
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://v2:3000".to_string(),
+                    methods: vec!["GET".to_string()],
+                    query_match: vec![QueryMatch::Equals("v".to_string(), "2".to_string())],
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://debug:3000".to_string(),
+                    query_match: vec![
+                        QueryMatch::Present("debug".to_string()),
+                        QueryMatch::Absent("legacy".to_string()),
+                    ],
+                    ..Default::default()
+                },
+                RouteConfig {
+                    path: "/api/*".to_string(),
+                    upstream: "http://default:3000".to_string(),
+                    ..Default::default()
+                },
+            ],
+            logging: crate::config::LoggingConfig::default(),
+        };
+        let proxy_state = ProxyState::new(config);
+        let upstream = |method: &str, query: &str| {
+            proxy_state
+                .find_matching_route("/api/items", method, &HeaderMap::new(), query, None)
+                .unwrap()
+                .upstream
+                .clone()
+        };
+
+        // Equals, combined with the route's method restriction
+        assert_eq!(upstream("GET", "v=2"), "http://v2:3000");
+        assert_eq!(upstream("POST", "v=2"), "http://default:3000");
+        assert_eq!(upstream("GET", "v=3"), "http://default:3000");
+
+        // Present and absent rules must both hold
+        assert_eq!(upstream("GET", "debug"), "http://debug:3000");
+        assert_eq!(upstream("GET", "debug=1&legacy=1"), "http://default:3000");
+        assert_eq!(upstream("GET", ""), "http://default:3000");
+    }
+
     #[test]
     fn test_header_filtering() {
         // Test headers that should NOT be forwarded
@@ -592,13 +2915,20 @@ mod tests {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             },
         );
 
         let proxy_state = ProxyState::new(config);
 
         // Should match the first (more specific) route
-        let route = proxy_state.find_matching_route("/api/v1/users/123", "GET");
+        let route = proxy_state.find_matching_route(
+            "/api/v1/users/123",
+            "GET",
+            &HeaderMap::new(),
+            "",
+            None,
+        );
         assert!(route.is_some());
         assert_eq!(route.unwrap().upstream, "http://specific-service:4000");
     }
@@ -612,6 +2942,7 @@ mod tests {
                 workers: None,
                 timeout_ms: Some(5000),
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: crate::config::LoggingConfig::default(),
@@ -620,7 +2951,8 @@ mod tests {
         let proxy_state = ProxyState::new(config);
 
         // No routes should match
-        let route = proxy_state.find_matching_route("/any/path", "GET");
+        let route =
+            proxy_state.find_matching_route("/any/path", "GET", &HeaderMap::new(), "", None);
         assert!(route.is_none());
     }
 
@@ -630,10 +2962,22 @@ mod tests {
         let proxy_state = ProxyState::new(config);
 
         // Test paths with special characters
-        let route = proxy_state.find_matching_route("/api/v1/users%20test", "GET");
+        let route = proxy_state.find_matching_route(
+            "/api/v1/users%20test",
+            "GET",
+            &HeaderMap::new(),
+            "",
+            None,
+        );
         assert!(route.is_some());
 
-        let route = proxy_state.find_matching_route("/api/v1/test?query=value", "GET");
+        let route = proxy_state.find_matching_route(
+            "/api/v1/test?query=value",
+            "GET",
+            &HeaderMap::new(),
+            "",
+            None,
+        );
         assert!(route.is_some());
     }
 
@@ -654,4 +2998,3096 @@ mod tests {
             cloned_state.config.routes.len()
         );
     }
+
+    // Helper to send a request through the proxy handler
+    async fn send_request(
+        state: ProxyState,
+        method: Method,
+        uri: &str,
+    ) -> axum::response::Response {
+        use tower::ServiceExt;
+
+        let app = proxy_router(state);
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    // Helper to create a config with a single retrying route to the given upstream
+    fn create_retry_config(upstream: &str, retries: u32) -> GatewayConfig {
+        GatewayConfig {
+            server: ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                timeout_ms: Some(5000),
+                ..Default::default()
+            },
+            routes: vec![RouteConfig {
+                path: "/api/*".to_string(),
+                upstream: upstream.to_string(),
+                retries,
+                retry_backoff_ms: Some(1),
+                ..Default::default()
+            }],
+            logging: crate::config::LoggingConfig::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_headers_time_out_with_504() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].headers_timeout_ms = Some(50);
+        let started = Instant::now();
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/slow").await;
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_stalled_body_is_aborted() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // An upstream that sends its headers and part of the body, then stalls
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 1024];
+                    let _ = socket.read(&mut request).await;
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\npartial")
+                        .await;
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                });
+            }
+        });
+
+        // Buffered responses are replaced with 504 before anything reaches the client
+        let mut config = create_retry_config(&format!("http://{addr}"), 0);
+        config.routes[0].body_timeout_ms = Some(100);
+        config.routes[0].response_mode = Some(ResponseMode::Buffer);
+        let started = Instant::now();
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/stream").await;
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // Streamed responses have already started, so the body is cut short instead
+        config.routes[0].response_mode = Some(ResponseMode::Stream);
+        let started = Instant::now();
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/stream").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.into_body().collect().await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_request_header_limits() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.max_request_headers = 4;
+        config.server.max_request_header_bytes = 40;
+        let state = ProxyState::new(config);
+        let send = |headers: &[(&str, &str)]| {
+            let mut request = axum::http::Request::builder().uri("/api/items");
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            proxy_router(state.clone()).oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        // Four 2-byte headers are at the count limit; a fifth is over it
+        let mut headers = vec![("x-a", "1"), ("x-b", "2"), ("x-c", "3"), ("x-d", "4")];
+        assert_eq!(send(&headers).await.unwrap().status(), StatusCode::OK);
+        headers.push(("x-e", "5"));
+        let response = send(&headers).await.unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+        assert_eq!(response_text(response).await, MSG_REQUEST_HEADERS_TOO_LARGE);
+
+        // Names and values of exactly 40 bytes are at the size limit; one more is over it
+        let value = "v".repeat(33);
+        let at_limit = [("x-token", value.as_str())];
+        assert_eq!(send(&at_limit).await.unwrap().status(), StatusCode::OK);
+        let over_limit = [("x-tokens", value.as_str())];
+        let response = send(&over_limit).await.unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+
+        // Repeated headers count once per value
+        let repeated = [
+            ("x-a", "1"),
+            ("x-a", "2"),
+            ("x-a", "3"),
+            ("x-a", "4"),
+            ("x-a", "5"),
+        ];
+        let response = send(&repeated).await.unwrap();
+        assert_eq!(
+            response.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_response_modes() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(100)))
+            .mount(&upstream)
+            .await;
+        let mut config = create_retry_config(&upstream.uri(), 0);
+
+        // Streaming is the default and forwards the body as it arrives
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-length"], "100");
+        assert_eq!(response.body().size_hint().exact(), None);
+        assert_eq!(response_text(response).await.len(), 100);
+
+        config.routes[0].response_mode = Some(ResponseMode::Buffer);
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().size_hint().exact(), Some(100));
+
+        // Buffer mode rejects bodies over its size cap
+        config.routes[0].max_response_body_bytes = Some(100);
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        config.routes[0].max_response_body_bytes = Some(99);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response_text(response).await,
+            MSG_UPSTREAM_RESPONSE_TOO_LARGE
+        );
+    }
+
+    /// Start an upstream answering every request with a 16-byte body in two chunks
+    ///
+    /// The body has no Content-Length, so size caps are only reached while reading it.
+    async fn start_chunked_upstream() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 1024];
+                    let _ = socket.read(&mut request).await;
+                    let _ = socket
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n\
+                              8\r\n01234567\r\n8\r\n89abcdef\r\n0\r\n\r\n",
+                        )
+                        .await;
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes_with_content_length() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("0123456789abcdef"))
+            .mount(&upstream)
+            .await;
+        let send = |server_limit: u64, route_limit: Option<u64>, mode: ResponseMode| {
+            let mut config = create_retry_config(&upstream.uri(), 0);
+            config.server.max_response_bytes = server_limit;
+            config.routes[0].max_response_bytes = route_limit;
+            config.routes[0].response_mode = Some(mode);
+            send_request(ProxyState::new(config), Method::GET, "/api/items")
+        };
+
+        for mode in [ResponseMode::Stream, ResponseMode::Buffer] {
+            // Exactly at the limit
+            let response = send(16, None, mode).await;
+            assert_eq!(response.status(), StatusCode::OK, "{mode:?}");
+            assert_eq!(response_text(response).await, "0123456789abcdef");
+
+            // One byte over, refused before anything is sent
+            let response = send(15, None, mode).await;
+            assert_eq!(response.status(), StatusCode::BAD_GATEWAY, "{mode:?}");
+            assert_eq!(
+                response_text(response).await,
+                MSG_UPSTREAM_RESPONSE_TOO_LARGE
+            );
+            let response = send(0, Some(15), mode).await;
+            assert_eq!(response.status(), StatusCode::BAD_GATEWAY, "{mode:?}");
+
+            // A route limit of 0 lifts the server limit
+            let response = send(15, Some(0), mode).await;
+            assert_eq!(response.status(), StatusCode::OK, "{mode:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes_while_streaming() {
+        let upstream = start_chunked_upstream().await;
+        let send = |limit: u64| {
+            let mut config = create_retry_config(&upstream, 0);
+            config.routes[0].max_response_bytes = Some(limit);
+            send_request(ProxyState::new(config), Method::GET, "/api/items")
+        };
+
+        let response = send(16).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "0123456789abcdef");
+
+        // The headers are already out when the limit is reached, so the body is cut short
+        let response = send(12).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.into_body().collect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_buffer_mode_size_cap_without_content_length() {
+        let upstream = start_chunked_upstream().await;
+        let mut config = create_retry_config(&upstream, 0);
+        config.routes[0].response_mode = Some(ResponseMode::Buffer);
+        config.routes[0].max_response_body_bytes = Some(12);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response_text(response).await,
+            MSG_UPSTREAM_RESPONSE_TOO_LARGE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_body_timeout_allows_complete_body() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("complete"))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].headers_timeout_ms = Some(1000);
+        config.routes[0].body_timeout_ms = Some(1000);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "complete");
+    }
+
+    #[tokio::test]
+    async fn test_retry_recovers_from_transient_failure() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&upstream)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 2));
+        let response = send_request(state, Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_requests_are_not_retried() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 3));
+        let response = send_request(state, Method::POST, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_suppresses_retries_under_high_failure_rate() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 3);
+        config.server.retry_budget_ratio = 0.1;
+        let state = ProxyState::new(config);
+
+        let requests = 50;
+        for _ in 0..requests {
+            let response = send_request(state.clone(), Method::GET, "/api/items").await;
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        // Without a budget every request would be attempted 4 times (200 upstream calls)
+        let max_retries = RETRY_BUDGET_MIN_RETRIES as usize + requests / 10;
+        let received = upstream.received_requests().await.unwrap().len();
+        assert!(
+            received <= requests + max_retries,
+            "expected at most {} upstream calls, got {}",
+            requests + max_retries,
+            received
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proxy_router_mounted_in_custom_router() {
+        use axum::routing::get;
+        use tower::ServiceExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/items"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("from upstream"))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+        let app = Router::new()
+            .route("/hello", get(|| async { "hello" }))
+            .nest("/gateway", proxy_router(state));
+
+        let request = axum::http::Request::builder()
+            .uri("/gateway/api/items")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "from upstream");
+
+        // The application's own routes are untouched
+        let request = axum::http::Request::builder()
+            .uri("/hello")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_expect_100_continue_upload() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use wiremock::matchers::{body_string, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/api/upload"))
+            .and(header("expect", "100-continue"))
+            .and(header("user-agent", CLIENT_USER_AGENT))
+            .and(header("accept", "*/*"))
+            .and(body_string("large payload"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&upstream)
+            .await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = proxy_router(ProxyState::new(create_retry_config(&upstream.uri(), 0)));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /api/upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 13\r\n\
+                  Expect: 100-continue\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        // The client must receive the interim response before sending the body
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let interim = String::from_utf8_lossy(&buf[..n]);
+        assert!(interim.starts_with("HTTP/1.1 100 Continue"), "{interim}");
+
+        stream.write_all(b"large payload").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 201"), "{response}");
+    }
+
+    // Read an HTTP/1.1 request head from a raw connection
+    async fn read_request_head(stream: &mut tokio::net::TcpStream) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8; 1];
+            if stream.read(&mut byte).await.unwrap() == 0 {
+                break;
+            }
+            head.push(byte[0]);
+        }
+        String::from_utf8(head).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_expect_100_continue_relays_upstream_interim_response() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel::<()>();
+        let upstream_task = tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            let head = read_request_head(&mut stream).await;
+            assert!(
+                head.to_ascii_lowercase().contains("expect: 100-continue"),
+                "{head}"
+            );
+
+            // The upstream decides when to ask for the body
+            ready_rx.await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .await
+                .unwrap();
+            let mut body = [0u8; 13];
+            stream.read_exact(&mut body).await.unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            body
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = create_retry_config(&format!("http://{upstream_addr}"), 0);
+        let app = proxy_router(ProxyState::new(config));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /api/upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 13\r\n\
+                  Expect: 100-continue\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        // Nothing reaches the client until the upstream sends its interim response
+        let mut buf = [0u8; 1024];
+        let early = tokio::time::timeout(Duration::from_millis(300), stream.read(&mut buf)).await;
+        assert!(
+            early.is_err(),
+            "client answered before the upstream asked for the body"
+        );
+
+        ready_tx.send(()).unwrap();
+        let n = stream.read(&mut buf).await.unwrap();
+        let interim = String::from_utf8_lossy(&buf[..n]);
+        assert!(interim.starts_with("HTTP/1.1 100 Continue"), "{interim}");
+
+        stream.write_all(b"large payload").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.contains("HTTP/1.1 201"), "{response}");
+        assert_eq!(&upstream_task.await.unwrap(), b"large payload");
+    }
+
+    #[tokio::test]
+    async fn test_expect_100_continue_upstream_rejection_skips_body() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        let upstream_task = tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            read_request_head(&mut stream).await;
+            stream
+                .write_all(
+                    b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\
+                      Connection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+
+            // The body is never sent to an upstream that refused it
+            let mut rest = Vec::new();
+            let _ = tokio::time::timeout(
+                Duration::from_millis(crate::constants::EXPECT_CONTINUE_TIMEOUT_MS + 500),
+                stream.read_to_end(&mut rest),
+            )
+            .await;
+            rest
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = create_retry_config(&format!("http://{upstream_addr}"), 0);
+        let app = proxy_router(ProxyState::new(config));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /api/upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 13\r\n\
+                  Expect: 100-continue\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        // The client gets the final status without ever being asked for the body
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 417"), "{response}");
+        assert!(!response.contains("100 Continue"), "{response}");
+        assert!(upstream_task.await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expect_100_continue_relay_times_out_slow_body() {
+        use http_body_util::BodyExt;
+        use tokio::io::AsyncWriteExt;
+        use tower::ServiceExt;
+
+        // The upstream answers without asking for the body, then stalls mid-body
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = upstream.accept().await.unwrap();
+            read_request_head(&mut stream).await;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut config = create_retry_config(&format!("http://{upstream_addr}"), 0);
+        config.routes[0].timeout_ms = Some(300);
+        let request = axum::http::Request::builder()
+            .method(Method::PUT)
+            .uri("/api/upload")
+            .header("expect", "100-continue")
+            .body(Body::from("payload"))
+            .unwrap();
+        let started = Instant::now();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // As with reqwest, the timeout covers the body, not only the headers
+        assert!(response.into_body().collect().await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_expect_100_continue_follows_redirects_when_configured() {
+        use tower::ServiceExt;
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/upload"))
+            .respond_with(ResponseTemplate::new(307).insert_header("location", "/api/stored"))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/stored"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].follow_redirects = true;
+        let request = axum::http::Request::builder()
+            .method(Method::PUT)
+            .uri("/api/upload")
+            .header("expect", "100-continue")
+            .body(Body::from("payload"))
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let received = upstream.received_requests().await.unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].body, b"payload");
+    }
+
+    #[test]
+    fn test_apply_upstream_user_agent_modes() {
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", HeaderValue::from_static("curl/8.0"));
+        let mut route = create_retry_config("http://localhost:3000", 0).routes[0].clone();
+
+        // Unset and passthrough forward the client's User-Agent
+        assert_eq!(
+            apply_upstream_user_agent(&route, &headers)["user-agent"],
+            "curl/8.0"
+        );
+        route.upstream_user_agent = Some(UpstreamUserAgent::Passthrough);
+        assert_eq!(
+            apply_upstream_user_agent(&route, &headers)["user-agent"],
+            "curl/8.0"
+        );
+
+        route.upstream_user_agent = Some(UpstreamUserAgent::GatewayDefault);
+        assert_eq!(
+            apply_upstream_user_agent(&route, &headers)["user-agent"],
+            CLIENT_USER_AGENT
+        );
+
+        route.upstream_user_agent = Some(UpstreamUserAgent::Literal("custom/2.0".to_string()));
+        let applied = apply_upstream_user_agent(&route, &headers);
+        assert_eq!(applied.get_all("user-agent").iter().count(), 1);
+        assert_eq!(applied["user-agent"], "custom/2.0");
+    }
+
+    // Layer collecting the fields recorded on `proxy_handler` spans
+    #[derive(Clone, Default)]
+    struct SpanFieldCapture(Arc<std::sync::Mutex<Vec<(String, String)>>>);
+
+    impl tracing::field::Visit for SpanFieldCapture {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanFieldCapture {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() == "proxy_handler" {
+                attrs.record(&mut self.clone());
+            }
+        }
+
+        fn on_record(
+            &self,
+            _id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    // Helper to proxy one request and collect the fields recorded on its span
+    async fn recorded_span_fields(
+        span_fields: crate::config::SpanFieldsConfig,
+        uri: &str,
+    ) -> HashMap<String, String> {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.logging.span_fields = span_fields;
+        proxied_span_fields(config, uri).await
+    }
+
+    // Helper to proxy one request with the given config and collect its span fields
+    async fn proxied_span_fields(config: GatewayConfig, uri: &str) -> HashMap<String, String> {
+        use tower::ServiceExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = SpanFieldCapture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let request = axum::http::Request::builder()
+            .uri(uri)
+            .header("user-agent", "probe/1.0")
+            .header("authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let fields = capture.0.lock().unwrap().iter().cloned().collect();
+        fields
+    }
+
+    // Layer collecting the messages of log events
+    #[derive(Clone, Default)]
+    struct LogCapture(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl tracing::field::Visit for LogCapture {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0.lock().unwrap().push(format!("{value:?}"));
+            }
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for LogCapture {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            event.record(&mut self.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logged_bodies_are_redacted() {
+        use crate::config::{BodyLoggingConfig, RedactPattern};
+        use tower::ServiceExt;
+        use tracing_subscriber::layer::SubscriberExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"session":"secret-f00d","ok":true}"#, "application/json"),
+            )
+            .mount(&upstream)
+            .await;
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].log_bodies = Some(BodyLoggingConfig {
+            redact_fields: vec!["credentials.password".to_string()],
+            redact_patterns: vec![RedactPattern::try_from(r"secret-\w+".to_string()).unwrap()],
+            ..Default::default()
+        });
+
+        let capture = LogCapture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/api/login")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"credentials":{"user":"ada","password":"hunter2"}}"#,
+            ))
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+        // The client still receives the body unredacted
+        assert_eq!(
+            response_text(response).await,
+            r#"{"session":"secret-f00d","ok":true}"#
+        );
+
+        let logs = capture.0.lock().unwrap().clone();
+        assert!(logs.contains(
+            &r#"Request body for route /api/*: {"credentials":{"password":"***","user":"ada"}}"#
+                .to_string()
+        ));
+        assert!(logs.contains(
+            &r#"Response body for route /api/*: {"ok":true,"session":"***"}"#.to_string()
+        ));
+        for secret in ["hunter2", "secret-f00d"] {
+            assert!(logs.iter().all(|line| !line.contains(secret)), "{logs:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_span_records_full_uri_by_default() {
+        let fields = recorded_span_fields(Default::default(), "/api/items?token=abc").await;
+        assert_eq!(fields["uri"], "/api/items?token=abc");
+        assert!(!fields.contains_key("headers"));
+    }
+
+    #[tokio::test]
+    async fn test_span_query_redaction_removes_parameter_values() {
+        use crate::config::{SpanFieldsConfig, SpanUri};
+
+        let span_fields = SpanFieldsConfig {
+            uri: SpanUri::RedactQuery,
+            headers: vec!["User-Agent".to_string()],
+        };
+        let fields = recorded_span_fields(span_fields, "/api/items?token=abc&page=2").await;
+        assert_eq!(fields["uri"], "/api/items?token=REDACTED&page=REDACTED");
+        assert_eq!(fields["headers"], "user-agent=probe/1.0");
+        assert!(fields.values().all(|value| !value.contains("abc")));
+        assert!(fields.values().all(|value| !value.contains("secret")));
+
+        let span_fields = SpanFieldsConfig {
+            uri: SpanUri::Path,
+            headers: Vec::new(),
+        };
+        let fields = recorded_span_fields(span_fields, "/api/items?token=abc").await;
+        assert_eq!(fields["uri"], "/api/items");
+    }
+
+    #[tokio::test]
+    async fn test_span_records_effective_timeout_and_upstream_duration() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(100)))
+            .mount(&upstream)
+            .await;
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].timeout_ms = Some(2000);
+
+        let fields = proxied_span_fields(config, "/api/items").await;
+        assert_eq!(fields["effective_timeout_ms"], "2000");
+        let duration: u64 = fields["upstream_duration_ms"].parse().unwrap();
+        assert!(
+            (100..2000).contains(&duration),
+            "upstream took {duration}ms"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upstream_accept_encoding_modes() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        let modes = [
+            (None, Some("gzip, br")),
+            (Some(UpstreamAcceptEncoding::Passthrough), Some("gzip, br")),
+            (Some(UpstreamAcceptEncoding::Strip), None),
+            (
+                Some(UpstreamAcceptEncoding::Literal("identity".to_string())),
+                Some("identity"),
+            ),
+        ];
+        for (mode, expected) in modes {
+            upstream.reset().await;
+            Mock::given(wiremock::matchers::any())
+                .respond_with(ResponseTemplate::new(200))
+                .mount(&upstream)
+                .await;
+
+            let mut config = create_retry_config(&upstream.uri(), 0);
+            config.routes[0].upstream_accept_encoding = mode.clone();
+            let request = axum::http::Request::builder()
+                .uri("/api/items")
+                .header("accept-encoding", "gzip, br")
+                .body(Body::empty())
+                .unwrap();
+            let response = proxy_router(ProxyState::new(config))
+                .oneshot(request)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let received = upstream.received_requests().await.unwrap();
+            let sent: Vec<_> = received[0]
+                .headers
+                .get_all("accept-encoding")
+                .iter()
+                .map(|value| value.to_str().unwrap())
+                .collect();
+            assert_eq!(sent, expected.into_iter().collect::<Vec<_>>(), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_header_template_placeholders() {
+        let request = RequestMetadata {
+            method: &Method::POST,
+            path: "/api/items",
+            query: "page=2",
+            client_ip: Some("203.0.113.7".parse().unwrap()),
+            request_id: "req-42".to_string(),
+            route_index: Some(0),
+        };
+
+        let cases = [
+            ("{method}", "POST"),
+            ("{path}", "/api/items"),
+            ("{query}", "page=2"),
+            ("{client_ip}", "203.0.113.7"),
+            ("{request_id}", "req-42"),
+            ("{method} {path}?{query}", "POST /api/items?page=2"),
+            ("static", "static"),
+            ("{unknown}-{path}", "{unknown}-/api/items"),
+            ("{path", "{path"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                render_header_template(template, &request),
+                expected,
+                "{template}"
+            );
+        }
+
+        let request = RequestMetadata {
+            client_ip: None,
+            ..request
+        };
+        assert_eq!(render_header_template("ip={client_ip}", &request), "ip=");
+    }
+
+    #[tokio::test]
+    async fn test_route_header_templates_sent_upstream() {
+        use tower::ServiceExt;
+        use wiremock::matchers::header;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(header("x-original-path", "/api/items"))
+            .and(header("x-client-ip", "198.51.100.9"))
+            .and(header("x-correlation-id", "abc-123"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].headers = HashMap::from([
+            ("X-Original-Path".to_string(), "{path}".to_string()),
+            ("X-Client-IP".to_string(), "{client_ip}".to_string()),
+            ("X-Correlation-Id".to_string(), "{request_id}".to_string()),
+        ]);
+        let request = axum::http::Request::builder()
+            .uri("/api/items")
+            .header("x-request-id", "abc-123")
+            .extension(ConnectInfo(SocketAddr::from(([198, 51, 100, 9], 40000))))
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_behind_trusted_hops() {
+        use tower::ServiceExt;
+        use wiremock::matchers::header;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(header("x-client-ip", "192.0.2.10"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        // Behind a CDN and a load balancer; the client forged the leading entry
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.trusted_hops = 2;
+        config.routes[0].headers =
+            HashMap::from([("X-Client-IP".to_string(), "{client_ip}".to_string())]);
+        let request = axum::http::Request::builder()
+            .uri("/api/items")
+            .header("x-forwarded-for", "1.1.1.1, 192.0.2.10, 198.51.100.20")
+            .extension(ConnectInfo(SocketAddr::from(([203, 0, 113, 5], 40000))))
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_forward_headers_modes() {
+        use tower::ServiceExt;
+        use wiremock::matchers::{header, header_exists};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        // Each mode answers with a distinct status so the assertions show which headers arrived
+        Mock::given(header_exists("cookie"))
+            .and(header_exists("authorization"))
+            .and(header("x-route", "set"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+        Mock::given(header_exists("authorization"))
+            .and(header("x-route", "set"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&upstream)
+            .await;
+        Mock::given(header("x-route", "set"))
+            .and(header("x-forwarded-for", "198.51.100.9"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&upstream)
+            .await;
+
+        let send = |forward_headers: ForwardHeaders| {
+            let mut config = create_retry_config(&upstream.uri(), 0);
+            config.routes[0].forward_headers = forward_headers;
+            config.routes[0].headers = HashMap::from([("X-Route".to_string(), "set".to_string())]);
+            let request = axum::http::Request::builder()
+                .uri("/api/items")
+                .header("authorization", "Bearer token")
+                .header("cookie", "session=1")
+                .header("connection", "keep-alive")
+                .extension(ConnectInfo(SocketAddr::from(([198, 51, 100, 9], 40000))))
+                .body(Body::empty())
+                .unwrap();
+            proxy_router(ProxyState::new(config)).oneshot(request)
+        };
+
+        let all = send(ForwardHeaders::All).await.unwrap();
+        assert_eq!(all.status(), StatusCode::OK);
+
+        // Hop-by-hop headers stay filtered even when allowlisted
+        let allowlist =
+            ForwardHeaders::Allowlist(vec!["authorization".to_string(), "connection".to_string()]);
+        let allowlisted = send(allowlist).await.unwrap();
+        assert_eq!(allowlisted.status(), StatusCode::CREATED);
+
+        let none = send(ForwardHeaders::None).await.unwrap();
+        assert_eq!(none.status(), StatusCode::NO_CONTENT);
+
+        let received = upstream.received_requests().await.unwrap();
+        assert!(received.iter().all(
+            |request| request.headers.get("connection") != Some(&"keep-alive".parse().unwrap())
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_header_sent_upstream() {
+        use tower::ServiceExt;
+        use wiremock::matchers::{header, header_exists};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(header(
+            "forwarded",
+            "for=198.51.100.9;host=gateway.example.com;proto=https",
+        ))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&upstream)
+        .await;
+        Mock::given(header_exists("forwarded"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&upstream)
+            .await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&upstream)
+            .await;
+
+        let request = |scheme: Option<Scheme>| {
+            let mut request = axum::http::Request::builder()
+                .uri("/api/items")
+                .header("host", "gateway.example.com")
+                .header("forwarded", "for=6.6.6.6")
+                .extension(ConnectInfo(SocketAddr::from(([198, 51, 100, 9], 40000))));
+            if let Some(scheme) = scheme {
+                request = request.extension(scheme);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.forwarded_header = true;
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request(Some(Scheme::HTTPS)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Disabled by default: the client's header passes through untouched
+        let config = create_retry_config(&upstream.uri(), 0);
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request(None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_request_id_generated_when_missing() {
+        let mut headers = HeaderMap::new();
+        assert!(!request_id(&headers).is_empty());
+
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("given"));
+        assert_eq!(request_id(&headers), "given");
+    }
+
+    #[tokio::test]
+    async fn test_upstream_stats_track_latency_and_errors() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/fail"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/fast"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+        for uri in ["/api/fast", "/api/fast", "/api/slow", "/api/fail"] {
+            send_request(state.clone(), Method::GET, uri).await;
+        }
+
+        let snapshot = state.upstream_stats.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        let stats = &snapshot[0];
+        assert_eq!(stats.upstream, upstream.uri());
+        assert_eq!(stats.requests, 4);
+        assert_eq!(stats.error_rate, 0.25);
+        assert!(stats.latency_p50_ms < 200.0);
+        assert!(stats.latency_p99_ms >= 200.0);
+    }
+
+    #[tokio::test]
+    async fn test_literal_user_agent_sent_upstream() {
+        use wiremock::matchers::header;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(header("user-agent", "custom/2.0"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].upstream_user_agent =
+            Some(UpstreamUserAgent::Literal("custom/2.0".to_string()));
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_continued_upstream_with_new_span() {
+        use crate::metrics::MetricsFormat;
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+
+        let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let request = axum::http::Request::builder()
+            .uri("/api/items")
+            .header("traceparent", incoming)
+            .header("tracestate", "vendor=value")
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        drop(response);
+
+        // Without a traceparent the gateway starts a new trace
+        let response = send_request(state.clone(), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        drop(response);
+
+        let received = upstream.received_requests().await.unwrap();
+        let traceparents: Vec<_> = received
+            .iter()
+            .map(|request| request.headers.get_all("traceparent").iter().count())
+            .collect();
+        assert_eq!(traceparents, [1, 1]);
+
+        let continued = TraceContext::from_headers(&received[0].headers).unwrap();
+        let parent = TraceContext::parse(incoming).unwrap();
+        assert_eq!(continued.trace_id(), parent.trace_id());
+        assert_ne!(continued.span_id(), parent.span_id());
+        assert!(continued.to_traceparent().ends_with("-01"));
+        assert_eq!(received[0].headers["tracestate"], "vendor=value");
+
+        let root = TraceContext::from_headers(&received[1].headers).unwrap();
+        assert_ne!(root.trace_id(), parent.trace_id());
+
+        // The latest request's latency bucket links to its trace
+        let metrics = state.metrics.render_as(MetricsFormat::OpenMetrics);
+        assert!(metrics.contains(&format!("trace_id=\"{}\"", root.trace_id())));
+    }
+
+    #[tokio::test]
+    async fn test_mirror_receives_copy_and_client_gets_primary_response() {
+        use tower::ServiceExt;
+        use wiremock::matchers::{body_string, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let primary = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("primary"))
+            .mount(&primary)
+            .await;
+        let mirror = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/items"))
+            .and(body_string("payload"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("mirror"))
+            .mount(&mirror)
+            .await;
+
+        let mut config = create_retry_config(&primary.uri(), 0);
+        config.routes[0].mirror_upstream = Some(mirror.uri());
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/api/items")
+            .body(Body::from("payload"))
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "primary");
+
+        // The mirror request is fire-and-forget, so wait for it to arrive
+        let mut mirrored = 0;
+        for _ in 0..50 {
+            mirrored = mirror.received_requests().await.unwrap().len();
+            if mirrored > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(mirrored, 1);
+    }
+
+    #[tokio::test]
+    async fn test_mirror_failure_does_not_affect_client() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let primary = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&primary)
+            .await;
+
+        let mut config = create_retry_config(&primary.uri(), 0);
+        config.routes[0].mirror_upstream = Some("http://127.0.0.1:1".to_string());
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_mirror_dropped_when_slots_exhausted() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let primary = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&primary)
+            .await;
+        let mirror = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mirror)
+            .await;
+
+        let mut config = create_retry_config(&primary.uri(), 0);
+        config.routes[0].mirror_upstream = Some(mirror.uri());
+        let state = ProxyState::new(config);
+        let _held = Arc::clone(&state.mirror_slots)
+            .acquire_many_owned(MAX_IN_FLIGHT_MIRROR_REQUESTS as u32)
+            .await
+            .unwrap();
+        let response = send_request(state, Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(mirror.received_requests().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_retry_after_value() {
+        assert_eq!(parse_retry_after_value("3"), Some(Duration::from_secs(3)));
+        assert_eq!(
+            parse_retry_after_value("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Duration::ZERO)
+        );
+        let future = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let delay = parse_retry_after_value(&future).unwrap();
+        assert!(delay > Duration::from_secs(50) && delay <= Duration::from_secs(60));
+        assert_eq!(parse_retry_after_value("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_waits_for_retry_after() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .mount(&upstream)
+            .await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 1));
+        let started = std::time::Instant::now();
+        let response = send_request(state, Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_beyond_limit_is_not_retried() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "120"))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 3));
+        let response = send_request(state, Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "120");
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+    }
+
+    // Helper to send a PUT with the given body through the proxy handler
+    async fn send_put(state: ProxyState, uri: &str, body: Vec<u8>) -> axum::response::Response {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method(Method::PUT)
+            .uri(uri)
+            .body(Body::from(body))
+            .unwrap();
+        proxy_router(state).oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_during_drain_get_shutdown_response() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(300)))
+            .mount(&upstream)
+            .await;
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+
+        // A request accepted before draining starts is allowed to finish
+        let in_flight = tokio::spawn(send_request(state.clone(), Method::GET, "/api/slow"));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        state.draining.cancel();
+
+        let response = send_request(state.clone(), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["connection"], "close");
+        assert_eq!(
+            response_text(response).await,
+            crate::constants::MSG_SHUTTING_DOWN
+        );
+
+        assert_eq!(in_flight.await.unwrap().status(), StatusCode::OK);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+
+        // The draining state survives a reload
+        let reloaded = state.reconfigure(create_retry_config(&upstream.uri(), 0));
+        let response = send_request(reloaded, Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_reload_keeps_latency_buckets() {
+        let mut config = create_retry_config("http://a:3000", 0);
+        config.server.metrics_latency_buckets = vec![0.1, 1.0];
+        let state = ProxyState::new(config.clone());
+
+        config.server.metrics_latency_buckets = vec![0.5, 5.0];
+        let reloaded = state.reconfigure(config);
+        assert_eq!(reloaded.config.server.metrics_latency_buckets, [0.1, 1.0]);
+        assert!(Arc::ptr_eq(&state.metrics, &reloaded.metrics));
+        assert!(reloaded.metrics.render().contains("le=\"1\""));
+    }
+
+    #[test]
+    fn test_upstream_state_survives_reload() {
+        use crate::config::HealthWeightingConfig;
+
+        let mut config = create_retry_config("http://a:3000", 0);
+        config.routes[0].upstreams = vec!["http://b:3000".to_string()];
+        config.routes[0].health_weighting = Some(HealthWeightingConfig {
+            eject_error_rate: Some(0.5),
+            ..Default::default()
+        });
+        let state = ProxyState::new(config.clone());
+        state
+            .upstream_stats
+            .eject("http://b:3000", Duration::from_secs(30));
+        state
+            .upstream_stats
+            .record("http://a:3000", Duration::from_millis(5), false);
+
+        // A reload that keeps both upstreams keeps b ejected
+        let mut reordered = config.clone();
+        reordered.routes[0].upstream = "http://b:3000".to_string();
+        reordered.routes[0].upstreams = vec!["http://a:3000".to_string()];
+        let reloaded = state.reconfigure(reordered);
+        assert!(reloaded.upstream_stats.is_ejected("http://b:3000"));
+        assert_eq!(
+            reloaded
+                .upstream_stats
+                .snapshot_for("http://a:3000")
+                .unwrap()
+                .requests,
+            1
+        );
+        for _ in 0..50 {
+            assert_eq!(
+                crate::balancer::choose_upstream(
+                    &reloaded.config.routes[0],
+                    &reloaded.upstream_stats
+                ),
+                Some("http://a:3000")
+            );
+        }
+
+        // Removing b forgets its ejection, so adding it back starts afresh
+        let mut without_b = config.clone();
+        without_b.routes[0].upstreams.clear();
+        let reloaded = reloaded.reconfigure(without_b);
+        assert!(reloaded
+            .upstream_stats
+            .snapshot_for("http://a:3000")
+            .is_some());
+        let reloaded = reloaded.reconfigure(config);
+        assert!(!reloaded.upstream_stats.is_ejected("http://b:3000"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_response_is_configurable() {
+        let mut config = create_retry_config("http://localhost:1", 0);
+        config.server.shutdown_status = 502;
+        config.server.shutdown_body = "Draining, try another instance".to_string();
+        let state = ProxyState::new(config);
+        state.draining.cancel();
+
+        let response = send_request(state, Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response_text(response).await,
+            "Draining, try another instance"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deny_rules_reject_before_routing() {
+        use crate::config::DenyRule;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.deny_rules = vec![
+            DenyRule {
+                methods: vec![],
+                path: Some(r"\.\./".to_string().try_into().unwrap()),
+            },
+            DenyRule {
+                methods: vec!["TRACE".to_string()],
+                path: None,
+            },
+        ];
+        let state = ProxyState::new(config);
+
+        for (method, uri) in [
+            (Method::GET, "/api/../etc/passwd"),
+            (Method::TRACE, "/api/items"),
+            // Unrouted requests are denied too, rather than reaching the 404
+            (Method::TRACE, "/unrouted"),
+        ] {
+            let response = send_request(state.clone(), method.clone(), uri).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{method} {uri}");
+            assert_eq!(response_text(response).await, MSG_REQUEST_DENIED);
+        }
+        assert!(upstream.received_requests().await.unwrap().is_empty());
+
+        for (method, uri) in [(Method::GET, "/api/items"), (Method::POST, "/api/a..b/")] {
+            let response = send_request(state.clone(), method.clone(), uri).await;
+            assert_eq!(response.status(), StatusCode::OK, "{method} {uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_priority_requests_reach_priority_pool_and_survive_shedding() {
+        use crate::config::{LoadSheddingConfig, PriorityConfig};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let standard = MockServer::start().await;
+        let fast = MockServer::start().await;
+        for upstream in [&standard, &fast] {
+            Mock::given(wiremock::matchers::any())
+                .respond_with(ResponseTemplate::new(200))
+                .mount(upstream)
+                .await;
+        }
+
+        let mut config = create_retry_config(&standard.uri(), 0);
+        config.server.load_shedding = Some(LoadSheddingConfig {
+            max_in_flight: Some(1),
+            max_latency_ms: None,
+        });
+        config.server.priority = Some(PriorityConfig {
+            header: "x-priority".to_string(),
+            bypass_shedding: vec!["high".to_string()],
+        });
+        config.routes[0].priority_upstreams =
+            HashMap::from([("high".to_string(), vec![fast.uri()])]);
+        let state = ProxyState::new(config);
+
+        let send = |priority: Option<&'static str>| {
+            use tower::ServiceExt;
+            let mut request = axum::http::Request::builder().uri("/api/items");
+            if let Some(priority) = priority {
+                request = request.header("x-priority", priority);
+            }
+            proxy_router(state.clone()).oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        // Below the limit, only high-priority requests use the priority pool
+        assert_eq!(send(None).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(send(Some("HIGH")).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(standard.received_requests().await.unwrap().len(), 1);
+        assert_eq!(fast.received_requests().await.unwrap().len(), 1);
+
+        // At the limit, other requests are shed while high-priority ones get through
+        let _busy = state.load_shedder.try_acquire().unwrap();
+        assert_eq!(
+            send(None).await.unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            send(Some("low")).await.unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(send(Some("high")).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(fast.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_in_flight_until_response_body_sent() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("streamed"))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+        let response = send_request(state.clone(), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.load_shedder.in_flight(), 1);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "streamed");
+        assert_eq!(state.load_shedder.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_requests_use_default_upstream() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let routed = MockServer::start().await;
+        let fallback = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("routed"))
+            .mount(&routed)
+            .await;
+        Mock::given(path("/other/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("fallback"))
+            .mount(&fallback)
+            .await;
+
+        let mut config = create_retry_config(&routed.uri(), 0);
+        config.routes[0].methods = vec!["GET".to_string()];
+        let unconfigured = ProxyState::new(config.clone());
+        config.server.default_upstream = Some(fallback.uri());
+        assert!(config.validate().is_ok());
+        let state = ProxyState::new(config);
+
+        // Configured routes keep precedence
+        let response = send_request(state.clone(), Method::GET, "/api/items").await;
+        assert_eq!(response_text(response).await, "routed");
+
+        let response = send_request(state.clone(), Method::GET, "/other/page").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "fallback");
+
+        // A route whose method does not match falls through as well
+        send_request(state, Method::POST, "/api/items").await;
+        let requests = fallback.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].url.path(), "/api/items");
+
+        let response = send_request(unconfigured, Method::GET, "/other/page").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_identical_concurrent_gets_are_coalesced() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("shared")
+                    .set_delay(Duration::from_millis(200)),
+            )
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].coalesce_requests = true;
+        let state = ProxyState::new(config);
+
+        let requests: Vec<_> = (0..25)
+            .map(|_| {
+                tokio::spawn(send_request(
+                    state.clone(),
+                    Method::GET,
+                    "/api/items?page=1",
+                ))
+            })
+            .collect();
+        for request in requests {
+            let response = request.await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response_text(response).await, "shared");
+        }
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+
+        // A different query is a different request
+        let response = send_request(state, Method::GET, "/api/items?page=2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_coalescing_keeps_ranges_and_encodings_apart() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("body")
+                    .set_delay(Duration::from_millis(200)),
+            )
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].coalesce_requests = true;
+        let state = ProxyState::new(config);
+
+        let variants = [
+            (header::RANGE, "bytes=0-1"),
+            (header::RANGE, "bytes=2-3"),
+            (header::ACCEPT_ENCODING, "gzip"),
+            (header::ACCEPT_ENCODING, "identity"),
+        ];
+        let requests: Vec<_> = variants
+            .into_iter()
+            .map(|(name, value)| {
+                let request = axum::http::Request::builder()
+                    .uri("/api/items")
+                    .header(name, value)
+                    .body(Body::empty())
+                    .unwrap();
+                tokio::spawn(proxy_router(state.clone()).oneshot(request))
+            })
+            .collect();
+        for request in requests {
+            let response = request.await.unwrap().unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let received = upstream.received_requests().await.unwrap();
+        assert_eq!(received.len(), 4);
+        let ranges: Vec<_> = received
+            .iter()
+            .filter_map(|request| request.headers.get("range"))
+            .collect();
+        assert_eq!(ranges.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_response_over_limit_is_rejected() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(vec![
+                        b'x';
+                        crate::constants::DEFAULT_MAX_RESPONSE_BODY_BYTES
+                            as usize
+                            + 1
+                    ])
+                    .set_delay(Duration::from_millis(100)),
+            )
+            .mount(&upstream)
+            .await;
+
+        // Streaming alone is unlimited, but sharing the response means buffering it
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.max_response_bytes = 0;
+        config.routes[0].coalesce_requests = true;
+        config.routes[0].response_mode = Some(ResponseMode::Stream);
+        let state = ProxyState::new(config);
+
+        let requests: Vec<_> = (0..5)
+            .map(|_| tokio::spawn(send_request(state.clone(), Method::GET, "/api/items")))
+            .collect();
+        for request in requests {
+            let response = request.await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+            assert_eq!(
+                response_text(response).await,
+                MSG_UPSTREAM_RESPONSE_TOO_LARGE
+            );
+        }
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_total_timeout_stops_retries_before_backoff_overruns() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&upstream)
+            .await;
+
+        // Backoffs of 100ms then 200ms: the second retry would end past 250ms
+        let mut config = create_retry_config(&upstream.uri(), 5);
+        config.routes[0].retry_backoff_ms = Some(100);
+        config.routes[0].total_timeout_ms = Some(250);
+        let started = Instant::now();
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+        assert!(started.elapsed() < Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn test_total_timeout_bounds_single_attempt_uploads() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(3)))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.max_retry_body_bytes = 4;
+        config.routes[0].total_timeout_ms = Some(200);
+        let app = proxy_router(ProxyState::new(config));
+
+        // A body over the retry buffer is streamed, one with Expect is held back
+        for expect in [None, Some("100-continue")] {
+            let mut request = axum::http::Request::builder()
+                .method(Method::PUT)
+                .uri("/api/upload");
+            if let Some(expect) = expect {
+                request = request.header("expect", expect);
+            }
+            let request = request.body(Body::from("larger payload")).unwrap();
+            let started = Instant::now();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT, "{expect:?}");
+            assert!(started.elapsed() < Duration::from_secs(1), "{expect:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_total_timeout_cuts_short_a_slow_attempt() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(1000)))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 3);
+        config.routes[0].timeout_ms = Some(2000);
+        config.routes[0].total_timeout_ms = Some(200);
+        let started = Instant::now();
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn test_client_timeout_header_tightens_upstream_timeout() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.client_timeout_header = Some("x-request-timeout-ms".to_string());
+        let state = ProxyState::new(config);
+        let send = |timeout: &'static str| {
+            let request = axum::http::Request::builder()
+                .uri("/api/items")
+                .header("x-request-timeout-ms", timeout)
+                .body(Body::empty())
+                .unwrap();
+            proxy_router(state.clone()).oneshot(request)
+        };
+
+        // A shorter client deadline cuts the slow upstream short
+        let started = Instant::now();
+        let response = send("100").await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        // Absurd, zero and malformed values fall back to the configured timeout
+        for timeout in ["99999999999", "0", "soon"] {
+            let response = send(timeout).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "timeout {timeout}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_small_request_body_is_buffered_and_retried() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&upstream)
+            .await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 1);
+        config.server.max_retry_body_bytes = 64;
+        let response = send_put(ProxyState::new(config), "/api/items", vec![b'a'; 64]).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let requests = upstream.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.body == vec![b'a'; 64]));
+    }
+
+    #[tokio::test]
+    async fn test_large_request_body_is_streamed_without_retries() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 3);
+        config.server.max_retry_body_bytes = 10;
+        let response = send_put(ProxyState::new(config), "/api/items", vec![b'b'; 100]).await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let requests = upstream.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].body, vec![b'b'; 100]);
+    }
+
+    async fn send_gzip_post(state: ProxyState, body: &[u8]) -> axum::response::Response {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        use tower::ServiceExt;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        let encoded = encoder.finish().unwrap();
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/api/items")
+            .header("content-encoding", "gzip")
+            .header("content-length", encoded.len())
+            .body(Body::from(encoded))
+            .unwrap();
+        proxy_router(state).oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gzip_request_body_is_decompressed() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].decompress_request = true;
+        let payload = br#"{"name":"widget","count":3}"#;
+        let response = send_gzip_post(ProxyState::new(config), payload).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let requests = upstream.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].body, payload.to_vec());
+        assert!(requests[0].headers.get("content-encoding").is_none());
+        assert_eq!(
+            requests[0].headers.get("content-length").unwrap(),
+            &payload.len().to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gzip_request_body_is_forwarded_encoded_without_opt_in() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let config = create_retry_config(&upstream.uri(), 0);
+        let response = send_gzip_post(ProxyState::new(config), b"left as is").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let requests = upstream.received_requests().await.unwrap();
+        assert_eq!(requests[0].headers.get("content-encoding").unwrap(), "gzip");
+        assert_ne!(requests[0].body, b"left as is".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_decompression_bomb_is_rejected() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].decompress_request = true;
+        config.routes[0].max_decompressed_bytes = Some(1024);
+        let state = ProxyState::new(config);
+
+        let response = send_gzip_post(state.clone(), &vec![0u8; 1_000_000]).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = {
+            use tower::ServiceExt;
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/api/items")
+                .header("content-encoding", "gzip")
+                .body(Body::from("not gzip"))
+                .unwrap();
+            proxy_router(state).oneshot(request).await.unwrap()
+        };
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(upstream.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_status_map_remaps_upstream_status() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/teapot"))
+            .respond_with(ResponseTemplate::new(418).set_body_string("short and stout"))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].status_map = HashMap::from([(418, 400), (500, 502)]);
+        let state = ProxyState::new(config);
+
+        let response = send_request(state.clone(), Method::GET, "/api/teapot").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response_text(response).await, "short and stout");
+
+        // Statuses without a mapping pass through unchanged
+        let response = send_request(state, Method::GET, "/api/missing").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_body_replacements_rewrite_matching_responses() {
+        use crate::config::BodyReplacement;
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let html = r#"<a href="http://internal.svc:8080/docs">docs</a>"#;
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(html, "text/html; charset=utf-8"))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/data"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(html, "application/octet-stream"))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/large"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(html.repeat(10), "text/html"))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].body_replacements = vec![BodyReplacement {
+            from: "http://internal.svc:8080".to_string(),
+            to: "https://example.com".to_string(),
+            content_types: vec!["text/*".to_string()],
+        }];
+        config.routes[0].max_replacement_body_bytes = Some(200);
+        let state = ProxyState::new(config);
+
+        let response = send_request(state.clone(), Method::GET, "/api/page").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let expected = r#"<a href="https://example.com/docs">docs</a>"#;
+        assert_eq!(
+            response.headers().get("content-length").unwrap(),
+            &expected.len().to_string()
+        );
+        assert_eq!(response_text(response).await, expected);
+
+        // Other content types and bodies over the cap pass through unchanged
+        let response = send_request(state.clone(), Method::GET, "/api/data").await;
+        assert_eq!(response_text(response).await, html);
+        let response = send_request(state, Method::GET, "/api/large").await;
+        assert_eq!(response_text(response).await, html.repeat(10));
+    }
+
+    #[tokio::test]
+    async fn test_gzipped_upstream_json_is_decompressed_and_rewritten() {
+        use crate::config::BodyReplacement;
+        use tower::ServiceExt;
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let json = r#"{"self":"http://internal.svc:8080/items/1"}"#;
+        let gzipped = |body: &[u8]| {
+            ResponseTemplate::new(200)
+                .insert_header("content-encoding", "gzip")
+                .set_body_raw(crate::decompress::gzip(body).to_vec(), "application/json")
+        };
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/item"))
+            .respond_with(gzipped(json.as_bytes()))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/bomb"))
+            .respond_with(gzipped(&vec![b' '; 1_000_000]))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/corrupt"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_raw("not gzip", "application/json"),
+            )
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].decompress_response = true;
+        config.routes[0].max_decompressed_bytes = Some(64 * 1024);
+        config.routes[0].body_replacements = vec![BodyReplacement {
+            from: "http://internal.svc:8080".to_string(),
+            to: "https://api.example.com".to_string(),
+            content_types: vec!["application/json".to_string()],
+        }];
+        let expected = r#"{"self":"https://api.example.com/items/1"}"#;
+
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/item").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(
+            response.headers()["content-length"],
+            expected.len().to_string()
+        );
+        assert_eq!(response_text(response).await, expected);
+
+        // Re-compressed only for clients that accept gzip
+        config.routes[0].recompress_response = true;
+        let state = ProxyState::new(config);
+        let get = |accept_encoding: &'static str| {
+            let request = axum::http::Request::builder()
+                .uri("/api/item")
+                .header("accept-encoding", accept_encoding)
+                .body(Body::empty())
+                .unwrap();
+            proxy_router(state.clone()).oneshot(request)
+        };
+        let response = get("gzip, br").await.unwrap();
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let decoded = decompress::decompress(&body, ContentCoding::Gzip, 1024).unwrap();
+        assert_eq!(&decoded[..], expected.as_bytes());
+
+        let response = get("identity").await.unwrap();
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response_text(response).await, expected);
+
+        // Decompression bombs and malformed bodies are refused
+        let response = send_request(state.clone(), Method::GET, "/api/bomb").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response_text(response).await,
+            MSG_UPSTREAM_RESPONSE_TOO_LARGE
+        );
+        let response = send_request(state, Method::GET, "/api/corrupt").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    // Helper to mount a two-hop redirect chain: /api/start -> /api/middle -> /api/end
+    async fn mount_redirect_chain(upstream: &wiremock::MockServer) {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, ResponseTemplate};
+
+        Mock::given(path("/api/start"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/api/middle"))
+            .mount(upstream)
+            .await;
+        Mock::given(path("/api/middle"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/api/end"))
+            .mount(upstream)
+            .await;
+        Mock::given(path("/api/end"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("done"))
+            .mount(upstream)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_forwarded_untouched_by_default() {
+        let upstream = wiremock::MockServer::start().await;
+        mount_redirect_chain(&upstream).await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+        let response = send_request(state, Method::GET, "/api/start").await;
+
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()["location"], "/api/middle");
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_client_identity_from_route() {
+        let mut route = RouteConfig::default();
+        assert_eq!(ClientIdentity::for_route(&route), ClientIdentity::default());
+
+        route.local_address = Some(IpAddr::from([127, 0, 0, 1]));
+        route.follow_redirects = true;
+        assert_eq!(
+            ClientIdentity::for_route(&route),
+            ClientIdentity {
+                max_redirects: Some(crate::constants::DEFAULT_MAX_REDIRECTS),
+                protocol: None,
+                local_address: Some(IpAddr::from([127, 0, 0, 1])),
+            }
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_local_address_sets_upstream_source_ip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // The whole 127.0.0.0/8 block is local on Linux
+        let source = IpAddr::from([127, 0, 0, 2]);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let upstream = tokio::spawn(async move {
+            let (mut stream, peer) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            peer.ip()
+        });
+
+        let mut config = create_retry_config(&format!("http://{addr}"), 0);
+        config.routes[0].local_address = Some(source);
+        let state = ProxyState::new(config);
+        let response = state
+            .client_for(Some(0))
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .unwrap();
+
+        assert!(response.status().is_success());
+        assert_eq!(upstream.await.unwrap(), source);
+    }
+
+    #[tokio::test]
+    async fn test_follow_redirects_up_to_max() {
+        let upstream = wiremock::MockServer::start().await;
+        mount_redirect_chain(&upstream).await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].follow_redirects = true;
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/start").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "done");
+
+        // A chain longer than the limit fails instead of returning the redirect
+        config.routes[0].max_redirects = Some(1);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/start").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_protocol_is_used_for_upstream_connection() {
+        // An upstream that reports the HTTP version each request arrived with
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        let echo_version = axum::routing::get(|request: axum::extract::Request| async move {
+            format!("{:?}", request.version())
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/{*path}", echo_version))
+                .await
+                .unwrap();
+        });
+
+        let mut config = create_retry_config(&upstream, 0);
+        for (protocol, expected) in [
+            (None, "HTTP/1.1"),
+            (Some(UpstreamProtocol::Http1), "HTTP/1.1"),
+            (Some(UpstreamProtocol::Http2), "HTTP/2.0"),
+        ] {
+            config.routes[0].upstream_protocol = protocol;
+            let response =
+                send_request(ProxyState::new(config.clone()), Method::GET, "/api/version").await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response_text(response).await, expected, "{protocol:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_translated_to_grpc_upstream() {
+        use base64::Engine;
+        use tower::ServiceExt;
+
+        // A gRPC upstream echoing each request message, with the status in trailers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        let echo = axum::routing::post(|request: axum::extract::Request| async move {
+            assert_eq!(request.version(), axum::http::Version::HTTP_2);
+            assert_eq!(request.headers()["content-type"], "application/grpc+proto");
+            assert_eq!(request.headers()["te"], "trailers");
+            let message = request.into_body().collect().await.unwrap().to_bytes();
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", HeaderValue::from_static("0"));
+            let frames = futures_util::stream::iter([
+                Ok::<_, std::convert::Infallible>(hyper::body::Frame::data(message)),
+                Ok(hyper::body::Frame::trailers(trailers)),
+            ]);
+            axum::response::Response::builder()
+                .header("content-type", "application/grpc+proto")
+                .body(Body::new(http_body_util::StreamBody::new(frames)))
+                .unwrap()
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/{*path}", echo))
+                .await
+                .unwrap();
+        });
+
+        let mut config = create_retry_config(&upstream, 0);
+        config.routes[0].grpc_web = true;
+        let state = ProxyState::new(config);
+        let call = |content_type: &'static str, body: Bytes| {
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/api/echo.Echo/Say")
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap();
+            proxy_router(state.clone()).oneshot(request)
+        };
+        let message = Bytes::from_static(b"\x00\x00\x00\x00\x05hello");
+        let trailer = b"\x80\x00\x00\x00\x0fgrpc-status:0\r\n";
+
+        let response = call("application/grpc-web+proto", message.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/grpc-web+proto"
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, [&message[..], trailer].concat());
+
+        // The text variant is base64 in both directions
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&message);
+        let response = call("application/grpc-web-text+proto", Bytes::from(encoded))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/grpc-web-text+proto"
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let decoded = crate::grpc_web::decode_text(&body).unwrap();
+        assert_eq!(decoded, [&message[..], trailer].concat());
+    }
+
+    #[tokio::test]
+    async fn test_grpc_web_upstream_unavailable() {
+        use tower::ServiceExt;
+
+        // Nothing listens on the upstream port
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let mut config = create_retry_config(&upstream, 0);
+        config.routes[0].grpc_web = true;
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/api/echo.Echo/Say")
+            .header("content-type", "application/grpc-web")
+            .body(Body::from(&b"\x00\x00\x00\x00\x00"[..]))
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response.headers()["grpc-status"], "14");
+    }
+
+    #[tokio::test]
+    async fn test_request_content_type_allowlist() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].allowed_request_content_types = vec!["application/json".to_string()];
+        let state = ProxyState::new(config);
+        let send = |content_type: Option<&'static str>, body: &'static str| {
+            let mut request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/api/items");
+            if let Some(content_type) = content_type {
+                request = request.header("content-type", content_type);
+            }
+            proxy_router(state.clone()).oneshot(request.body(Body::from(body)).unwrap())
+        };
+
+        let allowed = send(Some("Application/JSON; charset=utf-8"), "{}")
+            .await
+            .unwrap();
+        assert_eq!(allowed.status(), StatusCode::OK);
+        let without_body = send(None, "").await.unwrap();
+        assert_eq!(without_body.status(), StatusCode::OK);
+
+        let rejected = send(Some("application/xml"), "<a/>").await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(response_text(rejected).await, MSG_UNSUPPORTED_CONTENT_TYPE);
+        let untyped = send(None, "{}").await.unwrap();
+        assert_eq!(untyped.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_response_content_type_allowlist() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(path("/api/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("{}", "application/json"))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/html"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<html>", "text/html"))
+            .mount(&upstream)
+            .await;
+        Mock::given(path("/api/empty"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].allowed_response_content_types = vec!["application/*".to_string()];
+        let state = ProxyState::new(config);
+
+        let response = send_request(state.clone(), Method::GET, "/api/json").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "{}");
+
+        let response = send_request(state.clone(), Method::GET, "/api/empty").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = send_request(state, Method::GET, "/api/html").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response_text(response).await,
+            MSG_UPSTREAM_CONTENT_TYPE_NOT_ALLOWED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_via_and_server_response_headers() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("server", "nginx/1.25.3")
+                    .insert_header("via", "1.1 cdn"),
+            )
+            .mount(&upstream)
+            .await;
+
+        // By default the upstream's headers pass through untouched
+        let config = create_retry_config(&upstream.uri(), 0);
+        let response =
+            send_request(ProxyState::new(config.clone()), Method::GET, "/api/items").await;
+        assert_eq!(response.headers()["server"], "nginx/1.25.3");
+        assert_eq!(response.headers()["via"], "1.1 cdn");
+
+        let mut identified = config.clone();
+        identified.server.via = Some("ferragate".to_string());
+        identified.server.server_header = Some("FerraGate".to_string());
+        let response = send_request(ProxyState::new(identified), Method::GET, "/api/items").await;
+        assert_eq!(response.headers()["server"], "FerraGate");
+        assert_eq!(response.headers()["via"], "1.1 cdn, 1.1 ferragate");
+
+        let mut hidden = config;
+        hidden.server.server_header = Some(String::new());
+        let response = send_request(ProxyState::new(hidden), Method::GET, "/api/items").await;
+        assert!(response.headers().get("server").is_none());
+    }
+
+    #[test]
+    fn test_via_records_upstream_protocol_version() {
+        let server = ServerConfig {
+            via: Some("gw".to_string()),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        apply_gateway_response_headers(&server, Version::HTTP_2, &mut headers);
+        assert_eq!(headers["via"], "2 gw");
+        assert!(headers.get("server").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_upload_is_not_forwarded_or_answered() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&upstream)
+            .await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+        let app = proxy_router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // Promise 100 bytes, send 10, then hang up
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /api/upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 100\r\n\r\n\
+                  0123456789",
+            )
+            .await
+            .unwrap();
+        stream.shutdown().await.unwrap();
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+
+        // The request is recorded as closed by the client, and never reaches the upstream
+        let mut recorded = false;
+        for _ in 0..50 {
+            if state.metrics.render().contains("status=\"499\"") {
+                recorded = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(recorded);
+        assert!(!String::from_utf8_lossy(&response).contains("400 Bad Request"));
+        assert!(upstream.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_body_size_limit() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.server.max_request_body_bytes = Some(16);
+        let send_chunked = |config: GatewayConfig, chunks: &'static [&'static [u8]]| {
+            use tower::ServiceExt;
+            let body = futures_util::stream::iter(
+                chunks
+                    .iter()
+                    .map(|chunk| Ok::<_, std::io::Error>(Bytes::from_static(chunk))),
+            );
+            let request = axum::http::Request::builder()
+                .method(Method::PUT)
+                .uri("/api/items")
+                .body(Body::from_stream(body))
+                .unwrap();
+            proxy_router(ProxyState::new(config)).oneshot(request)
+        };
+
+        // Declared too large: rejected before anything is read
+        let response = send_put(ProxyState::new(config.clone()), "/api/items", vec![0; 17]).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response_text(response).await, MSG_REQUEST_BODY_TOO_LARGE);
+
+        // Growing too large while buffered for retries
+        let response = send_chunked(config.clone(), &[b"0123456789", b"abcdefghij"])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Growing too large while streamed upstream
+        let mut streaming = config.clone();
+        streaming.server.max_retry_body_bytes = 4;
+        let response = send_chunked(streaming, &[b"0123456789", b"abcdefghij"])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Within the limit
+        let response = send_chunked(config, &[b"0123456789", b"abcdef"])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_body_read_errors_are_classified() {
+        let disconnect = axum::Error::new(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        assert_eq!(
+            BodyReadError::classify(&disconnect),
+            BodyReadError::ClientDisconnected
+        );
+        let chunks = futures_util::stream::iter(
+            [Bytes::from_static(b"0123456789")].map(Ok::<_, std::io::Error>),
+        );
+        let mut limited = limit_request_body(Body::from_stream(chunks), Some(4)).unwrap();
+        let too_large = limited.frame().await.unwrap().unwrap_err();
+        assert_eq!(BodyReadError::classify(&too_large), BodyReadError::TooLarge);
+        let invalid = axum::Error::new(std::io::Error::other("bad chunk encoding"));
+        assert_eq!(BodyReadError::classify(&invalid), BodyReadError::Invalid);
+
+        assert_eq!(
+            BodyReadError::ClientDisconnected.into_response().status(),
+            CLIENT_CLOSED_REQUEST_STATUS
+        );
+        assert_eq!(
+            BodyReadError::Invalid.into_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_body_without_length_switches_to_streaming() {
+        let chunks = futures_util::stream::iter(
+            [
+                Bytes::from_static(b"0123456789"),
+                Bytes::from_static(b"abcdef"),
+            ]
+            .map(Ok::<_, std::io::Error>),
+        );
+        let body = read_request_body(Body::from_stream(chunks), 12)
+            .await
+            .unwrap();
+
+        assert!(matches!(body, RequestBody::Streaming(..)));
+        let RequestBody::Buffered(bytes) =
+            read_request_body(Body::from("short"), 12).await.unwrap()
+        else {
+            panic!("small body should be buffered");
+        };
+        assert_eq!(bytes, "short");
+    }
+
+    #[test]
+    fn test_select_upstream_canary_is_sticky_per_key() {
+        let config = create_retry_config("http://primary", 0);
+        let state = ProxyState::new(config.clone());
+        let mut route = config.routes[0].clone();
+        route.canary_upstream = Some("http://canary".to_string());
+        route.canary_percent = Some(50.0);
+        route.canary_key = Some("header:x-user-id".to_string());
+
+        let mut canary_users = 0;
+        for user in 0..200 {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-user-id", HeaderValue::from(user));
+            let selected = select_upstream(&state, &route, &headers, None);
+            assert_eq!(selected, select_upstream(&state, &route, &headers, None));
+            if selected == Some("http://canary") {
+                canary_users += 1;
+            }
+        }
+        assert!(canary_users > 50 && canary_users < 150);
+
+        // Requests without the key always go to the primary upstream
+        assert_eq!(
+            select_upstream(&state, &route, &HeaderMap::new(), None),
+            Some("http://primary")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_full_canary_routes_to_canary_upstream() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let primary = MockServer::start().await;
+        let canary = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("canary"))
+            .mount(&canary)
+            .await;
+
+        let mut config = create_retry_config(&primary.uri(), 0);
+        config.routes[0].canary_upstream = Some(canary.uri());
+        config.routes[0].canary_percent = Some(100.0);
+        config.routes[0].canary_key = Some("header:x-user-id".to_string());
+        let request = axum::http::Request::builder()
+            .uri("/api/items")
+            .header("x-user-id", "42")
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy_router(ProxyState::new(config))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(canary.received_requests().await.unwrap().len(), 1);
+        assert!(primary.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_byte_counts_recorded_for_known_payload() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("0123456789"))
+            .mount(&upstream)
+            .await;
+
+        let state = ProxyState::new(create_retry_config(&upstream.uri(), 0));
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/api/items")
+            .body(Body::from("x".repeat(1500)))
+            .unwrap();
+        let response = proxy_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The request is recorded once its response body has been sent
+        assert_eq!(state.metrics.response_bytes.count(), 0);
+        assert_eq!(response_text(response).await, "0123456789");
+        assert_eq!(state.metrics.request_bytes.sum(), 1500.0);
+        assert_eq!(state.metrics.response_bytes.sum(), 10.0);
+
+        // HEAD responses record zero body bytes
+        let response = send_request(state.clone(), Method::HEAD, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        drop(response);
+        assert_eq!(state.metrics.response_bytes.count(), 2);
+        assert_eq!(state.metrics.response_bytes.sum(), 10.0);
+
+        let rendered = state.metrics.render();
+        assert!(rendered.contains("ferragate_request_bytes_bucket{le=\"4096\"} 2"));
+        assert!(rendered.contains("ferragate_request_bytes_bucket{le=\"1024\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_byte_counts_recorded_for_streamed_bodies() {
+        use tower::ServiceExt;
+
+        // An upstream answering with a chunked body of unknown length
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        let chunked = axum::routing::put(|body: Body| async move {
+            let received = body.collect().await.unwrap().to_bytes();
+            assert_eq!(received.len(), 12);
+            let chunks = futures_util::stream::iter(
+                ["abc", "def", "gh"].map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk))),
+            );
+            Body::from_stream(chunks)
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/{*path}", chunked))
+                .await
+                .unwrap();
+        });
+
+        // A request body streamed past the retry buffer, without a Content-Length
+        let mut config = create_retry_config(&upstream, 0);
+        config.server.max_retry_body_bytes = 4;
+        let state = ProxyState::new(config);
+        let chunks = futures_util::stream::iter(
+            ["0123", "4567", "89ab"].map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk))),
+        );
+        let request = axum::http::Request::builder()
+            .method(Method::PUT)
+            .uri("/api/upload")
+            .body(Body::from_stream(chunks))
+            .unwrap();
+        let response = proxy_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+        assert_eq!(response_text(response).await, "abcdefgh");
+
+        assert_eq!(state.metrics.request_bytes.sum(), 12.0);
+        assert_eq!(state.metrics.response_bytes.sum(), 8.0);
+    }
+
+    async fn response_text(response: axum::response::Response) -> String {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_conditional_responses_stripped_to_not_modified() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "W/\"v1\"")
+                    .insert_header("Last-Modified", "Tue, 15 Oct 2024 08:00:00 GMT")
+                    .insert_header("Cache-Control", "max-age=60")
+                    .set_body_string("payload"),
+            )
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].strip_conditional_responses = true;
+        let app = proxy_router(ProxyState::new(config));
+        let send = |name: &'static str, value: &'static str| {
+            let request = axum::http::Request::builder()
+                .uri("/api/items")
+                .header(name, value)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        // A matching ETag gets a bodiless 304 that keeps the validators
+        let response = send("If-None-Match", "\"v0\", \"v1\"").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], "W/\"v1\"");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "max-age=60");
+        assert!(response.headers().get(header::CONTENT_TYPE).is_none());
+        assert_eq!(response_text(response).await, "");
+
+        // A different ETag gets the full response, even if the date would match
+        let response = send("If-None-Match", "\"v2\"").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "payload");
+
+        let response = send("If-Modified-Since", "Wed, 16 Oct 2024 08:00:00 GMT")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let response = send("If-Modified-Since", "Mon, 14 Oct 2024 08:00:00 GMT")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "payload");
+
+        // Every conditional request, including those answered with 304, reached the upstream
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_responses_not_stripped_by_default() {
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_string("payload"),
+            )
+            .mount(&upstream)
+            .await;
+
+        let app = proxy_router(ProxyState::new(create_retry_config(&upstream.uri(), 0)));
+        let request = axum::http::Request::builder()
+            .uri("/api/items")
+            .header("If-None-Match", "\"v1\"")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response_text(response).await, "payload");
+    }
+
+    #[tokio::test]
+    async fn test_all_upstreams_ejected_fails_fast() {
+        use crate::config::HealthWeightingConfig;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let first = MockServer::start().await;
+        let second = MockServer::start().await;
+        for upstream in [&first, &second] {
+            Mock::given(wiremock::matchers::any())
+                .respond_with(ResponseTemplate::new(500))
+                .expect(1)
+                .mount(upstream)
+                .await;
+        }
+        let mut config = create_retry_config(&first.uri(), 0);
+        config.routes[0].upstreams = vec![second.uri()];
+        config.routes[0].health_weighting = Some(HealthWeightingConfig {
+            eject_error_rate: Some(0.5),
+            min_requests: 1,
+            ..Default::default()
+        });
+        let state = ProxyState::new(config);
+
+        // Each upstream fails once and is ejected on the next pick
+        for _ in 0..2 {
+            let response = send_request(state.clone(), Method::GET, "/api/items").await;
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let started = Instant::now();
+        let response = send_request(state.clone(), Method::GET, "/api/items").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response_text(response).await,
+            format!("{MSG_NO_HEALTHY_UPSTREAM} for route /api/*")
+        );
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_upstream_timeout_returns_gateway_timeout() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].timeout_ms = Some(100);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(response_text(response).await, MSG_UPSTREAM_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_connection_refused_returns_sanitized_bad_gateway() {
+        let config = create_retry_config("http://127.0.0.1:1", 0);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response_text(response).await, MSG_UPSTREAM_CONNECT_FAILED);
+    }
+
+    #[tokio::test]
+    async fn test_tls_failure_returns_dedicated_message() {
+        use wiremock::MockServer;
+
+        // Speaking TLS to a plain HTTP server fails the handshake
+        let upstream = MockServer::start().await;
+        let https_upstream = upstream.uri().replace("http://", "https://");
+        let config = create_retry_config(&https_upstream, 0);
+        let response = send_request(ProxyState::new(config), Method::GET, "/api/items").await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response_text(response).await, MSG_UPSTREAM_TLS_FAILED);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_tiers_by_method() {
+        use crate::config::{RateLimitConfig, RateLimitTier};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_retry_config(&upstream.uri(), 0);
+        config.routes[0].rate_limit = Some(RateLimitConfig {
+            requests_per_second: 0.01,
+            burst: Some(2),
+            tiers: vec![RateLimitTier {
+                methods: vec!["POST".to_string()],
+                requests_per_second: 0.01,
+                burst: Some(1),
+            }],
+        });
+        let state = ProxyState::new(config);
+
+        let post = send_request(state.clone(), Method::POST, "/api/items").await;
+        assert_eq!(post.status(), StatusCode::OK);
+        let post = send_request(state.clone(), Method::POST, "/api/items").await;
+        assert_eq!(post.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        for _ in 0..2 {
+            let get = send_request(state.clone(), Method::GET, "/api/items").await;
+            assert_eq!(get.status(), StatusCode::OK);
+        }
+        let get = send_request(state.clone(), Method::GET, "/api/items").await;
+        assert_eq!(get.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(get.headers()["retry-after"], "1");
+    }
 }
@@ -0,0 +1,163 @@
+/// Token-bucket rate limiting for routes
+///
+/// Each rate-limited route owns a default bucket plus one bucket per method
+/// tier, so for example writes can be limited more strictly than reads.
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::config::{RateLimitConfig, RouteConfig};
+
+/// A classic token bucket refilled continuously at a fixed rate
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    ///
+    /// The burst defaults to the per-second rate, and is always at least 1.
+    pub fn new(requests_per_second: f64, burst: Option<u32>) -> Self {
+        let capacity = burst
+            .map(f64::from)
+            .unwrap_or(requests_per_second.ceil())
+            .max(1.0);
+        Self {
+            capacity,
+            refill_per_sec: requests_per_second,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token if one is available
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        state.last_refill = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Rate limiter for a single route, selecting a bucket by request method
+#[derive(Debug)]
+pub struct RouteRateLimiter {
+    default: TokenBucket,
+    tiers: Vec<(Vec<String>, TokenBucket)>,
+}
+
+impl RouteRateLimiter {
+    /// Create a limiter from the route's rate limit configuration
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            default: TokenBucket::new(config.requests_per_second, config.burst),
+            tiers: config
+                .tiers
+                .iter()
+                .map(|tier| {
+                    let methods = tier.methods.iter().map(|m| m.to_uppercase()).collect();
+                    (
+                        methods,
+                        TokenBucket::new(tier.requests_per_second, tier.burst),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Create a limiter for the route, if it has a rate limit configured
+    pub fn for_route(route: &RouteConfig) -> Option<Self> {
+        route.rate_limit.as_ref().map(Self::new)
+    }
+
+    /// Check whether a request with the given method is allowed
+    ///
+    /// Methods not listed in any tier fall through to the default bucket.
+    pub fn check(&self, method: &str) -> bool {
+        self.tiers
+            .iter()
+            .find(|(methods, _)| methods.iter().any(|m| m.eq_ignore_ascii_case(method)))
+            .map_or(&self.default, |(_, bucket)| bucket)
+            .try_acquire()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RateLimitTier;
+
+    #[test]
+    fn test_bucket_allows_burst_then_limits() {
+        let bucket = TokenBucket::new(1.0, Some(3));
+
+        assert!((0..3).all(|_| bucket.try_acquire()));
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let bucket = TokenBucket::new(100.0, Some(1));
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        assert!(bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_get_and_post_draw_from_separate_buckets() {
+        let limiter = RouteRateLimiter::new(&RateLimitConfig {
+            requests_per_second: 1.0,
+            burst: Some(3),
+            tiers: vec![RateLimitTier {
+                methods: vec!["post".to_string(), "PUT".to_string()],
+                requests_per_second: 1.0,
+                burst: Some(1),
+            }],
+        });
+
+        assert!(limiter.check("POST"));
+        assert!(!limiter.check("POST"));
+        assert!(!limiter.check("PUT"));
+
+        // GET still has its own, larger allowance
+        assert!((0..3).all(|_| limiter.check("GET")));
+        assert!(!limiter.check("GET"));
+    }
+
+    #[test]
+    fn test_unlisted_method_uses_default_tier() {
+        let limiter = RouteRateLimiter::new(&RateLimitConfig {
+            requests_per_second: 1.0,
+            burst: Some(1),
+            tiers: vec![RateLimitTier {
+                methods: vec!["POST".to_string()],
+                requests_per_second: 1.0,
+                burst: Some(5),
+            }],
+        });
+
+        assert!(limiter.check("DELETE"));
+        assert!(!limiter.check("GET"));
+        assert!(limiter.check("POST"));
+    }
+}
@@ -0,0 +1,395 @@
+/// Configuration hot reload
+///
+/// This module reloads the gateway configuration from disk without restarting the
+/// listeners. While a reload is in progress the readiness endpoint reports not
+/// ready, so orchestrators hold back new traffic until the new configuration is
+/// live. A failed reload keeps serving the previous configuration.
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::config::GatewayConfig;
+use crate::constants::CONFIG_POLL_JITTER_RATIO;
+use crate::error::{FerragateError, FerragateResult};
+use crate::health::AppState;
+use crate::proxy::ProxyState;
+
+/// Proxy state that can be replaced while the gateway is running
+///
+/// Each request takes a snapshot of the current state, so in-flight requests
+/// finish on the configuration they started with.
+#[derive(Clone)]
+pub struct SharedProxyState {
+    inner: Arc<RwLock<ProxyState>>,
+}
+
+impl SharedProxyState {
+    /// Wrap the initial proxy state
+    pub fn new(state: ProxyState) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(state)),
+        }
+    }
+
+    /// Get a snapshot of the current proxy state
+    pub fn current(&self) -> ProxyState {
+        self.inner.read().unwrap().clone()
+    }
+
+    /// Replace the proxy state used by new requests
+    fn replace(&self, state: ProxyState) {
+        *self.inner.write().unwrap() = state;
+    }
+}
+
+/// Reloads the configuration file and applies it to the running proxy
+#[derive(Clone)]
+pub struct ConfigReloader {
+    config_path: String,
+    proxy: SharedProxyState,
+    health: AppState,
+    /// Serializes reloads so overlapping ones cannot leave readiness cleared
+    reload_lock: Arc<Mutex<()>>,
+}
+
+impl ConfigReloader {
+    /// Create a reloader for the given configuration file
+    pub fn new(config_path: impl Into<String>, proxy: SharedProxyState, health: AppState) -> Self {
+        Self {
+            config_path: config_path.into(),
+            proxy,
+            health,
+            reload_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Reload the configuration file
+    pub fn reload(&self) -> FerragateResult<()> {
+        let path = self.config_path.clone();
+        self.reload_with(|| GatewayConfig::from_file(&path))
+    }
+
+    /// Reload only the separate routes file, keeping every other setting
+    ///
+    /// The server, TLS and logging settings of the running configuration are
+    /// kept as they are; only its route table is replaced.
+    pub fn reload_routes(&self) -> FerragateResult<()> {
+        let current = self.proxy.current().config.clone();
+        let routes_path = current.routes_file_path(&self.config_path).ok_or_else(|| {
+            FerragateError::config("No routes_file is configured; use reload instead")
+        })?;
+        self.reload_with(|| {
+            let mut config = GatewayConfig::clone(&current);
+            config.routes = GatewayConfig::read_routes_file(&routes_path)?;
+            config.validate()?;
+            Ok(config)
+        })
+    }
+
+    /// Reload using the given loader, gating readiness while it runs
+    ///
+    /// Readiness is cleared before the loader runs and restored afterwards,
+    /// whether or not the new configuration was applied.
+    pub fn reload_with<F>(&self, load: F) -> FerragateResult<()>
+    where
+        F: FnOnce() -> FerragateResult<GatewayConfig>,
+    {
+        let _reloading = self.reload_lock.lock().unwrap();
+        let was_ready = self.health.is_ready();
+        self.health.set_ready(false);
+        info!("Reloading configuration from: {}", self.config_path);
+
+        let result = load().map(|config| {
+            let current = self.proxy.current();
+            self.proxy.replace(current.reconfigure(config));
+        });
+
+        match &result {
+            Ok(()) => info!("Configuration reloaded from: {}", self.config_path),
+            Err(e) => warn!(
+                "Configuration reload failed, keeping current configuration: {}",
+                e
+            ),
+        }
+
+        self.health.set_ready(was_ready);
+        result
+    }
+
+    /// Poll the configuration file and reload it whenever it changes
+    ///
+    /// See [`FileWatcher`] for how changes are detected. Runs until `shutdown`
+    /// is cancelled.
+    pub async fn poll_config_file(
+        self,
+        interval: Duration,
+        debounce: Duration,
+        shutdown: CancellationToken,
+    ) {
+        let mut watcher = FileWatcher::new(&self.config_path, interval, debounce);
+        while watcher.changed(&shutdown).await {
+            info!("Configuration file changed, reloading...");
+            let _ = self.reload();
+        }
+    }
+}
+
+/// Detects changes to a file by polling it
+///
+/// The file's modification time and size are checked every `interval`, with a
+/// little jitter so a fleet of gateways does not poll shared storage in step.
+/// A change is only reported once the file has stayed the same for `debounce`,
+/// so a partially written file is not picked up.
+#[derive(Debug)]
+pub struct FileWatcher {
+    path: String,
+    interval: Duration,
+    debounce: Duration,
+    last_seen: Option<(SystemTime, u64)>,
+}
+
+impl FileWatcher {
+    /// Watch `path` for changes from its current state
+    pub fn new(path: impl Into<String>, interval: Duration, debounce: Duration) -> Self {
+        let path = path.into();
+        Self {
+            last_seen: file_fingerprint(&path),
+            path,
+            interval,
+            debounce,
+        }
+    }
+
+    /// Wait for the next settled change
+    ///
+    /// Returns false, without waiting further, once `shutdown` is cancelled.
+    pub async fn changed(&mut self, shutdown: &CancellationToken) -> bool {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return false,
+                _ = tokio::time::sleep(jittered(self.interval)) => {}
+            }
+
+            let mut current = file_fingerprint(&self.path);
+            if current.is_none() || current == self.last_seen {
+                continue;
+            }
+
+            // Wait for writes to settle before reporting the change
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return false,
+                    _ = tokio::time::sleep(self.debounce) => {}
+                }
+                let settled = file_fingerprint(&self.path);
+                if settled == current {
+                    break;
+                }
+                debug!("{} still changing, waiting for it to settle", self.path);
+                current = settled;
+            }
+
+            self.last_seen = current;
+            return true;
+        }
+    }
+}
+
+/// Modification time and size of a file, or None if it cannot be read
+fn file_fingerprint(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Spread a polling interval by up to `CONFIG_POLL_JITTER_RATIO` either way
+fn jittered(interval: Duration) -> Duration {
+    let factor = 1.0 + CONFIG_POLL_JITTER_RATIO * (fastrand::f64() * 2.0 - 1.0);
+    interval.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RouteConfig, ServerConfig};
+    use crate::error::FerragateError;
+
+    fn config_with_upstream(upstream: &str) -> GatewayConfig {
+        GatewayConfig {
+            server: ServerConfig::default(),
+            routes: vec![RouteConfig {
+                path: "/api/*".to_string(),
+                upstream: upstream.to_string(),
+                ..Default::default()
+            }],
+            logging: crate::config::LoggingConfig::default(),
+        }
+    }
+
+    fn create_reloader() -> (ConfigReloader, SharedProxyState, AppState) {
+        let proxy = SharedProxyState::new(ProxyState::new(config_with_upstream("http://old")));
+        let health = AppState::new();
+        let reloader = ConfigReloader::new("gateway.toml", proxy.clone(), health.clone());
+        (reloader, proxy, health)
+    }
+
+    #[test]
+    fn test_reload_gates_readiness_and_applies_config() {
+        let (reloader, proxy, health) = create_reloader();
+        assert!(health.is_ready());
+
+        reloader
+            .reload_with(|| {
+                assert!(!health.is_ready());
+                Ok(config_with_upstream("http://new"))
+            })
+            .unwrap();
+
+        assert!(health.is_ready());
+        assert_eq!(proxy.current().config.routes[0].upstream, "http://new");
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_old_config_and_stays_ready() {
+        let (reloader, proxy, health) = create_reloader();
+
+        let result = reloader.reload_with(|| {
+            assert!(!health.is_ready());
+            Err(FerragateError::config("invalid"))
+        });
+
+        assert!(result.is_err());
+        assert!(health.is_ready());
+        assert_eq!(proxy.current().config.routes[0].upstream, "http://old");
+    }
+
+    #[test]
+    fn test_reload_does_not_mark_unready_gateway_ready() {
+        let (reloader, _proxy, health) = create_reloader();
+        health.set_ready(false);
+
+        reloader
+            .reload_with(|| Ok(config_with_upstream("http://new")))
+            .unwrap();
+
+        assert!(!health.is_ready());
+    }
+
+    #[test]
+    fn test_reload_from_file() {
+        let (_reloader, proxy, health) = create_reloader();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            "[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"http://file\"\n",
+        )
+        .unwrap();
+
+        let reloader = ConfigReloader::new(file.path().to_str().unwrap(), proxy.clone(), health);
+        reloader.reload().unwrap();
+
+        assert_eq!(proxy.current().config.routes[0].upstream, "http://file");
+    }
+
+    #[test]
+    fn test_reload_routes_keeps_server_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("gateway.toml");
+        let routes_path = dir.path().join("routes.toml");
+        std::fs::write(
+            &config_path,
+            "[server]\nport = 9100\nroutes_file = \"routes.toml\"\n",
+        )
+        .unwrap();
+        let write_upstream = |upstream: &str| {
+            std::fs::write(
+                &routes_path,
+                format!("[[routes]]\npath = \"/api/*\"\nupstream = \"{upstream}\"\n"),
+            )
+            .unwrap();
+        };
+        write_upstream("http://first");
+
+        let config_path = config_path.to_str().unwrap();
+        let config = GatewayConfig::from_file(config_path).unwrap();
+        let proxy = SharedProxyState::new(ProxyState::new(config));
+        let health = AppState::new();
+        let reloader = ConfigReloader::new(config_path, proxy.clone(), health.clone());
+
+        // Server settings changed in the main file are not picked up
+        std::fs::write(
+            config_path,
+            "[server]\nport = 9200\nroutes_file = \"routes.toml\"\n",
+        )
+        .unwrap();
+        write_upstream("http://second");
+        reloader.reload_routes().unwrap();
+
+        let current = proxy.current();
+        assert_eq!(current.config.routes[0].upstream, "http://second");
+        assert_eq!(current.config.server.port, 9100);
+        assert!(health.is_ready());
+
+        // An invalid routes file keeps the running routes
+        write_upstream("not a url");
+        assert!(reloader.reload_routes().is_err());
+        assert_eq!(proxy.current().config.routes[0].upstream, "http://second");
+    }
+
+    #[test]
+    fn test_reload_routes_requires_routes_file() {
+        let (reloader, proxy, _health) = create_reloader();
+        let result = reloader.reload_routes();
+        assert!(matches!(result, Err(FerragateError::Config { .. })));
+        assert_eq!(proxy.current().config.routes[0].upstream, "http://old");
+    }
+
+    #[tokio::test]
+    async fn test_polling_reloads_changed_file() {
+        let (_reloader, proxy, health) = create_reloader();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let write_upstream = |upstream: &str| {
+            std::fs::write(
+                file.path(),
+                format!("[server]\n\n[[routes]]\npath = \"/api/*\"\nupstream = \"{upstream}\"\n"),
+            )
+            .unwrap();
+        };
+        write_upstream("http://old");
+
+        let shutdown = CancellationToken::new();
+        let reloader = ConfigReloader::new(file.path().to_str().unwrap(), proxy.clone(), health);
+        let poller = tokio::spawn(reloader.poll_config_file(
+            Duration::from_millis(20),
+            Duration::from_millis(20),
+            shutdown.clone(),
+        ));
+
+        // Unchanged files are not reloaded
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(proxy.current().config.routes[0].upstream, "http://old");
+
+        write_upstream("http://changed");
+        let reloaded = async {
+            while proxy.current().config.routes[0].upstream != "http://changed" {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), reloaded)
+            .await
+            .expect("changed configuration was not reloaded");
+
+        shutdown.cancel();
+        poller.await.unwrap();
+    }
+
+    #[test]
+    fn test_jittered_interval_stays_within_ratio() {
+        let interval = Duration::from_secs(10);
+        for _ in 0..100 {
+            let jittered = jittered(interval);
+            assert!(jittered >= interval.mul_f64(1.0 - CONFIG_POLL_JITTER_RATIO));
+            assert!(jittered <= interval.mul_f64(1.0 + CONFIG_POLL_JITTER_RATIO));
+        }
+    }
+}
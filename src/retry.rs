@@ -0,0 +1,157 @@
+/// Retry budget for upstream requests
+///
+/// This module implements a Finagle-style retry budget: retries are capped as a
+/// fraction of the requests seen over a sliding window, so that a spike in upstream
+/// failures cannot turn every request into several and overwhelm a recovering backend.
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Request and retry counts recorded during a single second of the window
+#[derive(Debug, Clone, Copy)]
+struct WindowSlot {
+    second: u64,
+    requests: u64,
+    retries: u64,
+}
+
+/// Global retry budget shared by all proxy handlers
+///
+/// Every proxied request deposits into the budget, and every retry withdraws from it.
+/// A retry is only allowed while the retries in the window stay below
+/// `min_retries + ratio * requests`.
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_retries: u64,
+    window_secs: u64,
+    started: Instant,
+    slots: Mutex<VecDeque<WindowSlot>>,
+}
+
+impl RetryBudget {
+    /// Create a new retry budget
+    ///
+    /// # Arguments
+    /// * `ratio` - Fraction of requests that may be retried (0.0 - 1.0)
+    /// * `window` - Length of the sliding window the ratio is computed over
+    /// * `min_retries` - Retries always allowed per window, regardless of traffic
+    pub fn new(ratio: f64, window: Duration, min_retries: u64) -> Self {
+        Self {
+            ratio: ratio.clamp(0.0, 1.0),
+            min_retries,
+            window_secs: window.as_secs().max(1),
+            started: Instant::now(),
+            slots: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a new request in the budget
+    pub fn record_request(&self) {
+        let now = self.current_second();
+        let mut slots = self.slots.lock().unwrap();
+        Self::current_slot(&mut slots, now, self.window_secs).requests += 1;
+    }
+
+    /// Try to withdraw a retry from the budget
+    ///
+    /// Returns true if the retry is allowed, false if the budget is exhausted.
+    pub fn try_withdraw(&self) -> bool {
+        let now = self.current_second();
+        let mut slots = self.slots.lock().unwrap();
+        Self::current_slot(&mut slots, now, self.window_secs);
+
+        let (requests, retries) = slots.iter().fold((0u64, 0u64), |(req, ret), slot| {
+            (req + slot.requests, ret + slot.retries)
+        });
+        let allowed = self.min_retries as f64 + self.ratio * requests as f64;
+
+        if (retries as f64) < allowed {
+            if let Some(slot) = slots.back_mut() {
+                slot.retries += 1;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn current_second(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Drop expired slots and return the slot for the current second
+    fn current_slot(
+        slots: &mut VecDeque<WindowSlot>,
+        now: u64,
+        window_secs: u64,
+    ) -> &mut WindowSlot {
+        while slots
+            .front()
+            .is_some_and(|slot| slot.second + window_secs <= now)
+        {
+            slots.pop_front();
+        }
+
+        if slots.back().is_none_or(|slot| slot.second != now) {
+            slots.push_back(WindowSlot {
+                second: now,
+                requests: 0,
+                retries: 0,
+            });
+        }
+
+        slots.back_mut().expect("current slot was just inserted")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_retries_allowed_without_traffic() {
+        let budget = RetryBudget::new(0.2, Duration::from_secs(10), 3);
+
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+    }
+
+    #[test]
+    fn test_budget_scales_with_requests() {
+        let budget = RetryBudget::new(0.1, Duration::from_secs(10), 0);
+
+        for _ in 0..100 {
+            budget.record_request();
+        }
+
+        let allowed = (0..100).filter(|_| budget.try_withdraw()).count();
+        assert_eq!(allowed, 10);
+    }
+
+    #[test]
+    fn test_zero_ratio_only_allows_minimum() {
+        let budget = RetryBudget::new(0.0, Duration::from_secs(10), 2);
+
+        for _ in 0..1000 {
+            budget.record_request();
+        }
+
+        let allowed = (0..100).filter(|_| budget.try_withdraw()).count();
+        assert_eq!(allowed, 2);
+    }
+
+    #[test]
+    fn test_ratio_is_clamped() {
+        let budget = RetryBudget::new(5.0, Duration::from_secs(10), 0);
+
+        for _ in 0..10 {
+            budget.record_request();
+        }
+
+        let allowed = (0..100).filter(|_| budget.try_withdraw()).count();
+        assert_eq!(allowed, 10);
+    }
+}
@@ -0,0 +1,172 @@
+/// Per-route concurrency limits with a bounded waiting queue
+///
+/// A slow but healthy upstream is better served by holding requests back
+/// briefly than by rejecting them outright. Each route with a `concurrency`
+/// setting forwards at most `max_concurrent` requests at once; further requests
+/// wait in line, first come first served, for up to `queue_timeout_ms`. Requests
+/// arriving while the queue is full, or still waiting at the timeout, are
+/// rejected. The number of waiting requests is exported as a metric.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{ConcurrencyConfig, RouteConfig};
+use crate::metrics::Metrics;
+
+/// Why a request did not get a concurrency slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueRejection {
+    /// Every slot was taken and the queue was full
+    QueueFull,
+    /// The request waited `queue_timeout_ms` without getting a slot
+    TimedOut,
+}
+
+/// Concurrency slots and waiting line for one route
+#[derive(Debug)]
+pub struct RouteQueue {
+    slots: Arc<Semaphore>,
+    queue_size: usize,
+    timeout: Duration,
+    waiting: AtomicUsize,
+}
+
+impl RouteQueue {
+    /// Create the queue for a route, or None if its concurrency is unlimited
+    pub fn for_route(route: &RouteConfig) -> Option<Self> {
+        route.concurrency.as_ref().map(Self::new)
+    }
+
+    /// Create a queue with the given limits
+    pub fn new(config: &ConcurrencyConfig) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(config.max_concurrent)),
+            queue_size: config.queue_size,
+            timeout: Duration::from_millis(config.queue_timeout_ms),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Wait for a concurrency slot, held until the returned permit is dropped
+    ///
+    /// Waiting requests are counted in the `route` queue depth metric.
+    /// Requests are granted slots in the order they started waiting.
+    pub async fn acquire(
+        &self,
+        metrics: &Metrics,
+        route: &str,
+    ) -> Result<OwnedSemaphorePermit, QueueRejection> {
+        if let Ok(permit) = Arc::clone(&self.slots).try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        if self.waiting.fetch_add(1, Ordering::AcqRel) >= self.queue_size {
+            self.waiting.fetch_sub(1, Ordering::AcqRel);
+            return Err(QueueRejection::QueueFull);
+        }
+        let _waiting = Waiting {
+            queue: self,
+            metrics,
+            route,
+        };
+        metrics.queue_entered(route);
+
+        match tokio::time::timeout(self.timeout, Arc::clone(&self.slots).acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(permit),
+            _ => Err(QueueRejection::TimedOut),
+        }
+    }
+
+    /// Number of requests currently waiting for a slot
+    #[allow(dead_code)] // Public API method
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Acquire)
+    }
+}
+
+/// Removes a request from the queue counts once it stops waiting, even if cancelled
+struct Waiting<'a> {
+    queue: &'a RouteQueue,
+    metrics: &'a Metrics,
+    route: &'a str,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.queue.waiting.fetch_sub(1, Ordering::AcqRel);
+        self.metrics.queue_left(self.route);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(max_concurrent: usize, queue_size: usize, queue_timeout_ms: u64) -> Arc<RouteQueue> {
+        Arc::new(RouteQueue::new(&ConcurrencyConfig {
+            max_concurrent,
+            queue_size,
+            queue_timeout_ms,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_proceeds_when_slot_frees() {
+        let queue = queue(1, 1, 5000);
+        let metrics = Arc::new(Metrics::new());
+        let held = queue.acquire(&metrics, "/api/*").await.unwrap();
+
+        let waiter = {
+            let (queue, metrics) = (Arc::clone(&queue), Arc::clone(&metrics));
+            tokio::spawn(async move { queue.acquire(&metrics, "/api/*").await.map(drop) })
+        };
+        while queue.waiting() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(metrics
+            .render()
+            .contains("ferragate_route_queue_depth{route=\"/api/*\"} 1"));
+
+        // The queue is full, so a third request is turned away at once
+        assert_eq!(
+            queue.acquire(&metrics, "/api/*").await.unwrap_err(),
+            QueueRejection::QueueFull
+        );
+
+        drop(held);
+        assert_eq!(waiter.await.unwrap(), Ok(()));
+        assert_eq!(queue.waiting(), 0);
+        assert!(metrics
+            .render()
+            .contains("ferragate_route_queue_depth{route=\"/api/*\"} 0"));
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_times_out() {
+        let queue = queue(1, 4, 50);
+        let metrics = Metrics::new();
+        let _held = queue.acquire(&metrics, "/api/*").await.unwrap();
+
+        let started = std::time::Instant::now();
+        assert_eq!(
+            queue.acquire(&metrics, "/api/*").await.unwrap_err(),
+            QueueRejection::TimedOut
+        );
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(queue.waiting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_no_queue_rejects_when_saturated() {
+        let queue = queue(2, 0, 1000);
+        let metrics = Metrics::new();
+        let _first = queue.acquire(&metrics, "/api/*").await.unwrap();
+        let _second = queue.acquire(&metrics, "/api/*").await.unwrap();
+
+        assert_eq!(
+            queue.acquire(&metrics, "/api/*").await.unwrap_err(),
+            QueueRejection::QueueFull
+        );
+    }
+}
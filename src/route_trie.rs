@@ -0,0 +1,206 @@
+/// Path index for matching requests against large route tables
+///
+/// Route paths are either exact (`/health`) or prefixes ending in `/*`, where
+/// `/api/*` matches every path starting with `/api`. The trie is keyed on the
+/// bytes of those paths, so finding the routes whose path matches a request
+/// walks the request path once instead of testing every route. Method, header,
+/// query and TLS rules are then checked on the candidates only. Candidates come
+/// back most specific first, as ranked by [`RouteConfig::specificity`], with
+/// ties kept in configuration order.
+use crate::config::RouteConfig;
+
+/// Byte trie over the paths of the enabled routes
+#[derive(Debug, Clone)]
+pub struct RouteTrie {
+    nodes: Vec<TrieNode>,
+    /// Enabled route indices, most specific first
+    order: Vec<usize>,
+    /// Position of each route in `order`, indexed by route
+    rank: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    /// Child nodes keyed by the next path byte, sorted by byte
+    children: Vec<(u8, usize)>,
+    /// Routes whose `/*` prefix ends at this node
+    prefix_routes: Vec<usize>,
+    /// Routes whose exact path ends at this node
+    exact_routes: Vec<usize>,
+}
+
+impl RouteTrie {
+    /// Index the enabled routes; disabled routes are never returned as candidates
+    pub fn new(routes: &[RouteConfig]) -> Self {
+        let mut order: Vec<usize> = (0..routes.len())
+            .filter(|&index| routes[index].enabled)
+            .collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(routes[index].specificity()));
+        let mut rank = vec![usize::MAX; routes.len()];
+        for (position, &index) in order.iter().enumerate() {
+            rank[index] = position;
+        }
+        let mut trie = Self {
+            nodes: vec![TrieNode::default()],
+            order,
+            rank,
+        };
+        for (index, route) in routes.iter().enumerate() {
+            if !route.enabled {
+                continue;
+            }
+            match route.path.strip_suffix("/*") {
+                Some(prefix) => {
+                    let node = trie.insert(prefix);
+                    trie.nodes[node].prefix_routes.push(index);
+                }
+                None => {
+                    let node = trie.insert(&route.path);
+                    trie.nodes[node].exact_routes.push(index);
+                }
+            }
+        }
+        trie
+    }
+
+    /// Node for the given key, created along with any missing ancestors
+    fn insert(&mut self, key: &str) -> usize {
+        let mut node = 0;
+        for &byte in key.as_bytes() {
+            node = match self.child(node, byte) {
+                Some(child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    let children = &mut self.nodes[node].children;
+                    let position = children.partition_point(|&(b, _)| b < byte);
+                    children.insert(position, (byte, child));
+                    child
+                }
+            };
+        }
+        node
+    }
+
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        let children = &self.nodes[node].children;
+        children
+            .binary_search_by_key(&byte, |&(b, _)| b)
+            .ok()
+            .map(|position| children[position].1)
+    }
+
+    /// Indices of the enabled routes in the order they are evaluated
+    pub fn evaluation_order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Indices of the routes whose path matches, most specific first
+    pub fn candidates(&self, path: &str) -> Vec<usize> {
+        let mut candidates = Vec::new();
+        let mut node = Some(0);
+        for &byte in path.as_bytes() {
+            let Some(current) = node else { break };
+            candidates.extend_from_slice(&self.nodes[current].prefix_routes);
+            node = self.child(current, byte);
+        }
+        if let Some(last) = node {
+            candidates.extend_from_slice(&self.nodes[last].prefix_routes);
+            candidates.extend_from_slice(&self.nodes[last].exact_routes);
+        }
+        candidates.sort_unstable_by_key(|&index| self.rank[index]);
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path: &str, enabled: bool) -> RouteConfig {
+        RouteConfig {
+            path: path.to_string(),
+            upstream: "http://backend:3000".to_string(),
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_candidates_match_linear_scan() {
+        let routes = vec![
+            route("/api/users/*", true),
+            route("/api/*", true),
+            route("/api", true),
+            route("/api/users/me", true),
+            route("/apiv2/*", true),
+            route("/health", true),
+            route("/health", false),
+            route("/static/*", false),
+            route("/*", true),
+            route("/", true),
+        ];
+        let trie = RouteTrie::new(&routes);
+
+        let paths = [
+            "",
+            "/",
+            "/api",
+            "/api/",
+            "/apiv2",
+            "/apiv2/items",
+            "/api/users",
+            "/api/users/me",
+            "/api/users/42",
+            "/health",
+            "/healthz",
+            "/static/app.js",
+            "/unknown",
+        ];
+        for path in paths {
+            let mut linear: Vec<usize> = routes
+                .iter()
+                .enumerate()
+                .filter(|(_, route)| route.enabled && route.matches_path(path))
+                .map(|(index, _)| index)
+                .collect();
+            linear.sort_by_key(|&index| std::cmp::Reverse(routes[index].specificity()));
+            assert_eq!(trie.candidates(path), linear, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_most_specific_route_wins() {
+        let routes = vec![
+            route("/api/*", true),
+            route("/api/users/*", true),
+            route("/api/users/me", true),
+            route("/api/users/*", true),
+        ];
+        let trie = RouteTrie::new(&routes);
+
+        assert_eq!(trie.candidates("/api/users/me"), [2, 1, 3, 0]);
+        assert_eq!(trie.candidates("/api/users/42"), [1, 3, 0]);
+        assert_eq!(trie.evaluation_order(), [2, 1, 3, 0]);
+        assert!(trie.candidates("/other").is_empty());
+    }
+
+    #[test]
+    fn test_large_route_table() {
+        let routes: Vec<RouteConfig> = (0..1000)
+            .flat_map(|i| {
+                [
+                    route(&format!("/service{i}/*"), true),
+                    route(&format!("/service{i}/health"), true),
+                ]
+            })
+            .collect();
+        let trie = RouteTrie::new(&routes);
+
+        // "/service9" is a byte prefix of "/service999", just as with `matches_path`
+        assert_eq!(trie.candidates("/service999/health"), [1999, 1998, 198, 18]);
+        assert_eq!(trie.candidates("/service12/items"), [24, 2]);
+        assert_eq!(trie.candidates("/service1000/"), [200, 20, 2]);
+        assert!(trie.candidates("/services").is_empty());
+    }
+}
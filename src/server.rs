@@ -1,26 +1,41 @@
 use axum::{
-    extract::Request,
-    response::Redirect,
-    routing::{any, get},
-    Router,
+    extract::{Request, State},
+    http::{uri::Scheme, StatusCode, Version},
+    middleware::{self, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    serve::ListenerExt,
+    Extension, Router,
 };
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
 use tokio_util::sync::CancellationToken;
 use tower_http::trace::TraceLayer;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
+use crate::admin::{route_order_handler, validate_config_handler};
+use crate::builtin_files::builtin_files_router;
 #[cfg_attr(not(test), allow(unused_imports))]
-use crate::config::{GatewayConfig, LoggingConfig, RouteConfig, ServerConfig};
+use crate::config::{GatewayConfig, HttpVersion, LoggingConfig, RouteConfig, ServerConfig};
 use crate::constants::{
-    CONTROL_SOCKET_BUFFER_SIZE, CONTROL_SOCKET_PREFIX, HEALTH_ENDPOINT, LIVENESS_ENDPOINT,
-    LOG_SERVER_SHUTDOWN, LOG_SERVER_STARTING, READINESS_ENDPOINT,
+    ADMIN_ROUTE_ORDER_ENDPOINT, ADMIN_UPSTREAMS_ENDPOINT, ADMIN_VALIDATE_ENDPOINT,
+    CONFIG_POLL_DEBOUNCE_MS, CONTROL_SOCKET_BUFFER_SIZE, CONTROL_SOCKET_FILE_PREFIX,
+    CONTROL_SOCKET_PREFIX, DEFAULT_STOP_TIMEOUT_SECS, HEALTH_ENDPOINT, LIVENESS_ENDPOINT,
+    LOG_SERVER_SHUTDOWN, LOG_SERVER_STARTING, METRICS_ENDPOINT, MSG_HTTP_VERSION_NOT_SUPPORTED,
+    READINESS_ENDPOINT,
 };
 use crate::error::{FerragateError, FerragateResult};
 use crate::health::{health_handler, liveness_handler, readiness_handler, AppState};
-use crate::proxy::{handle_not_found, proxy_handler, ProxyState};
+use crate::listener::{bind_listener, LimitedListener, TrackInFlight};
+use crate::metrics::metrics_handler;
+use crate::proxy::{handle_not_found, shared_proxy_router, ProxyState};
+use crate::reload::{ConfigReloader, SharedProxyState};
+use crate::shutdown::ShutdownHooks;
 use crate::tls;
+use crate::upstream_stats::upstreams_handler;
 
 fn write_pid_file(path: &str) -> FerragateResult<()> {
     use std::fs;
@@ -30,23 +45,88 @@ fn write_pid_file(path: &str) -> FerragateResult<()> {
     Ok(())
 }
 
-fn get_control_socket_path(config_path: &str) -> String {
-    #[cfg(unix)]
-    {
-        format!(
-            "{}{}.sock",
-            CONTROL_SOCKET_PREFIX,
-            config_path.replace(['/', '\\', '.'], "_")
-        )
+/// Identity of a gateway process, which names its PID file and control socket
+///
+/// Instances started from the same configuration file are told apart by an
+/// instance name; without one, the names derive from the configuration path
+/// alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    config_path: String,
+    name: Option<String>,
+    socket_dir: Option<String>,
+}
+
+impl Instance {
+    /// Instance using the configuration file at `config_path` (default: "gateway.toml")
+    pub fn new(config_path: Option<&str>, name: Option<&str>) -> Self {
+        Self {
+            config_path: config_path.unwrap_or("gateway.toml").to_string(),
+            name: name.map(|name| {
+                name.replace(
+                    |c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+                    "_",
+                )
+            }),
+            socket_dir: None,
+        }
     }
-    #[cfg(windows)]
-    {
-        // On Windows, we'll use a named pipe approach
-        format!(
-            "{}{}",
-            CONTROL_SOCKET_PREFIX,
-            config_path.replace(['/', '\\', '.', ':'], "_")
-        )
+
+    /// Place the control socket in `socket_dir` instead of the default directory
+    pub fn with_socket_dir(mut self, socket_dir: Option<String>) -> Self {
+        self.socket_dir = socket_dir;
+        self
+    }
+
+    /// Directory chosen for the control socket, if not the default
+    pub fn socket_dir(&self) -> Option<&str> {
+        self.socket_dir.as_deref()
+    }
+
+    /// Instance named after the address it binds
+    pub fn for_bind_address(config_path: Option<&str>, host: &str, port: u16) -> Self {
+        Self::new(config_path, Some(&format!("{host}:{port}")))
+    }
+
+    /// Path of the configuration file the instance loads
+    pub fn config_path(&self) -> &str {
+        &self.config_path
+    }
+
+    /// Path of the file holding the instance's process ID
+    pub fn pid_file(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{}.{}.pid", self.config_path, name),
+            None => format!("{}.pid", self.config_path),
+        }
+    }
+
+    /// Path of the instance's control socket
+    pub fn control_socket_path(&self) -> String {
+        let mut id = self.config_path.clone();
+        if let Some(name) = &self.name {
+            id = format!("{id}@{name}");
+        }
+        #[cfg(unix)]
+        {
+            let id = id.replace(['/', '\\', '.'], "_");
+            match &self.socket_dir {
+                Some(dir) => Path::new(dir)
+                    .join(format!("{CONTROL_SOCKET_FILE_PREFIX}{id}.sock"))
+                    .to_string_lossy()
+                    .into_owned(),
+                None => format!("{CONTROL_SOCKET_PREFIX}{id}.sock"),
+            }
+        }
+        #[cfg(windows)]
+        {
+            // On Windows, we'll use a named pipe approach
+            format!(
+                "{}{}",
+                CONTROL_SOCKET_PREFIX,
+                id.replace(['/', '\\', '.', ':'], "_")
+            )
+        }
     }
 }
 
@@ -54,6 +134,8 @@ fn get_control_socket_path(config_path: &str) -> String {
 async fn start_control_socket_listener(
     socket_path: String,
     shutdown_token: CancellationToken,
+    reloader: ConfigReloader,
+    health_state: AppState,
 ) -> FerragateResult<()> {
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::UnixListener;
@@ -78,6 +160,8 @@ async fn start_control_socket_listener(
                         info!("Received control connection");
 
                         let shutdown_token_clone = shutdown_token.clone();
+                        let reloader = reloader.clone();
+                        let health_state = health_state.clone();
                         tokio::spawn(async move {
                             let mut buffer = [0u8; CONTROL_SOCKET_BUFFER_SIZE];
                             match stream.read(&mut buffer).await {
@@ -91,6 +175,28 @@ async fn start_control_socket_listener(
                                         shutdown_token_clone.cancel();
                                     } else if command == "status" {
                                         let _ = stream.write_all(b"OK: Server running\n").await;
+                                    } else if command == "reload" {
+                                        info!("Received reload command via control socket");
+                                        let reply = match reloader.reload() {
+                                            Ok(()) => "OK: Configuration reloaded\n".to_string(),
+                                            Err(e) => format!("ERROR: {e}\n"),
+                                        };
+                                        let _ = stream.write_all(reply.as_bytes()).await;
+                                    } else if command == "reload-routes" {
+                                        info!("Received reload-routes command via control socket");
+                                        let reply = match reloader.reload_routes() {
+                                            Ok(()) => "OK: Routes reloaded\n".to_string(),
+                                            Err(e) => format!("ERROR: {e}\n"),
+                                        };
+                                        let _ = stream.write_all(reply.as_bytes()).await;
+                                    } else if command == "enter-maintenance" {
+                                        info!("Entering maintenance mode via control socket");
+                                        health_state.set_maintenance(true);
+                                        let _ = stream.write_all(b"OK: Maintenance mode enabled\n").await;
+                                    } else if command == "exit-maintenance" {
+                                        info!("Leaving maintenance mode via control socket");
+                                        health_state.set_maintenance(false);
+                                        let _ = stream.write_all(b"OK: Maintenance mode disabled\n").await;
                                     } else {
                                         let _ = stream.write_all(b"ERROR: Unknown command\n").await;
                                     }
@@ -120,6 +226,8 @@ async fn start_control_socket_listener(
 async fn start_control_socket_listener(
     _socket_path: String,
     shutdown_token: CancellationToken,
+    _reloader: ConfigReloader,
+    _health_state: AppState,
 ) -> FerragateResult<()> {
     // For Windows, we'll use a simpler file-based approach for now
     // This could be enhanced with named pipes in the future
@@ -128,144 +236,275 @@ async fn start_control_socket_listener(
     Ok(())
 }
 
-async fn shutdown_signal(shutdown_token: CancellationToken) {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
-        info!("Received Ctrl+C signal, shutting down gracefully...");
+/// Reload the configuration whenever the process receives SIGHUP
+#[cfg(unix)]
+async fn reload_on_sighup(reloader: ConfigReloader, shutdown_token: CancellationToken) {
+    let mut hangup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
     };
 
+    loop {
+        tokio::select! {
+            _ = shutdown_token.cancelled() => break,
+            _ = hangup.recv() => {
+                info!("Received SIGHUP signal, reloading configuration...");
+                let _ = reloader.reload();
+            }
+        }
+    }
+}
+
+/// What initiated a shutdown, which decides how long in-flight requests may drain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShutdownTrigger {
+    /// SIGINT (Ctrl+C) from an interactive session
+    Interrupt,
+    /// SIGTERM, typically from an orchestrator that already waits for the process
+    Terminate,
+    /// A stop request over the control socket
+    ControlSocket,
+}
+
+impl ShutdownTrigger {
+    /// Time in-flight requests are given to finish after this trigger
+    fn drain_timeout(self, server: &ServerConfig) -> Duration {
+        match self {
+            Self::Interrupt => Duration::from_secs(server.interrupt_drain_timeout_secs),
+            Self::Terminate | Self::ControlSocket => Duration::from_secs(server.drain_timeout_secs),
+        }
+    }
+}
+
+/// Shutdown signal handlers, installed up front so no signal is missed
+struct ShutdownSignals {
     #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
-        info!("Received terminate signal, shutting down gracefully...");
-    };
+    interrupt: signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: signal::unix::Signal,
+}
 
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
+impl ShutdownSignals {
+    fn install() -> std::io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            interrupt: signal::unix::signal(signal::unix::SignalKind::interrupt())?,
+            #[cfg(unix)]
+            terminate: signal::unix::signal(signal::unix::SignalKind::terminate())?,
+        })
+    }
 
-    let token_cancelled = async {
-        shutdown_token.cancelled().await;
-        info!("Received shutdown via control socket, shutting down gracefully...");
-    };
+    /// Wait for SIGINT, SIGTERM or a stop request over the control socket
+    async fn recv(&mut self, shutdown_token: &CancellationToken) -> ShutdownTrigger {
+        #[cfg(unix)]
+        let trigger = tokio::select! {
+            _ = self.interrupt.recv() => ShutdownTrigger::Interrupt,
+            _ = self.terminate.recv() => ShutdownTrigger::Terminate,
+            _ = shutdown_token.cancelled() => ShutdownTrigger::ControlSocket,
+        };
 
-    tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
-        _ = token_cancelled => {},
+        #[cfg(not(unix))]
+        let trigger = tokio::select! {
+            _ = signal::ctrl_c() => ShutdownTrigger::Interrupt,
+            _ = shutdown_token.cancelled() => ShutdownTrigger::ControlSocket,
+        };
+
+        match trigger {
+            ShutdownTrigger::Interrupt => info!("Received SIGINT (Ctrl+C), shutting down..."),
+            ShutdownTrigger::Terminate => info!("Received SIGTERM, shutting down gracefully..."),
+            ShutdownTrigger::ControlSocket => {
+                info!("Received shutdown via control socket, shutting down gracefully...")
+            }
+        }
+        trigger
     }
 }
 
-pub async fn start_server(config: GatewayConfig, config_path: Option<&str>) -> FerragateResult<()> {
+/// Fail unless `dir` is an existing directory the gateway can create files in
+fn check_socket_dir(dir: &str) -> FerragateResult<()> {
+    let probe = Path::new(dir).join(format!(
+        ".{CONTROL_SOCKET_FILE_PREFIX}probe_{}",
+        std::process::id()
+    ));
+    std::fs::File::create(&probe)
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|e| {
+            FerragateError::config(format!(
+                "Control socket directory {dir} is not writable: {e}"
+            ))
+        })
+}
+
+/// Log how a listener task ended
+fn log_server_exit(name: &str, result: Result<FerragateResult<()>, tokio::task::JoinError>) {
+    match result {
+        Ok(Ok(())) => info!("{} server shut down normally", name),
+        Ok(Err(e)) => error!("{} server error: {}", name, e),
+        Err(e) if e.is_cancelled() => {}
+        Err(e) => error!("{} server task panicked: {}", name, e),
+    }
+}
+
+pub async fn start_server(config: GatewayConfig, instance: &Instance) -> FerragateResult<()> {
+    start_server_with_hooks(config, instance, ShutdownHooks::new()).await
+}
+
+/// Start the gateway and run the given shutdown hooks before final cleanup
+///
+/// Hooks are awaited once the servers have stopped and the control socket has
+/// closed, but before the PID file is removed.
+pub async fn start_server_with_hooks(
+    config: GatewayConfig,
+    instance: &Instance,
+    shutdown_hooks: ShutdownHooks,
+) -> FerragateResult<()> {
     info!("{}", LOG_SERVER_STARTING);
 
+    // Refuse to start without a usable control socket, or `stop` could not reach us
+    if let Some(dir) = instance.socket_dir() {
+        check_socket_dir(dir)?;
+    }
+
     // Create a shutdown token for graceful shutdown coordination
     let shutdown_token = CancellationToken::new();
+    let mut shutdown_signals = ShutdownSignals::install()?;
 
     // Write PID file for graceful shutdown
-    let config_str = config_path.unwrap_or("gateway.toml");
-    let pid_file = format!("{config_str}.pid");
+    let pid_file = instance.pid_file();
     if let Err(e) = write_pid_file(&pid_file) {
         warn!("Failed to write PID file {}: {}", pid_file, e);
     }
 
-    // Start control socket listener for graceful shutdown
-    let socket_path = get_control_socket_path(config_str);
+    // Create proxy state, reloadable at runtime
+    let proxy_state = SharedProxyState::new(ProxyState::new(config.clone()));
+
+    // Create health state
+    let health_state = AppState::with_max_health_checks(config.server.max_health_checks);
+
+    info!("Application state initialized");
+
+    let reloader = ConfigReloader::new(
+        instance.config_path(),
+        proxy_state.clone(),
+        health_state.clone(),
+    );
+
+    // Start control socket listener for graceful shutdown and reloads
+    let socket_path = instance.control_socket_path();
     info!("Starting control socket listener at: {}", socket_path);
     let socket_shutdown_token = shutdown_token.clone();
+    let socket_reloader = reloader.clone();
+    let socket_health_state = health_state.clone();
     let socket_handle = tokio::spawn(async move {
-        if let Err(e) = start_control_socket_listener(socket_path, socket_shutdown_token).await {
+        if let Err(e) = start_control_socket_listener(
+            socket_path,
+            socket_shutdown_token,
+            socket_reloader,
+            socket_health_state,
+        )
+        .await
+        {
             warn!("Control socket listener error: {}", e);
         }
     });
 
-    // Create proxy state
-    let proxy_state = ProxyState::new(config.clone());
-
-    // Create health state
-    let health_state = AppState::new();
+    // Poll the configuration file for changes where signals are not available
+    if let Some(interval_secs) = config.server.config_poll_interval_secs {
+        info!(
+            "Polling configuration file for changes every {}s",
+            interval_secs
+        );
+        tokio::spawn(reloader.clone().poll_config_file(
+            Duration::from_secs(interval_secs),
+            Duration::from_millis(CONFIG_POLL_DEBOUNCE_MS),
+            shutdown_token.clone(),
+        ));
+    }
 
-    info!("Application state initialized");
+    #[cfg(unix)]
+    tokio::spawn(reload_on_sighup(reloader, shutdown_token.clone()));
 
     // Start background health check task
     let health_check_state = health_state.clone();
     tokio::spawn(async move {
         info!("Starting background health check task");
-        crate::health::health_check_background_task(health_check_state).await;
+        crate::health::supervise_health_check_task(health_check_state).await;
     });
 
+    // Probe the upstreams of routes with active health checks
+    tokio::spawn(crate::upstream_health::run_upstream_health_checks(
+        proxy_state.clone(),
+        shutdown_token.clone(),
+    ));
+
+    // Draining also turns away proxied requests still arriving on open connections
+    let drain_token = proxy_state.current().draining;
+
     // Build the router
-    let app = create_router_with_states(proxy_state, health_state);
-
-    // Check if TLS is enabled
-    if let Some(tls_config) = &config.server.tls {
-        if tls_config.enabled {
-            // Start both HTTP and HTTPS servers
-            let http_config = config.clone();
-            let https_config = config.clone();
-            let app_clone = app.clone();
-
-            let http_handle =
-                tokio::spawn(async move { start_http_server(http_config, app_clone).await });
-
-            let https_handle =
-                tokio::spawn(async move { start_https_server(https_config, app).await });
-
-            // Wait for either server to fail or shutdown signal
-            tokio::select! {
-                result = http_handle => {
-                    match result {
-                        Ok(Ok(())) => info!("HTTP server shut down normally"),
-                        Ok(Err(e)) => error!("HTTP server error: {}", e),
-                        Err(e) => error!("HTTP server task panicked: {}", e),
-                    }
-                }
-                result = https_handle => {
-                    match result {
-                        Ok(Ok(())) => info!("HTTPS server shut down normally"),
-                        Ok(Err(e)) => error!("HTTPS server error: {}", e),
-                        Err(e) => error!("HTTPS server task panicked: {}", e),
-                    }
-                }
-                _ = shutdown_signal(shutdown_token.clone()) => {
-                    info!("Received shutdown signal");
-                }
-            }
-        } else {
-            // TLS disabled, start only HTTP server
-            tokio::select! {
-                result = start_http_server(config, app) => {
-                    if let Err(e) = result {
-                        error!("HTTP server error: {}", e);
-                    }
-                }
-                _ = shutdown_signal(shutdown_token.clone()) => {
-                    info!("Received shutdown signal");
-                }
-            }
+    let app = create_router_with_states(proxy_state, health_state.clone());
+
+    // Start the listeners; they stop accepting once draining begins
+    let mut servers = tokio::task::JoinSet::new();
+    servers.spawn({
+        let (config, app, drain) = (config.clone(), app.clone(), drain_token.clone());
+        let health_state = health_state.clone();
+        async move {
+            let result = start_http_server(config, app, health_state, drain).await;
+            ("HTTP", result)
         }
-    } else {
-        // No TLS configuration, start only HTTP server
-        tokio::select! {
-            result = start_http_server(config, app) => {
-                if let Err(e) = result {
-                    error!("HTTP server error: {}", e);
-                }
+    });
+    if config.server.tls.as_ref().is_some_and(|tls| tls.enabled) {
+        servers.spawn({
+            let (config, drain) = (config.clone(), drain_token.clone());
+            async move { ("HTTPS", start_https_server(config, app, drain).await) }
+        });
+    }
+
+    // Wait for either server to fail or a shutdown signal
+    let trigger = tokio::select! {
+        Some(result) = servers.join_next() => {
+            match result {
+                Ok((name, result)) => log_server_exit(name, Ok(result)),
+                Err(e) => log_server_exit("Gateway", Err(e)),
             }
-            _ = shutdown_signal(shutdown_token.clone()) => {
-                info!("Received shutdown signal");
+            None
+        }
+        trigger = shutdown_signals.recv(&shutdown_token) => Some(trigger),
+    };
+
+    // Let in-flight requests finish, up to the trigger's drain timeout
+    drain_token.cancel();
+    let drain_timeout = trigger.map_or(Duration::ZERO, |trigger| {
+        trigger.drain_timeout(&config.server)
+    });
+    info!("Draining in-flight requests for up to {:?}", drain_timeout);
+    let drained = tokio::time::timeout(drain_timeout, async {
+        while let Some(result) = servers.join_next().await {
+            match result {
+                Ok((name, result)) => log_server_exit(name, Ok(result)),
+                Err(e) => log_server_exit("Gateway", Err(e)),
             }
         }
+    })
+    .await;
+    if drained.is_err() {
+        warn!(
+            "Drain timed out after {:?}, closing remaining connections",
+            drain_timeout
+        );
+        servers.shutdown().await;
     }
 
     // Clean up: cancel all tasks and wait for them to finish
     shutdown_token.cancel();
     let _ = socket_handle.await;
 
+    // Let embedding users flush their own resources
+    shutdown_hooks.run().await;
+
     info!("{}", LOG_SERVER_SHUTDOWN);
 
     // Clean up PID file on shutdown
@@ -278,7 +517,12 @@ pub async fn start_server(config: GatewayConfig, config_path: Option<&str>) -> F
     Ok(())
 }
 
-async fn start_http_server(config: GatewayConfig, app: Router) -> FerragateResult<()> {
+async fn start_http_server(
+    config: GatewayConfig,
+    app: Router,
+    health_state: AppState,
+    drain: CancellationToken,
+) -> FerragateResult<()> {
     let addr = SocketAddr::from((
         config
             .server
@@ -288,7 +532,24 @@ async fn start_http_server(config: GatewayConfig, app: Router) -> FerragateResul
         config.server.port,
     ));
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    // Serve from the socket passed by systemd when socket-activated
+    let (tcp_listener, addr) = match crate::socket_activation::take_inherited_listener()? {
+        Some(inherited) => {
+            let listener = tokio::net::TcpListener::from_std(inherited)?;
+            let addr = listener.local_addr()?;
+            info!("🔌 Using socket-activated listener on {}", addr);
+            (listener, addr)
+        }
+        None => (bind_listener(addr, config.server.listen_backlog)?, addr),
+    };
+    if config.server.loopback_health_check {
+        let url = loopback_url(tcp_listener.local_addr()?);
+        info!("Registering loopback health check against {}", url);
+        health_state.register_loopback_check(url);
+    }
+    let listener = LimitedListener::new(tcp_listener, &config.server)
+        // tap_io gives the wrapped listener axum's ConnectInfo<SocketAddr> support
+        .tap_io(|_| {});
 
     // Log startup information
     info!("🌐 HTTP server running on http://{}", addr);
@@ -307,8 +568,17 @@ async fn start_http_server(config: GatewayConfig, app: Router) -> FerragateResul
         app
     };
 
+    let app = with_http_version_policy(app, &config.server.http_versions, false);
+
     // Start the HTTP server
-    if let Err(e) = axum::serve(listener, app).await {
+    // Counting each connection's requests keeps it open while they wait on upstreams
+    if let Err(e) = axum::serve(
+        listener,
+        TrackInFlight(app.into_make_service_with_connect_info::<SocketAddr>()),
+    )
+    .with_graceful_shutdown(drain.cancelled_owned())
+    .await
+    {
         error!("HTTP Server error: {}", e);
         return Err(e.into());
     }
@@ -316,7 +586,25 @@ async fn start_http_server(config: GatewayConfig, app: Router) -> FerragateResul
     Ok(())
 }
 
-async fn start_https_server(config: GatewayConfig, app: Router) -> FerragateResult<()> {
+/// URL of the liveness endpoint on a listener, as reached from the same host
+fn loopback_url(addr: SocketAddr) -> String {
+    let ip = match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    format!(
+        "http://{}{}",
+        SocketAddr::new(ip, addr.port()),
+        LIVENESS_ENDPOINT
+    )
+}
+
+async fn start_https_server(
+    config: GatewayConfig,
+    app: Router,
+    drain: CancellationToken,
+) -> FerragateResult<()> {
     let tls_config = config
         .server
         .tls
@@ -332,10 +620,17 @@ async fn start_https_server(config: GatewayConfig, app: Router) -> FerragateResu
         tls_config.port,
     ));
 
-    // Generate self-signed certificates if they don't exist
+    // Generate self-signed certificates if they don't exist and generation is allowed
     if !std::path::Path::new(&tls_config.cert_file).exists()
         || !std::path::Path::new(&tls_config.key_file).exists()
     {
+        if !tls_config.auto_generate_cert {
+            return Err(FerragateError::tls(format!(
+                "Certificate files not found ({}, {}) and auto_generate_cert is disabled",
+                tls_config.cert_file, tls_config.key_file
+            )));
+        }
+
         // Create certs directory if it doesn't exist
         if let Some(parent) = std::path::Path::new(&tls_config.cert_file).parent() {
             std::fs::create_dir_all(parent)?;
@@ -346,15 +641,39 @@ async fn start_https_server(config: GatewayConfig, app: Router) -> FerragateResu
     }
 
     // Load TLS configuration
-    let rustls_config = tls::load_tls_config(&tls_config.cert_file, &tls_config.key_file).await?;
+    let rustls_config = match &tls_config.client_ca_file {
+        Some(client_ca_file) => tls::load_mutual_tls_config(
+            &tls_config.cert_file,
+            &tls_config.key_file,
+            client_ca_file,
+            tls_config.require_client_cert,
+        )?,
+        None => tls::load_tls_config(&tls_config.cert_file, &tls_config.key_file).await?,
+    };
+    tls::set_alpn_protocols(&rustls_config, &config.server.http_versions);
+    let app = with_http_version_policy(app, &config.server.http_versions, true)
+        // Lets the proxy report the scheme clients used in the Forwarded header
+        .layer(Extension(Scheme::HTTPS));
 
     info!("🔒 HTTPS server running on https://{}", addr);
     log_routes_info(&config);
     log_health_endpoints(&addr, true);
 
-    // Start the HTTPS server
-    if let Err(e) = axum_server::bind_rustls(addr, rustls_config)
-        .serve(app.into_make_service())
+    // Stop accepting connections once draining begins
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            drain.cancelled().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+
+    // Start the HTTPS server, attaching each connection's TLS details to its requests
+    if let Err(e) = axum_server::bind(addr)
+        .acceptor(tls::TlsInfoAcceptor::new(rustls_config))
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
     {
         error!("HTTPS Server error: {}", e);
@@ -364,6 +683,90 @@ async fn start_https_server(config: GatewayConfig, app: Router) -> FerragateResu
     Ok(())
 }
 
+/// HTTP versions accepted by a single listener
+#[derive(Clone)]
+struct HttpVersionPolicy {
+    allowed: Arc<[HttpVersion]>,
+    tls: bool,
+}
+
+impl HttpVersionPolicy {
+    fn new(allowed: &[HttpVersion], tls: bool) -> Self {
+        Self {
+            allowed: allowed.into(),
+            tls,
+        }
+    }
+
+    /// Check whether requests of the given version are accepted
+    ///
+    /// HTTP/2 counts as `h2` on the TLS listener and as `h2c` on the plain one.
+    fn allows(&self, version: Version) -> bool {
+        let version = match version {
+            Version::HTTP_10 => HttpVersion::Http10,
+            Version::HTTP_11 => HttpVersion::Http11,
+            Version::HTTP_2 if self.tls => HttpVersion::H2,
+            Version::HTTP_2 => HttpVersion::H2c,
+            _ => return false,
+        };
+        self.allowed.contains(&version)
+    }
+}
+
+/// Reject requests whose HTTP version is not enabled on the listener
+async fn enforce_http_version(
+    State(policy): State<HttpVersionPolicy>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if policy.allows(request.version()) {
+        return next.run(request).await;
+    }
+
+    warn!(
+        "Rejecting {:?} request to {}: HTTP version not enabled",
+        request.version(),
+        request.uri().path()
+    );
+    (
+        StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+        MSG_HTTP_VERSION_NOT_SUPPORTED,
+    )
+        .into_response()
+}
+
+/// State for rejecting proxied requests while in maintenance mode
+#[derive(Clone)]
+struct MaintenanceGuard {
+    health: AppState,
+    proxy: SharedProxyState,
+}
+
+/// Reject proxied requests with the configured body while in maintenance mode
+async fn reject_during_maintenance(
+    State(guard): State<MaintenanceGuard>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !guard.health.in_maintenance() {
+        return next.run(request).await;
+    }
+
+    debug!(
+        "Rejecting request to {} during maintenance",
+        request.uri().path()
+    );
+    let body = guard.proxy.current().config.server.maintenance_body.clone();
+    (StatusCode::SERVICE_UNAVAILABLE, body).into_response()
+}
+
+fn with_http_version_policy(app: Router, allowed: &[HttpVersion], tls: bool) -> Router {
+    app.layer(middleware::from_fn_with_state(
+        HttpVersionPolicy::new(allowed, tls),
+        enforce_http_version,
+    ))
+}
+
 fn create_redirect_router(https_port: u16) -> Router {
     Router::new().fallback(move |request: Request| async move {
         let host = request
@@ -402,18 +805,48 @@ fn create_redirect_router(https_port: u16) -> Router {
     })
 }
 
-fn create_router_with_states(proxy_state: ProxyState, health_state: AppState) -> Router {
+fn create_router_with_states(proxy_state: SharedProxyState, health_state: AppState) -> Router {
+    let span_uri = proxy_state.current().config.logging.span_fields.uri;
     Router::new()
         // Health endpoints (using health state)
         .route(HEALTH_ENDPOINT, get(health_handler))
         .route(LIVENESS_ENDPOINT, get(liveness_handler))
         .route(READINESS_ENDPOINT, get(readiness_handler))
-        .with_state(health_state)
-        // Proxy routes (using proxy state)
-        .route("/{*path}", any(proxy_handler))
-        .with_state(proxy_state)
-        // Request tracing
-        .layer(TraceLayer::new_for_http())
+        .with_state(health_state.clone())
+        // Metrics endpoint (using the proxy's metrics registry)
+        .route(METRICS_ENDPOINT, get(metrics_handler))
+        .with_state(proxy_state.current().metrics)
+        // Admin endpoints (using the proxy's upstream statistics)
+        .route(ADMIN_UPSTREAMS_ENDPOINT, get(upstreams_handler))
+        .with_state(proxy_state.current().upstream_stats)
+        // Route matching order, following reloads of the proxy state
+        .route(ADMIN_ROUTE_ORDER_ENDPOINT, get(route_order_handler))
+        .with_state(proxy_state.clone())
+        // Configuration pre-flight, never applied to the running gateway
+        .route(ADMIN_VALIDATE_ENDPOINT, post(validate_config_handler))
+        // Built-in favicon and robots.txt, answered before any route is matched
+        .merge(builtin_files_router(&proxy_state.current().config.server))
+        // Proxy routes (using the reloadable proxy state), closed during maintenance
+        .merge(shared_proxy_router(proxy_state.clone()).route_layer(
+            middleware::from_fn_with_state(
+                MaintenanceGuard {
+                    health: health_state.clone(),
+                    proxy: proxy_state,
+                },
+                reject_during_maintenance,
+            ),
+        ))
+        // Request tracing, recording the URI as configured for proxy spans
+        .layer(TraceLayer::new_for_http().make_span_with(
+            move |request: &axum::extract::Request| {
+                tracing::debug_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %span_uri.render(request.uri()),
+                    version = ?request.version(),
+                )
+            },
+        ))
         // Fallback for unmatched routes
         .fallback(handle_not_found)
 }
@@ -440,14 +873,21 @@ fn log_health_endpoints(addr: &SocketAddr, is_https: bool) {
     info!("🔧 Background health checks running every 30 seconds");
 }
 
-/// Stop a running FerraGate server gracefully using control socket
-pub async fn stop_server(config_path: Option<&str>, force: bool) -> FerragateResult<()> {
+/// Stop a running gateway instance
+///
+/// `timeout` bounds how long a graceful stop may take before the process is
+/// killed; it defaults to `DEFAULT_STOP_TIMEOUT_SECS`. A zero timeout leaves no
+/// time for a graceful stop, so the process is killed at once, as with `force`.
+pub async fn stop_server(
+    instance: &Instance,
+    force: bool,
+    timeout: Option<Duration>,
+) -> FerragateResult<()> {
+    let timeout = timeout.unwrap_or(Duration::from_secs(DEFAULT_STOP_TIMEOUT_SECS));
     info!("Attempting to stop FerraGate server...");
 
-    let config_str = config_path.unwrap_or("gateway.toml");
-
     // Try to find the PID file first
-    let pid_file = format!("{config_str}.pid");
+    let pid_file = instance.pid_file();
 
     if !Path::new(&pid_file).exists() {
         info!("No PID file found. Server might not be running.");
@@ -455,25 +895,39 @@ pub async fn stop_server(config_path: Option<&str>, force: bool) -> FerragateRes
     }
 
     // Try control socket communication first
-    let socket_path = get_control_socket_path(config_str);
+    let socket_path = instance.control_socket_path();
 
     #[cfg(unix)]
     {
         if let Ok(()) = send_shutdown_command(&socket_path, force).await {
-            // Wait a moment for graceful shutdown
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-            // Check if the server actually stopped by checking PID file
-            if !Path::new(&pid_file).exists() {
+            // The server removes its PID file once it has stopped
+            if wait_for_removal(&pid_file, timeout).await {
                 info!("✅ FerraGate server stopped gracefully!");
                 return Ok(());
             }
+            warn!("Graceful shutdown timed out, killing the process...");
+            return stop_server_by_pid(&pid_file, true, timeout).await;
         }
     }
 
     // If control socket failed, fall back to PID-based stopping
     warn!("Control socket communication failed, falling back to PID-based shutdown");
-    stop_server_by_pid(&pid_file, force).await
+    stop_server_by_pid(&pid_file, force, timeout).await
+}
+
+/// Wait up to `timeout` for a file to be removed, returning whether it was
+#[cfg_attr(not(unix), allow(dead_code))]
+async fn wait_for_removal(path: &str, timeout: Duration) -> bool {
+    let started = std::time::Instant::now();
+    loop {
+        if !Path::new(path).exists() {
+            return true;
+        }
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
 }
 
 #[cfg(unix)]
@@ -511,7 +965,7 @@ async fn send_shutdown_command(_socket_path: &str, _force: bool) -> FerragateRes
     ))
 }
 
-async fn stop_server_by_pid(pid_file: &str, force: bool) -> FerragateResult<()> {
+async fn stop_server_by_pid(pid_file: &str, force: bool, timeout: Duration) -> FerragateResult<()> {
     use std::fs;
 
     if let Ok(pid_content) = fs::read_to_string(pid_file) {
@@ -521,12 +975,12 @@ async fn stop_server_by_pid(pid_file: &str, force: bool) -> FerragateResult<()>
             // Use a more robust approach: just send signals and let the server handle cleanup
             #[cfg(unix)]
             {
-                return stop_unix_process(pid, force, pid_file).await;
+                return stop_unix_process(pid, force, pid_file, timeout).await;
             }
 
             #[cfg(windows)]
             {
-                return stop_windows_process(pid, force, pid_file).await;
+                return stop_windows_process(pid, force, pid_file, timeout).await;
             }
         }
     }
@@ -535,7 +989,12 @@ async fn stop_server_by_pid(pid_file: &str, force: bool) -> FerragateResult<()>
 }
 
 #[cfg(unix)]
-async fn stop_unix_process(pid: u32, force: bool, pid_file: &str) -> FerragateResult<()> {
+async fn stop_unix_process(
+    pid: u32,
+    force: bool,
+    pid_file: &str,
+    timeout: Duration,
+) -> FerragateResult<()> {
     // Check if process exists first
     let check_result = unsafe { libc::kill(pid as i32, 0) };
     if check_result != 0 {
@@ -544,7 +1003,8 @@ async fn stop_unix_process(pid: u32, force: bool, pid_file: &str) -> FerragateRe
         return Ok(());
     }
 
-    if force {
+    // A zero timeout leaves no time for a graceful stop, so it kills at once too
+    if force || timeout.is_zero() {
         info!("Force stopping process {}...", pid);
         let result = unsafe { libc::kill(pid as i32, libc::SIGKILL) };
         if result == 0 {
@@ -570,10 +1030,9 @@ async fn stop_unix_process(pid: u32, force: bool, pid_file: &str) -> FerragateRe
     info!("SIGTERM sent successfully, waiting for graceful shutdown...");
 
     // Wait for graceful shutdown with timeout
-    let timeout_duration = std::time::Duration::from_secs(10);
     let start_time = std::time::Instant::now();
 
-    while start_time.elapsed() < timeout_duration {
+    while start_time.elapsed() < timeout {
         // Check if process is still running
         let check_result = unsafe { libc::kill(pid as i32, 0) };
         if check_result != 0 {
@@ -583,7 +1042,7 @@ async fn stop_unix_process(pid: u32, force: bool, pid_file: &str) -> FerragateRe
             return Ok(());
         }
 
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
     }
 
     // If we reach here, graceful shutdown timed out
@@ -596,10 +1055,16 @@ async fn stop_unix_process(pid: u32, force: bool, pid_file: &str) -> FerragateRe
 }
 
 #[cfg(windows)]
-async fn stop_windows_process(pid: u32, force: bool, pid_file: &str) -> FerragateResult<()> {
+async fn stop_windows_process(
+    pid: u32,
+    force: bool,
+    pid_file: &str,
+    timeout: Duration,
+) -> FerragateResult<()> {
     use std::process::Command;
 
-    if force {
+    // A zero timeout leaves no time for a graceful stop, so it kills at once too
+    if force || timeout.is_zero() {
         info!("Force stopping process {}...", pid);
         let result = Command::new("taskkill")
             .args(["/F", "/PID", &pid.to_string()])
@@ -631,7 +1096,7 @@ async fn stop_windows_process(pid: u32, force: bool, pid_file: &str) -> Ferragat
             info!("Stop signal sent successfully, waiting for graceful shutdown...");
 
             // Wait for graceful shutdown with timeout
-            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            tokio::time::sleep(timeout).await;
 
             // Force stop after timeout
             warn!("Windows graceful shutdown timeout, force stopping...");
@@ -653,40 +1118,25 @@ async fn stop_windows_process(pid: u32, force: bool, pid_file: &str) -> Ferragat
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::LOOPBACK_HEALTH_CHECK_NAME;
+    use crate::health::HealthStatus;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use tempfile::TempDir;
 
     fn create_test_config() -> GatewayConfig {
-        GatewayConfig {
-            server: crate::config::ServerConfig {
-                host: "127.0.0.1".to_string(),
-                port: 8080,
-                workers: None,
-                timeout_ms: None,
-                tls: None,
-            },
-            routes: vec![
-                crate::config::RouteConfig {
-                    path: "/api/v1/{*wildcard}".to_string(),
-                    upstream: "http://backend1:3000".to_string(),
-                    methods: vec!["GET".to_string(), "POST".to_string()],
-                    headers: std::collections::HashMap::new(),
-                    strip_path: false,
-                    preserve_host: false,
-                    timeout_ms: None,
-                },
-                crate::config::RouteConfig {
-                    path: "/users/{*wildcard}".to_string(),
-                    upstream: "http://user-service:8000".to_string(),
-                    methods: vec![],
-                    headers: std::collections::HashMap::new(),
-                    strip_path: false,
-                    preserve_host: false,
-                    timeout_ms: None,
-                },
-            ],
-            logging: crate::config::LoggingConfig::default(),
-        }
+        GatewayConfig::builder()
+            .host("127.0.0.1")
+            .port(8080)
+            .route(
+                RouteConfig::builder("/api/v1/{*wildcard}", "http://backend1:3000")
+                    .methods(["GET", "POST"]),
+            )
+            .route(RouteConfig::builder(
+                "/users/{*wildcard}",
+                "http://user-service:8000",
+            ))
+            .build()
+            .unwrap()
     }
 
     fn create_test_config_with_tls() -> GatewayConfig {
@@ -697,6 +1147,9 @@ mod tests {
             key_file: "certs/server.key".to_string(),
             port: 8443,
             redirect_http: false,
+            auto_generate_cert: true,
+            client_ca_file: None,
+            require_client_cert: false,
         });
         config
     }
@@ -707,10 +1160,452 @@ mod tests {
         let proxy_state = ProxyState::new(config);
         let health_state = AppState::new();
 
-        let router = create_router_with_states(proxy_state, health_state);
+        let router = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
         let _service = router.into_make_service();
     }
 
+    #[tokio::test]
+    async fn test_wait_for_removal_is_bounded_by_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("gateway.pid");
+        let pid_file = pid_file.to_str().unwrap().to_string();
+        std::fs::write(&pid_file, "1").unwrap();
+
+        let started = std::time::Instant::now();
+        assert!(!wait_for_removal(&pid_file, Duration::from_millis(300)).await);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let removed = pid_file.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            std::fs::remove_file(removed).unwrap();
+        });
+        assert!(wait_for_removal(&pid_file, Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn test_overload_sheds_proxy_routes_but_not_health() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use tower::ServiceExt;
+
+        let mut config = create_test_config();
+        config.server.load_shedding = Some(crate::config::LoadSheddingConfig {
+            max_in_flight: Some(1),
+            max_latency_ms: None,
+        });
+        let proxy_state = ProxyState::new(config);
+        let router =
+            create_router_with_states(SharedProxyState::new(proxy_state.clone()), AppState::new());
+
+        // Simulate overload by occupying the only in-flight slot
+        let _busy = proxy_state.load_shedder.try_acquire().unwrap();
+
+        for endpoint in [HEALTH_ENDPOINT, LIVENESS_ENDPOINT] {
+            let request = Request::builder()
+                .uri(endpoint)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{endpoint}");
+        }
+
+        let request = Request::builder()
+            .uri("/api/users")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode_rejects_proxy_routes_but_not_health() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let mut config = create_test_config();
+        config.server.maintenance_body = "Back soon".to_string();
+        let health_state = AppState::new();
+        let router = create_router_with_states(
+            SharedProxyState::new(ProxyState::new(config)),
+            health_state.clone(),
+        );
+
+        let status_of = |uri: &str| {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap() }
+        };
+
+        health_state.set_maintenance(true);
+        for endpoint in [HEALTH_ENDPOINT, LIVENESS_ENDPOINT] {
+            assert_eq!(
+                status_of(endpoint).await.status(),
+                StatusCode::OK,
+                "{endpoint}"
+            );
+        }
+        assert_eq!(
+            status_of(READINESS_ENDPOINT).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        let response = status_of("/api/users").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Back soon");
+
+        // Leaving maintenance restores readiness and forwards proxy routes again
+        health_state.set_maintenance(false);
+        assert_eq!(status_of(READINESS_ENDPOINT).await.status(), StatusCode::OK);
+        let response = status_of("/api/users").await;
+        assert_ne!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_control_socket_toggles_maintenance_mode() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixStream;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("control.sock");
+        let proxy_state = SharedProxyState::new(ProxyState::new(create_test_config()));
+        let health_state = AppState::new();
+        let reloader = ConfigReloader::new("gateway.toml", proxy_state, health_state.clone());
+        let shutdown_token = CancellationToken::new();
+        let listener = tokio::spawn(start_control_socket_listener(
+            socket_path.to_str().unwrap().to_string(),
+            shutdown_token.clone(),
+            reloader,
+            health_state.clone(),
+        ));
+
+        let send = |command: &'static str| {
+            let socket_path = socket_path.clone();
+            async move {
+                let mut stream = loop {
+                    match UnixStream::connect(&socket_path).await {
+                        Ok(stream) => break stream,
+                        Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                    }
+                };
+                stream.write_all(command.as_bytes()).await.unwrap();
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply).await.unwrap();
+                reply
+            }
+        };
+
+        assert!(send("enter-maintenance").await.starts_with("OK"));
+        assert!(health_state.in_maintenance());
+        assert!(send("exit-maintenance").await.starts_with("OK"));
+        assert!(!health_state.in_maintenance());
+
+        shutdown_token.cancel();
+        listener.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_certs_fail_startup_without_auto_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_file = dir.path().join("server.crt");
+        let key_file = dir.path().join("server.key");
+        let mut config = create_test_config_with_tls();
+        if let Some(tls) = config.server.tls.as_mut() {
+            tls.cert_file = cert_file.to_str().unwrap().to_string();
+            tls.key_file = key_file.to_str().unwrap().to_string();
+            tls.auto_generate_cert = false;
+        }
+
+        let result = start_https_server(config, Router::new(), CancellationToken::new()).await;
+
+        assert!(matches!(result, Err(FerragateError::Tls { .. })));
+        assert!(!cert_file.exists());
+        assert!(!key_file.exists());
+    }
+
+    #[tokio::test]
+    async fn test_client_certificates_select_tls_matched_routes() {
+        use crate::config::{TlsField, TlsMatch};
+        use rcgen::{
+            BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+        };
+        use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+        let mut ca_params = CertificateParams::new(Vec::new());
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "Test Client CA");
+        let ca = Certificate::from_params(ca_params).unwrap();
+        std::fs::write(file("ca.crt"), ca.serialize_pem().unwrap()).unwrap();
+
+        let client_identity = |common_name: &str| {
+            let mut params = CertificateParams::new(vec![format!("{common_name}.internal")]);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, common_name);
+            params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+            let cert = Certificate::from_params(params).unwrap();
+            let pem = format!(
+                "{}{}",
+                cert.serialize_pem_with_signer(&ca).unwrap(),
+                cert.serialize_private_key_pem()
+            );
+            reqwest::Identity::from_pem(pem.as_bytes()).unwrap()
+        };
+
+        let upstream = MockServer::start().await;
+        Mock::given(path("/admin/stats"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("admin"))
+            .mount(&upstream)
+            .await;
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = create_test_config_with_tls();
+        config.server.host = "127.0.0.1".to_string();
+        if let Some(tls) = config.server.tls.as_mut() {
+            tls.cert_file = file("server.crt");
+            tls.key_file = file("server.key");
+            tls.port = port;
+            tls.client_ca_file = Some(file("ca.crt"));
+        }
+        config.routes = vec![RouteConfig {
+            path: "/admin/*".to_string(),
+            upstream: upstream.uri(),
+            tls_match: vec![TlsMatch {
+                field: TlsField::ClientCertSubject,
+                regex: "^CN=admin-client$".to_string().try_into().unwrap(),
+            }],
+            ..RouteConfig::default()
+        }];
+
+        let app = create_router_with_states(
+            SharedProxyState::new(ProxyState::new(config.clone())),
+            AppState::new(),
+        );
+        let drain = CancellationToken::new();
+        let server = tokio::spawn(start_https_server(config, app, drain.clone()));
+
+        let get = |identity: Option<reqwest::Identity>| async move {
+            let mut builder = reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .resolve("localhost", SocketAddr::from(([127, 0, 0, 1], port)));
+            if let Some(identity) = identity {
+                builder = builder.identity(identity);
+            }
+            let client = builder.build().unwrap();
+            for _ in 0..50 {
+                match client
+                    .get(format!("https://localhost:{port}/admin/stats"))
+                    .send()
+                    .await
+                {
+                    Ok(response) => return response.status().as_u16(),
+                    Err(e) if e.is_connect() => {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                    }
+                    Err(e) => panic!("request failed: {e}"),
+                }
+            }
+            panic!("HTTPS listener never came up");
+        };
+
+        assert_eq!(get(Some(client_identity("admin-client"))).await, 200);
+        assert_eq!(get(Some(client_identity("other-client"))).await, 404);
+        assert_eq!(get(None).await, 404);
+
+        drain.cancel();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admin_upstreams_endpoint_reports_stats() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let proxy_state = ProxyState::new(create_test_config());
+        proxy_state
+            .upstream_stats
+            .record("http://backend:8080", Duration::from_millis(40), true);
+        let router = create_router_with_states(SharedProxyState::new(proxy_state), AppState::new());
+
+        let request = Request::builder()
+            .uri(ADMIN_UPSTREAMS_ENDPOINT)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats[0]["upstream"], "http://backend:8080");
+        assert_eq!(stats[0]["requests"], 1);
+        assert_eq!(stats[0]["error_rate"], 1.0);
+        assert_eq!(stats[0]["latency_p90_ms"], 40.0);
+    }
+
+    #[tokio::test]
+    async fn test_builtin_files_short_circuit_before_proxy() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let upstream = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.routes = vec![RouteConfig {
+            path: "/*".to_string(),
+            upstream: upstream.uri(),
+            ..Default::default()
+        }];
+        config.server.serve_favicon = true;
+        config.server.serve_robots_txt = true;
+        let proxy_state = SharedProxyState::new(ProxyState::new(config.clone()));
+        let router = create_router_with_states(proxy_state, AppState::new());
+
+        let get = |path: &str| Request::builder().uri(path).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(get("/favicon.ico")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = router.clone().oneshot(get("/robots.txt")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(upstream.received_requests().await.unwrap().is_empty());
+
+        // Other paths, and the files themselves once disabled, reach the upstream
+        let response = router.oneshot(get("/index.html")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        config.server.serve_favicon = false;
+        let proxy_state = SharedProxyState::new(ProxyState::new(config));
+        let router = create_router_with_states(proxy_state, AppState::new());
+        let response = router.oneshot(get("/favicon.ico")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_admin_validate_endpoint_reports_without_applying() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        let shared = SharedProxyState::new(ProxyState::new(create_test_config()));
+        let router = create_router_with_states(shared.clone(), AppState::new());
+        let validate = |body: &'static str| {
+            Request::builder()
+                .method("POST")
+                .uri(ADMIN_VALIDATE_ENDPOINT)
+                .header("content-type", "application/toml")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let valid = r#"
+[server]
+port = 9090
+
+[[routes]]
+path = "/orders/*"
+upstream = "http://orders:8080"
+"#;
+        let response = router.clone().oneshot(validate(valid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["routes"][0]["path"], "/orders/*");
+
+        let invalid = r#"
+[server]
+port = 9090
+
+[[routes]]
+path = "/orders/*"
+upstream = "not a url"
+"#;
+        let response = router.oneshot(validate(invalid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report["valid"], false);
+        assert!(report["error"].as_str().unwrap().contains("upstream"));
+
+        // Neither submission touched the running configuration
+        assert_eq!(shared.current().config.server.port, 8080);
+    }
+
+    #[test]
+    fn test_http_version_policy() {
+        let defaults = ServerConfig::default().http_versions;
+        let plain = HttpVersionPolicy::new(&defaults, false);
+        let tls = HttpVersionPolicy::new(&defaults, true);
+
+        assert!(plain.allows(Version::HTTP_11));
+        assert!(!plain.allows(Version::HTTP_10));
+        assert!(!plain.allows(Version::HTTP_2));
+        assert!(tls.allows(Version::HTTP_2));
+
+        let h2c = HttpVersionPolicy::new(&[HttpVersion::H2c, HttpVersion::Http10], false);
+        assert!(h2c.allows(Version::HTTP_2));
+        assert!(h2c.allows(Version::HTTP_10));
+        assert!(!h2c.allows(Version::HTTP_11));
+    }
+
+    // Helper to serve the gateway on an ephemeral port and send one h2c request
+    async fn send_h2c_request(http_versions: &[HttpVersion]) -> StatusCode {
+        use hyper_util::rt::{TokioExecutor, TokioIo};
+
+        let app = create_router_with_states(
+            SharedProxyState::new(ProxyState::new(create_test_config())),
+            AppState::new(),
+        );
+        let app = with_http_version_policy(app, http_versions, false);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (mut sender, connection) =
+            hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+                .await
+                .unwrap();
+        tokio::spawn(connection);
+
+        let request = axum::http::Request::builder()
+            .uri(format!("http://{addr}{HEALTH_ENDPOINT}"))
+            .body(http_body_util::Empty::<bytes::Bytes>::new())
+            .unwrap();
+        sender.send_request(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_h2c_request_refused_when_disabled() {
+        let status = send_h2c_request(&ServerConfig::default().http_versions).await;
+        assert_eq!(status, StatusCode::HTTP_VERSION_NOT_SUPPORTED);
+    }
+
+    #[tokio::test]
+    async fn test_h2c_request_accepted_when_enabled() {
+        let status = send_h2c_request(&[HttpVersion::Http11, HttpVersion::H2c]).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
     #[test]
     fn test_create_redirect_router() {
         let router = create_redirect_router(8443);
@@ -729,6 +1624,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: crate::config::LoggingConfig::default(),
@@ -875,7 +1771,7 @@ mod tests {
         let proxy_state = ProxyState::new(config.clone());
         let health_state = AppState::new();
 
-        let router = create_router_with_states(proxy_state, health_state);
+        let router = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
         let _service = router.into_make_service();
 
         // Test with TLS config
@@ -883,7 +1779,8 @@ mod tests {
         let proxy_state_tls = ProxyState::new(tls_config);
         let health_state_tls = AppState::new();
 
-        let router_tls = create_router_with_states(proxy_state_tls, health_state_tls);
+        let router_tls =
+            create_router_with_states(SharedProxyState::new(proxy_state_tls), health_state_tls);
         let _service_tls = router_tls.into_make_service();
     }
 
@@ -910,6 +1807,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: crate::config::LoggingConfig::default(),
@@ -924,6 +1822,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![crate::config::RouteConfig {
                 path: "/single/{*wildcard}".to_string(),
@@ -933,6 +1832,7 @@ mod tests {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             }],
             logging: crate::config::LoggingConfig::default(),
         };
@@ -980,7 +1880,7 @@ mod tests {
 
         let proxy_state = ProxyState::new(config);
         let health_state = AppState::new();
-        let _router = create_router_with_states(proxy_state, health_state);
+        let _router = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
     }
 
     #[test]
@@ -996,13 +1896,14 @@ mod tests {
             strip_path: false,
             preserve_host: false,
             timeout_ms: None,
+            ..Default::default()
         });
 
         log_routes_info(&config);
 
         let proxy_state = ProxyState::new(config);
         let health_state = AppState::new();
-        let _router = create_router_with_states(proxy_state, health_state);
+        let _router = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
     }
 
     #[test]
@@ -1086,6 +1987,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: crate::config::LoggingConfig::default(),
@@ -1105,7 +2007,11 @@ mod tests {
                     key_file: "/custom/path/key.pem".to_string(),
                     port: 3443,
                     redirect_http: true,
+                    auto_generate_cert: true,
+                    client_ca_file: None,
+                    require_client_cert: false,
                 }),
+                ..Default::default()
             },
             routes: vec![crate::config::RouteConfig {
                 path: "/api/v2/{*wildcard}".to_string(),
@@ -1124,12 +2030,14 @@ mod tests {
                 strip_path: true,
                 preserve_host: true,
                 timeout_ms: Some(5000),
+                ..Default::default()
             }],
             logging: crate::config::LoggingConfig {
                 level: "debug".to_string(),
                 json: true,
                 file: true,
                 dir: Some("/var/log/ferragate/".to_string()),
+                span_fields: Default::default(),
             },
         };
         log_routes_info(&complex_config);
@@ -1166,6 +2074,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![crate::config::RouteConfig {
                 path: "/wildcard/{*wildcard}".to_string(),
@@ -1175,6 +2084,7 @@ mod tests {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             }],
             logging: crate::config::LoggingConfig::default(),
         };
@@ -1188,6 +2098,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![crate::config::RouteConfig {
                 path: "/single/{*wildcard}".to_string(),
@@ -1197,6 +2108,7 @@ mod tests {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             }],
             logging: crate::config::LoggingConfig::default(),
         };
@@ -1215,7 +2127,7 @@ mod tests {
         let health_state = AppState::new();
 
         // Test router creation
-        let _app = create_router_with_states(proxy_state, health_state);
+        let _app = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
 
         // Verify TLS config check logic
         assert!(config.server.tls.is_none());
@@ -1239,7 +2151,7 @@ mod tests {
         // Test proxy and health state creation
         let proxy_state = ProxyState::new(config.clone());
         let health_state = AppState::new();
-        let _app = create_router_with_states(proxy_state, health_state);
+        let _app = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
     }
 
     #[tokio::test]
@@ -1258,7 +2170,161 @@ mod tests {
 
         let proxy_state = ProxyState::new(config.clone());
         let health_state = AppState::new();
-        let _app = create_router_with_states(proxy_state, health_state);
+        let _app = create_router_with_states(SharedProxyState::new(proxy_state), health_state);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_hooks_run_on_shutdown() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir
+            .path()
+            .join("gateway.toml")
+            .to_string_lossy()
+            .to_string();
+        // Keep the control socket out of /tmp
+        let instance = Instance::new(Some(&config_path), None)
+            .with_socket_dir(Some(temp_dir.path().to_string_lossy().to_string()));
+        let socket_path = instance.control_socket_path();
+        assert!(socket_path.starts_with(temp_dir.path().to_str().unwrap()));
+
+        let mut config = create_test_config();
+        config.server.host = "127.0.0.1".to_string();
+        config.server.port = 0;
+
+        let hook_ran = Arc::new(AtomicBool::new(false));
+        let flag = hook_ran.clone();
+        let mut hooks = ShutdownHooks::new();
+        hooks.register("flush", move || async move {
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let server_instance = instance.clone();
+        let server =
+            tokio::spawn(
+                async move { start_server_with_hooks(config, &server_instance, hooks).await },
+            );
+
+        // Wait for the control socket to come up, then request shutdown
+        let mut sent = false;
+        for _ in 0..50 {
+            if send_shutdown_command(&socket_path, false).await.is_ok() {
+                sent = true;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert!(sent, "control socket never became available");
+
+        tokio::time::timeout(std::time::Duration::from_secs(10), server)
+            .await
+            .expect("server did not shut down")
+            .unwrap()
+            .unwrap();
+        assert!(hook_ran.load(Ordering::SeqCst));
+        assert!(!Path::new(&format!("{config_path}.pid")).exists());
+    }
+
+    #[tokio::test]
+    async fn test_loopback_health_check_reaches_http_listener() {
+        let mut config = create_test_config();
+        config.server.host = "127.0.0.1".to_string();
+        config.server.port = 0;
+        config.server.loopback_health_check = true;
+
+        let health_state = AppState::new();
+        let app = create_router_with_states(
+            SharedProxyState::new(ProxyState::new(config.clone())),
+            health_state.clone(),
+        );
+        let drain = CancellationToken::new();
+        let server = tokio::spawn(start_http_server(
+            config,
+            app,
+            health_state.clone(),
+            drain.clone(),
+        ));
+
+        // The check is registered once the listener is bound
+        let mut registered = false;
+        for _ in 0..50 {
+            if !health_state.get_health_checks().is_empty() {
+                registered = true;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(registered, "loopback check was never registered");
+
+        health_state.run_loopback_check().await;
+        let checks = health_state.get_health_checks();
+        assert_eq!(checks[0].name, LOOPBACK_HEALTH_CHECK_NAME);
+        assert_eq!(checks[0].status, HealthStatus::Healthy);
+
+        drain.cancel();
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_loopback_url_targets_localhost_for_wildcard_binds() {
+        assert_eq!(
+            loopback_url("0.0.0.0:8080".parse().unwrap()),
+            "http://127.0.0.1:8080/health/live"
+        );
+        assert_eq!(
+            loopback_url("[::]:8080".parse().unwrap()),
+            "http://[::1]:8080/health/live"
+        );
+        assert_eq!(
+            loopback_url("10.0.0.5:3000".parse().unwrap()),
+            "http://10.0.0.5:3000/health/live"
+        );
+    }
+
+    #[test]
+    fn test_instances_have_distinct_pid_and_socket_paths() {
+        let unnamed = Instance::new(Some("/etc/gateway.toml"), None);
+        assert_eq!(unnamed.pid_file(), "/etc/gateway.toml.pid");
+        assert_eq!(unnamed.config_path(), "/etc/gateway.toml");
+
+        let first = Instance::for_bind_address(Some("/etc/gateway.toml"), "0.0.0.0", 8080);
+        let second = Instance::for_bind_address(Some("/etc/gateway.toml"), "0.0.0.0", 8081);
+        let named = Instance::new(Some("/etc/gateway.toml"), Some("blue"));
+        assert_eq!(first.pid_file(), "/etc/gateway.toml.0_0_0_0_8080.pid");
+        assert_eq!(named.pid_file(), "/etc/gateway.toml.blue.pid");
+
+        let instances = [unnamed, first, second, named];
+        for (i, a) in instances.iter().enumerate() {
+            for b in &instances[i + 1..] {
+                assert_ne!(a.pid_file(), b.pid_file());
+                assert_ne!(a.control_socket_path(), b.control_socket_path());
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_custom_socket_dir() {
+        let instance = Instance::new(Some("/etc/gateway.toml"), None)
+            .with_socket_dir(Some("/run/ferragate".to_string()));
+        assert_eq!(instance.socket_dir(), Some("/run/ferragate"));
+        assert_eq!(
+            instance.control_socket_path(),
+            "/run/ferragate/ferragate__etc_gateway_toml.sock"
+        );
+        // The PID file still lives next to the configuration
+        assert_eq!(instance.pid_file(), "/etc/gateway.toml.pid");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(check_socket_dir(temp_dir.path().to_str().unwrap()).is_ok());
+        assert!(std::fs::read_dir(temp_dir.path()).unwrap().next().is_none());
+
+        let missing = temp_dir.path().join("missing");
+        let result = check_socket_dir(missing.to_str().unwrap());
+        assert!(matches!(result, Err(FerragateError::Config { .. })));
     }
 
     #[test]
@@ -1280,6 +2346,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: LoggingConfig::default(),
@@ -1324,6 +2391,7 @@ mod tests {
                 workers: Some(4),
                 timeout_ms: Some(30000),
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: LoggingConfig::default(),
@@ -1343,6 +2411,7 @@ mod tests {
                     workers: None,
                     timeout_ms: None,
                     tls: None,
+                    ..Default::default()
                 },
                 routes: vec![],
                 logging: LoggingConfig::default(),
@@ -1365,6 +2434,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![],
             logging: LoggingConfig::default(),
@@ -1379,6 +2449,7 @@ mod tests {
                 workers: None,
                 timeout_ms: None,
                 tls: None,
+                ..Default::default()
             },
             routes: vec![RouteConfig {
                 path: "/api/v2/{*wildcard}".to_string(),
@@ -1388,9 +2459,114 @@ mod tests {
                 strip_path: false,
                 preserve_host: false,
                 timeout_ms: None,
+                ..Default::default()
             }],
             logging: LoggingConfig::default(),
         };
         log_routes_info(&single_route_config);
     }
+
+    #[test]
+    fn test_shutdown_triggers_select_drain_timeout() {
+        let server = ServerConfig {
+            drain_timeout_secs: 45,
+            interrupt_drain_timeout_secs: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ShutdownTrigger::Interrupt.drain_timeout(&server),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            ShutdownTrigger::Terminate.drain_timeout(&server),
+            Duration::from_secs(45)
+        );
+        assert_eq!(
+            ShutdownTrigger::ControlSocket.drain_timeout(&server),
+            Duration::from_secs(45)
+        );
+    }
+
+    // Both signals are raised in one test so parallel tests never see each other's signal
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sigint_and_sigterm_select_their_drain_paths() {
+        let server = ServerConfig {
+            drain_timeout_secs: 30,
+            interrupt_drain_timeout_secs: 1,
+            ..Default::default()
+        };
+        let token = CancellationToken::new();
+        let mut signals = ShutdownSignals::install().unwrap();
+        let pid = std::process::id() as i32;
+
+        unsafe { libc::kill(pid, libc::SIGINT) };
+        let trigger = tokio::time::timeout(Duration::from_secs(5), signals.recv(&token))
+            .await
+            .unwrap();
+        assert_eq!(trigger, ShutdownTrigger::Interrupt);
+        assert_eq!(trigger.drain_timeout(&server), Duration::from_secs(1));
+
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+        let trigger = tokio::time::timeout(Duration::from_secs(5), signals.recv(&token))
+            .await
+            .unwrap();
+        assert_eq!(trigger, ShutdownTrigger::Terminate);
+        assert_eq!(trigger.drain_timeout(&server), Duration::from_secs(30));
+
+        token.cancel();
+        assert_eq!(signals.recv(&token).await, ShutdownTrigger::ControlSocket);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_timeout_is_passed_through() {
+        use std::process::Command;
+
+        let temp_dir = TempDir::new().unwrap();
+        let pid_file = temp_dir.path().join("gateway.toml.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        // A process that ignores SIGTERM is only stopped once the timeout expires
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; exec sleep 30"])
+            .spawn()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        std::fs::write(pid_file, child.id().to_string()).unwrap();
+
+        let started = std::time::Instant::now();
+        stop_server_by_pid(pid_file, false, Duration::from_secs(1))
+            .await
+            .unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+        assert!(!child.wait().unwrap().success());
+        assert!(!Path::new(pid_file).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_zero_stop_timeout_kills_immediately() {
+        use std::process::Command;
+
+        let temp_dir = TempDir::new().unwrap();
+        let pid_file = temp_dir.path().join("gateway.toml.pid");
+        let pid_file = pid_file.to_str().unwrap();
+
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; exec sleep 30"])
+            .spawn()
+            .unwrap();
+        std::fs::write(pid_file, child.id().to_string()).unwrap();
+
+        let started = std::time::Instant::now();
+        stop_server_by_pid(pid_file, true, Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!child.wait().unwrap().success());
+    }
 }
@@ -0,0 +1,170 @@
+/// Load shedding for proxied requests
+///
+/// The gateway tracks the number of in-flight proxy requests and a smoothed
+/// average of their latency. Once either exceeds its configured threshold, new
+/// proxy requests are rejected up front so the gateway degrades gracefully
+/// instead of queueing work it cannot finish.
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::config::LoadSheddingConfig;
+use crate::constants::LOAD_SHEDDING_LATENCY_SMOOTHING;
+
+/// Shared load shedding state
+#[derive(Debug, Default)]
+pub struct LoadShedder {
+    max_in_flight: Option<usize>,
+    max_latency_us: Option<u64>,
+    in_flight: AtomicUsize,
+    /// Exponentially weighted moving average of request latency in microseconds
+    latency_us: AtomicU64,
+}
+
+impl LoadShedder {
+    /// Create a load shedder from the given thresholds
+    ///
+    /// Passing `None` creates a shedder that never rejects requests.
+    pub fn new(config: Option<&LoadSheddingConfig>) -> Self {
+        Self {
+            max_in_flight: config.and_then(|c| c.max_in_flight),
+            max_latency_us: config.and_then(|c| c.max_latency_ms).map(|ms| ms * 1000),
+            ..Default::default()
+        }
+    }
+
+    /// Try to admit a new request
+    ///
+    /// Returns a guard that tracks the request until dropped, or None if the
+    /// request should be shed. While latency is over the threshold a single
+    /// request is still admitted at a time, so the average can recover.
+    pub fn try_acquire(self: &Arc<Self>) -> Option<InFlightGuard> {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::AcqRel);
+
+        let over_concurrency = self.max_in_flight.is_some_and(|max| in_flight >= max);
+        let over_latency = in_flight > 0
+            && self
+                .max_latency_us
+                .is_some_and(|max| self.latency_us.load(Ordering::Relaxed) > max);
+
+        if over_concurrency || over_latency {
+            self.in_flight.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+
+        Some(InFlightGuard {
+            shedder: Arc::clone(self),
+            started: Instant::now(),
+        })
+    }
+
+    /// Admit a request regardless of load, still tracking it
+    ///
+    /// Used for priority requests exempt from shedding; they count towards the
+    /// in-flight total, so other requests are shed first.
+    pub fn acquire(self: &Arc<Self>) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        InFlightGuard {
+            shedder: Arc::clone(self),
+            started: Instant::now(),
+        }
+    }
+
+    /// Number of requests currently in flight
+    #[allow(dead_code)] // Public API method
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    fn record_latency(&self, latency_us: u64) {
+        // Lost updates under contention only skew the average slightly
+        let previous = self.latency_us.load(Ordering::Relaxed);
+        let updated = if previous == 0 {
+            latency_us
+        } else {
+            (previous as f64 * (1.0 - LOAD_SHEDDING_LATENCY_SMOOTHING)
+                + latency_us as f64 * LOAD_SHEDDING_LATENCY_SMOOTHING) as u64
+        };
+        self.latency_us.store(updated, Ordering::Relaxed);
+    }
+}
+
+/// Tracks an admitted request, releasing its slot and recording its latency on drop
+#[derive(Debug)]
+pub struct InFlightGuard {
+    shedder: Arc<LoadShedder>,
+    started: Instant,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.shedder
+            .record_latency(self.started.elapsed().as_micros() as u64);
+        self.shedder.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_shedder_admits_everything() {
+        let shedder = Arc::new(LoadShedder::new(None));
+        let guards: Vec<_> = (0..100).map(|_| shedder.try_acquire()).collect();
+
+        assert!(guards.iter().all(Option::is_some));
+        assert_eq!(shedder.in_flight(), 100);
+        drop(guards);
+        assert_eq!(shedder.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_sheds_over_max_in_flight() {
+        let config = LoadSheddingConfig {
+            max_in_flight: Some(2),
+            max_latency_ms: None,
+        };
+        let shedder = Arc::new(LoadShedder::new(Some(&config)));
+
+        let first = shedder.try_acquire();
+        let second = shedder.try_acquire();
+        assert!(first.is_some() && second.is_some());
+        assert!(shedder.try_acquire().is_none());
+
+        drop(first);
+        assert!(shedder.try_acquire().is_some());
+    }
+
+    #[test]
+    fn test_sheds_when_latency_is_high() {
+        let config = LoadSheddingConfig {
+            max_in_flight: None,
+            max_latency_ms: Some(10),
+        };
+        let shedder = Arc::new(LoadShedder::new(Some(&config)));
+        shedder.record_latency(50_000);
+
+        // One request is still admitted so the average can recover
+        let probe = shedder.try_acquire();
+        assert!(probe.is_some());
+        assert!(shedder.try_acquire().is_none());
+    }
+
+    #[test]
+    fn test_acquire_admits_past_limit_and_counts_in_flight() {
+        let config = LoadSheddingConfig {
+            max_in_flight: Some(1),
+            max_latency_ms: None,
+        };
+        let shedder = Arc::new(LoadShedder::new(Some(&config)));
+
+        let exempt = shedder.acquire();
+        assert!(shedder.try_acquire().is_none());
+        let second_exempt = shedder.acquire();
+        assert_eq!(shedder.in_flight(), 2);
+
+        drop((exempt, second_exempt));
+        assert!(shedder.try_acquire().is_some());
+    }
+}
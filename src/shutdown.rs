@@ -0,0 +1,142 @@
+/// Shutdown hooks for embedding users
+///
+/// This module lets applications that embed the gateway register async callbacks
+/// that run once the servers have stopped accepting requests, before the gateway
+/// removes its PID file and returns from `start_server_with_hooks`.
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::constants::DEFAULT_SHUTDOWN_HOOK_TIMEOUT_SECS;
+
+type HookFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type Hook = Box<dyn FnOnce() -> HookFuture + Send>;
+
+/// Registry of callbacks to run during graceful shutdown
+///
+/// Hooks run sequentially in registration order. Each hook is bounded by the
+/// configured timeout so a misbehaving hook cannot hang shutdown forever.
+pub struct ShutdownHooks {
+    hooks: Vec<(String, Hook)>,
+    timeout: Duration,
+}
+
+impl ShutdownHooks {
+    /// Create an empty registry with the default per-hook timeout
+    pub fn new() -> Self {
+        Self {
+            hooks: Vec::new(),
+            timeout: Duration::from_secs(DEFAULT_SHUTDOWN_HOOK_TIMEOUT_SECS),
+        }
+    }
+
+    /// Set the maximum time each hook is allowed to run
+    #[allow(dead_code)] // Public API method
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Register a named hook to run on shutdown
+    #[allow(dead_code)] // Public API method
+    pub fn register<F, Fut>(&mut self, name: impl Into<String>, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.push((
+            name.into(),
+            Box::new(move || Box::pin(hook()) as HookFuture),
+        ));
+    }
+
+    /// Number of registered hooks
+    #[allow(dead_code)] // Public API method
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Check whether no hooks are registered
+    #[allow(dead_code)] // Public API method
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run every registered hook, consuming the registry
+    pub async fn run(self) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        info!("Running {} shutdown hook(s)", self.hooks.len());
+        for (name, hook) in self.hooks {
+            match tokio::time::timeout(self.timeout, hook()).await {
+                Ok(()) => info!("Shutdown hook '{}' completed", name),
+                Err(_) => warn!(
+                    "Shutdown hook '{}' timed out after {:?}, continuing shutdown",
+                    name, self.timeout
+                ),
+            }
+        }
+    }
+}
+
+impl Default for ShutdownHooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for ShutdownHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownHooks")
+            .field(
+                "hooks",
+                &self.hooks.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_hooks_run_in_registration_order() {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut hooks = ShutdownHooks::new();
+
+        for name in ["first", "second"] {
+            let order = order.clone();
+            hooks.register(name, move || async move {
+                order.lock().unwrap().push(name);
+            });
+        }
+        assert_eq!(hooks.len(), 2);
+
+        hooks.run().await;
+        assert_eq!(*order.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_hung_hook_times_out_and_later_hooks_still_run() {
+        let completed = Arc::new(AtomicUsize::new(0));
+        let mut hooks = ShutdownHooks::new().with_timeout(Duration::from_millis(20));
+
+        hooks.register("hangs", std::future::pending::<()>);
+        let counter = completed.clone();
+        hooks.register("flush", move || async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        tokio::time::timeout(Duration::from_secs(5), hooks.run())
+            .await
+            .expect("hung hook should not block shutdown");
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,355 @@
+/// Request coalescing for identical concurrent GETs
+///
+/// When many clients request the same resource at once, for example right after
+/// a cache expires upstream, routes with `coalesce_requests` send a single
+/// upstream request and hand a copy of its response to every waiting client.
+/// Requests are only shared while one is in flight; nothing is cached after it
+/// completes.
+use crate::constants::{MSG_UPSTREAM_INVALID_RESPONSE, MSG_UPSTREAM_RESPONSE_TOO_LARGE};
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri};
+use axum::response::IntoResponse;
+use bytes::Bytes;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tracing::{debug, error};
+
+const KEY_HEADER_COUNT: usize = 6;
+
+/// Request headers that can change the upstream's response
+///
+/// Credentials keep clients from receiving a response fetched on behalf of
+/// someone else; the others select the range, encoding, format and language of
+/// the body.
+const KEY_HEADERS: [header::HeaderName; KEY_HEADER_COUNT] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::RANGE,
+    header::ACCEPT,
+    header::ACCEPT_ENCODING,
+    header::ACCEPT_LANGUAGE,
+];
+
+/// Identity of a request for coalescing
+///
+/// Requests share a response only if they agree on the route, method, URI and
+/// every header in `KEY_HEADERS`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlightKey {
+    route: usize,
+    method: Method,
+    uri: Uri,
+    headers: [Option<HeaderValue>; KEY_HEADER_COUNT],
+}
+
+impl FlightKey {
+    /// Build the key for a request matched to the route at `route` index
+    pub fn new(route: usize, method: &Method, uri: &Uri, headers: &HeaderMap) -> Self {
+        Self {
+            route,
+            method: method.clone(),
+            uri: uri.clone(),
+            headers: KEY_HEADERS.map(|name| headers.get(name).cloned()),
+        }
+    }
+}
+
+/// Fully buffered response that can be handed to several clients
+#[derive(Debug, Clone)]
+pub struct SharedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl SharedResponse {
+    /// Buffer a response of at most `max_bytes` so it can be cloned
+    ///
+    /// Streamed bodies are read in full here. A body over the limit, or one
+    /// that fails while being read, is shared as a 502 instead.
+    pub async fn from_response(response: axum::response::Response, max_bytes: u64) -> Self {
+        let (parts, body) = response.into_parts();
+        let limit = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+        match Limited::new(body, limit).collect().await {
+            Ok(collected) => Self {
+                status: parts.status,
+                headers: parts.headers,
+                body: collected.to_bytes(),
+            },
+            Err(e) => {
+                let message = if e.is::<LengthLimitError>() {
+                    MSG_UPSTREAM_RESPONSE_TOO_LARGE
+                } else {
+                    MSG_UPSTREAM_INVALID_RESPONSE
+                };
+                error!("Failed to buffer coalesced response: {}", e);
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                );
+                Self {
+                    status: StatusCode::BAD_GATEWAY,
+                    headers,
+                    body: Bytes::from_static(message.as_bytes()),
+                }
+            }
+        }
+    }
+}
+
+impl IntoResponse for SharedResponse {
+    fn into_response(self) -> axum::response::Response {
+        let mut response = axum::response::Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
+
+type Flights<K, T> = Arc<Mutex<HashMap<K, watch::Receiver<Option<T>>>>>;
+
+/// Registry of in-flight calls, keyed by request identity
+#[derive(Debug)]
+pub struct SingleFlight<K, T> {
+    flights: Flights<K, T>,
+}
+
+impl<K, T> Default for SingleFlight<K, T> {
+    fn default() -> Self {
+        Self {
+            flights: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<K, T> SingleFlight<K, T>
+where
+    K: Eq + Hash + Clone,
+    T: Clone,
+{
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `call` unless an identical call is already in flight, and share its result
+    ///
+    /// The first caller for a key makes the call; callers arriving while it runs
+    /// wait for and receive a clone of its result. If the first caller is
+    /// cancelled before finishing, the waiting callers make the call themselves.
+    pub async fn run<F, Fut>(&self, key: K, call: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let leader = {
+            let mut flights = self.flights.lock().unwrap();
+            match flights.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    flights.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+
+        match leader {
+            Ok(sender) => {
+                let _flight = FlightGuard {
+                    flights: Arc::clone(&self.flights),
+                    key: Some(key),
+                };
+                let result = call().await;
+                sender.send_replace(Some(result.clone()));
+                result
+            }
+            Err(mut receiver) => {
+                debug!("Joining an identical in-flight request");
+                let shared = match receiver.wait_for(Option::is_some).await {
+                    Ok(result) => result.clone(),
+                    Err(_) => None,
+                };
+                match shared {
+                    Some(result) => result,
+                    None => call().await,
+                }
+            }
+        }
+    }
+
+    /// Number of distinct calls currently in flight
+    #[allow(dead_code)] // Public API method
+    pub fn in_flight(&self) -> usize {
+        self.flights.lock().unwrap().len()
+    }
+}
+
+/// Removes a flight from the registry once its leader finishes or is cancelled
+struct FlightGuard<K: Eq + Hash, T> {
+    flights: Flights<K, T>,
+    key: Option<K>,
+}
+
+impl<K: Eq + Hash, T> Drop for FlightGuard<K, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.flights.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_result() {
+        let flights = Arc::new(SingleFlight::<&str, usize>::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let flights = Arc::clone(&flights);
+                let calls = Arc::clone(&calls);
+                tokio::spawn(async move {
+                    flights
+                        .run("key", || async move {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            calls.fetch_add(1, Ordering::SeqCst) + 42
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 42);
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_distinct_keys_do_not_share() {
+        let flights = SingleFlight::<String, String>::new();
+        let (a, b) = tokio::join!(
+            flights.run("a".to_string(), || async { "a".to_string() }),
+            flights.run("b".to_string(), || async { "b".to_string() }),
+        );
+        assert_eq!((a.as_str(), b.as_str()), ("a", "b"));
+    }
+
+    #[tokio::test]
+    async fn test_waiters_retry_when_leader_is_cancelled() {
+        let flights = Arc::new(SingleFlight::<&str, &str>::new());
+
+        let leader = {
+            let flights = Arc::clone(&flights);
+            tokio::spawn(async move { flights.run("key", std::future::pending::<&str>).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let waiter = {
+            let flights = Arc::clone(&flights);
+            tokio::spawn(async move { flights.run("key", || async { "retried" }).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+
+        assert_eq!(waiter.await.unwrap(), "retried");
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shared_response_is_limited() {
+        let chunks = || {
+            futures_util::stream::iter([
+                Ok::<_, std::io::Error>(Bytes::from("abc")),
+                Ok(Bytes::from("def")),
+            ])
+        };
+        let streamed = |body| axum::response::Response::new(Body::from_stream(body));
+
+        let shared = SharedResponse::from_response(streamed(chunks()), 6).await;
+        assert_eq!(shared.status, StatusCode::OK);
+        assert_eq!(shared.body, "abcdef");
+
+        let shared = SharedResponse::from_response(streamed(chunks()), 5).await;
+        assert_eq!(shared.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(shared.body, MSG_UPSTREAM_RESPONSE_TOO_LARGE);
+
+        let failing = futures_util::stream::iter([
+            Ok(Bytes::from("abc")),
+            Err(std::io::Error::other("upstream reset")),
+        ]);
+        let shared = SharedResponse::from_response(streamed(failing), 6).await;
+        assert_eq!(shared.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(shared.body, MSG_UPSTREAM_INVALID_RESPONSE);
+    }
+
+    #[test]
+    fn test_flight_key_includes_credentials() {
+        let uri: Uri = "/api/items?page=1".parse().unwrap();
+        let mut alice = HeaderMap::new();
+        alice.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer a"));
+        let mut bob = HeaderMap::new();
+        bob.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer b"));
+
+        assert_eq!(
+            FlightKey::new(0, &Method::GET, &uri, &alice),
+            FlightKey::new(0, &Method::GET, &uri, &alice.clone())
+        );
+        assert_ne!(
+            FlightKey::new(0, &Method::GET, &uri, &alice),
+            FlightKey::new(0, &Method::GET, &uri, &bob)
+        );
+        let other: Uri = "/api/items?page=2".parse().unwrap();
+        assert_ne!(
+            FlightKey::new(0, &Method::GET, &uri, &alice),
+            FlightKey::new(0, &Method::GET, &other, &alice)
+        );
+    }
+
+    #[test]
+    fn test_flight_key_includes_representation_headers() {
+        let uri: Uri = "/files/report".parse().unwrap();
+        let key = |name, value| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            FlightKey::new(0, &Method::GET, &uri, &headers)
+        };
+
+        assert_eq!(
+            key(header::RANGE, "bytes=0-99"),
+            key(header::RANGE, "bytes=0-99")
+        );
+        assert_ne!(
+            key(header::RANGE, "bytes=0-99"),
+            key(header::RANGE, "bytes=100-199")
+        );
+        assert_ne!(
+            key(header::ACCEPT_ENCODING, "gzip"),
+            key(header::ACCEPT_ENCODING, "identity")
+        );
+        assert_ne!(
+            key(header::ACCEPT, "application/json"),
+            key(header::ACCEPT, "text/html")
+        );
+        assert_ne!(
+            key(header::ACCEPT_LANGUAGE, "en"),
+            key(header::ACCEPT_LANGUAGE, "fr")
+        );
+        assert_ne!(
+            key(header::ACCEPT_ENCODING, "gzip"),
+            FlightKey::new(0, &Method::GET, &uri, &HeaderMap::new())
+        );
+    }
+}
@@ -0,0 +1,138 @@
+/// Systemd socket activation for the HTTP listener
+///
+/// When systemd starts the gateway from a `.socket` unit, it opens the listening
+/// socket itself and passes it as file descriptor 3, announcing it through the
+/// `LISTEN_FDS` and `LISTEN_PID` environment variables. Serving from that socket
+/// lets systemd keep accepting connections while the gateway restarts. Without
+/// those variables, or on other platforms, the gateway binds its own socket.
+use std::io;
+
+/// Take the listening socket passed by systemd, if the process was socket-activated
+///
+/// The activation variables are removed so child processes do not try to claim
+/// the same socket. Only the first passed socket is used.
+#[cfg(target_os = "linux")]
+pub fn take_inherited_listener() -> io::Result<Option<std::net::TcpListener>> {
+    use crate::constants::{LISTEN_FDS_ENV, LISTEN_FDS_START, LISTEN_PID_ENV};
+
+    let listen_fds = std::env::var(LISTEN_FDS_ENV).ok();
+    let listen_pid = std::env::var(LISTEN_PID_ENV).ok();
+    std::env::remove_var(LISTEN_FDS_ENV);
+    std::env::remove_var(LISTEN_PID_ENV);
+
+    match activated_fd_count(
+        listen_fds.as_deref(),
+        listen_pid.as_deref(),
+        std::process::id(),
+    ) {
+        0 => Ok(None),
+        count => {
+            if count > 1 {
+                tracing::warn!("systemd passed {} sockets; only the first is used", count);
+            }
+            listener_from_fd(LISTEN_FDS_START).map(Some)
+        }
+    }
+}
+
+/// Socket activation is only supported on Linux
+#[cfg(not(target_os = "linux"))]
+pub fn take_inherited_listener() -> io::Result<Option<std::net::TcpListener>> {
+    Ok(None)
+}
+
+/// Number of sockets passed to this process according to the activation variables
+///
+/// Variables meant for another process (a different `LISTEN_PID`) are ignored.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn activated_fd_count(listen_fds: Option<&str>, listen_pid: Option<&str>, pid: u32) -> usize {
+    if let Some(listen_pid) = listen_pid {
+        if listen_pid.trim().parse::<u32>().ok() != Some(pid) {
+            return 0;
+        }
+    }
+    listen_fds
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Adopt an inherited file descriptor as a non-blocking TCP listener
+///
+/// The descriptor must be a listening stream socket; anything else is rejected
+/// rather than served from.
+#[cfg(target_os = "linux")]
+fn listener_from_fd(fd: std::os::fd::RawFd) -> io::Result<std::net::TcpListener> {
+    use std::os::fd::FromRawFd;
+
+    let mut accepting: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: `accepting` and `len` are valid for writes of the sizes passed
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_ACCEPTCONN,
+            &mut accepting as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if accepting == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("inherited file descriptor {fd} is not a listening socket"),
+        ));
+    }
+
+    // SAFETY: the descriptor was handed to this process and is owned from here on
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activated_fd_count() {
+        assert_eq!(activated_fd_count(None, None, 42), 0);
+        assert_eq!(activated_fd_count(Some("1"), Some("42"), 42), 1);
+        assert_eq!(activated_fd_count(Some("2"), None, 42), 2);
+        assert_eq!(activated_fd_count(Some("1"), Some("7"), 42), 0);
+        assert_eq!(activated_fd_count(Some("x"), Some("42"), 42), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_listener_from_inherited_fd() {
+        use std::io::{Read, Write};
+        use std::os::fd::IntoRawFd;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let fd = listener.into_raw_fd();
+
+        let listener = listener_from_fd(fd).unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+        listener.set_nonblocking(false).unwrap();
+
+        let mut client = std::net::TcpStream::connect(addr).unwrap();
+        client.write_all(b"ping").unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_non_listening_fd_is_rejected() {
+        use std::os::fd::AsRawFd;
+
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(listener_from_fd(socket.as_raw_fd()).is_err());
+    }
+}
@@ -1,6 +1,17 @@
-use axum_server::tls_rustls::RustlsConfig;
+use axum::middleware::AddExtension;
+use axum::Extension;
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use futures_util::future::BoxFuture;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
 use tracing::info;
 
+use crate::config::{HttpVersion, TlsField};
 use crate::constants::{CERT_COUNTRY, CERT_ORGANIZATION, LOG_TLS_ENABLED};
 use crate::error::{FerragateError, FerragateResult};
 
@@ -46,6 +57,235 @@ pub async fn load_tls_config(cert_file: &str, key_file: &str) -> FerragateResult
     Ok(config)
 }
 
+/// Load a TLS configuration that verifies client certificates against a CA bundle
+///
+/// With `require_client_cert` the handshake fails for clients without a valid
+/// certificate. Otherwise they connect without an identity, and routes that
+/// match on client certificate attributes do not serve them.
+pub fn load_mutual_tls_config(
+    cert_file: &str,
+    key_file: &str,
+    client_ca_file: &str,
+    require_client_cert: bool,
+) -> FerragateResult<RustlsConfig> {
+    info!(
+        "Loading TLS configuration from cert: {}, key: {}, client CA: {}",
+        cert_file, key_file, client_ca_file
+    );
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in read_pem_certs(client_ca_file)? {
+        roots.add(cert).map_err(|e| {
+            FerragateError::tls(format!(
+                "Invalid client CA certificate in {client_ca_file}: {e}"
+            ))
+        })?;
+    }
+    let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots));
+    let verifier = if require_client_cert {
+        verifier.build()
+    } else {
+        verifier.allow_unauthenticated().build()
+    }
+    .map_err(|e| {
+        FerragateError::tls(format!("Failed to build client certificate verifier: {e}"))
+    })?;
+
+    let certs = read_pem_certs(cert_file)?;
+    let key = read_pem_key(key_file)?;
+    let config = rustls::ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .map_err(|e| {
+            FerragateError::tls(format!(
+                "Failed to load TLS configuration from cert: {cert_file}, key: {key_file}: {e}"
+            ))
+        })?;
+
+    info!("{}", LOG_TLS_ENABLED);
+    Ok(RustlsConfig::from_config(Arc::new(config)))
+}
+
+/// Read every certificate from a PEM file
+fn read_pem_certs(path: &str) -> FerragateResult<Vec<CertificateDer<'static>>> {
+    let pem = std::fs::read(path)
+        .map_err(|e| FerragateError::tls(format!("Failed to read certificate file {path}: {e}")))?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| FerragateError::tls(format!("Invalid certificate file {path}: {e}")))?;
+    if certs.is_empty() {
+        return Err(FerragateError::tls(format!(
+            "No certificates found in {path}"
+        )));
+    }
+    Ok(certs)
+}
+
+/// Read the first private key from a PEM file
+fn read_pem_key(path: &str) -> FerragateResult<PrivateKeyDer<'static>> {
+    let pem = std::fs::read(path)
+        .map_err(|e| FerragateError::tls(format!("Failed to read private key file {path}: {e}")))?;
+    rustls_pemfile::private_key(&mut pem.as_slice())
+        .map_err(|e| FerragateError::tls(format!("Invalid private key file {path}: {e}")))?
+        .ok_or_else(|| FerragateError::tls(format!("No private key found in {path}")))
+}
+
+/// TLS details of a client connection, attached to every request it carries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Server name the client requested through SNI
+    pub sni: Option<String>,
+    /// Subject of the verified client certificate, such as `CN=admin, O=Example`
+    pub client_cert_subject: Option<String>,
+    /// DNS subject alternative names of the verified client certificate
+    pub client_cert_sans: Vec<String>,
+}
+
+impl TlsInfo {
+    /// Collect the details of an established connection
+    pub fn from_connection(connection: &rustls::ServerConnection) -> Self {
+        let client_cert = connection
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .and_then(|cert| webpki::EndEntityCert::try_from(cert).ok());
+        Self {
+            sni: connection.server_name().map(str::to_string),
+            client_cert_subject: client_cert
+                .as_ref()
+                .map(|cert| format_distinguished_name(cert.subject())),
+            client_cert_sans: client_cert
+                .as_ref()
+                .map(|cert| cert.valid_dns_names().map(str::to_string).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Values of a TLS attribute, for route matching
+    pub fn values(&self, field: TlsField) -> Vec<&str> {
+        match field {
+            TlsField::Sni => self.sni.as_deref().into_iter().collect(),
+            TlsField::ClientCertSubject => {
+                self.client_cert_subject.as_deref().into_iter().collect()
+            }
+            TlsField::ClientCertSan => self.client_cert_sans.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Render the contents of a DER-encoded X.509 name as `TYPE=value` pairs
+///
+/// Attributes appear in certificate order; types without a common short name,
+/// and values that are not text, are left out.
+fn format_distinguished_name(name: &[u8]) -> String {
+    const SET: u8 = 0x31;
+    const SEQUENCE: u8 = 0x30;
+    const OID: u8 = 0x06;
+
+    let mut parts = Vec::new();
+    let mut rdns = name;
+    while let Some((SET, mut attributes, rest)) = der_element(rdns) {
+        rdns = rest;
+        while let Some((SEQUENCE, attribute, rest)) = der_element(attributes) {
+            attributes = rest;
+            let Some((OID, oid, value)) = der_element(attribute) else {
+                continue;
+            };
+            let short_name = match oid {
+                [0x55, 0x04, 0x03] => "CN",
+                [0x55, 0x04, 0x06] => "C",
+                [0x55, 0x04, 0x07] => "L",
+                [0x55, 0x04, 0x08] => "ST",
+                [0x55, 0x04, 0x0a] => "O",
+                [0x55, 0x04, 0x0b] => "OU",
+                _ => continue,
+            };
+            if let Some(value) =
+                der_element(value).and_then(|(_, value, _)| std::str::from_utf8(value).ok())
+            {
+                parts.push(format!("{short_name}={value}"));
+            }
+        }
+    }
+    parts.join(", ")
+}
+
+/// Split the first DER element off `input` as its tag, contents and the remainder
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&length, rest) = rest.split_first()?;
+    let (length, rest) = if length < 0x80 {
+        (length as usize, rest)
+    } else {
+        let count = (length & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let (bytes, rest) = rest.split_at(count);
+        let length = bytes
+            .iter()
+            .fold(0usize, |length, &byte| (length << 8) | byte as usize);
+        (length, rest)
+    };
+    if rest.len() < length {
+        return None;
+    }
+    let (contents, rest) = rest.split_at(length);
+    Some((tag, contents, rest))
+}
+
+/// Acceptor performing the TLS handshake and attaching the connection's
+/// [`TlsInfo`] to every request served on it
+#[derive(Clone)]
+pub struct TlsInfoAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl TlsInfoAcceptor {
+    /// Accept connections with the given TLS configuration
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for TlsInfoAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, TlsInfo>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let handshake = self.inner.accept(stream, service);
+        Box::pin(async move {
+            let (stream, service) = handshake.await?;
+            let info = TlsInfo::from_connection(stream.get_ref().1);
+            Ok((stream, Extension(info).layer(service)))
+        })
+    }
+}
+
+/// Advertise only the enabled HTTP versions through ALPN
+///
+/// `h2` is offered when HTTP/2 is enabled and `http/1.1` when either HTTP/1
+/// version is, so clients do not negotiate a protocol the listener rejects.
+pub fn set_alpn_protocols(config: &RustlsConfig, versions: &[HttpVersion]) {
+    let mut alpn_protocols = Vec::new();
+    if versions.contains(&HttpVersion::H2) {
+        alpn_protocols.push(b"h2".to_vec());
+    }
+    if versions.contains(&HttpVersion::Http11) || versions.contains(&HttpVersion::Http10) {
+        alpn_protocols.push(b"http/1.1".to_vec());
+    }
+
+    let mut server_config = (*config.get_inner()).clone();
+    server_config.alpn_protocols = alpn_protocols;
+    config.reload_from_config(Arc::new(server_config));
+}
+
 /// Generate a self-signed certificate for development and testing
 ///
 /// Creates a self-signed X.509 certificate and private key for the given hostname.
@@ -118,7 +358,11 @@ pub fn create_self_signed_cert(
 
 #[cfg(test)]
 mod tests {
-    use super::{create_self_signed_cert, load_tls_config};
+    use super::{
+        create_self_signed_cert, format_distinguished_name, load_mutual_tls_config,
+        load_tls_config, TlsInfo,
+    };
+    use crate::config::TlsField;
     use std::fs;
     use tempfile::tempdir;
 
@@ -202,4 +446,66 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_format_distinguished_name() {
+        let temp_dir = tempdir().unwrap();
+        let cert_path = temp_dir.path().join("test.crt");
+        let key_path = temp_dir.path().join("test.key");
+        create_self_signed_cert(
+            cert_path.to_str().unwrap(),
+            key_path.to_str().unwrap(),
+            "client.example.com",
+        )
+        .unwrap();
+
+        let pem = fs::read(&cert_path).unwrap();
+        let der = rustls_pemfile::certs(&mut pem.as_slice())
+            .next()
+            .unwrap()
+            .unwrap();
+        let cert = webpki::EndEntityCert::try_from(&der).unwrap();
+        assert_eq!(
+            format_distinguished_name(cert.subject()),
+            format!(
+                "CN=client.example.com, O={}, C={}",
+                crate::constants::CERT_ORGANIZATION,
+                crate::constants::CERT_COUNTRY
+            )
+        );
+        assert_eq!(format_distinguished_name(&[0x31, 0x05]), "");
+    }
+
+    #[test]
+    fn test_tls_info_values() {
+        let info = TlsInfo {
+            sni: Some("api.example.com".to_string()),
+            client_cert_subject: None,
+            client_cert_sans: vec!["a.internal".to_string(), "b.internal".to_string()],
+        };
+        assert_eq!(info.values(TlsField::Sni), vec!["api.example.com"]);
+        assert!(info.values(TlsField::ClientCertSubject).is_empty());
+        assert_eq!(
+            info.values(TlsField::ClientCertSan),
+            vec!["a.internal", "b.internal"]
+        );
+    }
+
+    #[test]
+    fn test_load_mutual_tls_config_rejects_missing_client_ca() {
+        let temp_dir = tempdir().unwrap();
+        let cert_path = temp_dir.path().join("test.crt");
+        let key_path = temp_dir.path().join("test.key");
+        create_self_signed_cert(
+            cert_path.to_str().unwrap(),
+            key_path.to_str().unwrap(),
+            "test.local",
+        )
+        .unwrap();
+
+        let cert = cert_path.to_str().unwrap();
+        let key = key_path.to_str().unwrap();
+        assert!(load_mutual_tls_config(cert, key, cert, true).is_ok());
+        assert!(load_mutual_tls_config(cert, key, "missing-ca.crt", false).is_err());
+    }
 }
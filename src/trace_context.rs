@@ -0,0 +1,184 @@
+/// W3C Trace Context propagation
+///
+/// Upstream requests carry a `traceparent` header so that the gateway's hop shows
+/// up in distributed traces. An incoming `traceparent` is continued with a new
+/// span id for the upstream call; requests without one start a new trace.
+/// `tracestate` is forwarded unchanged.
+use axum::http::{HeaderMap, HeaderValue};
+
+use crate::constants::{TRACEPARENT_HEADER, TRACEPARENT_VERSION, TRACE_FLAG_SAMPLED};
+
+/// Parsed `traceparent` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+}
+
+impl TraceContext {
+    /// Start a new sampled trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: random_nonzero_u128(),
+            span_id: random_nonzero_u64(),
+            flags: TRACE_FLAG_SAMPLED,
+        }
+    }
+
+    /// Parse a `traceparent` header value
+    ///
+    /// Returns None for malformed values, the reserved `ff` version and all-zero
+    /// ids. Versions above `00` are parsed by their `00` prefix, as the
+    /// specification requires.
+    pub fn parse(value: &str) -> Option<Self> {
+        let version = value.get(..2)?;
+        if version == "ff" || (version == TRACEPARENT_VERSION && value.len() != 55) {
+            return None;
+        }
+        if value.len() > 55 && value.as_bytes()[55] != b'-' {
+            return None;
+        }
+
+        let mut fields = value.get(..55)?.split('-');
+        let _version = fields.next().filter(|field| is_lower_hex(field, 2))?;
+        let trace_id = fields.next().filter(|field| is_lower_hex(field, 32))?;
+        let span_id = fields.next().filter(|field| is_lower_hex(field, 16))?;
+        let flags = fields.next().filter(|field| is_lower_hex(field, 2))?;
+
+        let context = Self {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        };
+        (context.trace_id != 0 && context.span_id != 0).then_some(context)
+    }
+
+    /// Read the `traceparent` header of a request, if present and valid
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Self::parse(headers.get(TRACEPARENT_HEADER)?.to_str().ok()?)
+    }
+
+    /// Create a child context: same trace and flags with a new span id
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_nonzero_u64(),
+            ..*self
+        }
+    }
+
+    /// Continue the trace of a request, or start a new one when it has none
+    pub fn for_upstream(headers: &HeaderMap) -> Self {
+        Self::from_headers(headers).map_or_else(Self::new_root, |parent| parent.child())
+    }
+
+    /// Trace id as 32 lowercase hex digits
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// Span id as 16 lowercase hex digits
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// Format as a `traceparent` header value
+    pub fn to_traceparent(self) -> String {
+        format!(
+            "{}-{}-{}-{:02x}",
+            TRACEPARENT_VERSION,
+            self.trace_id(),
+            self.span_id(),
+            self.flags
+        )
+    }
+
+    /// Set this context as the `traceparent` of the given headers
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        let value = HeaderValue::from_str(&self.to_traceparent())
+            .expect("traceparent is always a valid header value");
+        headers.insert(TRACEPARENT_HEADER, value);
+    }
+}
+
+fn is_lower_hex(field: &str, len: usize) -> bool {
+    field.len() == len
+        && field
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn random_nonzero_u128() -> u128 {
+    loop {
+        let value = fastrand::u128(..);
+        if value != 0 {
+            return value;
+        }
+    }
+}
+
+fn random_nonzero_u64() -> u64 {
+    loop {
+        let value = fastrand::u64(..);
+        if value != 0 {
+            return value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_round_trip() {
+        let context = TraceContext::parse(SAMPLE).unwrap();
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id(), "00f067aa0ba902b7");
+        assert_eq!(context.to_traceparent(), SAMPLE);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_values() {
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "zz-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(TraceContext::parse(value).is_none(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_parse_accepts_future_versions() {
+        let value = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra";
+        let context = TraceContext::parse(value).unwrap();
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[test]
+    fn test_child_keeps_trace_and_flags() {
+        let parent = TraceContext::parse(SAMPLE).unwrap();
+        let child = parent.child();
+        assert_eq!(child.trace_id(), parent.trace_id());
+        assert_ne!(child.span_id(), parent.span_id());
+        assert!(child.to_traceparent().ends_with("-01"));
+    }
+
+    #[test]
+    fn test_for_upstream_starts_new_trace_without_parent() {
+        let context = TraceContext::for_upstream(&HeaderMap::new());
+        assert!(TraceContext::parse(&context.to_traceparent()).is_some());
+        assert_ne!(
+            context.trace_id(),
+            TraceContext::for_upstream(&HeaderMap::new()).trace_id()
+        );
+    }
+}
@@ -0,0 +1,131 @@
+/// Bodies that count their bytes and report when they finish
+///
+/// Bodies are streamed through in both directions, so the handler returns long
+/// before the response has been sent, and the size of a streamed body is only
+/// known once it ends. Work that must last as long as a body, or needs its size,
+/// is tied to a `TrackedBody`, which counts the data bytes passing through and
+/// calls its callback once the body ends, fails, or is dropped unfinished.
+use axum::body::{Body, Bytes};
+use hyper::body::{Frame, SizeHint};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Callback run with the number of data bytes when a tracked body finishes
+type OnEnd = Box<dyn FnOnce(u64) + Send>;
+
+/// Body counting its data bytes and calling a callback once it has been sent in full or abandoned
+pub struct TrackedBody {
+    inner: Body,
+    bytes: Arc<AtomicU64>,
+    on_end: Option<OnEnd>,
+}
+
+impl TrackedBody {
+    /// Wrap a body, calling `on_end` with its size once it finishes
+    pub fn new(inner: Body, on_end: impl FnOnce(u64) + Send + 'static) -> Self {
+        Self {
+            inner,
+            bytes: Arc::default(),
+            on_end: Some(Box::new(on_end)),
+        }
+    }
+
+    /// Wrap a body, adding its data bytes to `bytes` as they pass through
+    pub fn counting(inner: Body, bytes: Arc<AtomicU64>) -> Self {
+        Self {
+            inner,
+            bytes,
+            on_end: None,
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(self.bytes.load(Ordering::Relaxed));
+        }
+    }
+}
+
+impl hyper::body::Body for TrackedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let frame = Pin::new(&mut self.inner).poll_frame(cx);
+        match &frame {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    self.bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+                }
+            }
+            Poll::Ready(None | Some(Err(_))) => self.finish(),
+            Poll::Pending => {}
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for TrackedBody {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use std::sync::Mutex;
+
+    fn tracked(body: Body) -> (TrackedBody, Arc<Mutex<Option<u64>>>) {
+        let ended = Arc::new(Mutex::new(None));
+        let size = Arc::clone(&ended);
+        let body = TrackedBody::new(body, move |bytes| *size.lock().unwrap() = Some(bytes));
+        (body, ended)
+    }
+
+    #[tokio::test]
+    async fn test_calls_back_once_body_is_read() {
+        let chunks = futures_util::stream::iter([
+            Ok::<_, std::io::Error>(Bytes::from("a")),
+            Ok(Bytes::from("b")),
+        ]);
+        let (mut body, ended) = tracked(Body::from_stream(chunks));
+
+        body.frame().await.unwrap().unwrap();
+        assert_eq!(*ended.lock().unwrap(), None);
+        body.frame().await.unwrap().unwrap();
+        assert!(body.frame().await.is_none());
+        assert_eq!(*ended.lock().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_calls_back_when_dropped_unfinished() {
+        let (body, ended) = tracked(Body::from("unsent"));
+        drop(body);
+        assert_eq!(*ended.lock().unwrap(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_counting_adds_to_shared_total() {
+        let bytes = Arc::new(AtomicU64::new(0));
+        let mut body = TrackedBody::counting(Body::from("payload"), Arc::clone(&bytes));
+        assert_eq!(hyper::body::Body::size_hint(&body).exact(), Some(7));
+
+        while body.frame().await.is_some() {}
+        assert_eq!(bytes.load(Ordering::Relaxed), 7);
+    }
+}
@@ -0,0 +1,400 @@
+/// Active health checks of route upstreams
+///
+/// Passive signals such as `health_weighting` only notice a broken upstream
+/// after clients have been sent to it. Routes with a `health_check` have each
+/// upstream in their pool probed in the background instead, and the balancer
+/// skips upstreams whose latest check failed until one passes again.
+///
+/// HTTP checks request a path and pass on a 2xx status. gRPC checks call the
+/// standard `grpc.health.v1.Health/Check` RPC over cleartext HTTP/2 and pass
+/// only when the upstream reports the service as SERVING.
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Method, Request};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http_body_util::BodyExt;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::config::{UpstreamHealthCheckConfig, UpstreamHealthCheckType};
+use crate::constants::{
+    GRPC_CONTENT_TYPE, GRPC_HEALTH_CHECK_PATH, GRPC_HEALTH_SERVING, UPSTREAM_HEALTH_CHECK_TICK_MS,
+};
+use crate::grpc_web::GrpcClient;
+use crate::reload::SharedProxyState;
+use crate::upstream_stats::UpstreamStats;
+
+/// Probe the upstreams of every route with a `health_check` until shutdown
+///
+/// The routes are read from the current configuration on every tick, so
+/// reloads add and remove checks. An upstream shared by several routes is
+/// checked once, using the first route's settings.
+pub async fn run_upstream_health_checks(proxy: SharedProxyState, shutdown: CancellationToken) {
+    let mut last_checked: HashMap<String, Instant> = HashMap::new();
+    let mut ticker = tokio::time::interval(Duration::from_millis(UPSTREAM_HEALTH_CHECK_TICK_MS));
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = ticker.tick() => {}
+        }
+
+        let state = proxy.current();
+        let mut checked = HashSet::new();
+        for route in &state.config.routes {
+            let Some(check) = &route.health_check else {
+                continue;
+            };
+            let interval = Duration::from_secs(check.interval_secs);
+            for upstream in std::iter::once(&route.upstream).chain(&route.upstreams) {
+                if !checked.insert(upstream.clone()) {
+                    continue;
+                }
+                if last_checked
+                    .get(upstream)
+                    .is_some_and(|at| at.elapsed() < interval)
+                {
+                    continue;
+                }
+                last_checked.insert(upstream.clone(), Instant::now());
+
+                let (client, grpc_client) = (state.client.clone(), state.grpc_client.clone());
+                let stats = Arc::clone(&state.upstream_stats);
+                let (check, upstream) = (check.clone(), upstream.clone());
+                tokio::spawn(async move {
+                    let passed = check_upstream(&client, &grpc_client, &upstream, &check).await;
+                    record_result(&stats, &upstream, passed);
+                });
+            }
+        }
+
+        last_checked.retain(|upstream, _| checked.contains(upstream));
+        state
+            .upstream_stats
+            .retain_health_checks(|upstream| checked.contains(upstream));
+    }
+}
+
+fn record_result(stats: &UpstreamStats, upstream: &str, passed: bool) {
+    if stats.record_health_check(upstream, passed) {
+        if passed {
+            info!("Upstream {} passed its health check", upstream);
+        } else {
+            warn!(
+                "Upstream {} failed its health check, removing it from its pools",
+                upstream
+            );
+        }
+    }
+}
+
+/// Run one health check against an upstream, returning whether it passed
+pub async fn check_upstream(
+    client: &reqwest::Client,
+    grpc_client: &GrpcClient,
+    upstream: &str,
+    check: &UpstreamHealthCheckConfig,
+) -> bool {
+    let timeout = Duration::from_millis(check.timeout_ms);
+    let result = match check.check_type {
+        UpstreamHealthCheckType::Http => {
+            let url = format!("{}{}", upstream.trim_end_matches('/'), check.path);
+            match client.get(&url).timeout(timeout).send().await {
+                Ok(response) if response.status().is_success() => Ok(()),
+                Ok(response) => Err(format!("status {}", response.status())),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        UpstreamHealthCheckType::Grpc => {
+            match tokio::time::timeout(
+                timeout,
+                grpc_serving_status(grpc_client, upstream, &check.service),
+            )
+            .await
+            {
+                Ok(Ok(GRPC_HEALTH_SERVING)) => Ok(()),
+                Ok(Ok(status)) => Err(format!("serving status {status}")),
+                Ok(Err(e)) => Err(e),
+                Err(_) => Err(format!("no response within {timeout:?}")),
+            }
+        }
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(reason) => {
+            debug!("Health check of {} failed: {}", upstream, reason);
+            false
+        }
+    }
+}
+
+/// Call `grpc.health.v1.Health/Check` and return the reported serving status
+async fn grpc_serving_status(
+    client: &GrpcClient,
+    upstream: &str,
+    service: &str,
+) -> Result<u64, String> {
+    let mut request = Request::new(Body::from(health_check_request(service)));
+    *request.method_mut() = Method::POST;
+    *request.uri_mut() = format!(
+        "{}{}",
+        upstream.trim_end_matches('/'),
+        GRPC_HEALTH_CHECK_PATH
+    )
+    .parse()
+    .map_err(|e| format!("invalid upstream URL: {e}"))?;
+    let headers = request.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(GRPC_CONTENT_TYPE),
+    );
+    headers.insert(header::TE, HeaderValue::from_static("trailers"));
+
+    let response = client.request(request).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let (parts, body) = response.into_parts();
+    let body = body.collect().await.map_err(|e| e.to_string())?;
+
+    // Servers may answer errors with headers alone ("trailers-only")
+    let grpc_status = body
+        .trailers()
+        .and_then(|trailers| trailers.get("grpc-status"))
+        .or_else(|| parts.headers.get("grpc-status"));
+    match grpc_status.map(HeaderValue::as_bytes) {
+        Some(b"0") => {}
+        Some(status) => return Err(format!("grpc-status {}", String::from_utf8_lossy(status))),
+        None => return Err("missing grpc-status".to_string()),
+    }
+
+    let mut frame = body.to_bytes();
+    if frame.len() < 5 || frame.get_u8() != 0 {
+        return Err("malformed response message".to_string());
+    }
+    let length = frame.get_u32() as usize;
+    if frame.len() < length {
+        return Err("truncated response message".to_string());
+    }
+    serving_status(&frame[..length]).ok_or_else(|| "malformed response message".to_string())
+}
+
+/// A gRPC frame holding a `HealthCheckRequest` for `service`
+pub fn health_check_request(service: &str) -> Bytes {
+    // Field 1 (`service`) is length-delimited; empty strings are omitted
+    let mut message = BytesMut::new();
+    if !service.is_empty() {
+        message.put_u8(0x0a);
+        put_varint(&mut message, service.len() as u64);
+        message.extend_from_slice(service.as_bytes());
+    }
+
+    let mut frame = BytesMut::with_capacity(5 + message.len());
+    frame.put_u8(0);
+    frame.put_u32(message.len() as u32);
+    frame.extend_from_slice(&message);
+    frame.freeze()
+}
+
+/// The `status` of an encoded `HealthCheckResponse`, skipping unknown fields
+///
+/// A missing status decodes as 0, UNKNOWN. Returns None for malformed messages.
+pub fn serving_status(mut message: &[u8]) -> Option<u64> {
+    let mut status = 0;
+    while !message.is_empty() {
+        let key = get_varint(&mut message)?;
+        match (key >> 3, key & 0x7) {
+            (1, 0) => status = get_varint(&mut message)?,
+            (_, 0) => {
+                get_varint(&mut message)?;
+            }
+            (_, 1) => message = message.get(8..)?,
+            (_, 2) => {
+                let length = usize::try_from(get_varint(&mut message)?).ok()?;
+                message = message.get(length..)?;
+            }
+            (_, 5) => message = message.get(4..)?,
+            _ => return None,
+        }
+    }
+    Some(status)
+}
+
+fn put_varint(buf: &mut BytesMut, mut value: u64) {
+    while value >= 0x80 {
+        buf.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+fn get_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+    use axum::Router;
+
+    #[test]
+    fn test_health_check_request_encoding() {
+        assert_eq!(&health_check_request("")[..], b"\x00\x00\x00\x00\x00");
+        assert_eq!(
+            &health_check_request("echo.Echo")[..],
+            b"\x00\x00\x00\x00\x0b\x0a\x09echo.Echo"
+        );
+
+        // Long names need a multi-byte length
+        let service = "s".repeat(200);
+        let frame = health_check_request(&service);
+        assert_eq!(&frame[5..8], b"\x0a\xc8\x01");
+        assert_eq!(frame.len(), 5 + 3 + 200);
+    }
+
+    #[test]
+    fn test_serving_status_decoding() {
+        assert_eq!(serving_status(b""), Some(0));
+        assert_eq!(serving_status(b"\x08\x01"), Some(1));
+        assert_eq!(serving_status(b"\x08\x02"), Some(2));
+        // Unknown fields of every wire type are skipped
+        assert_eq!(
+            serving_status(b"\x10\x05\x19\x00\x00\x00\x00\x00\x00\x00\x00\x22\x02hi\x2d\x00\x00\x00\x00\x08\x01"),
+            Some(1)
+        );
+        assert_eq!(serving_status(b"\x08"), None);
+        assert_eq!(serving_status(b"\x22\x05hi"), None);
+        assert_eq!(serving_status(b"\x0b"), None);
+    }
+
+    /// Start a gRPC server exposing the health service over cleartext HTTP/2
+    ///
+    /// It reports "echo.Echo" and the server as a whole as SERVING, "billing" as
+    /// NOT_SERVING, and answers other services with NOT_FOUND.
+    async fn start_grpc_health_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        let check = axum::routing::post(|request: axum::extract::Request| async move {
+            assert_eq!(request.headers()["content-type"], "application/grpc");
+            assert_eq!(request.headers()["te"], "trailers");
+            let body = request.into_body().collect().await.unwrap().to_bytes();
+            let service = String::from_utf8(body[5..].get(2..).unwrap_or_default().to_vec());
+
+            let mut trailers = HeaderMap::new();
+            let message: &[u8] = match service.unwrap().as_str() {
+                "" | "echo.Echo" => b"\x00\x00\x00\x00\x02\x08\x01",
+                "billing" => b"\x00\x00\x00\x00\x02\x08\x02",
+                _ => {
+                    trailers.insert("grpc-status", HeaderValue::from_static("5"));
+                    b""
+                }
+            };
+            trailers
+                .entry("grpc-status")
+                .or_insert(HeaderValue::from_static("0"));
+            let frames = futures_util::stream::iter([
+                Ok::<_, std::convert::Infallible>(hyper::body::Frame::data(Bytes::from_static(
+                    message,
+                ))),
+                Ok(hyper::body::Frame::trailers(trailers)),
+            ]);
+            axum::response::Response::builder()
+                .header("content-type", "application/grpc")
+                .body(Body::new(http_body_util::StreamBody::new(frames)))
+                .unwrap()
+        });
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                Router::new().route("/grpc.health.v1.Health/Check", check),
+            )
+            .await
+            .unwrap();
+        });
+        upstream
+    }
+
+    fn grpc_check(service: &str) -> UpstreamHealthCheckConfig {
+        UpstreamHealthCheckConfig {
+            check_type: UpstreamHealthCheckType::Grpc,
+            service: service.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc_health_check_against_health_service() {
+        let upstream = start_grpc_health_server().await;
+        let client = reqwest::Client::new();
+        let grpc_client = crate::grpc_web::build_grpc_client();
+        let check = |service: &'static str| {
+            let (client, grpc_client, upstream) =
+                (client.clone(), grpc_client.clone(), upstream.clone());
+            async move { check_upstream(&client, &grpc_client, &upstream, &grpc_check(service)).await }
+        };
+
+        assert!(check("").await);
+        assert!(check("echo.Echo").await);
+        assert!(!check("billing").await);
+        assert!(!check("unknown.Service").await);
+
+        // Nothing listens on a closed port
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(!check_upstream(&client, &grpc_client, &closed, &grpc_check("")).await);
+    }
+
+    #[tokio::test]
+    async fn test_checker_removes_failing_upstreams_from_pool() {
+        use crate::config::{GatewayConfig, RouteConfig};
+        use crate::proxy::ProxyState;
+
+        let serving = start_grpc_health_server().await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let down = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let route = RouteConfig {
+            path: "/api/*".to_string(),
+            upstream: serving.clone(),
+            upstreams: vec![down.clone()],
+            health_check: Some(grpc_check("echo.Echo")),
+            ..Default::default()
+        };
+        let config = GatewayConfig {
+            server: crate::config::ServerConfig::default(),
+            routes: vec![route.clone()],
+            logging: crate::config::LoggingConfig::default(),
+        };
+        let proxy = SharedProxyState::new(ProxyState::new(config));
+        let stats = Arc::clone(&proxy.current().upstream_stats);
+        let shutdown = CancellationToken::new();
+        tokio::spawn(run_upstream_health_checks(proxy, shutdown.clone()));
+
+        while !stats.failed_health_check(&down) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!stats.failed_health_check(&serving));
+        for _ in 0..50 {
+            assert_eq!(
+                crate::balancer::choose_upstream(&route, &stats),
+                Some(serving.as_str())
+            );
+        }
+        shutdown.cancel();
+    }
+}
@@ -0,0 +1,293 @@
+/// Rolling latency and error statistics per upstream
+///
+/// Each upstream keeps a fixed-size ring of its most recent responses, so memory
+/// stays bounded however long the gateway runs. The statistics are served at
+/// `GET /admin/upstreams` to show which backend is degraded.
+use axum::{extract::State, response::Json};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::constants::UPSTREAM_STATS_WINDOW;
+
+/// Outcome of a single upstream request
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    latency_ms: f64,
+    error: bool,
+}
+
+/// Registry of rolling statistics keyed by upstream URL
+#[derive(Debug)]
+pub struct UpstreamStats {
+    window: usize,
+    upstreams: Mutex<BTreeMap<String, VecDeque<Sample>>>,
+    /// Ejected upstreams and when they rejoin their pools
+    ejected: Mutex<HashMap<String, Instant>>,
+    /// Upstreams whose latest active health check failed
+    failed_checks: Mutex<HashSet<String>>,
+}
+
+/// Statistics for one upstream over its most recent requests
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpstreamStatsSnapshot {
+    pub upstream: String,
+    /// Number of requests in the window
+    pub requests: usize,
+    /// Fraction of requests in the window that failed or returned a 5xx status
+    pub error_rate: f64,
+    pub latency_p50_ms: f64,
+    pub latency_p90_ms: f64,
+    pub latency_p99_ms: f64,
+}
+
+impl Default for UpstreamStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UpstreamStats {
+    /// Create an empty registry keeping the default number of samples per upstream
+    pub fn new() -> Self {
+        Self::with_window(UPSTREAM_STATS_WINDOW)
+    }
+
+    /// Create an empty registry keeping `window` samples per upstream
+    pub fn with_window(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            upstreams: Mutex::new(BTreeMap::new()),
+            ejected: Mutex::new(HashMap::new()),
+            failed_checks: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Record the outcome of a request to an upstream
+    pub fn record(&self, upstream: &str, latency: Duration, error: bool) {
+        let mut upstreams = self.upstreams.lock().unwrap();
+        let samples = match upstreams.get_mut(upstream) {
+            Some(samples) => samples,
+            None => upstreams
+                .entry(upstream.to_string())
+                .or_insert_with(|| VecDeque::with_capacity(self.window)),
+        };
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(Sample {
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            error,
+        });
+    }
+
+    /// Current statistics for every upstream that has served requests
+    pub fn snapshot(&self) -> Vec<UpstreamStatsSnapshot> {
+        let upstreams = self.upstreams.lock().unwrap();
+        upstreams
+            .iter()
+            .map(|(upstream, samples)| summarize(upstream, samples))
+            .collect()
+    }
+
+    /// Current statistics for one upstream, if it has served requests
+    pub fn snapshot_for(&self, upstream: &str) -> Option<UpstreamStatsSnapshot> {
+        let upstreams = self.upstreams.lock().unwrap();
+        upstreams
+            .get(upstream)
+            .map(|samples| summarize(upstream, samples))
+    }
+
+    /// Take an upstream out of its pools for `duration`
+    ///
+    /// Its samples are discarded, so it rejoins with a clean record rather than
+    /// being ejected again straight away for the failures that ejected it.
+    pub fn eject(&self, upstream: &str, duration: Duration) {
+        self.upstreams.lock().unwrap().remove(upstream);
+        self.ejected
+            .lock()
+            .unwrap()
+            .insert(upstream.to_string(), Instant::now() + duration);
+    }
+
+    /// Whether an upstream is currently ejected
+    pub fn is_ejected(&self, upstream: &str) -> bool {
+        let mut ejected = self.ejected.lock().unwrap();
+        match ejected.get(upstream) {
+            Some(until) if Instant::now() < *until => true,
+            Some(_) => {
+                ejected.remove(upstream);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Record the outcome of an active health check of an upstream
+    ///
+    /// Returns whether the upstream's health changed.
+    pub fn record_health_check(&self, upstream: &str, passed: bool) -> bool {
+        let mut failed_checks = self.failed_checks.lock().unwrap();
+        if passed {
+            failed_checks.remove(upstream)
+        } else {
+            failed_checks.insert(upstream.to_string())
+        }
+    }
+
+    /// Whether an upstream's latest active health check failed
+    pub fn failed_health_check(&self, upstream: &str) -> bool {
+        self.failed_checks.lock().unwrap().contains(upstream)
+    }
+
+    /// Forget everything recorded about upstreams for which `keep` returns false
+    ///
+    /// Called on reload, so an upstream removed from the configuration and later
+    /// added back starts with a clean record instead of a stale ejection.
+    pub fn retain_upstreams(&self, mut keep: impl FnMut(&str) -> bool) {
+        self.upstreams
+            .lock()
+            .unwrap()
+            .retain(|upstream, _| keep(upstream));
+        self.ejected
+            .lock()
+            .unwrap()
+            .retain(|upstream, _| keep(upstream));
+        self.failed_checks
+            .lock()
+            .unwrap()
+            .retain(|upstream| keep(upstream));
+    }
+
+    /// Forget the health check results of upstreams that are no longer checked
+    pub fn retain_health_checks(&self, mut checked: impl FnMut(&str) -> bool) {
+        self.failed_checks
+            .lock()
+            .unwrap()
+            .retain(|upstream| checked(upstream));
+    }
+}
+
+/// Summarize an upstream's samples
+fn summarize(upstream: &str, samples: &VecDeque<Sample>) -> UpstreamStatsSnapshot {
+    let mut latencies: Vec<f64> = samples.iter().map(|sample| sample.latency_ms).collect();
+    latencies.sort_by(f64::total_cmp);
+    let errors = samples.iter().filter(|sample| sample.error).count();
+
+    UpstreamStatsSnapshot {
+        upstream: upstream.to_string(),
+        requests: samples.len(),
+        error_rate: errors as f64 / samples.len() as f64,
+        latency_p50_ms: percentile(&latencies, 0.50),
+        latency_p90_ms: percentile(&latencies, 0.90),
+        latency_p99_ms: percentile(&latencies, 0.99),
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+pub fn percentile(sorted: &[f64], quantile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Admin endpoint handler listing per-upstream statistics
+pub async fn upstreams_handler(
+    State(stats): State<Arc<UpstreamStats>>,
+) -> Json<Vec<UpstreamStatsSnapshot>> {
+    Json(stats.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_latencies_and_errors_are_reported() {
+        let stats = UpstreamStats::new();
+        for ms in 1..=100 {
+            stats.record("http://a:3000", Duration::from_millis(ms), ms % 10 == 0);
+        }
+        stats.record("http://b:3000", Duration::from_millis(500), true);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        let a = &snapshot[0];
+        assert_eq!(a.upstream, "http://a:3000");
+        assert_eq!(a.requests, 100);
+        assert_eq!(a.error_rate, 0.1);
+        assert_eq!(a.latency_p50_ms, 50.0);
+        assert_eq!(a.latency_p90_ms, 90.0);
+        assert_eq!(a.latency_p99_ms, 99.0);
+
+        let b = &snapshot[1];
+        assert_eq!(b.error_rate, 1.0);
+        assert_eq!(b.latency_p50_ms, 500.0);
+    }
+
+    #[test]
+    fn test_window_keeps_only_recent_samples() {
+        let stats = UpstreamStats::with_window(4);
+        for _ in 0..10 {
+            stats.record("http://a:3000", Duration::from_millis(1000), true);
+        }
+        for _ in 0..4 {
+            stats.record("http://a:3000", Duration::from_millis(10), false);
+        }
+
+        let a = &stats.snapshot()[0];
+        assert_eq!(a.requests, 4);
+        assert_eq!(a.error_rate, 0.0);
+        assert_eq!(a.latency_p99_ms, 10.0);
+    }
+
+    #[test]
+    fn test_ejection_expires_with_a_clean_record() {
+        let stats = UpstreamStats::new();
+        stats.record("http://a:3000", Duration::from_millis(10), true);
+        stats.eject("http://a:3000", Duration::from_millis(20));
+
+        assert!(stats.is_ejected("http://a:3000"));
+        assert!(stats.snapshot_for("http://a:3000").is_none());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!stats.is_ejected("http://a:3000"));
+    }
+
+    #[test]
+    fn test_retain_upstreams_forgets_removed_upstreams() {
+        let stats = UpstreamStats::new();
+        stats.record("http://a:3000", Duration::from_millis(10), false);
+        stats.record("http://b:3000", Duration::from_millis(10), false);
+        stats.eject("http://a:3000", Duration::from_secs(30));
+        stats.eject("http://b:3000", Duration::from_secs(30));
+        stats.record_health_check("http://b:3000", false);
+        stats.record("http://a:3000", Duration::from_millis(10), true);
+
+        stats.retain_upstreams(|upstream| upstream == "http://a:3000");
+        assert!(stats.is_ejected("http://a:3000"));
+        assert_eq!(stats.snapshot_for("http://a:3000").unwrap().requests, 1);
+        assert!(!stats.is_ejected("http://b:3000"));
+        assert!(!stats.failed_health_check("http://b:3000"));
+        assert!(stats.snapshot_for("http://b:3000").is_none());
+    }
+
+    #[test]
+    fn test_health_check_results() {
+        let stats = UpstreamStats::new();
+        assert!(!stats.failed_health_check("http://a:3000"));
+
+        assert!(stats.record_health_check("http://a:3000", false));
+        assert!(!stats.record_health_check("http://a:3000", false));
+        assert!(stats.failed_health_check("http://a:3000"));
+        assert!(stats.record_health_check("http://a:3000", true));
+        assert!(!stats.failed_health_check("http://a:3000"));
+
+        stats.record_health_check("http://a:3000", false);
+        stats.retain_health_checks(|upstream| upstream != "http://a:3000");
+        assert!(!stats.failed_health_check("http://a:3000"));
+    }
+}
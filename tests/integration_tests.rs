@@ -30,6 +30,7 @@ async fn test_route_path_matching() {
         strip_path: true,
         preserve_host: false,
         timeout_ms: None,
+        ..Default::default()
     };
 
     // Test path matching
@@ -57,6 +58,7 @@ async fn test_path_transformation() {
         strip_path: true,
         preserve_host: false,
         timeout_ms: None,
+        ..Default::default()
     };
 
     // Test path transformation with strip_path = true
@@ -85,6 +87,7 @@ async fn test_httpbin_get_endpoint() {
         strip_path: true,
         preserve_host: false,
         timeout_ms: Some(30000),
+        ..Default::default()
     };
 
     // Test that the route configuration is valid for httpbin
@@ -111,6 +114,7 @@ async fn test_httpbin_post_endpoint() {
         strip_path: true,
         preserve_host: false,
         timeout_ms: Some(30000),
+        ..Default::default()
     };
 
     // Test that the route configuration is valid for httpbin POST
@@ -136,6 +140,7 @@ async fn test_httpbin_status_endpoint() {
         strip_path: true,
         preserve_host: false,
         timeout_ms: Some(30000),
+        ..Default::default()
     };
 
     // Test status code endpoints
@@ -162,6 +167,7 @@ async fn test_httpbin_json_endpoint() {
         strip_path: true,
         preserve_host: false,
         timeout_ms: Some(30000),
+        ..Default::default()
     };
 
     // Test JSON endpoint